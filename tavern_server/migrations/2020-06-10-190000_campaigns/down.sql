@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CampaignCharacters;
+DROP TABLE CampaignInvitations;
+DROP TABLE CampaignMembers;
+DROP TABLE Campaigns;
@@ -0,0 +1,34 @@
+-- A campaign is a table group: one game master and any number of players,
+-- each of whom may bring characters into the campaign.
+CREATE TABLE Campaigns (
+    id              UUID    PRIMARY KEY,
+    name            TEXT    NOT NULL,
+    description     TEXT    NOT NULL,
+    gm_id           UUID    REFERENCES Users(id) NOT NULL
+);
+
+CREATE TABLE CampaignMembers (
+    campaign_id     UUID    REFERENCES Campaigns(id) NOT NULL,
+    user_id         UUID    REFERENCES Users(id) NOT NULL,
+    PRIMARY KEY(campaign_id, user_id)
+);
+
+CREATE TABLE CampaignInvitations (
+    campaign_id     UUID    REFERENCES Campaigns(id) NOT NULL,
+    user_id         UUID    REFERENCES Users(id) NOT NULL,
+    PRIMARY KEY(campaign_id, user_id)
+);
+
+-- A character can only take part in a single campaign at a time.
+CREATE TABLE CampaignCharacters (
+    campaign_id     UUID    REFERENCES Campaigns(id) NOT NULL,
+    char_id         UUID    REFERENCES Characters(id) NOT NULL
+                            CONSTRAINT campaign_character_unique UNIQUE,
+    PRIMARY KEY(campaign_id, char_id)
+);
+
+CREATE INDEX campaign_gm_id ON Campaigns (gm_id);
+CREATE INDEX campaign_member_campaign_id ON CampaignMembers (campaign_id);
+CREATE INDEX campaign_member_user_id ON CampaignMembers (user_id);
+CREATE INDEX campaign_invitation_user_id ON CampaignInvitations (user_id);
+CREATE INDEX campaign_character_campaign_id ON CampaignCharacters (campaign_id);
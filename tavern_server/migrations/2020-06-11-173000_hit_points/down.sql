@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE ResistanceUnits;
+ALTER TABLE Characters DROP COLUMN temp_hp;
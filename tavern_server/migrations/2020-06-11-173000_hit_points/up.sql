@@ -0,0 +1,11 @@
+-- Temporary hit points are lost before any other hit points and do not stack;
+-- they are tracked separately from lethal and nonlethal damage.
+ALTER TABLE Characters ADD COLUMN temp_hp SMALLINT NOT NULL DEFAULT 0 CHECK (temp_hp >= 0);
+
+-- Energy resistance granted by an effect, e.g. "resist fire 10".
+CREATE TABLE ResistanceUnits (
+    effect_id       UUID            REFERENCES Effects(id) NOT NULL,
+    damage_type     damage_type     NOT NULL,
+    amount          SMALLINT        NOT NULL CHECK (amount > 0),
+    PRIMARY KEY (effect_id, damage_type)
+);
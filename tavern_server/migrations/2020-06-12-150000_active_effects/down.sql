@@ -0,0 +1,139 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CharacterActiveEffects;
+ALTER TABLE Campaigns DROP COLUMN game_time;
+
+DELETE FROM MiscUnits WHERE effect_id IN (
+    'c0000000-0000-4000-8000-000000000001',
+    'c0000000-0000-4000-8000-000000000002',
+    'c0000000-0000-4000-8000-000000000003',
+    'c0000000-0000-4000-8000-000000000004',
+    'c0000000-0000-4000-8000-000000000005',
+    'c0000000-0000-4000-8000-000000000006',
+    'c0000000-0000-4000-8000-000000000007',
+    'c0000000-0000-4000-8000-000000000008',
+    'c0000000-0000-4000-8000-000000000009',
+    'c0000000-0000-4000-8000-000000000010',
+    'c0000000-0000-4000-8000-000000000011',
+    'c0000000-0000-4000-8000-000000000012',
+    'c0000000-0000-4000-8000-000000000013',
+    'c0000000-0000-4000-8000-000000000014',
+    'c0000000-0000-4000-8000-000000000015',
+    'c0000000-0000-4000-8000-000000000016',
+    'c0000000-0000-4000-8000-000000000017',
+    'c0000000-0000-4000-8000-000000000018',
+    'c0000000-0000-4000-8000-000000000019',
+    'c0000000-0000-4000-8000-000000000020',
+    'c0000000-0000-4000-8000-000000000021',
+    'c0000000-0000-4000-8000-000000000022',
+    'c0000000-0000-4000-8000-000000000023',
+    'c0000000-0000-4000-8000-000000000024',
+    'c0000000-0000-4000-8000-000000000025'
+);
+DELETE FROM SkillUnits WHERE effect_id IN (
+    'c0000000-0000-4000-8000-000000000001',
+    'c0000000-0000-4000-8000-000000000002',
+    'c0000000-0000-4000-8000-000000000003',
+    'c0000000-0000-4000-8000-000000000004',
+    'c0000000-0000-4000-8000-000000000005',
+    'c0000000-0000-4000-8000-000000000006',
+    'c0000000-0000-4000-8000-000000000007',
+    'c0000000-0000-4000-8000-000000000008',
+    'c0000000-0000-4000-8000-000000000009',
+    'c0000000-0000-4000-8000-000000000010',
+    'c0000000-0000-4000-8000-000000000011',
+    'c0000000-0000-4000-8000-000000000012',
+    'c0000000-0000-4000-8000-000000000013',
+    'c0000000-0000-4000-8000-000000000014',
+    'c0000000-0000-4000-8000-000000000015',
+    'c0000000-0000-4000-8000-000000000016',
+    'c0000000-0000-4000-8000-000000000017',
+    'c0000000-0000-4000-8000-000000000018',
+    'c0000000-0000-4000-8000-000000000019',
+    'c0000000-0000-4000-8000-000000000020',
+    'c0000000-0000-4000-8000-000000000021',
+    'c0000000-0000-4000-8000-000000000022',
+    'c0000000-0000-4000-8000-000000000023',
+    'c0000000-0000-4000-8000-000000000024',
+    'c0000000-0000-4000-8000-000000000025'
+);
+DELETE FROM CombatUnits WHERE effect_id IN (
+    'c0000000-0000-4000-8000-000000000001',
+    'c0000000-0000-4000-8000-000000000002',
+    'c0000000-0000-4000-8000-000000000003',
+    'c0000000-0000-4000-8000-000000000004',
+    'c0000000-0000-4000-8000-000000000005',
+    'c0000000-0000-4000-8000-000000000006',
+    'c0000000-0000-4000-8000-000000000007',
+    'c0000000-0000-4000-8000-000000000008',
+    'c0000000-0000-4000-8000-000000000009',
+    'c0000000-0000-4000-8000-000000000010',
+    'c0000000-0000-4000-8000-000000000011',
+    'c0000000-0000-4000-8000-000000000012',
+    'c0000000-0000-4000-8000-000000000013',
+    'c0000000-0000-4000-8000-000000000014',
+    'c0000000-0000-4000-8000-000000000015',
+    'c0000000-0000-4000-8000-000000000016',
+    'c0000000-0000-4000-8000-000000000017',
+    'c0000000-0000-4000-8000-000000000018',
+    'c0000000-0000-4000-8000-000000000019',
+    'c0000000-0000-4000-8000-000000000020',
+    'c0000000-0000-4000-8000-000000000021',
+    'c0000000-0000-4000-8000-000000000022',
+    'c0000000-0000-4000-8000-000000000023',
+    'c0000000-0000-4000-8000-000000000024',
+    'c0000000-0000-4000-8000-000000000025'
+);
+DELETE FROM AttributeUnits WHERE effect_id IN (
+    'c0000000-0000-4000-8000-000000000001',
+    'c0000000-0000-4000-8000-000000000002',
+    'c0000000-0000-4000-8000-000000000003',
+    'c0000000-0000-4000-8000-000000000004',
+    'c0000000-0000-4000-8000-000000000005',
+    'c0000000-0000-4000-8000-000000000006',
+    'c0000000-0000-4000-8000-000000000007',
+    'c0000000-0000-4000-8000-000000000008',
+    'c0000000-0000-4000-8000-000000000009',
+    'c0000000-0000-4000-8000-000000000010',
+    'c0000000-0000-4000-8000-000000000011',
+    'c0000000-0000-4000-8000-000000000012',
+    'c0000000-0000-4000-8000-000000000013',
+    'c0000000-0000-4000-8000-000000000014',
+    'c0000000-0000-4000-8000-000000000015',
+    'c0000000-0000-4000-8000-000000000016',
+    'c0000000-0000-4000-8000-000000000017',
+    'c0000000-0000-4000-8000-000000000018',
+    'c0000000-0000-4000-8000-000000000019',
+    'c0000000-0000-4000-8000-000000000020',
+    'c0000000-0000-4000-8000-000000000021',
+    'c0000000-0000-4000-8000-000000000022',
+    'c0000000-0000-4000-8000-000000000023',
+    'c0000000-0000-4000-8000-000000000024',
+    'c0000000-0000-4000-8000-000000000025'
+);
+DELETE FROM Effects WHERE id IN (
+    'c0000000-0000-4000-8000-000000000001',
+    'c0000000-0000-4000-8000-000000000002',
+    'c0000000-0000-4000-8000-000000000003',
+    'c0000000-0000-4000-8000-000000000004',
+    'c0000000-0000-4000-8000-000000000005',
+    'c0000000-0000-4000-8000-000000000006',
+    'c0000000-0000-4000-8000-000000000007',
+    'c0000000-0000-4000-8000-000000000008',
+    'c0000000-0000-4000-8000-000000000009',
+    'c0000000-0000-4000-8000-000000000010',
+    'c0000000-0000-4000-8000-000000000011',
+    'c0000000-0000-4000-8000-000000000012',
+    'c0000000-0000-4000-8000-000000000013',
+    'c0000000-0000-4000-8000-000000000014',
+    'c0000000-0000-4000-8000-000000000015',
+    'c0000000-0000-4000-8000-000000000016',
+    'c0000000-0000-4000-8000-000000000017',
+    'c0000000-0000-4000-8000-000000000018',
+    'c0000000-0000-4000-8000-000000000019',
+    'c0000000-0000-4000-8000-000000000020',
+    'c0000000-0000-4000-8000-000000000021',
+    'c0000000-0000-4000-8000-000000000022',
+    'c0000000-0000-4000-8000-000000000023',
+    'c0000000-0000-4000-8000-000000000024',
+    'c0000000-0000-4000-8000-000000000025'
+);
@@ -0,0 +1,168 @@
+-- Effects applied to a character for a limited time, such as conditions or
+-- spells. Times are measured in rounds of the owning campaign's clock; an
+-- effect without an expiry lasts until it is removed.
+ALTER TABLE Campaigns ADD COLUMN game_time BIGINT NOT NULL DEFAULT 0 CHECK (game_time >= 0);
+
+CREATE TABLE CharacterActiveEffects (
+    id              UUID        PRIMARY KEY,
+    char_id         UUID        REFERENCES Characters(id) NOT NULL,
+    effect_id       UUID        REFERENCES Effects(id) NOT NULL,
+    source          TEXT        NOT NULL,
+    started_at      BIGINT      NOT NULL CHECK (started_at >= 0),
+    expires_at      BIGINT      CHECK (expires_at >= started_at)
+);
+
+CREATE INDEX character_active_effect_char_id ON CharacterActiveEffects (char_id);
+
+-- The standard conditions. Anything that can't be expressed as a modifier is
+-- described in a misc unit.
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000001', 'Bleed', 'Taking damage at the start of each turn.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000001', 'Takes the listed amount of damage at the beginning of each turn until stopped by a DC 15 Heal check or magical healing.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000002', 'Blinded', 'Cannot see.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000002', 'armor_class', -2);
+INSERT INTO SkillUnits (effect_id, skill, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000002', 'acrobatics', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'climb', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'disable_device', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'escape_artist', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'fly', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'ride', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'sleight_of_hand', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'stealth', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'swim', -4),
+    ('c0000000-0000-4000-8000-000000000002', 'perception', -4);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000002', 'Loses Dexterity bonus to AC, all opponents have total concealment, and moves at half speed.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000003', 'Confused', 'Acts randomly.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000003', 'Roll on the confusion table at the start of each turn to determine what the creature does.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000004', 'Cowering', 'Frozen in fear.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000004', 'armor_class', -2);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000004', 'Can take no actions and loses Dexterity bonus to AC.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000005', 'Dazed', 'Unable to act normally.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000005', 'Can take no actions, but has no penalty to AC.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000006', 'Dazzled', 'Unable to see well.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000006', 'melee_attack_bonus', -1),
+    ('c0000000-0000-4000-8000-000000000006', 'ranged_attack_bonus', -1);
+INSERT INTO SkillUnits (effect_id, skill, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000006', 'perception', -1);
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000007', 'Deafened', 'Cannot hear.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000007', 'initiative_bonus', -4);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000007', 'Automatically fails hearing-based Perception checks and has a 20% chance of spell failure on spells with verbal components.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000008', 'Entangled', 'Ensnared.');
+INSERT INTO AttributeUnits (effect_id, attr, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000008', 'dexterity', -4);
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000008', 'melee_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000008', 'ranged_attack_bonus', -2);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000008', 'Moves at half speed, cannot run or charge, and must make a concentration check to cast spells.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000009', 'Exhausted', 'Severely tired.');
+INSERT INTO AttributeUnits (effect_id, attr, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000009', 'strength', -6),
+    ('c0000000-0000-4000-8000-000000000009', 'dexterity', -6);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000009', 'Moves at half speed and cannot run or charge. Becomes fatigued after 1 hour of complete rest.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000010', 'Fascinated', 'Entranced by a supernatural or spell effect.');
+INSERT INTO SkillUnits (effect_id, skill, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000010', 'perception', -4);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000010', 'Stands or sits quietly, taking no actions other than paying attention to the fascinating effect.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000011', 'Fatigued', 'Tired.');
+INSERT INTO AttributeUnits (effect_id, attr, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000011', 'strength', -2),
+    ('c0000000-0000-4000-8000-000000000011', 'dexterity', -2);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000011', 'Cannot run or charge. Doing anything that would cause fatigue causes exhaustion instead.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000012', 'Flat-Footed', 'Has not yet acted in combat.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000012', 'Loses Dexterity bonus to AC and cannot make attacks of opportunity.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000013', 'Frightened', 'Fleeing from the source of fear.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000013', 'melee_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000013', 'ranged_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000013', 'fortitude', -2),
+    ('c0000000-0000-4000-8000-000000000013', 'reflex', -2),
+    ('c0000000-0000-4000-8000-000000000013', 'will', -2);
+INSERT INTO SkillUnits (effect_id, skill, modifier)
+    SELECT 'c0000000-0000-4000-8000-000000000013', skill, -2 FROM unnest(enum_range(NULL::skill)) AS skill;
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000013', 'Flees from the source of its fear as best it can, and takes a -2 penalty on ability checks.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000014', 'Grappled', 'Restrained by a creature, trap or effect.');
+INSERT INTO AttributeUnits (effect_id, attr, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000014', 'dexterity', -4);
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000014', 'melee_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000014', 'ranged_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000014', 'cmb', -2);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000014', 'Cannot move and must make a concentration check to cast spells. The combat maneuver penalty does not apply to checks made to grapple or escape a grapple.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000015', 'Helpless', 'Completely at an opponent''s mercy.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000015', 'Treated as having a Dexterity of 0. Melee attacks against the creature get a +4 bonus and it can be the target of a coup de grace.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000016', 'Nauseated', 'Experiencing stomach distress.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000016', 'Unable to attack, cast spells, concentrate on spells, or do anything else requiring attention. Can only take a single move action each turn.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000017', 'Panicked', 'Overcome with fear.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000017', 'fortitude', -2),
+    ('c0000000-0000-4000-8000-000000000017', 'reflex', -2),
+    ('c0000000-0000-4000-8000-000000000017', 'will', -2);
+INSERT INTO SkillUnits (effect_id, skill, modifier)
+    SELECT 'c0000000-0000-4000-8000-000000000017', skill, -2 FROM unnest(enum_range(NULL::skill)) AS skill;
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000017', 'Drops anything it holds and flees at top speed from the source of its fear. Takes a -2 penalty on ability checks.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000018', 'Paralyzed', 'Frozen in place and unable to move or act.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000018', 'Has effective Dexterity and Strength scores of 0 and is helpless, but can take purely mental actions.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000019', 'Pinned', 'Tightly bound.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000019', 'armor_class', -4);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000019', 'Cannot move and loses Dexterity bonus to AC. Can attempt to free itself with a combat maneuver check or Escape Artist check.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000020', 'Prone', 'Lying on the ground.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000020', 'melee_attack_bonus', -4);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000020', 'Cannot use a ranged weapon other than a crossbow. Gets a +4 bonus to AC against ranged attacks but a -4 penalty against melee attacks.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000021', 'Shaken', 'Mildly frightened.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000021', 'melee_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000021', 'ranged_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000021', 'fortitude', -2),
+    ('c0000000-0000-4000-8000-000000000021', 'reflex', -2),
+    ('c0000000-0000-4000-8000-000000000021', 'will', -2);
+INSERT INTO SkillUnits (effect_id, skill, modifier)
+    SELECT 'c0000000-0000-4000-8000-000000000021', skill, -2 FROM unnest(enum_range(NULL::skill)) AS skill;
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000021', 'Takes a -2 penalty on ability checks.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000022', 'Sickened', 'Ill.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000022', 'melee_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000022', 'ranged_attack_bonus', -2),
+    ('c0000000-0000-4000-8000-000000000022', 'fortitude', -2),
+    ('c0000000-0000-4000-8000-000000000022', 'reflex', -2),
+    ('c0000000-0000-4000-8000-000000000022', 'will', -2);
+INSERT INTO SkillUnits (effect_id, skill, modifier)
+    SELECT 'c0000000-0000-4000-8000-000000000022', skill, -2 FROM unnest(enum_range(NULL::skill)) AS skill;
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000022', 'Takes a -2 penalty on weapon damage rolls and ability checks.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000023', 'Staggered', 'Only able to take a single action each round.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000023', 'Can take a single move action or standard action each round, but not both, nor a full-round action.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000024', 'Stunned', 'Unable to act.');
+INSERT INTO CombatUnits (effect_id, stat, modifier) VALUES
+    ('c0000000-0000-4000-8000-000000000024', 'armor_class', -2);
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000024', 'Drops everything held, can take no actions and loses Dexterity bonus to AC.');
+
+INSERT INTO Effects (id, name, short_description) VALUES ('c0000000-0000-4000-8000-000000000025', 'Unconscious', 'Knocked out and helpless.');
+INSERT INTO MiscUnits (effect_id, description) VALUES ('c0000000-0000-4000-8000-000000000025', 'Is helpless and unaware of its surroundings.');
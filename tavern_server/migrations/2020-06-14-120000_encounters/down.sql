@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE Combatants;
+DROP TABLE Encounters;
+DROP TYPE combatant_status;
@@ -0,0 +1,33 @@
+CREATE TYPE combatant_status AS ENUM (
+    'active',
+    'delaying',
+    'readied'
+);
+
+-- A fight within a campaign. Round 0 means initiative has not been rolled
+-- yet; current_turn is the turn_order of the combatant that is acting.
+CREATE TABLE Encounters (
+    id              UUID        PRIMARY KEY,
+    campaign_id     UUID        REFERENCES Campaigns(id) NOT NULL,
+    name            TEXT        NOT NULL,
+    round           INT         NOT NULL DEFAULT 0 CHECK (round >= 0),
+    current_turn    SMALLINT    CHECK (current_turn >= 0)
+);
+
+-- Anything taking part in an encounter. Combatants without a character are
+-- ad-hoc NPCs that only exist for the duration of the encounter.
+CREATE TABLE Combatants (
+    id                  UUID                PRIMARY KEY,
+    encounter_id        UUID                REFERENCES Encounters(id) ON DELETE CASCADE NOT NULL,
+    char_id             UUID                REFERENCES Characters(id),
+    name                TEXT                NOT NULL,
+    initiative_bonus    SMALLINT            NOT NULL,
+    dexterity           SMALLINT            NOT NULL CHECK (dexterity >= 0),
+    initiative          SMALLINT,
+    turn_order          SMALLINT            CHECK (turn_order >= 0),
+    status              combatant_status    NOT NULL DEFAULT 'active',
+    CONSTRAINT combatant_character_unique UNIQUE (encounter_id, char_id)
+);
+
+CREATE INDEX encounter_campaign_id ON Encounters (campaign_id);
+CREATE INDEX combatant_encounter_id ON Combatants (encounter_id);
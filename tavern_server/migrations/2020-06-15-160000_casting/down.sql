@@ -0,0 +1,37 @@
+-- This file should undo anything in `up.sql`
+-- Effects using the new combat_stat values have to go before the type can
+-- lose them.
+DELETE FROM CombatUnits WHERE stat IN ('caster_level', 'save_dc', 'concentration');
+
+ALTER TYPE combat_stat RENAME TO combat_stat_old;
+CREATE TYPE combat_stat AS ENUM (
+    'melee_attack_bonus',
+    'ranged_attack_bonus',
+    'cmb',
+    'cmd',
+    'armor_class',
+    'touch_ac',
+    'flat_footed_ac',
+    'initiative_bonus',
+    'damage_reduction',
+    'spell_resistance',
+    'speed',
+    'fortitude',
+    'reflex',
+    'will'
+);
+ALTER TABLE CombatUnits ALTER COLUMN stat TYPE combat_stat USING stat::text::combat_stat;
+DROP TYPE combat_stat_old;
+
+DELETE FROM SpellComponents WHERE component_type IN ('focus', 'divine_focus');
+ALTER TYPE component_type RENAME TO component_type_old;
+CREATE TYPE component_type AS ENUM (
+    'somatic',
+    'material',
+    'verbal'
+);
+ALTER TABLE SpellComponents ALTER COLUMN component_type TYPE component_type USING component_type::text::component_type;
+DROP TYPE component_type_old;
+
+ALTER TABLE Subclasses DROP COLUMN tradition;
+DROP TYPE magic_tradition;
@@ -0,0 +1,43 @@
+CREATE TYPE magic_tradition AS ENUM (
+    'arcane',
+    'divine'
+);
+
+ALTER TABLE Subclasses ADD COLUMN tradition magic_tradition;
+
+-- ALTER TYPE ... ADD VALUE can't run inside a transaction before Postgres 12
+-- and can't be undone, so the type is recreated with the new values instead.
+ALTER TYPE combat_stat RENAME TO combat_stat_old;
+CREATE TYPE combat_stat AS ENUM (
+    'melee_attack_bonus',
+    'ranged_attack_bonus',
+    'cmb',
+    'cmd',
+    'armor_class',
+    'touch_ac',
+    'flat_footed_ac',
+    'initiative_bonus',
+    'damage_reduction',
+    'spell_resistance',
+    'speed',
+    'fortitude',
+    'reflex',
+    'will',
+    'caster_level',
+    'save_dc',
+    'concentration'
+);
+ALTER TABLE CombatUnits ALTER COLUMN stat TYPE combat_stat USING stat::text::combat_stat;
+DROP TYPE combat_stat_old;
+
+-- Foci are needed to cast a spell but aren't used up by it.
+ALTER TYPE component_type RENAME TO component_type_old;
+CREATE TYPE component_type AS ENUM (
+    'somatic',
+    'material',
+    'verbal',
+    'focus',
+    'divine_focus'
+);
+ALTER TABLE SpellComponents ALTER COLUMN component_type TYPE component_type USING component_type::text::component_type;
+DROP TYPE component_type_old;
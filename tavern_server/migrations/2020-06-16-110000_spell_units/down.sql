@@ -0,0 +1,34 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE Spells ADD COLUMN area TEXT NOT NULL DEFAULT '';
+UPDATE Spells SET area = CASE
+        WHEN area_shape IN ('cone', 'line', 'cube') THEN area_size || '-ft. ' || area_shape
+        ELSE area_size || '-ft.-radius ' || area_shape
+    END
+    WHERE area_shape IS NOT NULL;
+
+UPDATE Spells SET casting_time = 0 WHERE casting_time_unit <> 'round';
+-- The old schema required every spell to scale with level.
+UPDATE Spells SET duration_per_level = 1 WHERE duration_per_level = 0;
+
+ALTER TABLE Spells
+    DROP CONSTRAINT spells_casting_time_check,
+    DROP CONSTRAINT spells_duration_per_level_check,
+    ALTER COLUMN casting_time TYPE BIGINT,
+    ALTER COLUMN duration_per_level TYPE BIGINT,
+    ADD CHECK (casting_time >= 0),
+    ADD CHECK (duration_per_level > 0),
+    DROP COLUMN casting_time_unit,
+    DROP COLUMN area_shape,
+    DROP COLUMN area_size,
+    DROP COLUMN target_kind,
+    DROP COLUMN target_count,
+    DROP COLUMN targets_per_level,
+    DROP COLUMN duration_unit,
+    DROP COLUMN duration,
+    DROP COLUMN concentration,
+    DROP COLUMN dismissible;
+
+DROP TYPE target_kind;
+DROP TYPE area_shape;
+DROP TYPE duration_unit;
+DROP TYPE time_unit;
@@ -0,0 +1,78 @@
+CREATE TYPE time_unit AS ENUM (
+    'free',
+    'immediate',
+    'swift',
+    'move',
+    'standard',
+    'full_round',
+    'round',
+    'minute',
+    'hour',
+    'day'
+);
+
+CREATE TYPE duration_unit AS ENUM (
+    'instantaneous',
+    'round',
+    'minute',
+    'hour',
+    'day',
+    'permanent'
+);
+
+CREATE TYPE area_shape AS ENUM (
+    'burst',
+    'emanation',
+    'spread',
+    'cone',
+    'cylinder',
+    'line',
+    'cube'
+);
+
+CREATE TYPE target_kind AS ENUM (
+    'you',
+    'creature',
+    'object',
+    'creature_or_object'
+);
+
+-- Casting times and durations used to be a bare number of rounds. A casting
+-- time of 0 rounds was a standard action.
+ALTER TABLE Spells
+    ADD COLUMN casting_time_unit  time_unit       NOT NULL DEFAULT 'standard',
+    ADD COLUMN area_shape         area_shape,
+    ADD COLUMN area_size          SMALLINT        CHECK (area_size > 0),
+    ADD COLUMN target_kind        target_kind,
+    ADD COLUMN target_count       SMALLINT        NOT NULL DEFAULT 1 CHECK (target_count >= 0),
+    ADD COLUMN targets_per_level  SMALLINT        NOT NULL DEFAULT 0 CHECK (targets_per_level >= 0),
+    ADD COLUMN duration_unit      duration_unit   NOT NULL DEFAULT 'round',
+    ADD COLUMN duration           SMALLINT        NOT NULL DEFAULT 0 CHECK (duration >= 0),
+    ADD COLUMN concentration      BOOLEAN         NOT NULL DEFAULT false,
+    ADD COLUMN dismissible        BOOLEAN         NOT NULL DEFAULT false,
+    ADD CHECK ((area_shape IS NULL) = (area_size IS NULL));
+
+-- Areas written the way they're rendered, e.g. "20-ft.-radius burst", are
+-- moved across. Any other area text is kept at the end of the description.
+UPDATE Spells SET
+    area_shape = parsed.matched[2]::area_shape,
+    area_size = parsed.matched[1]::SMALLINT
+FROM (
+    SELECT id, regexp_match(lower(area), '^\s*([1-9]\d{0,3}) ?-?(?:ft\.?|feet|foot)-? ?(?:radius ?-?)?(burst|emanation|spread|cylinder|cone|line|cube)\s*$') AS matched
+    FROM Spells
+) AS parsed
+WHERE Spells.id = parsed.id AND parsed.matched IS NOT NULL;
+UPDATE Spells SET description = description || E'\n\nArea: ' || area
+    WHERE area <> '' AND area_shape IS NULL;
+
+UPDATE Spells SET casting_time_unit = 'round' WHERE casting_time > 0;
+UPDATE Spells SET casting_time = 1 WHERE casting_time = 0;
+
+ALTER TABLE Spells
+    DROP CONSTRAINT spells_casting_time_check,
+    DROP CONSTRAINT spells_duration_per_level_check,
+    ALTER COLUMN casting_time TYPE SMALLINT,
+    ALTER COLUMN duration_per_level TYPE SMALLINT,
+    ADD CHECK (casting_time > 0),
+    ADD CHECK (duration_per_level >= 0),
+    DROP COLUMN area;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE ChargedItems;
+DROP TABLE Consumables;
+DROP TYPE consumable_kind;
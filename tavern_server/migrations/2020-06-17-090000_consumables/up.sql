@@ -0,0 +1,24 @@
+CREATE TYPE consumable_kind AS ENUM (
+    'potion',
+    'scroll',
+    'wand'
+);
+
+CREATE TABLE Consumables (
+    id              UUID            PRIMARY KEY REFERENCES Items(id),
+    kind            consumable_kind NOT NULL,
+    spell_id        UUID            REFERENCES Spells(id) NOT NULL,
+    caster_level    SMALLINT        NOT NULL CHECK (caster_level > 0),
+    charges         SMALLINT        NOT NULL CHECK (charges > 0 AND charges <= 50)
+);
+
+-- The charges left on each individual wand a character carries. A wand is
+-- only given a row here once it has been used.
+CREATE TABLE ChargedItems (
+    id              UUID        PRIMARY KEY,
+    bag_id          UUID        REFERENCES Bags(id) ON DELETE CASCADE NOT NULL,
+    item_id         UUID        REFERENCES Items(id) NOT NULL,
+    charges         SMALLINT    NOT NULL CHECK (charges >= 0)
+);
+
+CREATE INDEX chargeditems_bag_item ON ChargedItems (bag_id, item_id);
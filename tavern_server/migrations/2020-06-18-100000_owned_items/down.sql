@@ -0,0 +1,79 @@
+-- This file should undo anything in `up.sql`
+CREATE OR REPLACE FUNCTION validate_character_equipment()
+    RETURNS trigger AS
+$$
+DECLARE
+    slot equipment_slot;
+    row RECORD;
+BEGIN
+    SELECT Items.equip_slot INTO slot FROM Items WHERE Items.id = NEW.item_id LIMIT 1;
+    IF slot IS NULL THEN
+        RAISE EXCEPTION 'Item does not have equipment slot %', item_id
+            USING HINT = 'Use an item with an equipment slot',
+                  ERRCODE = 'check_violation',
+                  COLUMN = 'item_id',
+                  TABLE = 'CharacterEquipment',
+                  CONSTRAINT = 'character_equipment_has_slot';
+    END IF;
+
+    FOR row IN
+        SELECT Items.equip_slot AS equip FROM Items INNER JOIN CharacterEquipment ON CharacterEquipment.item_id = Items.id
+            WHERE CharacterEquipment.char_id = NEW.char_id
+    LOOP
+        IF row.equip == slot THEN
+            RAISE EXCEPTION 'Character % already has an item equipped in %', NEW.char_id, slot
+                USING HINT = 'Remove the existing equipment in that slot first',
+                    ERRCODE = 'check_violation',
+                    COLUMN = 'item_id',
+                    TABLE = 'CharacterEquipment',
+                    CONSTRAINT = 'character_equipment_unique_slot';
+        END IF;
+    END LOOP;
+    RETURN NEW;
+END
+$$
+LANGUAGE plpgsql;
+
+DROP INDEX owneditems_item_id;
+
+ALTER TABLE CharacterEquipment ADD COLUMN item_id UUID REFERENCES Items(id);
+UPDATE CharacterEquipment SET item_id = OwnedItems.item_id
+    FROM OwnedItems WHERE OwnedItems.id = CharacterEquipment.owned_item_id;
+ALTER TABLE CharacterEquipment DROP CONSTRAINT characterequipment_pkey;
+ALTER TABLE CharacterEquipment DROP COLUMN owned_item_id;
+ALTER TABLE CharacterEquipment ALTER COLUMN item_id SET NOT NULL;
+ALTER TABLE CharacterEquipment ADD PRIMARY KEY (char_id, item_id);
+
+CREATE TABLE ChargedItems (
+    id              UUID        PRIMARY KEY,
+    bag_id          UUID        REFERENCES Bags(id) ON DELETE CASCADE NOT NULL,
+    item_id         UUID        REFERENCES Items(id) NOT NULL,
+    charges         SMALLINT    NOT NULL CHECK (charges >= 0)
+);
+
+CREATE INDEX chargeditems_bag_item ON ChargedItems (bag_id, item_id);
+
+INSERT INTO ChargedItems (id, bag_id, item_id, charges)
+    SELECT OwnedItems.id, ItemsInBags.bag_id, OwnedItems.item_id, OwnedItems.charges
+    FROM ItemsInBags INNER JOIN OwnedItems ON OwnedItems.id = ItemsInBags.owned_item_id
+    WHERE OwnedItems.charges IS NOT NULL;
+
+-- Owned items in the same bag are merged back into a single count.
+CREATE TABLE CatalogItemsInBags (
+    item_id     UUID    REFERENCES Items(id) NOT NULL,
+    bag_id      UUID    REFERENCES Bags(id) NOT NULL,
+    count       INT     NOT NULL CHECK (count > 0),
+    PRIMARY KEY(bag_id, item_id)
+);
+INSERT INTO CatalogItemsInBags (item_id, bag_id, count)
+    SELECT OwnedItems.item_id, ItemsInBags.bag_id, SUM(ItemsInBags.count)
+    FROM ItemsInBags INNER JOIN OwnedItems ON OwnedItems.id = ItemsInBags.owned_item_id
+    GROUP BY OwnedItems.item_id, ItemsInBags.bag_id;
+DROP TABLE ItemsInBags;
+ALTER TABLE CatalogItemsInBags RENAME TO ItemsInBags;
+CREATE INDEX item_in_bag_bag_id ON ItemsInBags (bag_id);
+
+DROP TABLE OwnedItemAbilities;
+DROP TABLE OwnedItems;
+DROP TABLE SpecialAbilities;
+ALTER TABLE Items DROP COLUMN thickness;
@@ -0,0 +1,114 @@
+CREATE TABLE SpecialAbilities (
+    id              UUID    PRIMARY KEY,
+    name            TEXT    UNIQUE NOT NULL,
+    description     TEXT    NOT NULL
+);
+
+-- How thick an object is, in inches. Together with the hit points per inch of
+-- its material, this gives an object its hit points.
+ALTER TABLE Items ADD COLUMN thickness DOUBLE PRECISION CHECK (thickness > 0);
+
+-- A particular copy of a catalog item that a character owns. Copies nobody
+-- has done anything to are kept together in one stack, while a copy that has
+-- been named, damaged, enchanted or used gets a row of its own.
+CREATE TABLE OwnedItems (
+    id              UUID        PRIMARY KEY,
+    item_id         UUID        REFERENCES Items(id) NOT NULL,
+    name            TEXT,
+    notes           TEXT,
+    -- NULL while the item is undamaged.
+    hit_points      INT         CHECK (hit_points >= 0),
+    enhancement     SMALLINT    NOT NULL DEFAULT 0 CHECK (enhancement >= 0 AND enhancement <= 5),
+    -- NULL until a charged item is first used.
+    charges         SMALLINT    CHECK (charges >= 0)
+);
+
+CREATE TABLE OwnedItemAbilities (
+    owned_item_id   UUID    REFERENCES OwnedItems(id) ON DELETE CASCADE NOT NULL,
+    ability_id      UUID    REFERENCES SpecialAbilities(id) NOT NULL,
+    PRIMARY KEY(owned_item_id, ability_id)
+);
+
+-- Every stack of items already in a bag becomes an owned item.
+ALTER TABLE ItemsInBags DROP CONSTRAINT itemsinbags_pkey;
+ALTER TABLE ItemsInBags ADD COLUMN owned_item_id UUID;
+UPDATE ItemsInBags SET owned_item_id = md5(random()::text || clock_timestamp()::text || item_id::text)::uuid;
+INSERT INTO OwnedItems (id, item_id)
+    SELECT owned_item_id, item_id FROM ItemsInBags;
+
+-- Wands that have been used are split off their stacks, keeping their
+-- charges.
+UPDATE ItemsInBags SET count = ItemsInBags.count - used.count
+    FROM (SELECT bag_id, item_id, COUNT(*) AS count FROM ChargedItems GROUP BY bag_id, item_id) AS used
+    WHERE ItemsInBags.bag_id = used.bag_id AND ItemsInBags.item_id = used.item_id
+        AND ItemsInBags.count > used.count;
+DELETE FROM ItemsInBags
+    USING (SELECT bag_id, item_id, COUNT(*) AS count FROM ChargedItems GROUP BY bag_id, item_id) AS used
+    WHERE ItemsInBags.bag_id = used.bag_id AND ItemsInBags.item_id = used.item_id
+        AND ItemsInBags.count <= used.count;
+DELETE FROM OwnedItems
+    WHERE id NOT IN (SELECT owned_item_id FROM ItemsInBags);
+INSERT INTO OwnedItems (id, item_id, charges)
+    SELECT id, item_id, charges FROM ChargedItems;
+INSERT INTO ItemsInBags (item_id, bag_id, count, owned_item_id)
+    SELECT item_id, bag_id, 1, id FROM ChargedItems;
+DROP TABLE ChargedItems;
+
+ALTER TABLE ItemsInBags DROP COLUMN item_id;
+ALTER TABLE ItemsInBags ALTER COLUMN owned_item_id SET NOT NULL;
+ALTER TABLE ItemsInBags ADD PRIMARY KEY (owned_item_id);
+ALTER TABLE ItemsInBags ADD FOREIGN KEY (owned_item_id) REFERENCES OwnedItems(id) ON DELETE CASCADE;
+
+-- Likewise for every item a character has equipped.
+ALTER TABLE CharacterEquipment DROP CONSTRAINT characterequipment_pkey;
+ALTER TABLE CharacterEquipment ADD COLUMN owned_item_id UUID;
+UPDATE CharacterEquipment SET owned_item_id = md5(random()::text || clock_timestamp()::text || item_id::text)::uuid;
+INSERT INTO OwnedItems (id, item_id)
+    SELECT owned_item_id, item_id FROM CharacterEquipment;
+ALTER TABLE CharacterEquipment DROP COLUMN item_id;
+ALTER TABLE CharacterEquipment ALTER COLUMN owned_item_id SET NOT NULL;
+ALTER TABLE CharacterEquipment ADD PRIMARY KEY (char_id, owned_item_id);
+ALTER TABLE CharacterEquipment ADD FOREIGN KEY (owned_item_id) REFERENCES OwnedItems(id) ON DELETE CASCADE;
+
+CREATE INDEX owneditems_item_id ON OwnedItems (item_id);
+
+-- The equipment slot now has to be looked up through the owned item.
+CREATE OR REPLACE FUNCTION validate_character_equipment()
+    RETURNS trigger AS
+$$
+DECLARE
+    slot equipment_slot;
+    row RECORD;
+BEGIN
+    SELECT Items.equip_slot INTO slot FROM Items
+        INNER JOIN OwnedItems ON OwnedItems.item_id = Items.id
+        WHERE OwnedItems.id = NEW.owned_item_id LIMIT 1;
+    IF slot IS NULL THEN
+        RAISE EXCEPTION 'Item does not have equipment slot %', NEW.owned_item_id
+            USING HINT = 'Use an item with an equipment slot',
+                  ERRCODE = 'check_violation',
+                  COLUMN = 'owned_item_id',
+                  TABLE = 'CharacterEquipment',
+                  CONSTRAINT = 'character_equipment_has_slot';
+    END IF;
+
+    FOR row IN
+        SELECT Items.equip_slot AS equip FROM Items
+            INNER JOIN OwnedItems ON OwnedItems.item_id = Items.id
+            INNER JOIN CharacterEquipment ON CharacterEquipment.owned_item_id = OwnedItems.id
+            WHERE CharacterEquipment.char_id = NEW.char_id
+                AND CharacterEquipment.owned_item_id <> NEW.owned_item_id
+    LOOP
+        IF row.equip = slot THEN
+            RAISE EXCEPTION 'Character % already has an item equipped in %', NEW.char_id, slot
+                USING HINT = 'Remove the existing equipment in that slot first',
+                    ERRCODE = 'check_violation',
+                    COLUMN = 'owned_item_id',
+                    TABLE = 'CharacterEquipment',
+                    CONSTRAINT = 'character_equipment_unique_slot';
+        END IF;
+    END LOOP;
+    RETURN NEW;
+END
+$$
+LANGUAGE plpgsql;
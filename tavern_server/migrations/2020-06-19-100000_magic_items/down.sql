@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE OwnedItems
+    DROP COLUMN masterwork,
+    DROP COLUMN material_id;
+DROP TABLE SpecialAbilityEffects;
+ALTER TABLE SpecialAbilities
+    DROP CONSTRAINT special_ability_has_cost,
+    DROP COLUMN flat_cost,
+    DROP COLUMN bonus_equivalent,
+    DROP COLUMN applies_to;
+DROP TYPE ability_target;
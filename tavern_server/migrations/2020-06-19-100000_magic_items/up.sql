@@ -0,0 +1,27 @@
+CREATE TYPE ability_target AS ENUM (
+    'weapon',
+    'armor'
+);
+
+-- A special ability either counts as some amount of enhancement bonus when
+-- pricing the item, or adds a flat cost (in copper) to it.
+ALTER TABLE SpecialAbilities
+    ADD COLUMN applies_to       ability_target  NOT NULL DEFAULT 'weapon',
+    ADD COLUMN bonus_equivalent SMALLINT        CHECK (bonus_equivalent > 0 AND bonus_equivalent <= 5),
+    ADD COLUMN flat_cost        INT             CHECK (flat_cost >= 0);
+UPDATE SpecialAbilities SET flat_cost = 0;
+ALTER TABLE SpecialAbilities
+    ALTER COLUMN applies_to DROP DEFAULT,
+    ADD CONSTRAINT special_ability_has_cost CHECK ((bonus_equivalent IS NULL) <> (flat_cost IS NULL));
+
+CREATE TABLE SpecialAbilityEffects (
+    ability_id  UUID    REFERENCES SpecialAbilities(id) ON DELETE CASCADE NOT NULL,
+    effect_id   UUID    REFERENCES Effects(id) NOT NULL,
+    PRIMARY KEY(ability_id, effect_id)
+);
+
+-- Crafted items can be made of a different material to the catalog item they
+-- are based on.
+ALTER TABLE OwnedItems
+    ADD COLUMN material_id  UUID    REFERENCES Materials(id),
+    ADD COLUMN masterwork   BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE Materials
+    DROP COLUMN weight_multiplier,
+    DROP COLUMN cost_multiplier,
+    DROP COLUMN cost_per_pound,
+    DROP COLUMN weapon_cost,
+    DROP COLUMN light_armor_cost,
+    DROP COLUMN medium_armor_cost,
+    DROP COLUMN heavy_armor_cost,
+    DROP COLUMN armor_category_shift,
+    DROP COLUMN max_dex_bonus,
+    DROP COLUMN check_penalty_reduction,
+    DROP COLUMN spell_failure_reduction;
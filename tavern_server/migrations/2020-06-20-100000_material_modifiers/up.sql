@@ -0,0 +1,16 @@
+-- How a special material changes the items made from it. Costs are in copper;
+-- `cost_per_pound` is charged for every pound the item weighs before the
+-- material is taken into account, and the armor costs depend on the armor's
+-- category before it is shifted.
+ALTER TABLE Materials
+    ADD COLUMN weight_multiplier        FLOAT8      NOT NULL DEFAULT 1 CHECK (weight_multiplier > 0),
+    ADD COLUMN cost_multiplier          FLOAT8      NOT NULL DEFAULT 1 CHECK (cost_multiplier > 0),
+    ADD COLUMN cost_per_pound           INT         NOT NULL DEFAULT 0 CHECK (cost_per_pound >= 0),
+    ADD COLUMN weapon_cost              INT         NOT NULL DEFAULT 0 CHECK (weapon_cost >= 0),
+    ADD COLUMN light_armor_cost         INT         NOT NULL DEFAULT 0 CHECK (light_armor_cost >= 0),
+    ADD COLUMN medium_armor_cost        INT         NOT NULL DEFAULT 0 CHECK (medium_armor_cost >= 0),
+    ADD COLUMN heavy_armor_cost         INT         NOT NULL DEFAULT 0 CHECK (heavy_armor_cost >= 0),
+    ADD COLUMN armor_category_shift     SMALLINT    NOT NULL DEFAULT 0 CHECK (armor_category_shift >= -2 AND armor_category_shift <= 2),
+    ADD COLUMN max_dex_bonus            SMALLINT    NOT NULL DEFAULT 0,
+    ADD COLUMN check_penalty_reduction  SMALLINT    NOT NULL DEFAULT 0 CHECK (check_penalty_reduction >= 0),
+    ADD COLUMN spell_failure_reduction  SMALLINT    NOT NULL DEFAULT 0 CHECK (spell_failure_reduction >= 0);
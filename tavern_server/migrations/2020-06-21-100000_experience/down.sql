@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE Characters
+    DROP COLUMN xp;
+ALTER TABLE Campaigns
+    DROP COLUMN advancement;
+DROP TYPE advancement_track;
@@ -0,0 +1,12 @@
+CREATE TYPE advancement_track AS ENUM (
+    'slow',
+    'medium',
+    'fast'
+);
+
+-- The experience table a campaign uses to decide when its characters level up.
+ALTER TABLE Campaigns
+    ADD COLUMN advancement  advancement_track   NOT NULL DEFAULT 'medium';
+
+ALTER TABLE Characters
+    ADD COLUMN xp           INT                 NOT NULL DEFAULT 0 CHECK (xp >= 0);
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE Combatants
+    DROP COLUMN creature_id;
+DROP TABLE CreatureEffects;
+DROP TABLE CreatureSpells;
+DROP TABLE CreatureFeats;
+DROP TABLE CreatureAbilities;
+DROP TABLE CreatureAttacks;
+DROP TABLE CreatureCombatStats;
+DROP TABLE CreatureAttributes;
+DROP TABLE Creatures;
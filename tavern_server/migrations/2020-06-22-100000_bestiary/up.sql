@@ -0,0 +1,73 @@
+-- Monsters and NPCs for game masters to use in encounters. Challenge ratings
+-- are stored as steps so that they sort and can be searched by range: CR 1
+-- and up are stored as is, and the fractional CRs 1/2, 1/3, 1/4, 1/6 and 1/8
+-- are stored as 0 down to -4.
+CREATE TABLE Creatures (
+    id              UUID        PRIMARY KEY,
+    name            TEXT        NOT NULL,
+    description     TEXT        NOT NULL,
+    cr              SMALLINT    NOT NULL CHECK (cr >= -4 AND cr <= 30),
+    type_id         UUID        REFERENCES RaceTypes(id) NOT NULL,
+    subtype_id      UUID        REFERENCES RaceSubtypes(id),
+    size            Size        NOT NULL,
+    alignment       alignment   NOT NULL,
+    hit_dice        SMALLINT    NOT NULL CHECK (hit_dice > 0),
+    hit_points      SMALLINT    NOT NULL CHECK (hit_points > 0),
+    -- What overcomes the creature's damage reduction, such as "magic" or
+    -- "cold iron". The amount is kept with its other combat stats.
+    dr_bypassed_by  TEXT
+);
+
+CREATE INDEX creatures_by_cr ON Creatures (cr);
+
+CREATE TABLE CreatureAttributes (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    attr            attribute   NOT NULL,
+    score           SMALLINT    NOT NULL CHECK (score >= 0),
+    PRIMARY KEY (creature_id, attr)
+);
+
+CREATE TABLE CreatureCombatStats (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    stat            combat_stat NOT NULL,
+    value           SMALLINT    NOT NULL,
+    PRIMARY KEY (creature_id, stat)
+);
+
+CREATE TABLE CreatureAttacks (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    name            TEXT        NOT NULL,
+    count           SMALLINT    NOT NULL CHECK (count > 0),
+    attack_bonus    SMALLINT    NOT NULL,
+    damage          TEXT        NOT NULL,
+    damage_type     damage_type NOT NULL,
+    PRIMARY KEY (creature_id, name)
+);
+
+CREATE TABLE CreatureAbilities (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    name            TEXT        NOT NULL,
+    description     TEXT        NOT NULL,
+    PRIMARY KEY (creature_id, name)
+);
+
+CREATE TABLE CreatureFeats (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    feat_id         UUID        REFERENCES Feats(id) NOT NULL,
+    PRIMARY KEY (creature_id, feat_id)
+);
+
+CREATE TABLE CreatureSpells (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    spell_id        UUID        REFERENCES Spells(id) NOT NULL,
+    PRIMARY KEY (creature_id, spell_id)
+);
+
+CREATE TABLE CreatureEffects (
+    creature_id     UUID        REFERENCES Creatures(id) ON DELETE CASCADE NOT NULL,
+    effect_id       UUID        REFERENCES Effects(id) NOT NULL,
+    PRIMARY KEY (creature_id, effect_id)
+);
+
+ALTER TABLE Combatants
+    ADD COLUMN creature_id  UUID    REFERENCES Creatures(id);
@@ -0,0 +1,15 @@
+CREATE TABLE StarterKits (
+    id              UUID        PRIMARY KEY,
+    class_id        UUID        REFERENCES Classes(id) ON DELETE CASCADE NOT NULL,
+    name            TEXT        NOT NULL,
+    description     TEXT        NOT NULL
+);
+
+CREATE INDEX starter_kits_class ON StarterKits(class_id);
+
+CREATE TABLE StarterKitItems (
+    kit_id          UUID        REFERENCES StarterKits(id) ON DELETE CASCADE NOT NULL,
+    item_id         UUID        REFERENCES Items(id) ON DELETE CASCADE NOT NULL,
+    count           INT         NOT NULL CHECK (count > 0),
+    PRIMARY KEY (kit_id, item_id)
+);
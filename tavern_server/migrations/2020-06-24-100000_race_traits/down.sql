@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE FavoredClassBonuses;
+DROP TABLE FavoredClassOptions;
+DROP TABLE CharacterRaceTraits;
+DROP TABLE RaceTraitEffects;
+DROP TABLE RaceTraitReplacements;
+DROP TABLE RaceTraits;
+ALTER TABLE Characters
+    DROP COLUMN favored_class_id,
+    DROP COLUMN racial_bonus;
+ALTER TABLE Races
+    DROP COLUMN flexible_bonus;
+DROP TYPE favored_class_bonus;
@@ -0,0 +1,65 @@
+CREATE TYPE favored_class_bonus AS ENUM (
+    'hit_point',
+    'skill_rank',
+    'class_option'
+);
+
+-- Races such as humans give a bonus to one ability of the player's choice
+-- instead of fixed ability modifiers.
+ALTER TABLE Races
+    ADD COLUMN flexible_bonus   SMALLINT    NOT NULL DEFAULT 0 CHECK (flexible_bonus >= 0);
+
+ALTER TABLE Characters
+    ADD COLUMN racial_bonus     attribute,
+    ADD COLUMN favored_class_id UUID        REFERENCES Classes(id) ON DELETE SET NULL;
+
+-- Standard racial traits, and the alternate traits that can be taken in
+-- place of them.
+CREATE TABLE RaceTraits (
+    id              UUID        PRIMARY KEY,
+    race_id         UUID        REFERENCES Races(id) ON DELETE CASCADE NOT NULL,
+    name            TEXT        NOT NULL,
+    description     TEXT        NOT NULL,
+    alternate       BOOLEAN     NOT NULL DEFAULT false
+);
+
+CREATE INDEX race_traits_race ON RaceTraits(race_id);
+
+CREATE TABLE RaceTraitReplacements (
+    trait_id        UUID        REFERENCES RaceTraits(id) ON DELETE CASCADE NOT NULL,
+    replaced_id     UUID        REFERENCES RaceTraits(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (trait_id, replaced_id),
+    CHECK (trait_id <> replaced_id)
+);
+
+CREATE TABLE RaceTraitEffects (
+    trait_id        UUID        REFERENCES RaceTraits(id) ON DELETE CASCADE NOT NULL,
+    effect_id       UUID        REFERENCES Effects(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (trait_id, effect_id)
+);
+
+CREATE TABLE CharacterRaceTraits (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    trait_id        UUID        REFERENCES RaceTraits(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (char_id, trait_id)
+);
+
+-- The bonuses a race can take in place of a hit point or skill rank when it
+-- levels up in its favored class.
+CREATE TABLE FavoredClassOptions (
+    id              UUID        PRIMARY KEY,
+    race_id         UUID        REFERENCES Races(id) ON DELETE CASCADE NOT NULL,
+    class_id        UUID        REFERENCES Classes(id) ON DELETE CASCADE NOT NULL,
+    description     TEXT        NOT NULL
+);
+
+CREATE INDEX favored_class_options_race_class ON FavoredClassOptions(race_id, class_id);
+
+CREATE TABLE FavoredClassBonuses (
+    char_id         UUID                REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    level           SMALLINT            NOT NULL CHECK (level > 0),
+    bonus           favored_class_bonus NOT NULL,
+    option_id       UUID                REFERENCES FavoredClassOptions(id) ON DELETE CASCADE,
+    PRIMARY KEY (char_id, level),
+    CHECK ((bonus = 'class_option') = (option_id IS NOT NULL))
+);
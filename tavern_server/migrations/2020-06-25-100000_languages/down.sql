@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CharacterSkillRanks;
+DROP TRIGGER race_languages_in_catalog ON Races;
+DROP FUNCTION add_race_languages;
+DROP TABLE CharacterLanguages;
+DROP TABLE RaceBonusLanguages;
+DROP TABLE Languages;
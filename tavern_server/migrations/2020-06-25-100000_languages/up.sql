@@ -0,0 +1,51 @@
+CREATE TABLE Languages (
+    id              UUID        PRIMARY KEY,
+    name            TEXT        UNIQUE NOT NULL,
+    description     TEXT        NOT NULL
+);
+
+-- The languages a race may pick as bonus languages for a high Intelligence.
+CREATE TABLE RaceBonusLanguages (
+    race_id         UUID        REFERENCES Races(id) ON DELETE CASCADE NOT NULL,
+    language_id     UUID        REFERENCES Languages(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (race_id, language_id)
+);
+
+-- The bonus languages a character chose. The automatic languages of their
+-- race aren't stored, as they follow from the race.
+CREATE TABLE CharacterLanguages (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    language_id     UUID        REFERENCES Languages(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (char_id, language_id)
+);
+
+-- Races list their automatic languages by name. This keeps each of them in
+-- the catalog whenever a race is saved, naming new entries after a hash of
+-- the language so that the same language always gets the same ID.
+CREATE FUNCTION add_race_languages()
+    RETURNS trigger AS
+$$
+BEGIN
+    INSERT INTO Languages (id, name, description)
+        SELECT DISTINCT md5('language:' || language)::uuid, language, ''
+        FROM unnest(NEW.languages) AS language
+        ON CONFLICT (name) DO NOTHING;
+    RETURN NEW;
+END
+$$
+LANGUAGE plpgsql;
+
+CREATE TRIGGER race_languages_in_catalog AFTER INSERT OR UPDATE OF languages ON Races
+    FOR EACH ROW EXECUTE FUNCTION add_race_languages();
+
+INSERT INTO Languages (id, name, description)
+    SELECT DISTINCT md5('language:' || language)::uuid, language, ''
+    FROM Races, unnest(Races.languages) AS language
+    ON CONFLICT (name) DO NOTHING;
+
+CREATE TABLE CharacterSkillRanks (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    skill           skill       NOT NULL,
+    ranks           SMALLINT    NOT NULL CHECK (ranks > 0),
+    PRIMARY KEY (char_id, skill)
+);
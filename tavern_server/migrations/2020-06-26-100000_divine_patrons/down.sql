@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CharacterDomainSpells;
+DROP TABLE CharacterDomains;
+ALTER TABLE Subclasses
+    DROP COLUMN domains,
+    DROP COLUMN divine_patron;
+ALTER TABLE Deities
+    DROP COLUMN alignment;
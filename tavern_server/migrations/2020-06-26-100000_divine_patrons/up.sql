@@ -0,0 +1,26 @@
+ALTER TABLE Deities
+    ADD COLUMN alignment        alignment   NOT NULL DEFAULT 'true_neutral';
+
+-- Classes such as the cleric draw their power from a deity: their alignment
+-- must be close to it, they are proficient with its favored weapons and they
+-- pick domains from its list.
+ALTER TABLE Subclasses
+    ADD COLUMN divine_patron    BOOLEAN     NOT NULL DEFAULT false,
+    ADD COLUMN domains          SMALLINT    NOT NULL DEFAULT 0 CHECK (domains >= 0);
+
+CREATE TABLE CharacterDomains (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    domain_id       UUID        REFERENCES Domains(id) ON DELETE CASCADE NOT NULL,
+    subdomain_id    UUID        REFERENCES Subdomains(id) ON DELETE SET NULL,
+    PRIMARY KEY (char_id, domain_id)
+);
+
+-- The spells a character knows because one of their domains granted them, so
+-- that dropping the domain only takes away those and not the ones they know
+-- from their class.
+CREATE TABLE CharacterDomainSpells (
+    char_id         UUID        NOT NULL,
+    spell_id        UUID        NOT NULL,
+    PRIMARY KEY (char_id, spell_id),
+    FOREIGN KEY (char_id, spell_id) REFERENCES CharacterSpells(char_id, spell_id) ON DELETE CASCADE
+);
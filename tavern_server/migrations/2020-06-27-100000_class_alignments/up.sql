@@ -0,0 +1,4 @@
+-- The alignments a class can be taken with, e.g. only lawful good for the
+-- paladin. An empty list allows any alignment.
+ALTER TABLE Classes
+    ADD COLUMN alignments       alignment[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CharacterTraits;
+DROP TABLE TraitEffects;
+DROP TABLE Traits;
+DROP TYPE trait_category;
@@ -0,0 +1,30 @@
+CREATE TYPE trait_category AS ENUM (
+    'combat',
+    'faith',
+    'magic',
+    'social',
+    'regional',
+    'race',
+    'drawback'
+);
+
+-- Character traits, which are weaker than feats. Drawbacks are traits with a
+-- downside that let the character take an extra trait.
+CREATE TABLE Traits (
+    id              UUID            PRIMARY KEY,
+    name            TEXT            UNIQUE NOT NULL,
+    description     TEXT            NOT NULL,
+    category        trait_category  NOT NULL
+);
+
+CREATE TABLE TraitEffects (
+    trait_id        UUID        REFERENCES Traits(id) ON DELETE CASCADE NOT NULL,
+    effect_id       UUID        REFERENCES Effects(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (trait_id, effect_id)
+);
+
+CREATE TABLE CharacterTraits (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    trait_id        UUID        REFERENCES Traits(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (char_id, trait_id)
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CompanionFeats;
+DROP TABLE Companions;
+ALTER TABLE Subclasses
+    DROP COLUMN companion;
+DROP TYPE companion_kind;
@@ -0,0 +1,30 @@
+CREATE TYPE companion_kind AS ENUM (
+    'animal_companion',
+    'familiar',
+    'eidolon'
+);
+
+-- The kind of companion a subclass grants, such as the druid's animal
+-- companion. Levels in these subclasses make up the master's level for the
+-- companion's progression.
+ALTER TABLE Subclasses
+    ADD COLUMN companion        companion_kind;
+
+-- A creature bound to a character. Its base stats come from the bestiary and
+-- improve with its master's level. `ability_increases` lists the ability
+-- raised by each ability score increase the companion has gained, in order.
+CREATE TABLE Companions (
+    id                  UUID            PRIMARY KEY,
+    char_id             UUID            REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    creature_id         UUID            REFERENCES Creatures(id) NOT NULL,
+    kind                companion_kind  NOT NULL,
+    name                TEXT            NOT NULL,
+    ability_increases   attribute[]     NOT NULL DEFAULT '{}',
+    UNIQUE (char_id, kind)
+);
+
+CREATE TABLE CompanionFeats (
+    companion_id    UUID        REFERENCES Companions(id) ON DELETE CASCADE NOT NULL,
+    feat_id         UUID        REFERENCES Feats(id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (companion_id, feat_id)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE CharacterAbilityDamage;
+ALTER TABLE Characters
+    DROP COLUMN negative_levels;
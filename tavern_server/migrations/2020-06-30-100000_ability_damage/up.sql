@@ -0,0 +1,14 @@
+-- Each negative level imposes a -1 penalty on d20 rolls and costs the
+-- character 5 maximum hit points until it is removed.
+ALTER TABLE Characters
+    ADD COLUMN negative_levels  SMALLINT    NOT NULL DEFAULT 0 CHECK (negative_levels >= 0);
+
+-- Ability damage penalises an ability's modifier and heals with rest, while
+-- drain lowers the score itself and has to be restored by magic.
+CREATE TABLE CharacterAbilityDamage (
+    char_id         UUID        REFERENCES Characters(id) ON DELETE CASCADE NOT NULL,
+    attr            attribute   NOT NULL,
+    damage          SMALLINT    NOT NULL DEFAULT 0 CHECK (damage >= 0),
+    drain           SMALLINT    NOT NULL DEFAULT 0 CHECK (drain >= 0),
+    PRIMARY KEY (char_id, attr)
+);
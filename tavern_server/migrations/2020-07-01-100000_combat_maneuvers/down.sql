@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE FeatManeuverBonuses;
+DROP TYPE combat_maneuver;
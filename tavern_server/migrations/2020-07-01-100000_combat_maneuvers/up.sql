@@ -0,0 +1,22 @@
+CREATE TYPE combat_maneuver AS ENUM (
+    'bull_rush',
+    'dirty_trick',
+    'disarm',
+    'drag',
+    'grapple',
+    'overrun',
+    'reposition',
+    'steal',
+    'sunder',
+    'trip'
+);
+
+-- Bonuses feats such as Improved Trip give to the CMB of one maneuver and to
+-- the CMD against it.
+CREATE TABLE FeatManeuverBonuses (
+    feat_id         UUID            REFERENCES Feats(id) ON DELETE CASCADE NOT NULL,
+    maneuver        combat_maneuver NOT NULL,
+    offense         SMALLINT        NOT NULL DEFAULT 0,
+    defense         SMALLINT        NOT NULL DEFAULT 0,
+    PRIMARY KEY (feat_id, maneuver)
+);
@@ -0,0 +1,839 @@
+use crate::auth::{self, User, FIELD_EMAIL, FIELD_USERNAME};
+use crate::db::{self, Connection, Delete, DeleteById, Error as DBError, GetAll, GetById, Insert, TryFromDb, Update};
+use crate::events::{self, Event};
+use crate::pathfinder::active_effect::{self, ActiveEffect};
+use crate::pathfinder::character::{Character, DBCharacter};
+use crate::pathfinder::experience::{self, AdvancementTrack, Experience, ExperienceAward};
+use crate::pathfinder::summary::{Summarize, Summary};
+use crate::pathfinder::treasure;
+use crate::pathfinder::Links;
+use crate::schema::{campaigncharacters, campaigninvitations, campaignmembers, campaigns};
+use crate::status::{self, Success};
+use crate::forms;
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(username: &str) -> Member {
+        Member {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+        }
+    }
+
+    fn campaign(game_master: Member, members: Vec<Member>) -> Campaign {
+        Campaign {
+            id: Uuid::new_v4(),
+            links: Links::new(),
+            name: "Rise of the Runelords".to_string(),
+            description: String::new(),
+            game_master,
+            members: members.into_iter().collect(),
+            characters: BTreeSet::new(),
+            game_time: 0,
+            advancement: AdvancementTrack::default(),
+        }
+    }
+
+    #[test]
+    fn roles_follow_membership() {
+        let gm = member("gm");
+        let player = member("player");
+        let stranger = member("stranger");
+        let campaign = campaign(gm.clone(), vec![player.clone()]);
+
+        assert_eq!(campaign.role_of(&gm.id), Some(CampaignRole::GameMaster));
+        assert_eq!(campaign.role_of(&player.id), Some(CampaignRole::Player));
+        assert_eq!(campaign.role_of(&stranger.id), None);
+    }
+
+    #[test]
+    fn only_outsiders_can_be_invited() {
+        let gm = member("gm");
+        let player = member("player");
+        let stranger = member("stranger");
+        let campaign = campaign(gm.clone(), vec![player.clone()]);
+
+        assert!(!campaign.can_invite(&gm.id));
+        assert!(!campaign.can_invite(&player.id));
+        assert!(campaign.can_invite(&stranger.id));
+    }
+
+    #[test]
+    fn characters_are_in_their_own_party() {
+        let (character, owner) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(shares_party(&character, &character, &[None, None], &[(character, owner)]));
+        assert!(shares_party(&character, &character, &[None, None], &[]));
+    }
+
+    #[test]
+    fn parties_are_shared_through_a_campaign_or_an_owner() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let (owner, other_owner) = (Uuid::new_v4(), Uuid::new_v4());
+        let campaign_id = Some(Uuid::new_v4());
+
+        let apart = [(first, owner), (second, other_owner)];
+        assert!(!shares_party(&first, &second, &[None, None], &apart));
+        assert!(!shares_party(&first, &second, &[campaign_id, None], &apart));
+        assert!(shares_party(&first, &second, &[campaign_id, campaign_id], &apart));
+        assert!(shares_party(&first, &second, &[None, None], &[(first, owner), (second, owner)]));
+    }
+}
+
+/// The expected form field name for the campaign name.
+pub const FIELD_NAME: &str = "name";
+/// The expected form field name for the campaign description.
+pub const FIELD_DESCRIPTION: &str = "description";
+/// The expected form field name for a character being added to a campaign.
+pub const FIELD_CHARACTER_ID: &str = "character-id";
+/// The form field holding the number of rounds to advance the clock by.
+pub const FIELD_ROUNDS: &str = "rounds";
+/// The form field holding the number of minutes to advance the clock by.
+pub const FIELD_MINUTES: &str = "minutes";
+/// The form field holding the number of hours to advance the clock by.
+pub const FIELD_HOURS: &str = "hours";
+/// The form field holding the campaign's advancement track.
+pub const FIELD_ADVANCEMENT: &str = "advancement";
+/// The form field holding an amount of experience to split between characters.
+pub const FIELD_XP: &str = "xp";
+/// The form field holding a JSON list of the characters sharing an award.
+pub const FIELD_CHARACTER_IDS: &str = "character-ids";
+/// The form field holding a JSON object of character IDs to the experience
+/// each one is awarded.
+pub const FIELD_AWARDS: &str = "awards";
+
+/// The number of rounds in a minute of game time.
+pub const ROUNDS_PER_MINUTE: i64 = 10;
+/// The number of rounds in an hour of game time.
+pub const ROUNDS_PER_HOUR: i64 = 60 * ROUNDS_PER_MINUTE;
+
+/// The part a user plays in a campaign.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CampaignRole {
+    GameMaster,
+    Player,
+}
+
+/// A member of a campaign, as shown to other members.
+#[derive(Serialize, Deserialize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct Member {
+    pub id: Uuid,
+    pub username: String,
+}
+
+/// A group of users playing together, run by a single game master.
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct Campaign {
+    id: Uuid,
+    links: Links,
+    name: String,
+    description: String,
+    game_master: Member,
+    members: BTreeSet<Member>,
+    characters: BTreeSet<Summary<Character>>,
+    /// The in-game time, in rounds since the campaign started.
+    game_time: i64,
+    advancement: AdvancementTrack,
+}
+
+impl Campaign {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn game_master(&self) -> &Member {
+        &self.game_master
+    }
+
+    pub fn game_time(&self) -> i64 {
+        self.game_time
+    }
+
+    pub fn advancement(&self) -> AdvancementTrack {
+        self.advancement
+    }
+
+    /// The IDs of every character taking part in this campaign.
+    pub fn character_ids(&self) -> Vec<Uuid> {
+        self.characters.iter()
+            .map(|character| character.id().to_owned())
+            .collect()
+    }
+
+    /// Returns the role the given user has in this campaign, if any.
+    pub fn role_of(&self, user_id: &Uuid) -> Option<CampaignRole> {
+        if &self.game_master.id == user_id {
+            Some(CampaignRole::GameMaster)
+        } else if self.members.iter().any(|member| &member.id == user_id) {
+            Some(CampaignRole::Player)
+        } else {
+            None
+        }
+    }
+
+    /// Only users that aren't already part of the campaign, as its game
+    /// master or a player, can be invited to it.
+    pub fn can_invite(&self, user_id: &Uuid) -> bool {
+        self.role_of(user_id).is_none()
+    }
+}
+
+impl TryFromDb for Campaign {
+    type DBType = DBCampaign;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let game_master = member_by_id(&other.gm_id, conn)?;
+        let members = other.get_members(conn)?;
+        let characters = other.get_characters(conn)?;
+        let mut links = Links::new();
+        links.insert("self".to_string(), format!("/campaigns/{}", other.id));
+        links.insert("characters".to_string(), format!("/campaigns/{}/characters", other.id));
+
+        let campaign = Campaign {
+            id: other.id,
+            links,
+            name: other.name,
+            description: other.description,
+            game_master,
+            members,
+            characters,
+            game_time: other.game_time,
+            advancement: other.advancement,
+        };
+
+        Ok(campaign)
+    }
+}
+
+impl From<Campaign> for Bytes {
+    fn from(campaign: Campaign) -> Self {
+        status::serialize_to_bytes(&campaign)
+    }
+}
+
+/// The result of moving the campaign clock forward.
+#[derive(Serialize, Clone, Debug)]
+pub struct TimeAdvanced {
+    pub game_time: i64,
+    pub expired: Vec<ActiveEffect>,
+}
+
+impl From<TimeAdvanced> for Bytes {
+    fn from(advanced: TimeAdvanced) -> Self {
+        status::serialize_to_bytes(&advanced)
+    }
+}
+
+/// The experience handed out to a campaign's characters.
+#[derive(Serialize, Clone, Debug)]
+pub struct ExperienceAwarded {
+    pub awards: Vec<ExperienceAward>,
+}
+
+impl From<ExperienceAwarded> for Bytes {
+    fn from(awarded: ExperienceAwarded) -> Self {
+        status::serialize_to_bytes(&awarded)
+    }
+}
+
+/// The list of campaigns a user is taking part in.
+#[derive(Serialize, Clone, Debug)]
+pub struct CampaignList {
+    pub campaigns: Vec<Summary<Campaign>>,
+}
+
+impl From<CampaignList> for Bytes {
+    fn from(list: CampaignList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+/// The characters in a campaign, as visible to a specific user. The game
+/// master sees every character in full; players see their own characters in
+/// full and a summary of everyone else's.
+#[derive(Serialize, Clone, Debug)]
+pub struct Party {
+    pub role: CampaignRole,
+    pub characters: Vec<Character>,
+    pub party: Vec<Summary<Character>>,
+}
+
+impl From<Party> for Bytes {
+    fn from(party: Party) -> Self {
+        status::serialize_to_bytes(&party)
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "campaigns"]
+pub struct DBCampaign {
+    id: Uuid,
+    name: String,
+    description: String,
+    gm_id: Uuid,
+    game_time: i64,
+    advancement: AdvancementTrack,
+}
+
+impl DBCampaign {
+    fn get_members(&self, conn: &Connection) -> Result<BTreeSet<Member>, DBError> {
+        DBCampaignMember::belonging_to(self)
+            .load::<DBCampaignMember>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|member| member_by_id(&member.user_id, conn))
+            .collect()
+    }
+
+    fn get_characters(&self, conn: &Connection) -> Result<BTreeSet<Summary<Character>>, DBError> {
+        DBCampaignCharacter::belonging_to(self)
+            .load::<DBCampaignCharacter>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|character| Summary::<Character>::db_get_by_id(&character.char_id, conn))
+            .collect()
+    }
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "campaignmembers"]
+#[primary_key(campaign_id, user_id)]
+#[belongs_to(DBCampaign, foreign_key = "campaign_id")]
+pub struct DBCampaignMember {
+    campaign_id: Uuid,
+    user_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "campaigninvitations"]
+#[primary_key(campaign_id, user_id)]
+#[belongs_to(DBCampaign, foreign_key = "campaign_id")]
+pub struct DBCampaignInvitation {
+    campaign_id: Uuid,
+    user_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "campaigncharacters"]
+#[primary_key(campaign_id, char_id)]
+#[belongs_to(DBCampaign, foreign_key = "campaign_id")]
+pub struct DBCampaignCharacter {
+    campaign_id: Uuid,
+    char_id: Uuid,
+}
+
+fn member_by_id(user_id: &Uuid, conn: &Connection) -> Result<Member, DBError> {
+    use crate::schema::users::dsl::*;
+    users.select(username)
+        .filter(id.eq(user_id))
+        .first::<String>(conn)
+        .map(|name| Member { id: user_id.to_owned(), username: name })
+        .map_err(DBError::RunQuery)
+}
+
+/// Returns the campaign the given character is part of, if any.
+pub fn campaign_of_character(character_id: &Uuid, conn: &Connection) -> Result<Option<Uuid>, DBError> {
+    use crate::schema::campaigncharacters::dsl::*;
+    campaigncharacters.select(campaign_id)
+        .filter(char_id.eq(character_id))
+        .first::<Uuid>(conn)
+        .optional()
+        .map_err(DBError::RunQuery)
+}
+
+/// Whether two characters adventure together: they are either the same
+/// character, in the same campaign or belong to the same user.
+pub fn same_party(first: &Uuid, second: &Uuid, conn: &Connection) -> Result<bool, DBError> {
+    if first == second {
+        return Ok(true);
+    }
+    let campaigns = [campaign_of_character(first, conn)?, campaign_of_character(second, conn)?];
+    use crate::schema::characters::dsl::*;
+    let owners = characters.select((id, user_id))
+        .filter(id.eq_any(vec![*first, *second]))
+        .load::<(Uuid, Uuid)>(conn)
+        .map_err(DBError::RunQuery)?;
+    Ok(shares_party(first, second, &campaigns, &owners))
+}
+
+/// Decides `same_party` given the campaigns the two characters are part of
+/// and the `(character, owner)` rows of both.
+fn shares_party(first: &Uuid, second: &Uuid, campaigns: &[Option<Uuid>; 2], owners: &[(Uuid, Uuid)]) -> bool {
+    if first == second || (campaigns[0].is_some() && campaigns[0] == campaigns[1]) {
+        return true;
+    }
+    let owner_of = |character: &Uuid| owners.iter()
+        .find(|(char_id, _)| char_id == character)
+        .map(|(_, owner)| owner);
+    owner_of(first).is_some() && owner_of(first) == owner_of(second)
+}
+
+/// Returns the current in-game time of a campaign, in rounds.
+pub fn game_time(campaign_id: &Uuid, conn: &Connection) -> Result<i64, DBError> {
+    use crate::schema::campaigns::dsl::*;
+    campaigns.select(game_time)
+        .filter(id.eq(campaign_id))
+        .first::<i64>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+/// Moves a campaign's clock forward and removes any effects on its characters
+/// that have run out. Returns the new time and the expired effects.
+pub fn advance_time(campaign: &Campaign, rounds: i64, conn: &Connection) -> Result<(i64, Vec<ActiveEffect>), DBError> {
+    conn.transaction::<_, DBError, _>(|| {
+        let now = {
+            use crate::schema::campaigns::dsl::*;
+            diesel::update(campaigns.filter(id.eq(&campaign.id)))
+                .set(game_time.eq(game_time + rounds))
+                .returning(game_time)
+                .get_result::<i64>(conn)?
+        };
+        let expired = active_effect::expire_effects(&campaign.character_ids(), now, conn)?;
+        Ok((now, expired))
+    })
+}
+
+/// Determines whether the user may view and modify the given character: the
+/// owner of the character always can, as can the game master of the campaign
+/// the character is part of.
+pub fn can_manage_character(user: &User, character: &DBCharacter, conn: &Connection) -> Result<bool, DBError> {
+    let user_id = match &user.id {
+        Some(id) => id,
+        None => return Ok(false),
+    };
+
+    if character.user_id() == user_id {
+        return Ok(true);
+    }
+
+    match campaign_of_character(character.id(), conn)? {
+        None => Ok(false),
+        Some(campaign) => {
+            DBCampaign::db_get_by_id(&campaign, conn)
+                .map(|campaign| &campaign.gm_id == user_id)
+        }
+    }
+}
+
+/// Loads the character with the given ID if the user is allowed to manage it
+/// (see `can_manage_character`), or generates the appropriate Rejection.
+pub(crate) fn managed_character(user: &User, char_id: Uuid, conn: &Connection) -> Result<DBCharacter, Rejection> {
+    let character: DBCharacter = forms::value_by_id(char_id, conn)
+        .map_err(|_| status::not_found())?;
+    if can_manage_character(user, &character, conn)? {
+        Ok(character)
+    } else {
+        Err(status::not_authorized())
+    }
+}
+
+/// Loads the campaign with the given ID along with the user's role in it.
+/// Users that are not part of the campaign are told it does not exist.
+pub(crate) fn campaign_with_role(user: &User, campaign_id: Uuid, conn: &Connection) -> Result<(Campaign, CampaignRole), Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    let campaign: Campaign = forms::value_by_id(campaign_id, conn)
+        .map_err(|_| status::not_found())?;
+    let role = campaign.role_of(&user_id)
+        .ok_or_else(status::not_found)?;
+    Ok((campaign, role))
+}
+
+/// Like `campaign_with_role`, but only allows the game master through.
+pub(crate) fn campaign_as_gm(user: &User, campaign_id: Uuid, conn: &Connection) -> Result<Campaign, Rejection> {
+    match campaign_with_role(user, campaign_id, conn)? {
+        (campaign, CampaignRole::GameMaster) => Ok(campaign),
+        (_, CampaignRole::Player) => Err(status::not_authorized()),
+    }
+}
+
+/// Allows only admins and users running a campaign through, as they are the
+/// ones who add to the shared catalogs.
+pub(crate) fn catalog_editor(user: &User, conn: &Connection) -> Result<(), Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    if user.is_admin {
+        return Ok(());
+    }
+    let runs_campaign = {
+        use crate::schema::campaigns::dsl::*;
+        diesel::select(diesel::dsl::exists(campaigns.filter(gm_id.eq(user_id))))
+            .get_result::<bool>(conn)
+            .map_err(DBError::RunQuery)?
+    };
+    if runs_campaign {
+        Ok(())
+    } else {
+        Err(status::not_authorized())
+    }
+}
+
+fn ok<T: Serialize + nebula_status::StatusData>(data: T) -> Status<Success<T>> {
+    Status::with_data(&StatusCode::OK, Success::new(data))
+}
+
+async fn create_campaign(user: User, conn: Connection, form: Form) -> Result<Status<Success<Campaign>>, Rejection> {
+    let gm_id = user.id.ok_or_else(status::not_authorized)?;
+    let name = forms::get_required_form_text_field(&form, FIELD_NAME)?;
+    let description = forms::get_required_form_text_field(&form, FIELD_DESCRIPTION)?;
+    let advancement = forms::get_optional_form_text_field(&form, FIELD_ADVANCEMENT)?
+        .unwrap_or_default();
+    let db_campaign = DBCampaign {
+        id: Uuid::new_v4(),
+        name,
+        description,
+        gm_id,
+        game_time: 0,
+        advancement,
+    };
+    db_campaign.db_insert(&conn)?;
+    events::memberships_changed(&gm_id, &conn)?;
+    let campaign = Campaign::try_from_db(db_campaign, &conn)?;
+    Ok(ok(campaign))
+}
+
+/// Returns the IDs of every campaign the user either runs or is a member of.
+pub fn campaign_ids_of_user(member_id: &Uuid, conn: &Connection) -> Result<Vec<Uuid>, DBError> {
+    let member_of = {
+        use crate::schema::campaignmembers::dsl::*;
+        campaignmembers.select(campaign_id)
+            .filter(user_id.eq(member_id))
+            .load::<Uuid>(conn)
+            .map_err(DBError::RunQuery)?
+    };
+    use crate::schema::campaigns::dsl::*;
+    campaigns.select(id)
+        .filter(gm_id.eq(member_id).or(id.eq_any(member_of)))
+        .load::<Uuid>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+async fn list_campaigns(user: User, conn: Connection) -> Result<Status<Success<CampaignList>>, Rejection> {
+    let member_id = user.id.ok_or_else(status::not_authorized)?;
+    let campaign_ids = campaign_ids_of_user(&member_id, &conn)?;
+    let db_campaigns = {
+        use crate::schema::campaigns::dsl::*;
+        campaigns.filter(id.eq_any(campaign_ids))
+            .load::<DBCampaign>(&conn)
+            .map_err(DBError::RunQuery)?
+    };
+    let campaigns = db_campaigns.into_iter()
+        .map(|campaign| Summary::<Campaign>::try_from_db(campaign, &conn))
+        .collect::<Result<_, DBError>>()?;
+    Ok(ok(CampaignList { campaigns }))
+}
+
+async fn get_campaign(campaign_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Campaign>>, Rejection> {
+    let (campaign, _) = campaign_with_role(&user, campaign_id, &conn)?;
+    Ok(ok(campaign))
+}
+
+async fn invite_user(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Campaign>>, Rejection> {
+    let campaign = campaign_as_gm(&user, campaign_id, &conn)?;
+    let invited_username: Option<String> = forms::get_optional_form_text_field(&form, FIELD_USERNAME)?;
+    let invited_email: Option<String> = forms::get_optional_form_text_field(&form, FIELD_EMAIL)?;
+
+    let invited = {
+        use crate::schema::users::dsl::*;
+        let query = match (invited_username, invited_email) {
+            (Some(name), _) => users.select(id).filter(username.eq(name)).into_boxed(),
+            (None, Some(address)) => users.select(id).filter(email.eq(address)).into_boxed(),
+            (None, None) => return Err(forms::missing_field_error(FIELD_USERNAME)),
+        };
+        query.first::<Uuid>(&conn)
+            .optional()
+            .map_err(DBError::RunQuery)?
+            .ok_or_else(|| status::bad_request("no user with that username or email exists".to_string()))?
+    };
+
+    if !campaign.can_invite(&invited) {
+        return Err(status::bad_request("that user is already part of the campaign".to_string()));
+    }
+
+    let invitation = DBCampaignInvitation {
+        campaign_id: campaign.id,
+        user_id: invited,
+    };
+    {
+        use crate::schema::campaigninvitations::dsl::*;
+        diesel::insert_into(campaigninvitations)
+            .values(&invitation)
+            .on_conflict_do_nothing()
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+    }
+
+    Ok(ok(campaign))
+}
+
+async fn accept_invitation(campaign_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Campaign>>, Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    let invitation = DBCampaignInvitation {
+        campaign_id,
+        user_id,
+    };
+
+    conn.transaction::<_, DBError, _>(|| {
+        use crate::schema::campaigninvitations::dsl;
+        let deleted = diesel::delete(dsl::campaigninvitations
+                .filter(dsl::campaign_id.eq(&invitation.campaign_id))
+                .filter(dsl::user_id.eq(&invitation.user_id)))
+            .execute(&conn)?;
+        if deleted == 0 {
+            return Err(DBError::NoRows);
+        }
+        DBCampaignMember { campaign_id, user_id }.db_insert(&conn)
+    }).map_err(|err| match err {
+        DBError::NoRows => status::not_found(),
+        err => Rejection::from(err),
+    })?;
+    events::memberships_changed(&user_id, &conn)?;
+
+    let campaign: Campaign = forms::value_by_id(campaign_id, &conn)?;
+    Ok(ok(campaign))
+}
+
+async fn add_character(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Campaign>>, Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    let (campaign, _) = campaign_with_role(&user, campaign_id, &conn)?;
+    let char_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CHARACTER_ID)?;
+    let character: DBCharacter = forms::value_by_id(char_id, &conn)?;
+
+    // Only the owner of a character can bring it into a campaign, even if
+    // they happen to be the game master.
+    if character.user_id() != &user_id {
+        return Err(status::not_authorized());
+    }
+
+    if campaign_of_character(&char_id, &conn)?.is_some() {
+        return Err(status::bad_request("that character is already part of a campaign".to_string()));
+    }
+
+    DBCampaignCharacter { campaign_id: campaign.id, char_id }.db_insert(&conn)?;
+
+    let campaign: Campaign = forms::value_by_id(campaign_id, &conn)?;
+    Ok(ok(campaign))
+}
+
+async fn pass_time(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<TimeAdvanced>>, Rejection> {
+    let campaign = campaign_as_gm(&user, campaign_id, &conn)?;
+    let rounds: i64 = forms::get_optional_form_text_field(&form, FIELD_ROUNDS)?.unwrap_or(0);
+    let minutes: i64 = forms::get_optional_form_text_field(&form, FIELD_MINUTES)?.unwrap_or(0);
+    let hours: i64 = forms::get_optional_form_text_field(&form, FIELD_HOURS)?.unwrap_or(0);
+    let total = minutes.checked_mul(ROUNDS_PER_MINUTE)
+        .and_then(|minutes| hours.checked_mul(ROUNDS_PER_HOUR)?.checked_add(minutes))
+        .and_then(|total| total.checked_add(rounds))
+        .ok_or_else(|| status::bad_request("time can't be advanced that far".to_string()))?;
+    if rounds < 0 || minutes < 0 || hours < 0 || total == 0 {
+        return Err(status::bad_request("time can only be advanced by a positive amount".to_string()));
+    }
+
+    let (game_time, expired) = advance_time(&campaign, total, &conn)?;
+    events::publish(campaign_id, Event::TimeAdvanced { game_time, expired: expired.clone() });
+    Ok(ok(TimeAdvanced { game_time, expired }))
+}
+
+async fn set_advancement(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Campaign>>, Rejection> {
+    campaign_as_gm(&user, campaign_id, &conn)?;
+    let track: AdvancementTrack = forms::get_required_form_text_field(&form, FIELD_ADVANCEMENT)?;
+    {
+        use crate::schema::campaigns::dsl::*;
+        diesel::update(campaigns.filter(id.eq(campaign_id)))
+            .set(advancement.eq(track))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+    }
+
+    let campaign: Campaign = forms::value_by_id(campaign_id, &conn)?;
+    Ok(ok(campaign))
+}
+
+/// Reads the experience each character should get from the form: either an
+/// amount split evenly between some (by default all) of the party, or an
+/// individual amount for each character.
+fn awards_from_form(campaign: &Campaign, form: &Form) -> Result<BTreeMap<Uuid, i32>, Rejection> {
+    let total: Option<i32> = forms::get_optional_form_text_field(form, FIELD_XP)?;
+    let individual: Option<String> = forms::get_optional_form_text_field(form, FIELD_AWARDS)?;
+    let awards = match (total, individual) {
+        (Some(total), None) => {
+            let char_ids = match forms::get_optional_form_text_field::<String>(form, FIELD_CHARACTER_IDS)? {
+                Some(ids) => serde_json::from_str::<BTreeSet<Uuid>>(&ids)
+                    .map_err(|_| forms::field_is_invalid_error(FIELD_CHARACTER_IDS))?,
+                None => campaign.character_ids().into_iter().collect(),
+            };
+            let share = experience::split_evenly(total, char_ids.len());
+            char_ids.into_iter()
+                .map(|id| (id, share))
+                .collect()
+        },
+        (None, Some(individual)) => serde_json::from_str::<BTreeMap<Uuid, i32>>(&individual)
+            .map_err(|_| forms::field_is_invalid_error(FIELD_AWARDS))?,
+        (Some(_), Some(_)) => return Err(status::bad_request("experience can either be split or given individually, not both".to_string())),
+        (None, None) => return Err(forms::missing_field_error(FIELD_XP)),
+    };
+
+    let party = campaign.character_ids();
+    if awards.keys().any(|id| !party.contains(id)) {
+        return Err(status::bad_request("experience can only be awarded to characters in the campaign".to_string()));
+    }
+    if awards.is_empty() || awards.values().any(|xp| *xp <= 0) {
+        return Err(status::bad_request("awarded experience must be positive".to_string()));
+    }
+    Ok(awards)
+}
+
+async fn award_experience(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<ExperienceAwarded>>, Rejection> {
+    let campaign = campaign_as_gm(&user, campaign_id, &conn)?;
+    let awards = awards_from_form(&campaign, &form)?;
+
+    let awards = conn.transaction::<_, DBError, _>(|| {
+        awards.into_iter()
+            .map(|(character_id, awarded)| {
+                let character = experience::award_xp(&character_id, awarded, &conn)?;
+                let level = character.total_level(&conn)?;
+                let experience = Experience::new(campaign.advancement, character.xp(), level);
+                Ok(ExperienceAward { character_id, awarded, experience })
+            })
+            .collect::<Result<Vec<_>, DBError>>()
+    })?;
+
+    events::publish(campaign_id, Event::ExperienceAwarded { awards: awards.clone() });
+    Ok(ok(ExperienceAwarded { awards }))
+}
+
+async fn get_party(campaign_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Party>>, Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    let (campaign, role) = campaign_with_role(&user, campaign_id, &conn)?;
+    let char_ids = campaign.characters.iter()
+        .map(|character| character.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    let db_characters = {
+        use crate::schema::characters::dsl::*;
+        characters.filter(id.eq_any(char_ids))
+            .load::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)?
+    };
+
+    let mut party = Party {
+        role,
+        characters: Vec::new(),
+        party: Vec::new(),
+    };
+
+    for db_character in db_characters.into_iter() {
+        let is_visible = role == CampaignRole::GameMaster || db_character.user_id() == &user_id;
+        let character = Character::try_from_db(db_character, &conn)?;
+        party.party.push(Summary::<Character>::from(&character));
+        if is_visible {
+            party.characters.push(character);
+        }
+    }
+
+    Ok(ok(party))
+}
+
+/// A warp Filter containing all of the campaign endpoints, relative to
+/// the `/campaigns` path.
+pub fn campaigns_filter() -> BoxedFilter<(impl Reply,)> {
+    let create = warp::post()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(create_campaign);
+    let list = warp::get()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(list_campaigns);
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_campaign);
+    let invite = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("invitations"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(invite_user);
+    let join = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("join"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(accept_invitation);
+    let add_char = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("characters"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(add_character);
+    let party = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("characters"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_party);
+
+    let time = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("time"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(pass_time);
+    let advancement = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("advancement"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(set_advancement);
+    let experience = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("experience"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(award_experience);
+
+    create.or(list)
+        .or(get)
+        .or(invite)
+        .or(join)
+        .or(add_char)
+        .or(party)
+        .or(time)
+        .or(advancement)
+        .or(experience)
+        .or(treasure::treasure_filter())
+        .boxed()
+}
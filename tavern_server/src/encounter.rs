@@ -0,0 +1,1061 @@
+use crate::auth::{self, User};
+use crate::campaign::{self, Campaign, CampaignRole};
+use crate::db::{self, Connection, Delete, DeleteById, Error as DBError, GetAll, GetById, Insert, TryFromDb, Update};
+use crate::events::{self, Event};
+use crate::forms;
+use crate::pathfinder::active_effect::ActiveEffect;
+use crate::pathfinder::bestiary::{ChallengeRating, Creature};
+use crate::pathfinder::character::DBCharacter;
+use crate::pathfinder::sheet::CharacterSheet;
+use crate::pathfinder::summary::{Summarize, Summary};
+use crate::pathfinder::{Attribute, CombatStat, Links};
+use crate::schema::{combatants, encounters};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use diesel_derive_enum::DbEnum;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combatant(name: &str, initiative: i16, dexterity: i16) -> Combatant {
+        Combatant {
+            id: Uuid::new_v4(),
+            character: None,
+            creature: None,
+            name: name.to_string(),
+            initiative_bonus: 0,
+            dexterity,
+            initiative: Some(initiative),
+            turn_order: None,
+            status: CombatantStatus::Active,
+        }
+    }
+
+    fn encounter(combatants: Vec<Combatant>) -> Encounter {
+        let mut encounter = Encounter {
+            id: Uuid::new_v4(),
+            links: Links::new(),
+            campaign_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            round: 0,
+            current_turn: None,
+            combatants,
+        };
+        encounter.start().expect("all combatants have initiative");
+        encounter
+    }
+
+    fn current_name(encounter: &Encounter) -> &str {
+        &encounter.current().unwrap().name
+    }
+
+    #[test]
+    fn initiative_ties_are_broken_by_dexterity() {
+        let encounter = encounter(vec![
+            combatant("slow", 10, 10),
+            combatant("fast", 10, 16),
+            combatant("first", 18, 8),
+        ]);
+        let names: Vec<&str> = encounter.combatants.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["first", "fast", "slow"]);
+        assert_eq!(encounter.round, 1);
+        assert_eq!(current_name(&encounter), "first");
+    }
+
+    #[test]
+    fn turns_wrap_into_the_next_round() {
+        let mut encounter = encounter(vec![combatant("a", 15, 10), combatant("b", 5, 10)]);
+        assert!(!encounter.next_turn());
+        assert_eq!(current_name(&encounter), "b");
+        assert!(encounter.next_turn());
+        assert_eq!(current_name(&encounter), "a");
+        assert_eq!(encounter.round, 2);
+    }
+
+    #[test]
+    fn delaying_combatants_are_skipped_until_they_act() {
+        let mut encounter = encounter(vec![
+            combatant("a", 20, 10),
+            combatant("b", 15, 10),
+            combatant("c", 10, 10),
+        ]);
+        encounter.delay();
+        assert_eq!(current_name(&encounter), "b");
+        encounter.next_turn();
+        assert_eq!(current_name(&encounter), "c");
+
+        let delayer = encounter.combatants[0].id;
+        encounter.act(&delayer).expect("a is delaying");
+        assert_eq!(current_name(&encounter), "a");
+        assert_eq!(encounter.combatants[1].initiative, Some(10));
+        let names: Vec<&str> = encounter.combatants.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "a", "c"]);
+
+        assert!(!encounter.next_turn());
+        assert_eq!(current_name(&encounter), "c");
+    }
+
+    #[test]
+    fn readied_actions_expire_on_the_next_turn() {
+        let mut encounter = encounter(vec![combatant("a", 20, 10), combatant("b", 15, 10)]);
+        encounter.ready();
+        assert_eq!(current_name(&encounter), "b");
+        encounter.next_turn();
+        assert_eq!(current_name(&encounter), "a");
+        assert_eq!(encounter.current().unwrap().status, CombatantStatus::Active);
+    }
+
+    #[test]
+    fn removing_the_current_combatant_passes_the_turn() {
+        let mut encounter = encounter(vec![combatant("a", 20, 10), combatant("b", 15, 10)]);
+        let first = encounter.combatants[0].id;
+        assert!(!encounter.remove(&first));
+        assert_eq!(current_name(&encounter), "b");
+        assert_eq!(encounter.round, 1);
+    }
+
+    #[test]
+    fn removing_the_sole_combatant_ends_the_turn_order() {
+        let mut encounter = encounter(vec![combatant("a", 20, 10)]);
+        let only = encounter.combatants[0].id;
+        assert!(!encounter.remove(&only));
+        assert!(encounter.combatants.is_empty());
+        assert_eq!(encounter.current_turn, None);
+        assert_eq!(encounter.round, 1);
+    }
+
+    #[test]
+    fn party_level_is_adjusted_for_party_size() {
+        let four = EncounterBudget::new(&[3, 3, 4, 4], Difficulty::Average);
+        assert_eq!(four.average_level, 4);
+        assert_eq!(four.party_level, 4);
+        assert_eq!(four.xp_budget, 1200);
+
+        let three = EncounterBudget::new(&[4, 4, 4], Difficulty::Average);
+        assert_eq!(three.party_level, 3);
+        let six = EncounterBudget::new(&[4; 6], Difficulty::Average);
+        assert_eq!(six.party_level, 5);
+    }
+
+    #[test]
+    fn difficulty_raises_the_budget_cr() {
+        let party = [5, 5, 5, 5];
+        assert_eq!(EncounterBudget::new(&party, Difficulty::Easy).budget_cr.to_string(), "4");
+        assert_eq!(EncounterBudget::new(&party, Difficulty::Epic).budget_cr.to_string(), "8");
+        // A small low level party faces fractional CRs.
+        assert_eq!(EncounterBudget::new(&[1, 1], Difficulty::Easy).budget_cr.to_string(), "1/3");
+    }
+
+    #[test]
+    fn encounters_are_rated_by_their_total_xp() {
+        let budget = EncounterBudget::new(&[5, 5, 5, 5], Difficulty::Challenging);
+        assert_eq!(budget.xp_budget, 2400);
+
+        let rating = budget.rate(2 * 1200);
+        assert_eq!(rating.encounter_cr.map(|cr| cr.to_string()), Some("6".to_string()));
+        assert_eq!(rating.difficulty, Some(Difficulty::Challenging));
+        assert!(rating.within_budget);
+
+        let rating = budget.rate(4 * 1200);
+        assert_eq!(rating.difficulty, Some(Difficulty::Epic));
+        assert!(!rating.within_budget);
+
+        assert_eq!(budget.rate(0).difficulty, None);
+    }
+}
+
+/// The form field holding the ID of the campaign an encounter is part of.
+pub const FIELD_CAMPAIGN_ID: &str = "campaign-id";
+/// The form field holding the name of an encounter or combatant.
+pub const FIELD_NAME: &str = "name";
+/// The form field holding the ID of a character to add as a combatant.
+pub const FIELD_CHARACTER_ID: &str = "character-id";
+/// The form field holding the ID of a bestiary creature to add as a
+/// combatant.
+pub const FIELD_CREATURE_ID: &str = "creature-id";
+/// The form field holding an NPC combatant's initiative bonus.
+pub const FIELD_INITIATIVE_BONUS: &str = "initiative-bonus";
+/// The form field holding an NPC combatant's Dexterity score.
+pub const FIELD_DEXTERITY: &str = "dexterity";
+/// The form field holding the combatant whose initiative is being set.
+pub const FIELD_COMBATANT_ID: &str = "combatant-id";
+/// The form field holding an initiative result rolled at the table. If
+/// missing, the server rolls instead.
+pub const FIELD_INITIATIVE: &str = "initiative";
+
+/// The form field holding how hard a planned encounter should be.
+pub const FIELD_DIFFICULTY: &str = "difficulty";
+/// The form field holding a JSON object of bestiary creature IDs to how many
+/// of each a planned encounter has.
+pub const FIELD_CREATURES: &str = "creatures";
+
+/// Where a combatant is in the turn order. Delaying combatants are skipped
+/// until they choose to act; readied combatants may act before their next
+/// turn, at which point the readied action is lost.
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum CombatantStatus {
+    Active,
+    Delaying,
+    Readied,
+}
+
+/// Anything taking part in an encounter: a character, a creature from the
+/// bestiary or an ad-hoc NPC.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Combatant {
+    pub id: Uuid,
+    pub character: Option<Uuid>,
+    pub creature: Option<Uuid>,
+    pub name: String,
+    pub initiative_bonus: i16,
+    pub dexterity: i16,
+    pub initiative: Option<i16>,
+    pub turn_order: Option<i16>,
+    pub status: CombatantStatus,
+}
+
+impl Combatant {
+    /// Roll 1d20 and add the combatant's initiative bonus.
+    pub fn roll_initiative(&mut self) {
+        let roll: i16 = rand::thread_rng().gen_range(1, 21);
+        self.initiative = Some(roll + self.initiative_bonus);
+    }
+
+    /// Orders combatants from first to act to last. Ties are broken by
+    /// Dexterity, then by initiative bonus. Combatants without initiative go
+    /// last.
+    fn cmp_initiative(&self, other: &Self) -> Ordering {
+        other.initiative.cmp(&self.initiative)
+            .then(other.dexterity.cmp(&self.dexterity))
+            .then(other.initiative_bonus.cmp(&self.initiative_bonus))
+            .then(self.name.cmp(&other.name))
+    }
+
+    fn into_db(self, encounter_id: Uuid) -> DBCombatant {
+        DBCombatant {
+            id: self.id,
+            encounter_id,
+            char_id: self.character,
+            name: self.name,
+            initiative_bonus: self.initiative_bonus,
+            dexterity: self.dexterity,
+            initiative: self.initiative,
+            turn_order: self.turn_order,
+            status: self.status,
+            creature_id: self.creature,
+        }
+    }
+}
+
+impl TryFromDb for Combatant {
+    type DBType = DBCombatant;
+
+    fn try_from_db(other: Self::DBType, _conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let combatant = Combatant {
+            id: other.id,
+            character: other.char_id,
+            creature: other.creature_id,
+            name: other.name,
+            initiative_bonus: other.initiative_bonus,
+            dexterity: other.dexterity,
+            initiative: other.initiative,
+            turn_order: other.turn_order,
+            status: other.status,
+        };
+        Ok(combatant)
+    }
+}
+
+/// A fight within a campaign. `round` is 0 until the encounter has started.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Encounter {
+    id: Uuid,
+    links: Links,
+    campaign_id: Uuid,
+    name: String,
+    round: i32,
+    current_turn: Option<i16>,
+    /// Sorted by turn order.
+    combatants: Vec<Combatant>,
+}
+
+impl Encounter {
+    pub fn current(&self) -> Option<&Combatant> {
+        self.current_turn.and_then(|turn| self.combatants.get(turn as usize))
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.round > 0
+    }
+
+    fn current_mut(&mut self) -> Option<&mut Combatant> {
+        let turn = self.current_turn?;
+        self.combatants.get_mut(turn as usize)
+    }
+
+    /// Sorts the combatants by initiative and renumbers the turn order,
+    /// keeping the current combatant's turn.
+    fn sort(&mut self) {
+        let current = self.current().map(|c| c.id);
+        self.combatants.sort_by(Combatant::cmp_initiative);
+        self.renumber(current);
+    }
+
+    fn renumber(&mut self, current: Option<Uuid>) {
+        for (order, combatant) in self.combatants.iter_mut().enumerate() {
+            combatant.turn_order = combatant.initiative.map(|_| order as i16);
+        }
+        self.current_turn = current
+            .and_then(|id| self.combatants.iter().position(|c| c.id == id))
+            .map(|pos| pos as i16);
+    }
+
+    /// Begin the first round. Every combatant must have an initiative.
+    pub fn start(&mut self) -> Result<(), String> {
+        if self.is_started() {
+            return Err("the encounter has already started".to_string());
+        }
+        if self.combatants.is_empty() {
+            return Err("the encounter has no combatants".to_string());
+        }
+        if let Some(combatant) = self.combatants.iter().find(|c| c.initiative.is_none()) {
+            return Err(format!("{} has not rolled initiative", combatant.name));
+        }
+        self.round = 1;
+        self.current_turn = None;
+        self.sort();
+        self.current_turn = Some(0);
+        Ok(())
+    }
+
+    /// Move to the next combatant's turn, skipping anyone that is delaying.
+    /// Returns whether a new round has started.
+    pub fn next_turn(&mut self) -> bool {
+        let count = self.combatants.len();
+        if count == 0 || !self.is_started() {
+            return false;
+        }
+
+        let mut new_round = false;
+        let mut turn = self.current_turn.unwrap_or(-1) as isize;
+        // Everyone can be delaying at once, so give up after one full pass
+        // and let the next combatant act regardless.
+        for _ in 0..count {
+            turn += 1;
+            if turn as usize >= count {
+                turn = 0;
+                self.round += 1;
+                new_round = true;
+            }
+            if self.combatants[turn as usize].status != CombatantStatus::Delaying {
+                break;
+            }
+        }
+
+        self.current_turn = Some(turn as i16);
+        if let Some(combatant) = self.current_mut() {
+            // Delaying or readying only lasts until the combatant's next turn.
+            combatant.status = CombatantStatus::Active;
+        }
+        new_round
+    }
+
+    /// The current combatant delays their turn. Returns whether a new round
+    /// has started.
+    pub fn delay(&mut self) -> bool {
+        self.set_current_status(CombatantStatus::Delaying)
+    }
+
+    /// The current combatant readies an action. Returns whether a new round
+    /// has started.
+    pub fn ready(&mut self) -> bool {
+        self.set_current_status(CombatantStatus::Readied)
+    }
+
+    fn set_current_status(&mut self, status: CombatantStatus) -> bool {
+        let current = match self.current_mut() {
+            Some(current) => current,
+            None => return false,
+        };
+        current.status = status;
+        let turn = self.current_turn;
+        let new_round = self.next_turn();
+        // If the only combatant delays, they stay where they are.
+        if self.current_turn == turn {
+            if let Some(current) = self.current_mut() {
+                current.status = status;
+            }
+        }
+        new_round
+    }
+
+    /// A delaying or readied combatant acts now, before the current
+    /// combatant. Their initiative becomes that of the current combatant.
+    pub fn act(&mut self, combatant_id: &Uuid) -> Result<(), String> {
+        let current = self.current()
+            .ok_or_else(|| "the encounter has not started".to_string())?
+            .clone();
+        let pos = self.combatants.iter()
+            .position(|c| &c.id == combatant_id)
+            .ok_or_else(|| "no such combatant".to_string())?;
+        if self.combatants[pos].status == CombatantStatus::Active {
+            return Err(format!("{} is not delaying or readied", self.combatants[pos].name));
+        }
+
+        let mut combatant = self.combatants.remove(pos);
+        let current_pos = self.combatants.iter()
+            .position(|c| c.id == current.id)
+            .unwrap_or(0);
+        combatant.initiative = current.initiative;
+        combatant.status = CombatantStatus::Active;
+        let id = combatant.id;
+        self.combatants.insert(current_pos, combatant);
+        self.renumber(Some(id));
+        Ok(())
+    }
+
+    /// Remove a combatant. If it is their turn and anyone else is left, the
+    /// next combatant's turn begins. Returns whether a new round has started.
+    pub fn remove(&mut self, combatant_id: &Uuid) -> bool {
+        let is_current = self.current()
+            .map(|current| &current.id == combatant_id)
+            .unwrap_or(false);
+        let others_remain = self.combatants.iter().any(|c| &c.id != combatant_id);
+        let new_round = if is_current && others_remain { self.next_turn() } else { false };
+        let current = self.current().map(|c| c.id);
+        self.combatants.retain(|c| &c.id != combatant_id);
+        self.renumber(current);
+        if self.combatants.is_empty() {
+            self.current_turn = None;
+        }
+        new_round
+    }
+
+    /// Sets a combatant's initiative, re-sorting the turn order if the
+    /// encounter has already started.
+    pub fn set_initiative(&mut self, combatant_id: &Uuid, initiative: Option<i16>) -> Result<(), String> {
+        let combatant = self.combatants.iter_mut()
+            .find(|c| &c.id == combatant_id)
+            .ok_or_else(|| "no such combatant".to_string())?;
+        match initiative {
+            Some(initiative) => combatant.initiative = Some(initiative),
+            None => combatant.roll_initiative(),
+        }
+        if self.is_started() {
+            self.sort();
+        }
+        Ok(())
+    }
+
+    fn save(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction::<_, DBError, _>(|| {
+            {
+                use crate::schema::encounters::dsl::*;
+                diesel::update(encounters.filter(id.eq(&self.id)))
+                    .set((round.eq(self.round), current_turn.eq(self.current_turn)))
+                    .execute(conn)?;
+            }
+            for combatant in self.combatants.iter() {
+                combatant.clone().into_db(self.id).db_update(conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Let everyone in the campaign know the encounter has changed.
+    fn publish(&self) {
+        events::publish(self.campaign_id, Event::EncounterChanged { encounter: self.clone() });
+    }
+}
+
+impl TryFromDb for Encounter {
+    type DBType = DBEncounter;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let mut combatants = DBCombatant::belonging_to(&other)
+            .load::<DBCombatant>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|combatant| Combatant::try_from_db(combatant, conn))
+            .collect::<Result<Vec<_>, _>>()?;
+        combatants.sort_by(|a, b| match (a.turn_order, b.turn_order) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.cmp_initiative(b),
+        });
+        let mut links = Links::new();
+        links.insert("self".to_string(), format!("/encounters/{}", other.id));
+        links.insert("campaign".to_string(), format!("/campaigns/{}", other.campaign_id));
+
+        let encounter = Encounter {
+            id: other.id,
+            links,
+            campaign_id: other.campaign_id,
+            name: other.name,
+            round: other.round,
+            current_turn: other.current_turn,
+            combatants,
+        };
+        Ok(encounter)
+    }
+}
+
+impl From<Encounter> for Bytes {
+    fn from(encounter: Encounter) -> Self {
+        status::serialize_to_bytes(&encounter)
+    }
+}
+
+/// How hard an encounter is for a party, by how far its CR is above the
+/// party's level.
+#[derive(Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Average,
+    Challenging,
+    Hard,
+    Epic,
+}
+
+impl Difficulty {
+    /// How far above the party's level the encounter's CR is.
+    pub fn cr_offset(self) -> i16 {
+        match self {
+            Difficulty::Easy => -1,
+            Difficulty::Average => 0,
+            Difficulty::Challenging => 1,
+            Difficulty::Hard => 2,
+            Difficulty::Epic => 3,
+        }
+    }
+
+    /// The difficulty of an encounter whose CR is `offset` above the party's
+    /// level. Anything easier than easy or harder than epic counts as such.
+    pub fn for_cr_offset(offset: i16) -> Self {
+        match offset {
+            std::i16::MIN..=-1 => Difficulty::Easy,
+            0 => Difficulty::Average,
+            1 => Difficulty::Challenging,
+            2 => Difficulty::Hard,
+            _ => Difficulty::Epic,
+        }
+    }
+}
+
+/// The experience a party can be faced with for an encounter of a given
+/// difficulty.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct EncounterBudget {
+    pub party_size: usize,
+    /// The party's total level divided by its size, rounded to the nearest
+    /// level.
+    pub average_level: i16,
+    /// The average level adjusted for parties of fewer than four or more than
+    /// five characters.
+    pub party_level: i16,
+    pub difficulty: Difficulty,
+    pub budget_cr: ChallengeRating,
+    pub xp_budget: i32,
+}
+
+impl EncounterBudget {
+    /// Works out the budget for a party with the given total character
+    /// levels.
+    pub fn new(levels: &[i16], difficulty: Difficulty) -> Self {
+        let party_size = levels.len();
+        let total: i32 = levels.iter().map(|level| i32::from(*level)).sum();
+        let average_level = if party_size == 0 {
+            0
+        } else {
+            ((total as f64) / (party_size as f64)).round() as i16
+        };
+        let party_level = match party_size {
+            0..=3 => average_level - 1,
+            4..=5 => average_level,
+            _ => average_level + 1,
+        };
+        let budget_cr = ChallengeRating::nearest(party_level + difficulty.cr_offset());
+        EncounterBudget {
+            party_size,
+            average_level,
+            party_level,
+            difficulty,
+            budget_cr,
+            xp_budget: budget_cr.xp(),
+        }
+    }
+
+    /// Rates an encounter worth the given total experience against this
+    /// budget.
+    pub fn rate(&self, total_xp: i32) -> EncounterRating {
+        let encounter_cr = ChallengeRating::for_xp(total_xp);
+        EncounterRating {
+            total_xp,
+            encounter_cr,
+            difficulty: encounter_cr.map(|cr| Difficulty::for_cr_offset(cr.step() - self.party_level)),
+            within_budget: total_xp <= self.xp_budget,
+        }
+    }
+}
+
+/// How hard a proposed encounter actually is.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct EncounterRating {
+    pub total_xp: i32,
+    /// The CR of the encounter as a whole, if it is worth any experience.
+    pub encounter_cr: Option<ChallengeRating>,
+    pub difficulty: Option<Difficulty>,
+    pub within_budget: bool,
+}
+
+/// Some number of a creature in a planned encounter.
+#[derive(Serialize, Clone, Debug)]
+pub struct PlannedCreature {
+    pub creature: Summary<Creature>,
+    pub cr: ChallengeRating,
+    pub count: i16,
+    pub xp: i32,
+}
+
+/// A budget for an encounter against a campaign's party, along with the
+/// rating of the creatures proposed for it.
+#[derive(Serialize, Clone, Debug)]
+pub struct EncounterPlan {
+    pub budget: EncounterBudget,
+    pub creatures: Vec<PlannedCreature>,
+    pub rating: EncounterRating,
+}
+
+impl From<EncounterPlan> for Bytes {
+    fn from(plan: EncounterPlan) -> Self {
+        status::serialize_to_bytes(&plan)
+    }
+}
+
+/// The state of an encounter after a change, along with any timed effects
+/// that ran out because a new round started.
+#[derive(Serialize, Clone, Debug)]
+pub struct EncounterUpdate {
+    pub encounter: Encounter,
+    pub expired: Vec<ActiveEffect>,
+}
+
+impl From<EncounterUpdate> for Bytes {
+    fn from(update: EncounterUpdate) -> Self {
+        status::serialize_to_bytes(&update)
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "encounters"]
+pub struct DBEncounter {
+    id: Uuid,
+    campaign_id: Uuid,
+    name: String,
+    round: i32,
+    current_turn: Option<i16>,
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "combatants"]
+#[belongs_to(DBEncounter, foreign_key = "encounter_id")]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct DBCombatant {
+    id: Uuid,
+    encounter_id: Uuid,
+    char_id: Option<Uuid>,
+    name: String,
+    initiative_bonus: i16,
+    dexterity: i16,
+    initiative: Option<i16>,
+    turn_order: Option<i16>,
+    status: CombatantStatus,
+    creature_id: Option<Uuid>,
+}
+
+/// Loads an encounter along with its campaign and the user's role in it.
+fn encounter_with_role(user: &User, encounter_id: Uuid, conn: &Connection) -> Result<(Encounter, Campaign, CampaignRole), Rejection> {
+    let encounter: Encounter = forms::value_by_id(encounter_id, conn)
+        .map_err(|_| status::not_found())?;
+    let (campaign, role) = campaign::campaign_with_role(user, encounter.campaign_id, conn)?;
+    Ok((encounter, campaign, role))
+}
+
+/// The game master controls every combatant; players control the combatants
+/// of their own characters.
+fn can_control(user: &User, role: CampaignRole, combatant: &Combatant, conn: &Connection) -> Result<bool, DBError> {
+    if role == CampaignRole::GameMaster {
+        return Ok(true);
+    }
+    match (&combatant.character, &user.id) {
+        (Some(char_id), Some(user_id)) => {
+            DBCharacter::db_get_by_id(char_id, conn)
+                .map(|character| character.user_id() == user_id)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Saves the encounter and, if a new round started, ticks the campaign clock
+/// so that timed effects expire.
+fn finish_update(encounter: Encounter, campaign: &Campaign, new_round: bool, conn: &Connection) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    let advanced = conn.transaction::<_, DBError, _>(|| {
+        encounter.save(conn)?;
+        if new_round {
+            campaign::advance_time(campaign, 1, conn).map(Some)
+        } else {
+            Ok(None)
+        }
+    })?;
+    encounter.publish();
+    let expired = match advanced {
+        Some((game_time, expired)) => {
+            events::publish(*campaign.id(), Event::TimeAdvanced { game_time, expired: expired.clone() });
+            expired
+        }
+        None => Vec::new(),
+    };
+    let update = EncounterUpdate { encounter, expired };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(update)))
+}
+
+async fn create_encounter(user: User, conn: Connection, form: Form) -> Result<Status<Success<Encounter>>, Rejection> {
+    let campaign_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CAMPAIGN_ID)?;
+    let campaign = campaign::campaign_as_gm(&user, campaign_id, &conn)?;
+    let name = forms::get_required_form_text_field(&form, FIELD_NAME)?;
+    let db_encounter = DBEncounter {
+        id: Uuid::new_v4(),
+        campaign_id: *campaign.id(),
+        name,
+        round: 0,
+        current_turn: None,
+    };
+    db_encounter.db_insert(&conn)?;
+    let encounter = Encounter::try_from_db(db_encounter, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+async fn plan_encounter(user: User, conn: Connection, form: Form) -> Result<Status<Success<EncounterPlan>>, Rejection> {
+    let campaign_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CAMPAIGN_ID)?;
+    let campaign = campaign::campaign_as_gm(&user, campaign_id, &conn)?;
+    let difficulty: Difficulty = forms::get_required_form_text_field(&form, FIELD_DIFFICULTY)?;
+    let proposed = match forms::get_optional_form_text_field::<String>(&form, FIELD_CREATURES)? {
+        Some(proposed) => serde_json::from_str::<BTreeMap<Uuid, i16>>(&proposed)
+            .map_err(|_| forms::field_is_invalid_error(FIELD_CREATURES))?,
+        None => BTreeMap::new(),
+    };
+    if proposed.values().any(|count| *count <= 0) {
+        return Err(forms::field_is_invalid_error(FIELD_CREATURES));
+    }
+
+    let party = {
+        use crate::schema::characters::dsl::*;
+        characters.filter(id.eq_any(campaign.character_ids()))
+            .load::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)?
+    };
+    if party.is_empty() {
+        return Err(status::bad_request("the campaign has no characters to plan an encounter for".to_string()));
+    }
+    let levels = party.iter()
+        .map(|character| character.total_level(&conn))
+        .collect::<Result<Vec<i16>, DBError>>()?;
+    let budget = EncounterBudget::new(&levels, difficulty);
+
+    let mut creatures = Vec::new();
+    for (creature_id, count) in proposed.into_iter() {
+        let creature: Creature = forms::value_by_id(creature_id, &conn)
+            .map_err(|_| forms::field_is_invalid_error(FIELD_CREATURES))?;
+        let xp = creature.xp.checked_mul(i32::from(count))
+            .ok_or_else(|| forms::field_is_invalid_error(FIELD_CREATURES))?;
+        creatures.push(PlannedCreature {
+            creature: Summary::from(&creature),
+            cr: creature.cr,
+            count,
+            xp,
+        });
+    }
+    let total_xp = creatures.iter()
+        .try_fold(0i32, |total, planned| total.checked_add(planned.xp))
+        .ok_or_else(|| forms::field_is_invalid_error(FIELD_CREATURES))?;
+    let rating = budget.rate(total_xp);
+
+    let plan = EncounterPlan { budget, creatures, rating };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(plan)))
+}
+
+async fn get_encounter(encounter_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Encounter>>, Rejection> {
+    let (encounter, _, _) = encounter_with_role(&user, encounter_id, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+async fn add_combatant(encounter_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Encounter>>, Rejection> {
+    let (encounter, campaign, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    if role != CampaignRole::GameMaster {
+        return Err(status::not_authorized());
+    }
+
+    let char_id: Option<Uuid> = forms::get_optional_form_text_field(&form, FIELD_CHARACTER_ID)?;
+    let creature_id: Option<Uuid> = forms::get_optional_form_text_field(&form, FIELD_CREATURE_ID)?;
+    let initiative: Option<i16> = forms::get_optional_form_text_field(&form, FIELD_INITIATIVE)?;
+    let mut combatant = match (char_id, creature_id) {
+        (Some(char_id), _) => {
+            if !campaign.character_ids().contains(&char_id) {
+                return Err(forms::field_is_invalid_error(FIELD_CHARACTER_ID));
+            }
+            let character = DBCharacter::db_get_by_id(&char_id, &conn)?;
+            let sheet = CharacterSheet::load_from_db(character, &conn)?;
+            Combatant {
+                id: Uuid::new_v4(),
+                character: Some(char_id),
+                creature: None,
+                name: sheet.character.name().to_owned(),
+                initiative_bonus: sheet.attribute_modifier(Attribute::Dexterity)
+                    + sheet.modifiers.combat(CombatStat::InitiativeBonus),
+                dexterity: sheet.attributes[&Attribute::Dexterity],
+                initiative: None,
+                turn_order: None,
+                status: CombatantStatus::Active,
+            }
+        }
+        (None, Some(creature_id)) => {
+            let creature: Creature = forms::value_by_id(creature_id, &conn)
+                .map_err(|_| forms::field_is_invalid_error(FIELD_CREATURE_ID))?;
+            // Several of the same creature can be told apart by name, such
+            // as "Goblin 2".
+            let name = forms::get_optional_form_text_field(&form, FIELD_NAME)?
+                .unwrap_or_else(|| creature.name().to_string());
+            Combatant {
+                id: Uuid::new_v4(),
+                character: None,
+                creature: Some(creature_id),
+                name,
+                initiative_bonus: creature.initiative_bonus(),
+                dexterity: creature.dexterity(),
+                initiative: None,
+                turn_order: None,
+                status: CombatantStatus::Active,
+            }
+        }
+        (None, None) => Combatant {
+            id: Uuid::new_v4(),
+            character: None,
+            creature: None,
+            name: forms::get_required_form_text_field(&form, FIELD_NAME)?,
+            initiative_bonus: forms::get_required_form_text_field(&form, FIELD_INITIATIVE_BONUS)?,
+            dexterity: forms::get_required_form_text_field(&form, FIELD_DEXTERITY)?,
+            initiative: None,
+            turn_order: None,
+            status: CombatantStatus::Active,
+        },
+    };
+    combatant.initiative = initiative;
+
+    let combatant_id = combatant.id;
+    combatant.into_db(encounter.id)
+        .db_insert(&conn)
+        .map_err(|err| forms::db_error_to_rejection(err, FIELD_CHARACTER_ID))?;
+
+    // Combatants joining a fight in progress need a place in the turn order.
+    let mut encounter: Encounter = forms::value_by_id(encounter_id, &conn)?;
+    if encounter.is_started() {
+        encounter.set_initiative(&combatant_id, initiative)
+            .map_err(status::bad_request)?;
+        encounter.save(&conn)?;
+    }
+    encounter.publish();
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+async fn remove_combatant(encounter_id: Uuid, combatant_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    let (mut encounter, campaign, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    if role != CampaignRole::GameMaster {
+        return Err(status::not_authorized());
+    }
+    if !encounter.combatants.iter().any(|c| c.id == combatant_id) {
+        return Err(status::not_found());
+    }
+    let new_round = encounter.remove(&combatant_id);
+    DBCombatant::db_delete_by_id(&combatant_id, &conn)?;
+    finish_update(encounter, &campaign, new_round, &conn)
+}
+
+async fn set_initiative(encounter_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Encounter>>, Rejection> {
+    let (mut encounter, _, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    let combatant_id: Uuid = forms::get_required_form_text_field(&form, FIELD_COMBATANT_ID)?;
+    let initiative: Option<i16> = forms::get_optional_form_text_field(&form, FIELD_INITIATIVE)?;
+    let combatant = encounter.combatants.iter()
+        .find(|c| c.id == combatant_id)
+        .ok_or_else(|| forms::field_is_invalid_error(FIELD_COMBATANT_ID))?;
+    if !can_control(&user, role, combatant, &conn)? {
+        return Err(status::not_authorized());
+    }
+    encounter.set_initiative(&combatant_id, initiative)
+        .map_err(status::bad_request)?;
+    encounter.save(&conn)?;
+    encounter.publish();
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+async fn start_encounter(encounter_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Encounter>>, Rejection> {
+    let (mut encounter, _, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    if role != CampaignRole::GameMaster {
+        return Err(status::not_authorized());
+    }
+    // Anyone who hasn't rolled by the time the fight starts gets rolled for.
+    for combatant in encounter.combatants.iter_mut().filter(|c| c.initiative.is_none()) {
+        combatant.roll_initiative();
+    }
+    encounter.start().map_err(status::bad_request)?;
+    encounter.save(&conn)?;
+    encounter.publish();
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+/// The actions that can be taken on the current combatant's turn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TurnAction {
+    End,
+    Delay,
+    Ready,
+}
+
+async fn take_turn_action(encounter_id: Uuid, user: User, conn: Connection, action: TurnAction) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    let (mut encounter, campaign, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    let current = encounter.current()
+        .ok_or_else(|| status::bad_request("the encounter has not started".to_string()))?;
+    if !can_control(&user, role, current, &conn)? {
+        return Err(status::not_authorized());
+    }
+    let new_round = match action {
+        TurnAction::End => encounter.next_turn(),
+        TurnAction::Delay => encounter.delay(),
+        TurnAction::Ready => encounter.ready(),
+    };
+    finish_update(encounter, &campaign, new_round, &conn)
+}
+
+async fn end_turn(encounter_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    take_turn_action(encounter_id, user, conn, TurnAction::End).await
+}
+
+async fn delay_turn(encounter_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    take_turn_action(encounter_id, user, conn, TurnAction::Delay).await
+}
+
+async fn ready_action(encounter_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<EncounterUpdate>>, Rejection> {
+    take_turn_action(encounter_id, user, conn, TurnAction::Ready).await
+}
+
+async fn act_now(encounter_id: Uuid, combatant_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Encounter>>, Rejection> {
+    let (mut encounter, _, role) = encounter_with_role(&user, encounter_id, &conn)?;
+    let combatant = encounter.combatants.iter()
+        .find(|c| c.id == combatant_id)
+        .ok_or_else(status::not_found)?;
+    if !can_control(&user, role, combatant, &conn)? {
+        return Err(status::not_authorized());
+    }
+    encounter.act(&combatant_id).map_err(status::bad_request)?;
+    encounter.save(&conn)?;
+    encounter.publish();
+    Ok(Status::with_data(&StatusCode::OK, Success::new(encounter)))
+}
+
+fn turn_filter(action: &'static str) -> BoxedFilter<(Uuid, User, Connection)> {
+    warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path(action))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .boxed()
+}
+
+/// A warp Filter containing all of the encounter endpoints, relative to the
+/// `/encounters` path.
+pub fn encounters_filter() -> BoxedFilter<(impl Reply,)> {
+    let create = warp::post()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(create_encounter);
+    let plan = warp::post()
+        .and(warp::path("plan"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(plan_encounter);
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_encounter);
+    let add = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("combatants"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(add_combatant);
+    let remove = warp::delete()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("combatants"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(remove_combatant);
+    let act = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("combatants"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("act"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(act_now);
+    let initiative = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("initiative"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(set_initiative);
+    let start = turn_filter("start").and_then(start_encounter);
+    let next = turn_filter("next").and_then(end_turn);
+    let delay = turn_filter("delay").and_then(delay_turn);
+    let ready = turn_filter("ready").and_then(ready_action);
+
+    create.or(plan)
+        .or(get)
+        .or(add)
+        .or(remove)
+        .or(act)
+        .or(initiative)
+        .or(start)
+        .or(next)
+        .or(delay)
+        .or(ready)
+        .boxed()
+}
@@ -0,0 +1,352 @@
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error};
+use crate::encounter::Encounter;
+use crate::pathfinder::active_effect::ActiveEffect;
+use crate::pathfinder::experience::ExperienceAward;
+use crate::pathfinder::treasure::DistributedShare;
+use crate::pathfinder::health::HealthReport;
+use crate::pathfinder::item::OwnedItem;
+use crate::pathfinder::summary::Summary;
+use crate::status;
+use futures::{SinkExt, StreamExt};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use uuid::Uuid;
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_receive_missed_events_then_new_ones() {
+        let campaign_id = Uuid::new_v4();
+        publish(campaign_id, Event::TimeAdvanced { game_time: 1, expired: Vec::new() });
+        publish(campaign_id, Event::TimeAdvanced { game_time: 2, expired: Vec::new() });
+        let first_id = subscribe(campaign_id, Some(0)).1[0].id;
+
+        let (mut receiver, missed) = subscribe(campaign_id, Some(first_id));
+        assert_eq!(missed.len(), 1);
+        assert!(missed[0].id > first_id);
+
+        publish(campaign_id, Event::TimeAdvanced { game_time: 3, expired: Vec::new() });
+        let event = receiver.try_recv().expect("event should be broadcast");
+        assert!(event.id > missed[0].id);
+    }
+
+    #[test]
+    fn event_history_is_bounded() {
+        let campaign_id = Uuid::new_v4();
+        for time in 0..(HISTORY_LENGTH as i64 + 10) {
+            publish(campaign_id, Event::TimeAdvanced { game_time: time, expired: Vec::new() });
+        }
+        assert_eq!(subscribe(campaign_id, Some(0)).1.len(), HISTORY_LENGTH);
+    }
+
+    #[test]
+    fn sockets_hear_about_membership_changes() {
+        let user_id = Uuid::new_v4();
+        let campaign_id = Uuid::new_v4();
+        let mut changes = watch_memberships(user_id);
+        send_memberships(user_id, vec![campaign_id]);
+        assert_eq!(changes.try_recv().expect("change should be broadcast"), vec![campaign_id]);
+
+        // Once every socket is gone, nothing is kept for the user.
+        drop(changes);
+        send_memberships(user_id, Vec::new());
+        assert!(!MEMBERSHIPS.lock().unwrap().contains_key(&user_id));
+    }
+}
+
+/// The number of past events kept per campaign for clients that reconnect.
+pub const HISTORY_LENGTH: usize = 256;
+/// The close code sent to a socket that fell too far behind, telling the
+/// client to reconnect with the last event ID it saw. 1013 is "try again
+/// later".
+const LAGGED_CLOSE_CODE: u16 = 1013;
+
+/// Something that happened in a campaign, sent to every connected member.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+    HitPointsChanged {
+        character_id: Uuid,
+        report: HealthReport,
+    },
+    EffectApplied {
+        character_id: Uuid,
+        effect: ActiveEffect,
+    },
+    EffectRemoved {
+        character_id: Uuid,
+        effect: ActiveEffect,
+    },
+    TimeAdvanced {
+        game_time: i64,
+        expired: Vec<ActiveEffect>,
+    },
+    EncounterChanged {
+        encounter: Encounter,
+    },
+    InventoryTransferred {
+        from_character: Uuid,
+        to_character: Uuid,
+        item: Summary<OwnedItem>,
+        count: i32,
+    },
+    ExperienceAwarded {
+        awards: Vec<ExperienceAward>,
+    },
+    LootDistributed {
+        shares: Vec<DistributedShare>,
+    },
+}
+
+/// An event along with its ID, which increases with every event published.
+/// Clients pass the last ID they saw when reconnecting to receive anything
+/// they missed.
+#[derive(Serialize, Clone, Debug)]
+pub struct EventMessage {
+    pub id: u64,
+    pub campaign_id: Uuid,
+    pub event: Event,
+}
+
+struct CampaignChannel {
+    sender: broadcast::Sender<Arc<EventMessage>>,
+    history: VecDeque<Arc<EventMessage>>,
+}
+
+impl CampaignChannel {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(HISTORY_LENGTH);
+        CampaignChannel {
+            sender,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+        }
+    }
+}
+
+lazy_static! {
+    static ref CHANNELS: Mutex<HashMap<Uuid, CampaignChannel>> = Mutex::new(HashMap::new());
+    /// The campaigns each user with an open socket is part of, sent whenever
+    /// they change so the sockets can follow new campaigns.
+    static ref MEMBERSHIPS: Mutex<HashMap<Uuid, broadcast::Sender<Vec<Uuid>>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_EVENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Send an event to every member of a campaign.
+pub fn publish(campaign_id: Uuid, event: Event) {
+    let message = Arc::new(EventMessage {
+        id: NEXT_EVENT_ID.fetch_add(1, Ordering::SeqCst),
+        campaign_id,
+        event,
+    });
+    let mut channels = CHANNELS.lock().unwrap();
+    let channel = channels.entry(campaign_id).or_insert_with(CampaignChannel::new);
+    if channel.history.len() == HISTORY_LENGTH {
+        channel.history.pop_front();
+    }
+    channel.history.push_back(message.clone());
+    // Sending only fails when nobody is listening, which is fine.
+    let _ = channel.sender.send(message);
+}
+
+/// Send an event to every member of the campaign the character is part of,
+/// if any.
+pub fn publish_for_character(char_id: &Uuid, event: Event, conn: &Connection) -> Result<(), Error> {
+    if let Some(campaign_id) = campaign::campaign_of_character(char_id, conn)? {
+        publish(campaign_id, event);
+    }
+    Ok(())
+}
+
+/// Start listening to a campaign's events. Also returns every stored event
+/// newer than `last_seen`. Both happen under the same lock, so no event is
+/// missed or received twice.
+fn subscribe(campaign_id: Uuid, last_seen: Option<u64>) -> (broadcast::Receiver<Arc<EventMessage>>, Vec<Arc<EventMessage>>) {
+    let mut channels = CHANNELS.lock().unwrap();
+    let channel = channels.entry(campaign_id).or_insert_with(CampaignChannel::new);
+    let receiver = channel.sender.subscribe();
+    let missed = match last_seen {
+        None => Vec::new(),
+        Some(last_seen) => channel.history.iter()
+            .filter(|message| message.id > last_seen)
+            .cloned()
+            .collect(),
+    };
+    (receiver, missed)
+}
+
+/// Start listening for changes to the campaigns a user is part of.
+fn watch_memberships(user_id: Uuid) -> broadcast::Receiver<Vec<Uuid>> {
+    let mut memberships = MEMBERSHIPS.lock().unwrap();
+    memberships.entry(user_id)
+        .or_insert_with(|| broadcast::channel(HISTORY_LENGTH).0)
+        .subscribe()
+}
+
+fn send_memberships(user_id: Uuid, campaigns: Vec<Uuid>) {
+    let mut memberships = MEMBERSHIPS.lock().unwrap();
+    if let Some(sender) = memberships.get(&user_id) {
+        // Sending only fails once the user has no sockets left.
+        if sender.send(campaigns).is_err() {
+            memberships.remove(&user_id);
+        }
+    }
+}
+
+/// Tell the user's open sockets that the campaigns they're part of have
+/// changed, so they start or stop streaming events to match.
+pub fn memberships_changed(user_id: &Uuid, conn: &Connection) -> Result<(), Error> {
+    let campaigns = campaign::campaign_ids_of_user(user_id, conn)?;
+    send_memberships(*user_id, campaigns);
+    Ok(())
+}
+
+/// What a campaign's forwarder passes on to its socket.
+enum Forwarded {
+    Event(Arc<EventMessage>),
+    /// The socket fell so far behind that some events were dropped.
+    Lagged,
+}
+
+/// Passes a campaign's events on to a socket until the returned sender is
+/// dropped.
+fn forward(mut receiver: broadcast::Receiver<Arc<EventMessage>>, events_tx: mpsc::UnboundedSender<Forwarded>) -> oneshot::Sender<()> {
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => break,
+                received = receiver.recv() => match received {
+                    Ok(message) => if events_tx.send(Forwarded::Event(message)).is_err() {
+                        break;
+                    },
+                    // A slow client misses some events rather than holding
+                    // everyone else up. The socket is closed so that it
+                    // reconnects and catches up from the history.
+                    Err(broadcast::RecvError::Lagged(_)) => {
+                        let _ = events_tx.send(Forwarded::Lagged);
+                        break;
+                    },
+                    Err(broadcast::RecvError::Closed) => break,
+                },
+            }
+        }
+    });
+    stop_tx
+}
+
+/// Forward the events of exactly the given campaigns, stopping the
+/// forwarders of any others. Returns the stored events newer than
+/// `last_seen` for the campaigns that weren't already followed.
+fn follow(forwarders: &mut HashMap<Uuid, oneshot::Sender<()>>, campaigns: Vec<Uuid>, last_seen: Option<u64>, events_tx: &mpsc::UnboundedSender<Forwarded>) -> Vec<Arc<EventMessage>> {
+    forwarders.retain(|campaign_id, _| campaigns.contains(campaign_id));
+    let mut missed = Vec::new();
+    for campaign_id in campaigns {
+        if forwarders.contains_key(&campaign_id) {
+            continue;
+        }
+        let (receiver, campaign_missed) = subscribe(campaign_id, last_seen);
+        missed.extend(campaign_missed);
+        forwarders.insert(campaign_id, forward(receiver, events_tx.clone()));
+    }
+    missed
+}
+
+#[derive(Deserialize)]
+struct ConnectQuery {
+    #[serde(rename = "last-event-id")]
+    last_event_id: Option<u64>,
+}
+
+fn to_message(event: &EventMessage) -> Message {
+    Message::text(serde_json::to_string(event).unwrap())
+}
+
+async fn connect(ws: Ws, user: User, conn: Connection, query: ConnectQuery) -> Result<impl Reply, Rejection> {
+    let user_id = user.id.ok_or_else(status::not_authorized)?;
+    // Watch before loading the campaigns, so no change in between is lost.
+    let changes = watch_memberships(user_id);
+    let campaigns = campaign::campaign_ids_of_user(&user_id, &conn)?;
+    Ok(ws.on_upgrade(move |socket| run_socket(socket, user_id, campaigns, changes, query.last_event_id)))
+}
+
+/// Loads the campaigns a user is part of again, for when some membership
+/// changes were missed.
+async fn reload_memberships(user_id: &Uuid) -> Result<Vec<Uuid>, Error> {
+    let conn = db::get_connection().await?;
+    campaign::campaign_ids_of_user(user_id, &conn)
+}
+
+async fn run_socket(socket: WebSocket, user_id: Uuid, campaigns: Vec<Uuid>, mut changes: broadcast::Receiver<Vec<Uuid>>, last_seen: Option<u64>) {
+    let (mut socket_tx, mut socket_rx) = socket.split();
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    // Dropping these when the socket closes stops every forwarder.
+    let mut forwarders = HashMap::new();
+
+    let mut missed = follow(&mut forwarders, campaigns, last_seen, &events_tx);
+    missed.sort_by_key(|message| message.id);
+    for message in missed {
+        if socket_tx.send(to_message(&message)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            incoming = socket_rx.next() => match incoming {
+                Some(Ok(message)) if !message.is_close() => continue,
+                _ => break,
+            },
+            event = events_rx.recv() => match event {
+                Some(Forwarded::Event(message)) => if socket_tx.send(to_message(&message)).await.is_err() {
+                    break;
+                },
+                Some(Forwarded::Lagged) => {
+                    let close = Message::close_with(LAGGED_CLOSE_CODE, "missed events, reconnect with last-event-id");
+                    let _ = socket_tx.send(close).await;
+                    break;
+                },
+                None => break,
+            },
+            changed = changes.recv() => match changed {
+                // Campaigns joined from now on start with their new events.
+                Ok(campaigns) => {
+                    follow(&mut forwarders, campaigns, None, &events_tx);
+                },
+                // Some changes were dropped, so the latest one received
+                // might not be current. Ask the database instead.
+                Err(broadcast::RecvError::Lagged(_)) => match reload_memberships(&user_id).await {
+                    Ok(campaigns) => {
+                        follow(&mut forwarders, campaigns, None, &events_tx);
+                    },
+                    Err(_) => break,
+                },
+                Err(broadcast::RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+/// A warp Filter for the `/ws` endpoint, which streams the events of every
+/// campaign the user is part of. Pass `?last-event-id=N` when reconnecting to
+/// receive any events missed since event N. A socket that falls too far
+/// behind is closed with code 1013, and should reconnect the same way.
+pub fn ws_filter() -> BoxedFilter<(impl Reply,)> {
+    warp::ws()
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(warp::query::<ConnectQuery>())
+        .and_then(connect)
+        .boxed()
+}
@@ -8,8 +8,11 @@ use warp::filters::BoxedFilter;
 use warp::{Filter, Reply};
 
 pub mod auth;
+pub mod campaign;
 pub mod config;
 pub mod db;
+pub mod encounter;
+pub mod events;
 pub mod forms;
 pub mod pathfinder;
 mod schema;
@@ -23,6 +26,22 @@ pub fn setup_server() -> BoxedFilter<(impl Reply,)> {
     let register = warp::post()
         .and(warp::path("register"))
         .and(auth::register_filter());
+    let campaigns = warp::path("campaigns")
+        .and(campaign::campaigns_filter());
+    let characters = warp::path("characters")
+        .and(pathfinder::character::characters_filter());
+    let encounters = warp::path("encounters")
+        .and(encounter::encounters_filter());
+    let bestiary = warp::path("bestiary")
+        .and(pathfinder::bestiary::bestiary_filter());
+    let languages = warp::path("languages")
+        .and(pathfinder::languages::languages_filter());
+    let traits = warp::path("traits")
+        .and(pathfinder::traits::traits_filter());
+    let ws = warp::path("ws")
+        .and(events::ws_filter());
 
-    warp::any().and(login.or(register)).boxed()
+    warp::any()
+        .and(login.or(register).or(campaigns).or(characters).or(encounters).or(bestiary).or(languages).or(traits).or(ws))
+        .boxed()
 }
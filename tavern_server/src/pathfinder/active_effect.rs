@@ -0,0 +1,316 @@
+use super::character::DBCharacter;
+use super::effects::Effect;
+use super::summary::{Summarize, Summary};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Delete, DeleteById, Error, GetById, Insert, TryFromDb};
+use crate::events::{self, Event};
+use crate::forms;
+use crate::schema::characteractiveeffects;
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use nebula_form::Form;
+use nebula_status::{Empty, Status, StatusCode};
+use serde::Serialize;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_start_now_and_run_for_their_duration() {
+        assert_eq!(timing(Some(12), Some(10)), Some((12, Some(22))));
+        assert_eq!(timing(Some(12), None), Some((12, None)));
+    }
+
+    #[test]
+    fn only_untimed_effects_apply_outside_a_campaign() {
+        assert_eq!(timing(None, None), Some((0, None)));
+        assert_eq!(timing(None, Some(10)), None);
+    }
+
+    #[test]
+    fn instantaneous_effects_need_no_clock() {
+        assert_eq!(timing(Some(12), Some(0)), Some((12, Some(12))));
+        assert_eq!(timing(None, Some(0)), Some((0, Some(0))));
+        assert!(is_instantaneous(12, Some(12)));
+        assert!(!is_instantaneous(12, Some(13)));
+        assert!(!is_instantaneous(12, None));
+    }
+
+    #[test]
+    fn effects_expire_once_the_clock_reaches_their_expiry() {
+        let (now, expires_at) = timing(Some(0), Some(3)).unwrap();
+        assert!(!has_expired(expires_at, now));
+        assert!(!has_expired(expires_at, now + 2));
+        assert!(has_expired(expires_at, now + 3));
+        assert!(has_expired(expires_at, now + 10));
+    }
+
+    #[test]
+    fn untimed_effects_never_expire() {
+        assert!(!has_expired(None, 0));
+        assert!(!has_expired(None, i64::MAX));
+    }
+}
+
+/// The form field holding the ID of the effect to apply.
+pub const FIELD_EFFECT_ID: &str = "effect-id";
+/// The form field holding the name of a condition to apply, as an
+/// alternative to `FIELD_EFFECT_ID`.
+pub const FIELD_CONDITION: &str = "condition";
+/// The form field describing where an effect came from.
+pub const FIELD_SOURCE: &str = "source";
+/// The form field holding how many rounds an effect lasts for.
+pub const FIELD_DURATION: &str = "duration";
+
+/// An `Effect` applied to a character for a limited time. Times are in rounds
+/// of the campaign clock (see `campaign::advance_time`). An effect without an
+/// expiry lasts until it is removed. Only characters in a campaign have a
+/// clock, so timed effects can't be applied to anyone else. Instantaneous
+/// effects, which expire as soon as they start, are never kept.
+#[derive(Serialize, Clone, Debug)]
+pub struct ActiveEffect {
+    pub id: Uuid,
+    pub effect: Summary<Effect>,
+    pub source: String,
+    pub started_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl ActiveEffect {
+    pub fn effect_id(&self) -> &Uuid {
+        self.effect.id()
+    }
+}
+
+impl TryFromDb for ActiveEffect {
+    type DBType = DBCharacterActiveEffect;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, Error> where Self: Sized {
+        let effect = Summary::<Effect>::db_get_by_id(&other.effect_id, conn)?;
+        let active = ActiveEffect {
+            id: other.id,
+            effect,
+            source: other.source,
+            started_at: other.started_at,
+            expires_at: other.expires_at,
+        };
+        Ok(active)
+    }
+}
+
+/// The effects currently applied to a character.
+#[derive(Serialize, Clone, Debug)]
+pub struct ActiveEffectList {
+    /// The campaign clock, or None for characters outside a campaign.
+    pub game_time: Option<i64>,
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl From<ActiveEffect> for Bytes {
+    fn from(active: ActiveEffect) -> Self {
+        status::serialize_to_bytes(&active)
+    }
+}
+
+impl From<ActiveEffectList> for Bytes {
+    fn from(list: ActiveEffectList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetById, Delete, DeleteById, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "characteractiveeffects"]
+#[belongs_to(DBCharacter, foreign_key = "char_id")]
+pub struct DBCharacterActiveEffect {
+    id: Uuid,
+    char_id: Uuid,
+    effect_id: Uuid,
+    source: String,
+    started_at: i64,
+    expires_at: Option<i64>,
+}
+
+/// Returns the current game time for the character, in rounds. Characters
+/// that are not part of a campaign have no clock.
+pub fn current_time(char_id: &Uuid, conn: &Connection) -> Result<Option<i64>, Error> {
+    match campaign::campaign_of_character(char_id, conn)? {
+        Some(campaign_id) => campaign::game_time(&campaign_id, conn).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// When an effect lasting `duration` rounds starts and expires, given the
+/// character's clock. Effects on characters without a clock start at 0 and
+/// can't last for a number of rounds, since nothing would ever expire them;
+/// None is returned for those.
+fn timing(now: Option<i64>, duration: Option<i64>) -> Option<(i64, Option<i64>)> {
+    match (now, duration) {
+        (now, None) => Some((now.unwrap_or(0), None)),
+        (Some(now), Some(rounds)) => Some((now, Some(now.saturating_add(rounds)))),
+        (None, Some(0)) => Some((0, Some(0))),
+        (None, Some(_)) => None,
+    }
+}
+
+/// Whether an effect is over as soon as it starts.
+fn is_instantaneous(started_at: i64, expires_at: Option<i64>) -> bool {
+    expires_at == Some(started_at)
+}
+
+/// Whether an effect expiring at `expires_at` has run out by `now`. This is
+/// the same check `expire_effects` makes in the database.
+fn has_expired(expires_at: Option<i64>, now: i64) -> bool {
+    matches!(expires_at, Some(expires_at) if expires_at <= now)
+}
+
+/// Apply an effect to a character, starting now and lasting for `duration`
+/// rounds (or until removed, if there is no duration). An effect lasting 0
+/// rounds takes effect but isn't stored, since it's already over. Fails for
+/// timed effects on characters outside a campaign.
+pub fn apply_effect(char_id: &Uuid, effect_id: &Uuid, source: String, duration: Option<i64>, conn: &Connection) -> Result<ActiveEffect, Error> {
+    let (started_at, expires_at) = timing(current_time(char_id, conn)?, duration)
+        .ok_or_else(|| Error::InvalidValues(vec![FIELD_DURATION.to_string()]))?;
+    let active = DBCharacterActiveEffect {
+        id: Uuid::new_v4(),
+        char_id: char_id.to_owned(),
+        effect_id: effect_id.to_owned(),
+        source,
+        started_at,
+        expires_at,
+    };
+    if !is_instantaneous(started_at, expires_at) {
+        active.db_insert(conn)?;
+    }
+    ActiveEffect::try_from_db(active, conn)
+}
+
+/// All of the effects currently applied to a character. Effects that ran out
+/// on another campaign's clock, e.g. before the character moved to this
+/// one, are left out.
+pub fn active_effects(character_id: &Uuid, conn: &Connection) -> Result<Vec<ActiveEffect>, Error> {
+    let now = current_time(character_id, conn)?;
+    let effects = {
+        use crate::schema::characteractiveeffects::dsl::*;
+        characteractiveeffects.filter(char_id.eq(character_id))
+            .order(started_at)
+            .load::<DBCharacterActiveEffect>(conn)
+            .map_err(Error::RunQuery)?
+    };
+    effects.into_iter()
+        .filter(|active| !matches!(now, Some(now) if has_expired(active.expires_at, now)))
+        .map(|active| ActiveEffect::try_from_db(active, conn))
+        .collect()
+}
+
+/// Remove every effect on the given characters that has expired by `now`,
+/// returning the removed effects.
+pub fn expire_effects(char_ids: &[Uuid], now: i64, conn: &Connection) -> Result<Vec<ActiveEffect>, Error> {
+    use crate::schema::characteractiveeffects::dsl::*;
+    let expired = diesel::delete(characteractiveeffects
+            .filter(char_id.eq_any(char_ids))
+            .filter(expires_at.le(now)))
+        .get_results::<DBCharacterActiveEffect>(conn)
+        .map_err(Error::RunQuery)?;
+    expired.into_iter()
+        .map(|active| ActiveEffect::try_from_db(active, conn))
+        .collect()
+}
+
+fn effect_from_form(form: &Form, conn: &Connection) -> Result<Uuid, Rejection> {
+    let effect: Option<Uuid> = forms::get_optional_form_text_field(form, FIELD_EFFECT_ID)?;
+    if let Some(effect) = effect {
+        return forms::valid_id::<Effect>(effect, conn);
+    }
+
+    let condition: String = forms::get_optional_form_text_field(form, FIELD_CONDITION)?
+        .ok_or_else(|| forms::missing_field_error(FIELD_EFFECT_ID))?;
+    let effect = {
+        use crate::schema::effects::dsl::*;
+        effects.select(id)
+            .filter(name.ilike(condition))
+            .first::<Uuid>(conn)
+            .optional()
+            .map_err(Error::RunQuery)?
+    };
+    effect.ok_or_else(|| forms::field_is_invalid_error(FIELD_CONDITION))
+}
+
+async fn list_effects(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<ActiveEffectList>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let list = ActiveEffectList {
+        game_time: current_time(&char_id, &conn)?,
+        effects: active_effects(&char_id, &conn)?,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(list)))
+}
+
+async fn add_effect(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<ActiveEffect>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let effect_id = effect_from_form(&form, &conn)?;
+    let source: String = forms::get_required_form_text_field(&form, FIELD_SOURCE)?;
+    let duration: Option<i64> = forms::get_optional_form_text_field(&form, FIELD_DURATION)?;
+    if duration.map(|rounds| rounds < 0).unwrap_or(false) {
+        return Err(forms::field_is_invalid_error(FIELD_DURATION));
+    }
+    let active = apply_effect(&char_id, &effect_id, source, duration, &conn)?;
+    events::publish_for_character(&char_id, Event::EffectApplied {
+        character_id: char_id,
+        effect: active.clone(),
+    }, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(active)))
+}
+
+async fn remove_effect(char_id: Uuid, active_id: Uuid, user: User, conn: Connection) -> Result<Status<Empty>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let active = DBCharacterActiveEffect::db_get_by_id(&active_id, &conn)
+        .map_err(|_| status::not_found())?;
+    if active.char_id != char_id {
+        return Err(status::not_found());
+    }
+    active.db_delete(&conn)?;
+    events::publish_for_character(&char_id, Event::EffectRemoved {
+        character_id: char_id,
+        effect: ActiveEffect::try_from_db(active, &conn)?,
+    }, &conn)?;
+    Ok(Status::new(&StatusCode::OK))
+}
+
+/// A warp Filter containing the endpoints for applying and removing timed
+/// effects, relative to `/characters`.
+pub fn active_effects_filter() -> BoxedFilter<(impl Reply,)> {
+    let list = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("effects"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(list_effects);
+    let add = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("effects"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(add_effect);
+    let remove = warp::delete()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("effects"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(remove_effect);
+
+    list.or(add)
+        .or(remove)
+        .boxed()
+}
@@ -0,0 +1,528 @@
+use super::character::DBCharacter;
+use super::effects::Modifiers;
+use super::experience::MAX_LEVEL;
+use super::health::{self, HealthReport, HitPoints};
+use super::sheet::CharacterSheet;
+use super::{Attribute, Attributes, CombatStat};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError};
+use crate::forms;
+use crate::schema::{characterabilitydamage, characters};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn afflictions(damage: Vec<(Attribute, i16)>, drain: Vec<(Attribute, i16)>, negative_levels: i16) -> Afflictions {
+        Afflictions {
+            damage: damage.into_iter().collect(),
+            drain: drain.into_iter().collect(),
+            negative_levels,
+        }
+    }
+
+    #[test]
+    fn drain_lowers_the_score_and_damage_the_modifier() {
+        let afflictions = afflictions(vec![(Attribute::Strength, 3)], vec![(Attribute::Strength, 4), (Attribute::Wisdom, 12)], 0);
+        assert_eq!(afflictions.drained_score(Attribute::Strength, 16), 12);
+        assert_eq!(afflictions.drained_score(Attribute::Wisdom, 10), 0);
+        assert_eq!(afflictions.drained_score(Attribute::Charisma, 10), 10);
+        // Every 2 points of damage is a -1 penalty.
+        assert_eq!(afflictions.damaged_modifier(Attribute::Strength, 1), 0);
+        assert_eq!(afflictions.damaged_modifier(Attribute::Dexterity, 1), 1);
+    }
+
+    #[test]
+    fn negative_levels_penalise_d20_rolls_and_hit_points() {
+        let afflictions = afflictions(vec![], vec![], 2);
+        assert_eq!(afflictions.d20_penalty(), -2);
+        assert_eq!(afflictions.hp_penalty(), 10);
+        assert_eq!(self::afflictions(vec![], vec![], i16::MAX).hp_penalty(), i16::MAX);
+
+        let mut modifiers = Modifiers::default();
+        modifiers.combat.insert(CombatStat::Will, 3);
+        modifiers.combat.insert(CombatStat::DamageReduction, 5);
+        afflictions.apply(&mut modifiers);
+        assert_eq!(modifiers.combat(CombatStat::Will), 1);
+        assert_eq!(modifiers.combat(CombatStat::MeleeAttackBonus), -2);
+        assert_eq!(modifiers.combat(CombatStat::CMD), -2);
+        assert_eq!(modifiers.combat(CombatStat::DamageReduction), 5);
+    }
+
+    #[test]
+    fn a_negative_level_per_character_level_is_fatal() {
+        assert!(!afflictions(vec![], vec![], 2).is_fatal(3));
+        assert!(afflictions(vec![], vec![], 3).is_fatal(3));
+    }
+
+    #[test]
+    fn rest_heals_damage_but_not_drain() {
+        let mut afflictions = afflictions(vec![(Attribute::Strength, 3), (Attribute::Wisdom, 1)], vec![(Attribute::Strength, 2)], 1);
+        afflictions.rest(1, false);
+        assert_eq!(afflictions.damage(Attribute::Strength), 2);
+        assert!(!afflictions.damage.contains_key(&Attribute::Wisdom));
+
+        afflictions.rest(1, true);
+        assert!(afflictions.damage.is_empty());
+        assert_eq!(afflictions.drain(Attribute::Strength), 2);
+        assert_eq!(afflictions.negative_levels, 1);
+
+        let mut afflictions = self::afflictions(vec![(Attribute::Strength, 3)], vec![], 0);
+        afflictions.rest(i16::MAX, true);
+        assert!(afflictions.damage.is_empty());
+    }
+
+    #[test]
+    fn restoration_strength_decides_what_is_restored() {
+        let start = afflictions(
+            vec![(Attribute::Strength, 4), (Attribute::Dexterity, 2)],
+            vec![(Attribute::Strength, 2), (Attribute::Dexterity, 2)],
+            2,
+        );
+
+        let mut lesser = start.clone();
+        lesser.restore(Restoration::Lesser, Some(Attribute::Strength), 3);
+        assert_eq!(lesser.damage(Attribute::Strength), 1);
+        assert_eq!(lesser.damage(Attribute::Dexterity), 2);
+        assert_eq!(lesser.drain, start.drain);
+        assert_eq!(lesser.negative_levels, 2);
+
+        let mut restoration = start.clone();
+        restoration.restore(Restoration::Restoration, Some(Attribute::Strength), 0);
+        assert!(restoration.damage.is_empty());
+        assert_eq!(restoration.drain(Attribute::Strength), 0);
+        assert_eq!(restoration.drain(Attribute::Dexterity), 2);
+        assert_eq!(restoration.negative_levels, 1);
+
+        let mut greater = start;
+        greater.restore(Restoration::Greater, None, 0);
+        assert_eq!(greater, Afflictions::default());
+    }
+}
+
+/// The form field holding the ability that is damaged, drained or restored.
+pub const FIELD_ATTRIBUTE: &str = "attribute";
+/// The form field that makes ability damage drain instead.
+pub const FIELD_DRAIN: &str = "drain";
+/// The form field holding the kind of restoration used.
+pub const FIELD_RESTORATION: &str = "restoration";
+/// The form field holding how many nights a character rests for.
+pub const FIELD_NIGHTS: &str = "nights";
+/// The form field set when a character spends the whole day in bed.
+pub const FIELD_BED_REST: &str = "bed-rest";
+
+/// The maximum hit points each negative level costs.
+pub const HP_PER_NEGATIVE_LEVEL: i16 = 5;
+/// The most negative levels a character can have. By then a character of
+/// any level is dead.
+pub const MAX_NEGATIVE_LEVELS: i16 = MAX_LEVEL;
+/// The most nights a character can rest for at once.
+pub const MAX_NIGHTS: i16 = 365;
+
+/// The combat stats that are rolled on a d20, or defend against one, and so
+/// take the negative level penalty.
+const D20_STATS: [CombatStat; 9] = [
+    CombatStat::MeleeAttackBonus,
+    CombatStat::RangedAttackBonus,
+    CombatStat::CMB,
+    CombatStat::CMD,
+    CombatStat::Fortitude,
+    CombatStat::Reflex,
+    CombatStat::Will,
+    CombatStat::CasterLevel,
+    CombatStat::Concentration,
+];
+
+/// The spells that undo ability damage, drain and negative levels.
+#[derive(Serialize, Deserialize, Display, FromStr, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Restoration {
+    /// Heals a rolled amount of damage to one ability.
+    Lesser,
+    /// Heals all ability damage, the drain to one ability and a negative
+    /// level.
+    Restoration,
+    /// Heals all ability damage, drain and negative levels.
+    Greater,
+}
+
+/// The ability damage, ability drain and negative levels a character has
+/// taken. Damage penalises an ability's modifier by 1 for every 2 points and
+/// heals with rest, while drain lowers the score itself and only magic
+/// restores it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Ord, PartialOrd, PartialEq, Eq)]
+pub struct Afflictions {
+    pub damage: Attributes,
+    pub drain: Attributes,
+    pub negative_levels: i16,
+}
+
+impl Afflictions {
+    pub fn load(character: &DBCharacter, conn: &Connection) -> Result<Self, DBError> {
+        let mut afflictions = Afflictions {
+            negative_levels: character.negative_levels(),
+            ..Default::default()
+        };
+        for loss in DBCharacterAbilityDamage::belonging_to(character).load::<DBCharacterAbilityDamage>(conn).map_err(DBError::RunQuery)? {
+            afflictions.damage.insert(loss.attr, loss.damage);
+            afflictions.drain.insert(loss.attr, loss.drain);
+        }
+        afflictions.clear_healed();
+        Ok(afflictions)
+    }
+
+    pub fn damage(&self, attr: Attribute) -> i16 {
+        self.damage.get(&attr).copied().unwrap_or(0)
+    }
+
+    pub fn drain(&self, attr: Attribute) -> i16 {
+        self.drain.get(&attr).copied().unwrap_or(0)
+    }
+
+    /// An ability score after drain, which can't take it below 0.
+    pub fn drained_score(&self, attr: Attribute, score: i16) -> i16 {
+        (score - self.drain(attr)).max(0)
+    }
+
+    /// An ability modifier after the penalty for ability damage.
+    pub fn damaged_modifier(&self, attr: Attribute, modifier: i16) -> i16 {
+        modifier - self.damage(attr) / 2
+    }
+
+    /// The penalty on every d20 roll: attack rolls, saving throws, skill and
+    /// ability checks.
+    pub fn d20_penalty(&self) -> i16 {
+        -self.negative_levels
+    }
+
+    /// The maximum hit points lost to negative levels.
+    pub fn hp_penalty(&self) -> i16 {
+        self.negative_levels.saturating_mul(HP_PER_NEGATIVE_LEVEL)
+    }
+
+    /// A character with as many negative levels as character levels dies.
+    pub fn is_fatal(&self, level: i16) -> bool {
+        self.negative_levels > 0 && self.negative_levels >= level
+    }
+
+    /// Add the negative level penalty to the combat stats it affects.
+    pub fn apply(&self, modifiers: &mut Modifiers) {
+        if self.negative_levels == 0 {
+            return;
+        }
+        for stat in D20_STATS.iter() {
+            *modifiers.combat.entry(*stat).or_insert(0) += self.d20_penalty();
+        }
+    }
+
+    pub fn take(&mut self, attr: Attribute, amount: i16, drain: bool) {
+        let losses = if drain { &mut self.drain } else { &mut self.damage };
+        let total = losses.entry(attr).or_insert(0);
+        *total = total.saturating_add(amount);
+        self.clear_healed();
+    }
+
+    /// A night's rest heals 1 point of damage to each ability, or 2 after a
+    /// full day of bed rest. Drain and negative levels aren't affected.
+    pub fn rest(&mut self, nights: i16, bed_rest: bool) {
+        let healed = if bed_rest { nights.saturating_mul(2) } else { nights };
+        for damage in self.damage.values_mut() {
+            *damage = damage.saturating_sub(healed);
+        }
+        self.clear_healed();
+    }
+
+    /// Cast a restoration spell. Lesser restoration and restoration target
+    /// the ability `attr`, and `amount` is the damage lesser restoration
+    /// rolled to heal; the other spells don't use it.
+    pub fn restore(&mut self, restoration: Restoration, attr: Option<Attribute>, amount: i16) {
+        match restoration {
+            Restoration::Lesser => {
+                if let Some(damage) = attr.and_then(|attr| self.damage.get_mut(&attr)) {
+                    *damage -= amount;
+                }
+            },
+            Restoration::Restoration => {
+                self.damage.clear();
+                if let Some(attr) = attr {
+                    self.drain.remove(&attr);
+                }
+                self.negative_levels = (self.negative_levels - 1).max(0);
+            },
+            Restoration::Greater => {
+                self.damage.clear();
+                self.drain.clear();
+                self.negative_levels = 0;
+            },
+        }
+        self.clear_healed();
+    }
+
+    fn clear_healed(&mut self) {
+        self.damage.retain(|_, damage| *damage > 0);
+        self.drain.retain(|_, drain| *drain > 0);
+    }
+
+    fn save(&self, char_id: &Uuid, conn: &Connection) -> Result<(), DBError> {
+        let attrs = self.damage.keys()
+            .chain(self.drain.keys())
+            .copied()
+            .collect::<BTreeSet<Attribute>>();
+        let losses = attrs.into_iter()
+            .map(|attr| DBCharacterAbilityDamage {
+                char_id: *char_id,
+                attr,
+                damage: self.damage(attr),
+                drain: self.drain(attr),
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction::<_, DBError, _>(|| {
+            diesel::delete(characterabilitydamage::table.filter(characterabilitydamage::char_id.eq(char_id)))
+                .execute(conn)
+                .map_err(DBError::RunQuery)?;
+            diesel::insert_into(characterabilitydamage::table)
+                .values(&losses)
+                .execute(conn)
+                .map_err(DBError::RunQuery)?;
+            diesel::update(characters::table.filter(characters::id.eq(char_id)))
+                .set(characters::negative_levels.eq(self.negative_levels))
+                .execute(conn)
+                .map(|_| ())
+                .map_err(DBError::RunQuery)
+        })
+    }
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[table_name = "characterabilitydamage"]
+#[primary_key(char_id, attr)]
+#[belongs_to(DBCharacter, foreign_key = "char_id")]
+pub struct DBCharacterAbilityDamage {
+    pub(crate) char_id: Uuid,
+    pub(crate) attr: Attribute,
+    pub(crate) damage: i16,
+    pub(crate) drain: i16,
+}
+
+/// A character's afflictions and the ability scores and hit points they
+/// leave the character with.
+#[derive(Serialize, Clone, Debug)]
+pub struct AfflictionReport {
+    pub afflictions: Afflictions,
+    pub attributes: Attributes,
+    pub attribute_modifiers: Attributes,
+    pub d20_penalty: i16,
+    pub hit_points: HitPoints,
+    /// Whether the character has as many negative levels as levels, which
+    /// kills them.
+    pub slain: bool,
+}
+
+impl AfflictionReport {
+    fn load(character: DBCharacter, conn: &Connection) -> Result<Self, DBError> {
+        let level = character.total_level(conn)?;
+        let sheet = CharacterSheet::load_from_db(character, conn)?;
+        Ok(AfflictionReport {
+            slain: sheet.character.afflictions().is_fatal(level),
+            d20_penalty: sheet.character.afflictions().d20_penalty(),
+            afflictions: sheet.character.afflictions().clone(),
+            attributes: sheet.attributes,
+            attribute_modifiers: sheet.attribute_modifiers,
+            hit_points: sheet.hit_points,
+        })
+    }
+}
+
+impl From<AfflictionReport> for Bytes {
+    fn from(report: AfflictionReport) -> Self {
+        status::serialize_to_bytes(&report)
+    }
+}
+
+/// The outcome of resting: the hit points recovered and the afflictions
+/// left afterwards.
+#[derive(Serialize, Clone, Debug)]
+pub struct RestReport {
+    pub health: HealthReport,
+    pub afflictions: AfflictionReport,
+}
+
+impl From<RestReport> for Bytes {
+    fn from(report: RestReport) -> Self {
+        status::serialize_to_bytes(&report)
+    }
+}
+
+/// The character's row, locked until the end of the transaction.
+fn locked_character(char_id: Uuid, conn: &Connection) -> Result<DBCharacter, DBError> {
+    characters::table.filter(characters::id.eq(char_id))
+        .for_update()
+        .first(conn)
+        .map_err(DBError::RunQuery)
+}
+
+/// Applies `change` to the character's afflictions and saves the result. The
+/// character's row is locked while this happens, so concurrent changes can't
+/// overwrite each other.
+fn change_afflictions<F>(char_id: Uuid, user: &User, conn: &Connection, change: F) -> Result<AfflictionReport, Rejection>
+where
+    F: FnOnce(&mut Afflictions),
+{
+    campaign::managed_character(user, char_id, conn)?;
+    conn.transaction::<_, DBError, _>(|| {
+        let character = locked_character(char_id, conn)?;
+        let mut afflictions = Afflictions::load(&character, conn)?;
+        change(&mut afflictions);
+        afflictions.save(&char_id, conn)?;
+        AfflictionReport::load(locked_character(char_id, conn)?, conn)
+    }).map_err(Rejection::from)
+}
+
+async fn get_afflictions(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<AfflictionReport>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let report = AfflictionReport::load(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+async fn damage_ability(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<AfflictionReport>>, Rejection> {
+    let attr: Attribute = forms::get_required_form_text_field(&form, FIELD_ATTRIBUTE)?;
+    let amount = health::amount_from_form(&form)?;
+    let drain = forms::get_optional_form_text_field(&form, FIELD_DRAIN)?
+        .unwrap_or(false);
+    let report = change_afflictions(char_id, &user, &conn, |afflictions| {
+        afflictions.take(attr, amount, drain);
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+/// Positive amounts bestow negative levels, while negative amounts remove
+/// them, e.g. after a successful save.
+async fn change_negative_levels(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<AfflictionReport>>, Rejection> {
+    let amount: i16 = forms::get_required_form_text_field(&form, health::FIELD_AMOUNT)?;
+    let report = change_afflictions(char_id, &user, &conn, |afflictions| {
+        afflictions.negative_levels = afflictions.negative_levels.saturating_add(amount).clamp(0, MAX_NEGATIVE_LEVELS);
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+async fn restore(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<AfflictionReport>>, Rejection> {
+    let restoration: Restoration = forms::get_required_form_text_field(&form, FIELD_RESTORATION)?;
+    let attr = match restoration {
+        Restoration::Greater => None,
+        _ => Some(forms::get_required_form_text_field(&form, FIELD_ATTRIBUTE)?),
+    };
+    let amount = match restoration {
+        Restoration::Lesser => health::amount_from_form(&form)?,
+        _ => 0,
+    };
+    let report = change_afflictions(char_id, &user, &conn, |afflictions| {
+        afflictions.restore(restoration, attr, amount);
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+/// Each night of rest recovers hit points equal to the character's level,
+/// all nonlethal damage and a point of damage to each ability. A full day
+/// of bed rest doubles the hit points and ability damage recovered.
+async fn rest(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<RestReport>>, Rejection> {
+    let nights: i16 = forms::get_optional_form_text_field(&form, FIELD_NIGHTS)?
+        .unwrap_or(1);
+    if !(1..=MAX_NIGHTS).contains(&nights) {
+        return Err(forms::field_is_invalid_error(FIELD_NIGHTS));
+    }
+    let bed_rest = forms::get_optional_form_text_field(&form, FIELD_BED_REST)?
+        .unwrap_or(false);
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+
+    // Both the afflictions and the hit points are changed while the
+    // character is locked. A slain character can't rest, so there's no
+    // report for them.
+    let report = conn.transaction::<_, DBError, _>(|| {
+        let locked = locked_character(char_id, &conn)?;
+        let level = locked.total_level(&conn)?;
+        let mut afflictions = Afflictions::load(&locked, &conn)?;
+        if afflictions.is_fatal(level) {
+            return Ok(None);
+        }
+        afflictions.rest(nights, bed_rest);
+        afflictions.save(&char_id, &conn)?;
+
+        let sheet = CharacterSheet::load_from_db(locked_character(char_id, &conn)?, &conn)?;
+        let mut hit_points = sheet.hit_points;
+        let recovered = level.max(1).saturating_mul(nights).saturating_mul(if bed_rest { 2 } else { 1 });
+        hit_points.heal(recovered, false);
+        hit_points.heal(hit_points.nonlethal, true);
+        let health = health::save_and_report(char_id, hit_points, None, &conn)?;
+
+        Ok(Some(RestReport {
+            health,
+            afflictions: AfflictionReport::load(locked_character(char_id, &conn)?, &conn)?,
+        }))
+    })?;
+    let report = report
+        .ok_or_else(|| status::bad_request(format!("{} has been slain by negative levels", character.name())))?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+/// A warp Filter containing the ability damage, negative level, restoration
+/// and rest endpoints, relative to the `/characters` path.
+pub fn afflictions_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("afflictions"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_afflictions);
+    let damage = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("ability-damage"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(damage_ability);
+    let negative_levels = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("negative-levels"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(change_negative_levels);
+    let restore = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(restore);
+    let rest = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("rest"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(rest);
+
+    get.or(damage)
+        .or(negative_levels)
+        .or(restore)
+        .or(rest)
+        .boxed()
+}
@@ -0,0 +1,791 @@
+use super::character::{RaceSubtype, RaceType};
+use super::effects::Effect;
+use super::feat::Feat;
+use super::sheet;
+use super::spell::Spell;
+use super::summary::{Summarize, Summary};
+use super::{Alignment, Attribute, Attributes, CombatStat, CombatStats, DamageType, Links, Size};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Delete, DeleteById, Error as DBError, GetAll, GetById, Insert, IntoDb, TryFromDb, Update};
+use crate::forms::{self, TryFromForm};
+use crate::schema::{
+    creatureabilities, creatureattacks, creatureattributes, creaturecombatstats, creatureeffects, creaturefeats,
+    creatures, creaturespells,
+};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_ratings_round_trip_through_text() {
+        for text in &["1/8", "1/6", "1/4", "1/3", "1/2", "1", "7", "30"] {
+            let cr: ChallengeRating = text.parse().expect("valid challenge rating");
+            assert_eq!(&cr.to_string(), text);
+        }
+        assert!("0".parse::<ChallengeRating>().is_err());
+        assert!("1/5".parse::<ChallengeRating>().is_err());
+        assert!("31".parse::<ChallengeRating>().is_err());
+    }
+
+    #[test]
+    fn fractional_challenge_ratings_sort_below_one() {
+        let eighth: ChallengeRating = "1/8".parse().unwrap();
+        let half: ChallengeRating = "1/2".parse().unwrap();
+        let one: ChallengeRating = "1".parse().unwrap();
+        assert!(eighth < half);
+        assert!(half < one);
+    }
+
+    #[test]
+    fn xp_values_follow_the_challenge_rating() {
+        let xp = |cr: &str| cr.parse::<ChallengeRating>().unwrap().xp();
+        assert_eq!(xp("1/8"), 50);
+        assert_eq!(xp("1/3"), 135);
+        assert_eq!(xp("1"), 400);
+        assert_eq!(xp("5"), 1600);
+        assert_eq!(xp("20"), 307_200);
+        assert_eq!(xp("30"), 9_830_400);
+    }
+
+    #[test]
+    fn challenge_rating_for_xp_rounds_down() {
+        assert_eq!(ChallengeRating::for_xp(49), None);
+        assert_eq!(ChallengeRating::for_xp(50).map(|cr| cr.to_string()), Some("1/8".to_string()));
+        assert_eq!(ChallengeRating::for_xp(1000).map(|cr| cr.to_string()), Some("3".to_string()));
+        assert_eq!(ChallengeRating::for_xp(i32::MAX).map(|cr| cr.to_string()), Some("30".to_string()));
+        assert_eq!(ChallengeRating::nearest(-10).to_string(), "1/8");
+        assert_eq!(ChallengeRating::nearest(40).to_string(), "30");
+    }
+
+    #[test]
+    fn like_wildcards_are_searched_for_literally() {
+        assert_eq!(escape_like("goblin"), "goblin");
+        assert_eq!(escape_like("100%_orc\\"), "100\\%\\_orc\\\\");
+    }
+}
+
+/// The denominators of the fractional challenge ratings, from the lowest.
+const CR_FRACTIONS: [i16; 5] = [8, 6, 4, 3, 2];
+const FRACTIONAL_XP: [i32; 5] = [50, 65, 100, 135, 200];
+const CR_XP: [i32; 30] = [
+    400, 600, 800, 1200, 1600, 2400, 3200, 4800, 6400, 9600,
+    12800, 19200, 25600, 38400, 51200, 76800, 102400, 153600, 204800, 307200,
+    409600, 614400, 819200, 1228800, 1638400, 2457600, 3276800, 4915200, 6553600, 9830400,
+];
+
+/// How dangerous a creature is. CR 1 and up are stored as is; the fractional
+/// CRs from 1/2 down to 1/8 are stored as the steps 0 down to -4, so that
+/// challenge ratings sort correctly.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct ChallengeRating(i16);
+
+impl ChallengeRating {
+    pub const MIN_STEP: i16 = 1 - CR_FRACTIONS.len() as i16;
+    pub const MAX_STEP: i16 = CR_XP.len() as i16;
+
+    pub fn from_step(step: i16) -> Option<Self> {
+        if (Self::MIN_STEP..=Self::MAX_STEP).contains(&step) {
+            Some(ChallengeRating(step))
+        } else {
+            None
+        }
+    }
+
+    /// The CR at the given step, or the nearest one that exists.
+    pub fn nearest(step: i16) -> Self {
+        ChallengeRating(step.clamp(Self::MIN_STEP, Self::MAX_STEP))
+    }
+
+    /// The highest CR worth no more than the given experience, if any.
+    pub fn for_xp(xp: i32) -> Option<Self> {
+        (Self::MIN_STEP..=Self::MAX_STEP)
+            .map(ChallengeRating)
+            .take_while(|cr| cr.xp() <= xp)
+            .last()
+    }
+
+    pub fn step(self) -> i16 {
+        self.0
+    }
+
+    /// The experience earned for defeating a creature of this CR.
+    pub fn xp(self) -> i32 {
+        if self.0 > 0 {
+            CR_XP[self.0 as usize - 1]
+        } else {
+            FRACTIONAL_XP[(self.0 - Self::MIN_STEP) as usize]
+        }
+    }
+}
+
+impl fmt::Display for ChallengeRating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 > 0 {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "1/{}", CR_FRACTIONS[(self.0 - Self::MIN_STEP) as usize])
+        }
+    }
+}
+
+impl FromStr for ChallengeRating {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(format!("invalid challenge rating: {}", val));
+        let val = val.trim();
+        let step = if let Some(denominator) = val.strip_prefix("1/") {
+            let denominator: i16 = denominator.parse().map_err(|_| invalid())?;
+            let pos = CR_FRACTIONS.iter()
+                .position(|d| *d == denominator)
+                .ok_or_else(invalid)?;
+            Self::MIN_STEP + pos as i16
+        } else {
+            let cr: i16 = val.parse().map_err(|_| invalid())?;
+            if cr < 1 {
+                return Err(invalid());
+            }
+            cr
+        };
+        ChallengeRating::from_step(step).ok_or_else(invalid)
+    }
+}
+
+impl Serialize for ChallengeRating {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChallengeRating {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(|err: Error| serde::de::Error::custom(err.message))
+    }
+}
+
+/// One of a creature's natural attacks, such as a bite or a pair of claws.
+#[derive(Serialize, Deserialize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct NaturalAttack {
+    pub name: String,
+    /// How many of this attack the creature makes in a full attack.
+    pub count: i16,
+    pub attack_bonus: i16,
+    /// The damage dealt, such as "1d6+4".
+    pub damage: String,
+    pub damage_type: DamageType,
+}
+
+/// A creature's special ability, such as poison or a breath weapon.
+#[derive(Serialize, Deserialize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct CreatureAbility {
+    pub name: String,
+    pub description: String,
+}
+
+/// A monster or NPC from the bestiary, used by game masters in encounters.
+/// Damage reduction and spell resistance are kept with the other combat
+/// stats.
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct Creature {
+    id: Uuid,
+    links: Links,
+    name: String,
+    description: String,
+    pub cr: ChallengeRating,
+    /// The experience earned for defeating the creature.
+    pub xp: i32,
+    pub main_type: RaceType,
+    pub sub_type: Option<RaceSubtype>,
+    pub size: Size,
+    pub alignment: Alignment,
+    pub hit_dice: i16,
+    pub hit_points: i16,
+    pub attributes: Attributes,
+    pub combat_stats: CombatStats,
+    /// What overcomes the creature's damage reduction, such as "magic".
+    pub dr_bypassed_by: Option<String>,
+    pub natural_attacks: BTreeSet<NaturalAttack>,
+    pub special_abilities: BTreeSet<CreatureAbility>,
+    pub feats: BTreeSet<Summary<Feat>>,
+    pub spells: BTreeSet<Summary<Spell>>,
+    pub effects: BTreeSet<Summary<Effect>>,
+}
+
+impl Creature {
+    const FIELD_NAME: &'static str = "name";
+    const FIELD_DESCRIPTION: &'static str = "description";
+    const FIELD_CR: &'static str = "cr";
+    const FIELD_MAIN_TYPE: &'static str = "main-type";
+    const FIELD_SUB_TYPE: &'static str = "sub-type";
+    const FIELD_SIZE: &'static str = "size";
+    const FIELD_ALIGNMENT: &'static str = "alignment";
+    const FIELD_HIT_DICE: &'static str = "hit-dice";
+    const FIELD_HIT_POINTS: &'static str = "hit-points";
+    const FIELD_ATTRIBUTES: &'static str = "attributes";
+    const FIELD_COMBAT_STATS: &'static str = "combat-stats";
+    const FIELD_DR_BYPASSED_BY: &'static str = "dr-bypassed-by";
+    const FIELD_NATURAL_ATTACKS: &'static str = "natural-attacks";
+    const FIELD_SPECIAL_ABILITIES: &'static str = "special-abilities";
+    const FIELD_FEATS: &'static str = "feats";
+    const FIELD_SPELLS: &'static str = "spells";
+    const FIELD_EFFECTS: &'static str = "effects";
+
+    fn links_for(id: &Uuid) -> Links {
+        let mut links = Links::new();
+        links.insert("self".to_string(), format!("/bestiary/{}", id));
+        links
+    }
+
+    /// The creature's bonus on initiative checks: the one in its stat block
+    /// if it has one, otherwise its Dexterity modifier.
+    pub fn initiative_bonus(&self) -> i16 {
+        self.combat_stats.get(&CombatStat::InitiativeBonus)
+            .copied()
+            .unwrap_or_else(|| sheet::attribute_modifier(self.dexterity()))
+    }
+
+    /// The creature's Dexterity score. Creatures without one count as 0.
+    pub fn dexterity(&self) -> i16 {
+        self.attributes.get(&Attribute::Dexterity).copied().unwrap_or(0)
+    }
+}
+
+/// Parses a JSON object whose keys are enum values, such as
+/// `{"strength": 18}`.
+fn enum_map_from_form<K: FromStr<Err = Error> + Ord>(form: &Form, field_name: &str) -> Result<BTreeMap<K, i16>, Rejection> {
+    let map: String = forms::get_required_form_text_field(form, field_name)?;
+    serde_json::from_str::<BTreeMap<String, i16>>(&map)
+        .map_err(|_| forms::field_is_invalid_error(field_name))?
+        .into_iter()
+        .map(|(key, value)| {
+            let key = key.as_str().parse()
+                .map_err(|e| Rejection::from(Status::with_data(&StatusCode::BAD_REQUEST, e)))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Parses a JSON list of IDs and looks up each of them.
+fn summaries_from_form<T>(form: &Form, field_name: &str, conn: &Connection) -> Result<BTreeSet<Summary<T>>, Rejection>
+    where Summary<T>: GetById
+{
+    let ids: String = forms::get_required_form_text_field(form, field_name)?;
+    serde_json::from_str::<Vec<Uuid>>(&ids)
+        .map_err(|_| forms::field_is_invalid_error(field_name))?
+        .into_iter()
+        .map(|id| forms::value_by_id(id, conn))
+        .collect()
+}
+
+impl TryFromForm for Creature {
+    fn try_from_form(conn: &Connection, form: Form, this_id: Option<Uuid>, _parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
+        let id = forms::valid_id_or_new::<Creature>(this_id, conn)?;
+        let name = forms::get_required_form_text_field(&form, Creature::FIELD_NAME)?;
+        let description = forms::get_required_form_text_field(&form, Creature::FIELD_DESCRIPTION)?;
+        let cr: ChallengeRating = forms::get_required_form_text_field(&form, Creature::FIELD_CR)?;
+        let main_type = forms::get_required_form_text_field(&form, Creature::FIELD_MAIN_TYPE)?;
+        let main_type = forms::value_by_id(main_type, conn)?;
+        let sub_type = forms::get_optional_form_text_field(&form, Creature::FIELD_SUB_TYPE)?
+            .map(|id| forms::value_by_id(id, conn))
+            .transpose()?;
+        let size = forms::get_required_form_text_field(&form, Creature::FIELD_SIZE)?;
+        let alignment = forms::get_required_form_text_field(&form, Creature::FIELD_ALIGNMENT)?;
+        let hit_dice: i16 = forms::get_required_form_text_field(&form, Creature::FIELD_HIT_DICE)?;
+        if hit_dice <= 0 {
+            return Err(forms::field_is_invalid_error(Creature::FIELD_HIT_DICE));
+        }
+        let hit_points: i16 = forms::get_required_form_text_field(&form, Creature::FIELD_HIT_POINTS)?;
+        if hit_points <= 0 {
+            return Err(forms::field_is_invalid_error(Creature::FIELD_HIT_POINTS));
+        }
+        let attributes: Attributes = enum_map_from_form(&form, Creature::FIELD_ATTRIBUTES)?;
+        if attributes.values().any(|score| *score < 0) {
+            return Err(forms::field_is_invalid_error(Creature::FIELD_ATTRIBUTES));
+        }
+        let combat_stats = enum_map_from_form(&form, Creature::FIELD_COMBAT_STATS)?;
+        let dr_bypassed_by = forms::get_optional_form_text_field(&form, Creature::FIELD_DR_BYPASSED_BY)?;
+
+        let natural_attacks: String = forms::get_required_form_text_field(&form, Creature::FIELD_NATURAL_ATTACKS)?;
+        let natural_attacks: BTreeSet<NaturalAttack> = serde_json::from_str(&natural_attacks)
+            .map_err(|_| forms::field_is_invalid_error(Creature::FIELD_NATURAL_ATTACKS))?;
+        if natural_attacks.iter().any(|attack| attack.count <= 0) {
+            return Err(forms::field_is_invalid_error(Creature::FIELD_NATURAL_ATTACKS));
+        }
+        let special_abilities: String = forms::get_required_form_text_field(&form, Creature::FIELD_SPECIAL_ABILITIES)?;
+        let special_abilities = serde_json::from_str(&special_abilities)
+            .map_err(|_| forms::field_is_invalid_error(Creature::FIELD_SPECIAL_ABILITIES))?;
+
+        let feats = summaries_from_form(&form, Creature::FIELD_FEATS, conn)?;
+        let spells = summaries_from_form(&form, Creature::FIELD_SPELLS, conn)?;
+        let effects = summaries_from_form(&form, Creature::FIELD_EFFECTS, conn)?;
+
+        let creature = Creature {
+            id,
+            links: Creature::links_for(&id),
+            name,
+            description,
+            cr,
+            xp: cr.xp(),
+            main_type,
+            sub_type,
+            size,
+            alignment,
+            hit_dice,
+            hit_points,
+            attributes,
+            combat_stats,
+            dr_bypassed_by,
+            natural_attacks,
+            special_abilities,
+            feats,
+            spells,
+            effects,
+        };
+
+        Ok(creature)
+    }
+}
+
+impl TryFromDb for Creature {
+    type DBType = DBCreature;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let cr = ChallengeRating::from_step(other.cr)
+            .ok_or_else(|| DBError::Other(format!("invalid challenge rating step {}", other.cr)))?;
+        let main_type = RaceType::db_get_by_id(&other.type_id, conn)?;
+        let sub_type = other.subtype_id.map(|id| RaceSubtype::db_get_by_id(&id, conn)).transpose()?;
+        let attributes = DBCreatureAttribute::belonging_to(&other)
+            .load::<DBCreatureAttribute>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|attr| (attr.attr, attr.score))
+            .collect();
+        let combat_stats = DBCreatureCombatStat::belonging_to(&other)
+            .load::<DBCreatureCombatStat>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|stat| (stat.stat, stat.value))
+            .collect();
+        let natural_attacks = DBCreatureAttack::belonging_to(&other)
+            .load::<DBCreatureAttack>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|attack| NaturalAttack {
+                name: attack.name,
+                count: attack.count,
+                attack_bonus: attack.attack_bonus,
+                damage: attack.damage,
+                damage_type: attack.damage_type,
+            })
+            .collect();
+        let special_abilities = DBCreatureAbility::belonging_to(&other)
+            .load::<DBCreatureAbility>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|ability| CreatureAbility { name: ability.name, description: ability.description })
+            .collect();
+        let feats = DBCreatureFeat::belonging_to(&other)
+            .load::<DBCreatureFeat>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|feat| Summary::<Feat>::db_get_by_id(&feat.feat_id, conn))
+            .collect::<Result<_, DBError>>()?;
+        let spells = DBCreatureSpell::belonging_to(&other)
+            .load::<DBCreatureSpell>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|spell| Summary::<Spell>::db_get_by_id(&spell.spell_id, conn))
+            .collect::<Result<_, DBError>>()?;
+        let effects = DBCreatureEffect::belonging_to(&other)
+            .load::<DBCreatureEffect>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|effect| Summary::<Effect>::db_get_by_id(&effect.effect_id, conn))
+            .collect::<Result<_, DBError>>()?;
+
+        let creature = Creature {
+            id: other.id,
+            links: Creature::links_for(&other.id),
+            name: other.name,
+            description: other.description,
+            cr,
+            xp: cr.xp(),
+            main_type,
+            sub_type,
+            size: other.size,
+            alignment: other.alignment,
+            hit_dice: other.hit_dice,
+            hit_points: other.hit_points,
+            attributes,
+            combat_stats,
+            dr_bypassed_by: other.dr_bypassed_by,
+            natural_attacks,
+            special_abilities,
+            feats,
+            spells,
+            effects,
+        };
+        Ok(creature)
+    }
+}
+
+impl IntoDb for Creature {
+    type DBType = (DBCreature, CreatureRows);
+
+    fn into_db(self) -> Self::DBType {
+        let id = self.id;
+        let rows = CreatureRows {
+            attributes: self.attributes.iter()
+                .map(|(attr, score)| DBCreatureAttribute { creature_id: id, attr: *attr, score: *score })
+                .collect(),
+            combat_stats: self.combat_stats.iter()
+                .map(|(stat, value)| DBCreatureCombatStat { creature_id: id, stat: *stat, value: *value })
+                .collect(),
+            attacks: self.natural_attacks.into_iter()
+                .map(|attack| DBCreatureAttack {
+                    creature_id: id,
+                    name: attack.name,
+                    count: attack.count,
+                    attack_bonus: attack.attack_bonus,
+                    damage: attack.damage,
+                    damage_type: attack.damage_type,
+                })
+                .collect(),
+            abilities: self.special_abilities.into_iter()
+                .map(|ability| DBCreatureAbility { creature_id: id, name: ability.name, description: ability.description })
+                .collect(),
+            feats: self.feats.iter()
+                .map(|feat| DBCreatureFeat { creature_id: id, feat_id: feat.id().to_owned() })
+                .collect(),
+            spells: self.spells.iter()
+                .map(|spell| DBCreatureSpell { creature_id: id, spell_id: spell.id().to_owned() })
+                .collect(),
+            effects: self.effects.iter()
+                .map(|effect| DBCreatureEffect { creature_id: id, effect_id: effect.id().to_owned() })
+                .collect(),
+        };
+
+        let creature = DBCreature {
+            id,
+            name: self.name,
+            description: self.description,
+            cr: self.cr.step(),
+            type_id: self.main_type.id().to_owned(),
+            subtype_id: self.sub_type.map(|sub| sub.id().to_owned()),
+            size: self.size,
+            alignment: self.alignment,
+            hit_dice: self.hit_dice,
+            hit_points: self.hit_points,
+            dr_bypassed_by: self.dr_bypassed_by,
+        };
+
+        (creature, rows)
+    }
+}
+
+/// Everything about a creature that is kept outside of the Creatures table.
+pub struct CreatureRows {
+    attributes: Vec<DBCreatureAttribute>,
+    combat_stats: Vec<DBCreatureCombatStat>,
+    attacks: Vec<DBCreatureAttack>,
+    abilities: Vec<DBCreatureAbility>,
+    feats: Vec<DBCreatureFeat>,
+    spells: Vec<DBCreatureSpell>,
+    effects: Vec<DBCreatureEffect>,
+}
+
+impl CreatureRows {
+    fn db_insert(&self, conn: &Connection) -> Result<(), DBError> {
+        for row in self.attributes.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.combat_stats.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.attacks.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.abilities.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.feats.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.spells.iter() {
+            row.db_insert(conn)?;
+        }
+        for row in self.effects.iter() {
+            row.db_insert(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Removes every row belonging to the creature.
+    fn db_clear(creature: &DBCreature, conn: &Connection) -> Result<(), DBError> {
+        diesel::delete(DBCreatureAttribute::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureCombatStat::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureAttack::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureAbility::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureFeat::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureSpell::belonging_to(creature)).execute(conn)?;
+        diesel::delete(DBCreatureEffect::belonging_to(creature)).execute(conn)?;
+        Ok(())
+    }
+}
+
+impl Insert for Creature {
+    fn db_insert(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction::<_, DBError, _>(|| {
+            let (creature, rows) = self.to_owned().into_db();
+            creature.db_insert(conn)?;
+            rows.db_insert(conn)
+        })
+    }
+}
+
+impl Update for Creature {
+    fn db_update(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction::<_, DBError, _>(|| {
+            let (creature, rows) = self.to_owned().into_db();
+            creature.db_update(conn)?;
+            CreatureRows::db_clear(&creature, conn)?;
+            rows.db_insert(conn)
+        })
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[changeset_options(treat_none_as_null = "true")]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creatures"]
+pub struct DBCreature {
+    id: Uuid,
+    name: String,
+    description: String,
+    cr: i16,
+    type_id: Uuid,
+    subtype_id: Option<Uuid>,
+    size: Size,
+    alignment: Alignment,
+    hit_dice: i16,
+    hit_points: i16,
+    dr_bypassed_by: Option<String>,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creatureattributes"]
+#[primary_key(creature_id, attr)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureAttribute {
+    creature_id: Uuid,
+    attr: Attribute,
+    score: i16,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creaturecombatstats"]
+#[primary_key(creature_id, stat)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureCombatStat {
+    creature_id: Uuid,
+    stat: CombatStat,
+    value: i16,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creatureattacks"]
+#[primary_key(creature_id, name)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureAttack {
+    creature_id: Uuid,
+    name: String,
+    count: i16,
+    attack_bonus: i16,
+    damage: String,
+    damage_type: DamageType,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creatureabilities"]
+#[primary_key(creature_id, name)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureAbility {
+    creature_id: Uuid,
+    name: String,
+    description: String,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creaturefeats"]
+#[primary_key(creature_id, feat_id)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureFeat {
+    creature_id: Uuid,
+    feat_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creaturespells"]
+#[primary_key(creature_id, spell_id)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureSpell {
+    creature_id: Uuid,
+    spell_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "creatureeffects"]
+#[primary_key(creature_id, effect_id)]
+#[belongs_to(DBCreature, foreign_key = "creature_id")]
+pub struct DBCreatureEffect {
+    creature_id: Uuid,
+    effect_id: Uuid,
+}
+
+impl From<Creature> for Bytes {
+    fn from(creature: Creature) -> Self {
+        status::serialize_to_bytes(&creature)
+    }
+}
+
+/// The creatures matching a bestiary search, from the lowest CR up.
+#[derive(Serialize, Clone, Debug)]
+pub struct CreatureList {
+    pub creatures: Vec<Summary<Creature>>,
+}
+
+impl From<CreatureList> for Bytes {
+    fn from(list: CreatureList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+#[derive(Deserialize)]
+struct BestiaryQuery {
+    #[serde(rename = "min-cr")]
+    min_cr: Option<String>,
+    #[serde(rename = "max-cr")]
+    max_cr: Option<String>,
+    #[serde(rename = "type-id")]
+    type_id: Option<Uuid>,
+    name: Option<String>,
+}
+
+/// Escapes the characters that have a special meaning in a LIKE pattern, so
+/// that names are searched for as they were written.
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '%' || c == '_' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn parse_cr(cr: Option<String>) -> Result<Option<ChallengeRating>, Rejection> {
+    cr.map(|cr| cr.parse::<ChallengeRating>())
+        .transpose()
+        .map_err(|err| Status::with_data(&StatusCode::BAD_REQUEST, err).into())
+}
+
+async fn search_creatures(user: User, conn: Connection, query: BestiaryQuery) -> Result<Status<Success<CreatureList>>, Rejection> {
+    user.id.ok_or_else(status::not_authorized)?;
+    let min_cr = parse_cr(query.min_cr)?;
+    let max_cr = parse_cr(query.max_cr)?;
+
+    let mut search = creatures::table.into_boxed();
+    if let Some(min_cr) = min_cr {
+        search = search.filter(creatures::cr.ge(min_cr.step()));
+    }
+    if let Some(max_cr) = max_cr {
+        search = search.filter(creatures::cr.le(max_cr.step()));
+    }
+    if let Some(type_id) = query.type_id {
+        search = search.filter(creatures::type_id.eq(type_id));
+    }
+    if let Some(name) = query.name {
+        search = search.filter(creatures::name.ilike(format!("%{}%", escape_like(&name))));
+    }
+    let found = search.order((creatures::cr, creatures::name))
+        .load::<DBCreature>(&conn)
+        .map_err(DBError::RunQuery)?;
+
+    let creatures = found.into_iter()
+        .map(|creature| Summary::<Creature>::try_from_db(creature, &conn))
+        .collect::<Result<_, DBError>>()?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(CreatureList { creatures })))
+}
+
+async fn get_creature(creature_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Creature>>, Rejection> {
+    user.id.ok_or_else(status::not_authorized)?;
+    let creature: Creature = forms::value_by_id(creature_id, &conn)
+        .map_err(|_| status::not_found())?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(creature)))
+}
+
+async fn create_creature(user: User, conn: Connection, form: Form) -> Result<Status<Success<Creature>>, Rejection> {
+    campaign::catalog_editor(&user, &conn)?;
+    let creature = Creature::try_from_form(&conn, form, None, None)?;
+    creature.db_insert(&conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(creature)))
+}
+
+/// A warp Filter containing the bestiary endpoints, relative to the
+/// `/bestiary` path.
+pub fn bestiary_filter() -> BoxedFilter<(impl Reply,)> {
+    let search = warp::get()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(warp::query::<BestiaryQuery>())
+        .and_then(search_creatures);
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_creature);
+    let create = warp::post()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(create_creature);
+
+    search.or(get)
+        .or(create)
+        .boxed()
+}
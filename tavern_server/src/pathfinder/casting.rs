@@ -0,0 +1,481 @@
+use super::character::{DBCharacter, DBCharacterSubclass};
+use super::class::Subclass;
+use super::inventory;
+use super::item::{DBOwnedItem, Item};
+use super::sheet::CharacterSheet;
+use super::spell::{ComponentType, MagicTradition, Spell};
+use super::summary::{Summarize, Summary};
+use super::{CombatStat, EquipmentSlot};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error, GetById};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_dc_adds_spell_level_and_modifiers() {
+        assert_eq!(save_dc(0, 3, 0), 13);
+        assert_eq!(save_dc(3, 4, 1), 18);
+    }
+
+    #[test]
+    fn check_chance_is_capped_between_zero_and_certain() {
+        // d20 + 5 against 15 needs a 10 or better.
+        assert_eq!(check_chance(5, 15), 55);
+        assert_eq!(check_chance(20, 15), 100);
+        assert_eq!(check_chance(0, 25), 0);
+        assert_eq!(check_chance(0, 20), 5);
+        assert_eq!(check_chance(i16::MIN, i16::MAX), 0);
+        assert_eq!(check_chance(i16::MAX, i16::MIN), 100);
+    }
+
+    #[test]
+    fn spell_failure_is_capped_at_one_hundred_percent() {
+        assert_eq!(total_spell_failure(&[]), 0);
+        assert_eq!(total_spell_failure(&[35, 15]), 50);
+        assert_eq!(total_spell_failure(&[50, 40, 20]), 100);
+    }
+
+    #[test]
+    fn caster_levels_do_not_stack_across_classes() {
+        let wizard = Uuid::new_v4();
+        let sorcerer = Uuid::new_v4();
+        let cleric = Uuid::new_v4();
+        let classes = [(wizard, 3), (sorcerer, 2), (cleric, 4)];
+        // Both arcane, but each class only counts its own levels.
+        assert_eq!(caster_level(&wizard, &classes), 3);
+        assert_eq!(caster_level(&sorcerer, &classes), 2);
+        assert_eq!(caster_level(&cleric, &classes), 4);
+        assert_eq!(caster_level(&Uuid::new_v4(), &classes), 0);
+    }
+
+    #[test]
+    fn components_are_taken_from_several_bags() {
+        let (pouch, pack) = (Uuid::new_v4(), Uuid::new_v4());
+        let (pearl, bat_guano) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut required = BTreeMap::new();
+        required.insert(pearl, 3);
+        let held = vec![(pouch, pearl, 2), (pack, bat_guano, 5), (pack, pearl, 4)];
+
+        let plan = plan_components(&required, &held).expect("enough pearls are held");
+        assert_eq!(plan, vec![(pouch, pearl, 2), (pack, pearl, 1)]);
+    }
+
+    #[test]
+    fn only_material_components_are_consumed() {
+        assert!(ComponentType::Material.is_consumed());
+        assert!(!ComponentType::Focus.is_consumed());
+        assert!(!ComponentType::DivineFocus.is_consumed());
+        assert!(ComponentType::Focus.needs_item());
+        assert!(!ComponentType::DivineFocus.needs_item());
+    }
+
+    #[test]
+    fn missing_components_report_how_many_are_held() {
+        let bag = Uuid::new_v4();
+        let (pearl, bat_guano) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut required = BTreeMap::new();
+        required.insert(pearl, 1);
+        required.insert(bat_guano, 2);
+        let held = vec![(bag, pearl, 1), (bag, bat_guano, 1)];
+
+        let missing = plan_components(&required, &held).unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[&bat_guano], 1);
+    }
+}
+
+/// One of the character's spellcasting classes and the caster level it
+/// casts at, which counts the levels of every class of its tradition.
+#[derive(Serialize, Clone, Debug)]
+pub struct CasterClass {
+    pub subclass: Summary<Subclass>,
+    pub tradition: Option<MagicTradition>,
+    pub caster_level: i16,
+}
+
+/// A caster level check against a target's spell resistance.
+#[derive(Serialize, Clone, Debug)]
+pub struct ResistanceCheck {
+    pub spell_resistance: i16,
+    /// The percentage chance of overcoming the spell resistance.
+    pub chance: i16,
+}
+
+/// Everything needed to cast a particular spell as a particular character.
+#[derive(Serialize, Clone, Debug)]
+pub struct CastingProfile {
+    pub spell: Summary<Spell>,
+    /// Every class the character casts spells from.
+    pub classes: Vec<CasterClass>,
+    /// The class the spell is being cast from.
+    pub subclass: Summary<Subclass>,
+    pub caster_level: i16,
+    pub casting_modifier: i16,
+    /// Only present for spells that allow a saving throw.
+    pub save_dc: Option<i16>,
+    pub concentration: i16,
+    /// The bonus on caster level checks made to overcome spell resistance.
+    /// Only present for spells that spell resistance applies to.
+    pub caster_level_check: Option<i16>,
+    pub resistance_check: Option<ResistanceCheck>,
+    /// The percentage chance that casting the spell fails due to armor.
+    pub arcane_spell_failure: i16,
+    /// The spell's casting time, area, targets and duration, written out as
+    /// they are in a stat block.
+    pub casting_time: String,
+    pub area: Option<String>,
+    pub targets: Option<String>,
+    pub duration: String,
+}
+
+impl From<CastingProfile> for Bytes {
+    fn from(profile: CastingProfile) -> Self {
+        status::serialize_to_bytes(&profile)
+    }
+}
+
+/// A material component or focus the caster doesn't have enough of.
+#[derive(Serialize, Clone, Debug)]
+pub struct MissingComponent {
+    pub item: Summary<Item>,
+    pub required: i32,
+    pub held: i32,
+}
+
+/// The response when a spell can't be cast for lack of material components
+/// or foci.
+#[derive(Serialize, Clone, Debug)]
+pub struct CastRefused {
+    pub message: String,
+    pub missing: Vec<MissingComponent>,
+}
+
+impl From<CastRefused> for Bytes {
+    fn from(refused: CastRefused) -> Self {
+        status::serialize_to_bytes(&refused)
+    }
+}
+
+/// Material components used up by casting a spell.
+#[derive(Serialize, Clone, Debug)]
+pub struct ConsumedComponent {
+    pub item: Summary<Item>,
+    pub bag_id: Uuid,
+    pub count: i32,
+}
+
+/// The result of successfully casting a spell.
+#[derive(Serialize, Clone, Debug)]
+pub struct CastResult {
+    pub spell: Summary<Spell>,
+    pub consumed: Vec<ConsumedComponent>,
+}
+
+impl From<CastResult> for Bytes {
+    fn from(result: CastResult) -> Self {
+        status::serialize_to_bytes(&result)
+    }
+}
+
+/// The DC of saving throws against a spell.
+pub fn save_dc(spell_level: i16, casting_modifier: i16, bonus: i16) -> i16 {
+    10 + spell_level + casting_modifier + bonus
+}
+
+/// The percentage chance that `d20 + bonus` meets or beats `dc`.
+pub fn check_chance(bonus: i16, dc: i16) -> i16 {
+    let needed = dc.saturating_sub(bonus);
+    21i16.saturating_sub(needed).clamp(0, 20) * 5
+}
+
+/// The combined arcane spell failure chance of all worn armor, in percent.
+pub fn total_spell_failure(failures: &[i32]) -> i16 {
+    failures.iter().sum::<i32>().clamp(0, 100) as i16
+}
+
+/// A number of copies of an item in a bag, as `(bag, item, count)`.
+type BagStack = (Uuid, Uuid, i32);
+
+/// Works out which bags to take each required item from, given the stacks
+/// `held`. Bags are used in the order given. If the caster is short of
+/// anything, returns how many of each missing item they do hold.
+pub fn plan_components(required: &BTreeMap<Uuid, i32>, held: &[BagStack]) -> Result<Vec<BagStack>, BTreeMap<Uuid, i32>> {
+    let mut plan = Vec::new();
+    let mut missing = BTreeMap::new();
+    for (item, needed) in required.iter() {
+        let mut remaining = *needed;
+        for (bag, held_item, count) in held.iter().filter(|(_, held_item, _)| held_item == item) {
+            if remaining == 0 {
+                break;
+            }
+            let taken = remaining.min(*count);
+            plan.push((*bag, *held_item, taken));
+            remaining -= taken;
+        }
+        if remaining > 0 {
+            missing.insert(*item, needed - remaining);
+        }
+    }
+    if missing.is_empty() {
+        Ok(plan)
+    } else {
+        Err(missing)
+    }
+}
+
+/// The caster level of a class, out of all of the character's caster classes
+/// as `(subclass, levels)`. Levels in different classes don't stack, even
+/// within the same tradition, so only the class's own levels count.
+fn caster_level(subclass: &Uuid, classes: &[(Uuid, i16)]) -> i16 {
+    classes.iter()
+        .find(|(other, _)| other == subclass)
+        .map(|(_, levels)| *levels)
+        .unwrap_or(0)
+}
+
+/// Every class the character has taken levels in that can cast spells.
+pub fn caster_classes(character: &DBCharacter, conn: &Connection) -> Result<Vec<(Subclass, i16)>, Error> {
+    let taken = DBCharacterSubclass::belonging_to(character)
+        .load::<DBCharacterSubclass>(conn)
+        .map_err(Error::RunQuery)?;
+    let mut classes = Vec::new();
+    for row in taken {
+        let subclass = Subclass::db_get_by_id(&row.subclass_id, conn)?;
+        if subclass.caster_type.is_some() {
+            classes.push((subclass, row.levels_taken));
+        }
+    }
+    Ok(classes)
+}
+
+/// The spell failure chances of the armor and shield the character has on.
+fn worn_spell_failure(sheet: &CharacterSheet, conn: &Connection) -> Result<Vec<i32>, Error> {
+    let mut failures = Vec::new();
+    let worn = sheet.character.equipment().iter()
+        .filter(|(slot, _)| **slot == EquipmentSlot::Armor || **slot == EquipmentSlot::Shield);
+    for (_, item) in worn {
+        if let Some(armor) = DBOwnedItem::db_get_by_id(item.id(), conn)?.armor(conn)? {
+            failures.push(armor.effective.spell_failure);
+        }
+    }
+    Ok(failures)
+}
+
+#[derive(Deserialize)]
+struct CastingQuery {
+    #[serde(rename = "subclass-id")]
+    subclass_id: Option<Uuid>,
+    #[serde(rename = "spell-resistance")]
+    spell_resistance: Option<i16>,
+}
+
+async fn get_casting_profile(char_id: Uuid, spell_id: Uuid, user: User, conn: Connection, query: CastingQuery) -> Result<Status<Success<CastingProfile>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let classes = caster_classes(&character, &conn)?;
+    let sheet = CharacterSheet::load_from_db(character, &conn)?;
+    let spell = sheet.character.spells().iter()
+        .find(|spell| *spell.id() == spell_id)
+        .ok_or_else(status::not_found)?;
+    let spell = Spell::db_get_by_id(spell.id(), &conn)?;
+
+    // Without a class to cast from, use whichever has the most levels.
+    let casting = match query.subclass_id {
+        Some(subclass_id) => classes.iter().find(|(subclass, _)| subclass.id == subclass_id),
+        None => classes.iter().max_by_key(|(_, levels)| *levels),
+    };
+    let (subclass, _) = casting.ok_or_else(status::not_found)?;
+    let levels = classes.iter()
+        .map(|(subclass, levels)| (subclass.id, *levels))
+        .collect::<Vec<_>>();
+
+    let caster_level = self::caster_level(&subclass.id, &levels) + sheet.modifiers.combat(CombatStat::CasterLevel);
+    let casting_modifier = subclass.casting_attr
+        .map(|attr| sheet.attribute_modifier(attr))
+        .unwrap_or(0);
+    let save_dc = spell.saving_throw
+        .map(|_| save_dc(spell.level, casting_modifier, sheet.modifiers.combat(CombatStat::SaveDC)));
+    let concentration = caster_level + casting_modifier + sheet.modifiers.combat(CombatStat::Concentration);
+    let caster_level_check = if spell.spell_resistance {
+        Some(caster_level)
+    } else {
+        None
+    };
+    let resistance_check = caster_level_check
+        .and_then(|bonus| query.spell_resistance.map(|sr| ResistanceCheck {
+            spell_resistance: sr,
+            chance: check_chance(bonus, sr),
+        }));
+    let has_somatic = spell.components.iter()
+        .any(|component| component.component_type == ComponentType::Somatic);
+    let arcane_spell_failure = if subclass.tradition == Some(MagicTradition::Arcane) && has_somatic {
+        total_spell_failure(&worn_spell_failure(&sheet, &conn)?)
+    } else {
+        0
+    };
+
+    let profile = CastingProfile {
+        spell: Summary::from(&spell),
+        classes: classes.iter()
+            .map(|(subclass, _)| CasterClass {
+                subclass: Summary::from(subclass),
+                tradition: subclass.tradition,
+                caster_level: self::caster_level(&subclass.id, &levels),
+            })
+            .collect(),
+        subclass: Summary::from(subclass),
+        caster_level,
+        casting_modifier,
+        save_dc,
+        concentration,
+        caster_level_check,
+        resistance_check,
+        arcane_spell_failure,
+        casting_time: spell.casting_time.to_string(),
+        area: spell.area.map(|area| area.to_string()),
+        targets: spell.targets.map(|targets| targets.to_string()),
+        duration: spell.duration.to_string(),
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(profile)))
+}
+
+/// Every item in the character's bags, as `(bag, item, count)`. Separate
+/// copies of an item in the same bag are counted together. The stacks stay
+/// locked until the end of the transaction, so nothing else can take them
+/// in the meantime.
+fn held_items(owner: &Uuid, conn: &Connection) -> Result<Vec<BagStack>, Error> {
+    let bag_ids = {
+        use crate::schema::bags::dsl::*;
+        bags.select(id)
+            .filter(char_id.eq(owner))
+            .order(id)
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+    };
+    use crate::schema::{itemsinbags, owneditems};
+    let stacks = itemsinbags::table.inner_join(owneditems::table)
+        .select((itemsinbags::bag_id, owneditems::item_id, itemsinbags::count))
+        .filter(itemsinbags::bag_id.eq_any(bag_ids))
+        .for_update()
+        .load::<(Uuid, Uuid, i32)>(conn)
+        .map_err(Error::RunQuery)?;
+    let mut held = BTreeMap::new();
+    for (bag, item, count) in stacks {
+        *held.entry((bag, item)).or_insert(0) += count;
+    }
+    Ok(held.into_iter()
+        .map(|((bag, item), count)| (bag, item, count))
+        .collect())
+}
+
+async fn cast_spell(caster_id: Uuid, cast_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<CastResult>>, Rejection> {
+    campaign::managed_character(&user, caster_id, &conn)?;
+    let known = {
+        use crate::schema::characterspells::dsl::*;
+        characterspells.filter(char_id.eq(&caster_id))
+            .filter(spell_id.eq(&cast_id))
+            .count()
+            .get_result::<i64>(&conn)
+            .map_err(Error::RunQuery)?
+    };
+    if known == 0 {
+        return Err(status::not_found());
+    }
+    let spell = Spell::db_get_by_id(&cast_id, &conn)?;
+
+    // Every listed item has to be held, but only material components are
+    // used up. Foci stay with the caster.
+    let mut required = BTreeMap::new();
+    let mut consumed = BTreeMap::new();
+    let mut items = BTreeMap::new();
+    for component in spell.components.iter() {
+        if let Some((item, count)) = &component.item {
+            *required.entry(*item.id()).or_insert(0) += i32::from(*count);
+            if component.component_type.is_consumed() {
+                *consumed.entry(*item.id()).or_insert(0) += i32::from(*count);
+            }
+            items.insert(*item.id(), item.clone());
+        }
+    }
+
+    let planned = conn.transaction::<_, Error, _>(|| {
+        let held = held_items(&caster_id, &conn)?;
+        if let Err(missing) = plan_components(&required, &held) {
+            return Ok(Err(missing));
+        }
+        let plan = plan_components(&consumed, &held)
+            .map_err(|_| Error::InvalidValues(vec![inventory::FIELD_COUNT.to_string()]))?;
+        for (bag, item, count) in plan.iter() {
+            inventory::take_from_bag(bag, item, *count, &conn)?;
+        }
+        Ok(Ok(plan))
+    })?;
+    let plan = match planned {
+        Ok(plan) => plan,
+        Err(missing) => {
+            let missing = missing.into_iter()
+                .map(|(item, held)| MissingComponent {
+                    item: items[&item].clone(),
+                    required: required[&item],
+                    held,
+                })
+                .collect();
+            let refused = CastRefused {
+                message: format!("missing components for {}", spell.name),
+                missing,
+            };
+            return Err(Status::with_data(&StatusCode::BAD_REQUEST, refused).into());
+        }
+    };
+
+    let result = CastResult {
+        spell: Summary::from(&spell),
+        consumed: plan.into_iter()
+            .map(|(bag_id, item, count)| ConsumedComponent {
+                item: items[&item].clone(),
+                bag_id,
+                count,
+            })
+            .collect(),
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(result)))
+}
+
+/// A warp Filter serving casting profiles for a character's spells and
+/// casting them, relative to `/characters`.
+pub fn casting_filter() -> BoxedFilter<(impl Reply,)> {
+    let cast = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("spells"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("cast"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(cast_spell);
+
+    warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("spells"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("casting"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(warp::query::<CastingQuery>())
+        .and_then(get_casting_profile)
+        .or(cast)
+        .boxed()
+}
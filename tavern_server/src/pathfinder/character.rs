@@ -1,14 +1,19 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::class::{Feature, Subclass};
+use super::afflictions::{self, Afflictions};
+use super::class::{Class, Feature, Subclass};
+use super::effects::Effect;
 use super::feat::Feat;
-use super::item::{Bag, DBBag, Item};
+use super::item::{Bag, DBBag, DBOwnedItem, Item, OwnedItem};
+use super::languages::Language;
 use super::religion::Deity;
 use super::spell::Spell;
 use super::summary::{Summarize, Summary};
+use super::traits::Trait;
 use super::Links;
-use super::{Alignment, EquipmentSlot, Gender, Size};
+use super::{Alignment, Attribute, Attributes, EquipmentSlot, Gender, Size};
+use super::{active_effect, casting, companions, crafting, divine, experience, health, inventory, languages, membership, racial, sheet, skills, traits, wealth};
 
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
@@ -16,17 +21,20 @@ use diesel::connection::Connection as DieselConnection;
 
 //use tavern_derive::Summarize;
 use crate::schema::{
-    characterequipment, characterfeats, characterfeatures, characters, characterspells,
-    charactersubclasses, races, racesubtypes, racetypes,
+    characterequipment, characterfeats, characterfeatures, characterracetraits, characters,
+    characterspells, charactersubclasses, charactertraits, racebonuslanguages, raceeffects, races, racesubtypeeffects, racesubtypes,
+    racetraiteffects, racetraitreplacements, racetraits, racetypeeffects, racetypes,
 };
 use std::cmp::Ordering;
 use crate::db::{TryFromDb, IntoDb, Connection, Error, GetAll, GetById, Delete, DeleteById, Insert, Update, StandaloneDbMarker};
 use std::collections::{BTreeSet, BTreeMap};
 use crate::forms::{self, TryFromForm};
-use warp::Rejection;
+use crate::status;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
 use nebula_form::Form;
 
-#[derive(Serialize, Deserialize, Summarize, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
 pub struct Character {
     id: Uuid,
     race: Race,
@@ -35,8 +43,19 @@ pub struct Character {
     feats: Vec<Summary<Feat>>,
     spells: Vec<Summary<Spell>>,
     bags: BTreeSet<Summary<Bag>>,
-    equipment: BTreeMap<EquipmentSlot, Summary<Item>>,
+    equipment: BTreeMap<EquipmentSlot, Summary<OwnedItem>>,
     features: Vec<Summary<Feature>>,
+    /// The alternate racial traits the character took in place of standard
+    /// ones.
+    race_traits: Vec<Summary<RaceTrait>>,
+    /// The ability the character put their race's flexible bonus in.
+    racial_bonus: Option<Attribute>,
+    favored_class: Option<Summary<Class>>,
+    /// The character traits and drawbacks the character took.
+    traits: Vec<Summary<Trait>>,
+    /// The ability damage, drain and negative levels the character has
+    /// taken.
+    afflictions: Afflictions,
 
     name: String,
     age: i16,
@@ -57,12 +76,15 @@ pub struct Character {
     max_hp: i16,
     damage: i16,
     nonlethal: i16,
+    temp_hp: i16,
 
     copper: i16,
     silver: i16,
     gold: i16,
     platinum: i16,
 
+    xp: i32,
+
     links: Links,
     #[serde(skip)]
     description: String,
@@ -89,12 +111,15 @@ impl Character {
     const FIELD_MAX_HP: &'static str = "max-hp";
     const FIELD_DAMAGE: &'static str = "lethal-damage";
     const FIELD_NONLETHAL: &'static str = "nonlethal-damage";
+    const FIELD_TEMP_HP: &'static str = "temp-hp";
 
     const FIELD_COPPER: &'static str = "copper";
     const FIELD_SILVER: &'static str = "silver";
     const FIELD_GOLD: &'static str = "gold";
     const FIELD_PLATINUM: &'static str = "platinum";
 
+    const FIELD_XP: &'static str = "xp";
+
     const FIELD_DEITY: &'static str = "deity-id";
     const FIELD_SUBCLASSES: &'static str = "subclasses";
     const FIELD_FEATS: &'static str = "feats";
@@ -102,6 +127,10 @@ impl Character {
     const FIELD_BAGS: &'static str = "bags";
     const FIELD_EQUIPMENT: &'static str = "equipment";
     const FIELD_FEATURES: &'static str = "features";
+    const FIELD_RACE_TRAITS: &'static str = "race-traits";
+    const FIELD_RACIAL_BONUS: &'static str = "racial-bonus";
+    const FIELD_FAVORED_CLASS: &'static str = "favored-class-id";
+    const FIELD_TRAITS: &'static str = "traits";
 }
 
 impl TryFromForm for Character {
@@ -128,12 +157,20 @@ impl TryFromForm for Character {
         let max_hp = forms::get_required_form_text_field(&form, Character::FIELD_MAX_HP)?;
         let damage = forms::get_required_form_text_field(&form, Character::FIELD_DAMAGE)?;
         let nonlethal = forms::get_required_form_text_field(&form, Character::FIELD_NONLETHAL)?;
+        let temp_hp = forms::get_optional_form_text_field(&form, Character::FIELD_TEMP_HP)?
+            .unwrap_or(0);
 
         let copper = forms::get_required_form_text_field(&form, Character::FIELD_COPPER)?;
         let silver = forms::get_required_form_text_field(&form, Character::FIELD_SILVER)?;
         let gold = forms::get_required_form_text_field(&form, Character::FIELD_GOLD)?;
         let platinum = forms::get_required_form_text_field(&form, Character::FIELD_PLATINUM)?;
 
+        let xp = forms::get_optional_form_text_field(&form, Character::FIELD_XP)?
+            .unwrap_or(0);
+        if xp < 0 {
+            return Err(forms::field_is_invalid_error(Character::FIELD_XP));
+        }
+
         let deity = forms::get_optional_form_text_field(&form, Character::FIELD_DEITY)?
             .map(|id| forms::value_by_id(id, conn))
             .transpose()?;
@@ -173,11 +210,40 @@ impl TryFromForm for Character {
             .map(|id| forms::value_by_id(id, conn))
             .collect::<Result<_, _>>()?;
 
+        let race_traits = match forms::get_optional_form_text_field::<String>(&form, Character::FIELD_RACE_TRAITS)? {
+            Some(race_traits) => serde_json::from_str::<Vec<Uuid>>(&race_traits)
+                .map_err(|_| forms::field_is_invalid_error(Character::FIELD_RACE_TRAITS))?
+                .into_iter()
+                .map(|id| forms::value_by_id(id, conn))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let racial_bonus = forms::get_optional_form_text_field(&form, Character::FIELD_RACIAL_BONUS)?;
+        let favored_class = forms::get_optional_form_text_field(&form, Character::FIELD_FAVORED_CLASS)?
+            .map(|id| forms::value_by_id(id, conn))
+            .transpose()?;
+        let traits = match forms::get_optional_form_text_field::<String>(&form, Character::FIELD_TRAITS)? {
+            Some(traits) => serde_json::from_str::<BTreeSet<Uuid>>(&traits)
+                .map_err(|_| forms::field_is_invalid_error(Character::FIELD_TRAITS))?
+                .into_iter()
+                .map(|id| forms::value_by_id::<Trait>(id, conn))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let categories = traits.iter()
+            .map(|character_trait| character_trait.category)
+            .collect::<Vec<_>>();
+        traits::check_traits(&categories)
+            .map_err(|err| status::bad_request(err.message))?;
+        let traits = traits.iter()
+            .map(Summary::from)
+            .collect();
+
         let equipment: String = forms::get_required_form_text_field(&form, Character::FIELD_EQUIPMENT)?;
         let equipment = serde_json::from_str::<BTreeMap<String, Uuid>>(&equipment)
             .map_err(|_| forms::field_is_invalid_error(Character::FIELD_EQUIPMENT))?
             .into_iter()
-            .map::<Result<(EquipmentSlot, Summary<Item>), Rejection>, _>(|(slot, id)| {
+            .map::<Result<(EquipmentSlot, Summary<OwnedItem>), Rejection>, _>(|(slot, id)| {
                 let slot = slot.as_str().parse()
                     .map_err(|_| forms::field_is_invalid_error(Character::FIELD_EQUIPMENT))?;
                 let item = forms::value_by_id(id, conn)?;
@@ -185,7 +251,7 @@ impl TryFromForm for Character {
             })
             .collect::<Result<_, _>>()?;
 
-        let character = Character {
+        let mut character = Character {
             id,
             race,
             deity,
@@ -195,6 +261,11 @@ impl TryFromForm for Character {
             bags,
             equipment,
             features,
+            race_traits,
+            racial_bonus,
+            favored_class,
+            traits,
+            afflictions: Default::default(),
             name,
             age,
             gender,
@@ -212,13 +283,16 @@ impl TryFromForm for Character {
             max_hp,
             damage,
             nonlethal,
+            temp_hp,
             copper,
             silver,
             gold,
             platinum,
+            xp,
             links: Default::default(),
             description: Default::default(),
         };
+        character.update_desc();
 
         Ok(character)
     }
@@ -236,8 +310,12 @@ impl TryFromDb for Character {
         let bags = other.get_bags(conn)?;
         let equipment = other.get_equipment(conn)?;
         let features = other.get_features(conn)?;
+        let race_traits = other.get_race_traits(conn)?;
+        let favored_class = other.favored_class_id.map(|id| Summary::<Class>::db_get_by_id(&id, conn)).transpose()?;
+        let traits = other.get_traits(conn)?;
+        let afflictions = Afflictions::load(&other, conn)?;
         let links = Links::new();
-        let character = Character {
+        let mut character = Character {
             id: other.id,
             race,
             deity,
@@ -247,6 +325,11 @@ impl TryFromDb for Character {
             bags,
             equipment,
             features,
+            race_traits,
+            racial_bonus: other.racial_bonus,
+            favored_class,
+            traits,
+            afflictions,
             name: other.name,
             age: other.age,
             gender: other.gender,
@@ -264,13 +347,16 @@ impl TryFromDb for Character {
             max_hp: other.max_hp,
             damage: other.damage,
             nonlethal: other.nonlethal,
+            temp_hp: other.temp_hp,
             copper: other.copper,
             silver: other.silver,
             gold: other.gold,
             platinum: other.platinum,
+            xp: other.xp,
             links,
             description: Default::default(),
         };
+        character.update_desc();
 
         Ok(character)
     }
@@ -285,6 +371,95 @@ impl IntoDb for Character {
 }
 
 impl Character {
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn race(&self) -> &Race {
+        &self.race
+    }
+
+    pub fn racial_bonus(&self) -> Option<Attribute> {
+        self.racial_bonus
+    }
+
+    pub fn favored_class(&self) -> Option<&Summary<Class>> {
+        self.favored_class.as_ref()
+    }
+
+    pub fn feats(&self) -> &[Summary<Feat>] {
+        &self.feats
+    }
+
+    pub fn features(&self) -> &[Summary<Feature>] {
+        &self.features
+    }
+
+    pub fn traits(&self) -> &[Summary<Trait>] {
+        &self.traits
+    }
+
+    pub fn spells(&self) -> &[Summary<Spell>] {
+        &self.spells
+    }
+
+    pub fn equipment(&self) -> &BTreeMap<EquipmentSlot, Summary<OwnedItem>> {
+        &self.equipment
+    }
+
+    pub fn afflictions(&self) -> &Afflictions {
+        &self.afflictions
+    }
+
+    pub fn max_hp(&self) -> i16 {
+        self.max_hp
+    }
+
+    pub fn damage(&self) -> i16 {
+        self.damage
+    }
+
+    pub fn nonlethal(&self) -> i16 {
+        self.nonlethal
+    }
+
+    pub fn temp_hp(&self) -> i16 {
+        self.temp_hp
+    }
+
+    /// The character's ability scores before any effects are applied. This
+    /// includes the bonus of races that let the player choose which ability
+    /// they improve.
+    pub fn base_attributes(&self) -> Attributes {
+        let mut attrs = Attributes::new();
+        attrs.insert(Attribute::Strength, self.strength);
+        attrs.insert(Attribute::Dexterity, self.dexterity);
+        attrs.insert(Attribute::Constitution, self.constitution);
+        attrs.insert(Attribute::Intelligence, self.intelligence);
+        attrs.insert(Attribute::Wisdom, self.wisdom);
+        attrs.insert(Attribute::Charisma, self.charisma);
+        if let Some(attr) = self.racial_bonus {
+            *attrs.entry(attr).or_insert(0) += self.race.flexible_bonus;
+        }
+        attrs
+    }
+
+    /// The racial traits the character has: their race's standard traits,
+    /// less any they swapped for the alternate traits they took. The chosen
+    /// traits are checked when they are taken, so this only fails if the race
+    /// has changed since.
+    pub fn active_race_traits(&self) -> Result<Vec<&RaceTrait>, Error> {
+        let chosen = self.race_traits.iter()
+            .map(|race_trait| race_trait.id().to_owned())
+            .collect::<Vec<Uuid>>();
+        racial::active_traits(&self.race.traits, &chosen)
+            .map_err(|err| Error::Other(err.message))
+    }
+
     fn update_desc(&mut self) {
         let level = self.subclasses.iter().count();
         self.description = format!(
@@ -294,6 +469,28 @@ impl Character {
     }
 }
 
+/// A warp Filter containing all of the endpoints that act on a single
+/// character, relative to the `/characters` path.
+pub fn characters_filter() -> BoxedFilter<(impl Reply,)> {
+    sheet::sheet_filter()
+        .or(health::health_filter())
+        .or(active_effect::active_effects_filter())
+        .or(inventory::inventory_filter())
+        .or(casting::casting_filter())
+        .or(crafting::crafting_filter())
+        .or(experience::experience_filter())
+        .or(wealth::wealth_filter())
+        .or(racial::racial_filter())
+        .or(skills::skills_filter())
+        .or(languages::known_languages_filter())
+        .or(divine::divine_filter())
+        .or(membership::membership_filter())
+        .or(traits::character_traits_filter())
+        .or(companions::companions_filter())
+        .or(afflictions::afflictions_filter())
+        .boxed()
+}
+
 #[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
 #[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
 #[tavern(is_identifiable, is_insertable, is_queryable)]
@@ -328,9 +525,67 @@ pub struct DBCharacter {
     silver: i16,
     gold: i16,
     platinum: i16,
+
+    temp_hp: i16,
+
+    xp: i32,
+    racial_bonus: Option<Attribute>,
+    favored_class_id: Option<Uuid>,
+    negative_levels: i16,
 }
 
 impl DBCharacter {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    /// The ID of the user that owns this character.
+    pub fn user_id(&self) -> &Uuid {
+        &self.user_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    pub fn race_id(&self) -> &Uuid {
+        &self.race_id
+    }
+
+    pub fn deity_id(&self) -> Option<&Uuid> {
+        self.deity_id.as_ref()
+    }
+
+    pub fn xp(&self) -> i32 {
+        self.xp
+    }
+
+    pub fn favored_class_id(&self) -> Option<&Uuid> {
+        self.favored_class_id.as_ref()
+    }
+
+    pub fn negative_levels(&self) -> i16 {
+        self.negative_levels
+    }
+
+    /// The copper, silver, gold and platinum coins the character carries.
+    pub fn purse(&self) -> (i16, i16, i16, i16) {
+        (self.copper, self.silver, self.gold, self.platinum)
+    }
+
+    /// The character's total level: the sum of the levels taken in each class.
+    pub fn total_level(&self, conn: &Connection) -> Result<i16, Error> {
+        let levels = DBCharacterSubclass::belonging_to(self)
+            .select(charactersubclasses::levels_taken)
+            .load::<i16>(conn)
+            .map_err(Error::RunQuery)?;
+        Ok(levels.into_iter().sum())
+    }
+
     fn get_subclasses(&self, conn: &Connection) -> Result<Vec<Summary<Subclass>>, Error> {
         DBCharacterSubclass::belonging_to(self)
             .load::<DBCharacterSubclass>(conn)
@@ -363,18 +618,19 @@ impl DBCharacter {
             .map(|b| Summary::<Bag>::try_from_db(b, conn))
             .collect()
     }
-    fn get_equipment(&self, conn: &Connection) -> Result<BTreeMap<EquipmentSlot, Summary<Item>>, Error> {
+    fn get_equipment(&self, conn: &Connection) -> Result<BTreeMap<EquipmentSlot, Summary<OwnedItem>>, Error> {
         DBCharacterEquipment::belonging_to(self)
             .load::<DBCharacterEquipment>(conn)
             .map_err(Error::RunQuery)?
             .into_iter()
             .map(|e| {
+                let owned = DBOwnedItem::db_get_by_id(&e.owned_item_id, conn)?;
                 // The database should ensure that all items marked as equipment
                 // have an equipment slot. So, an unwrap should be safe here. If this
                 // is ever not the case, ensure an INSERT/UPDATE trigger is set on the
                 // database to catch this.
-                Item::db_get_by_id(&e.item_id, conn)
-                    .map(|i| (i.equip_slot.unwrap(), Summary::<Item>::from(&i)))
+                let slot = Item::db_get_by_id(&owned.item_id, conn)?.equip_slot.unwrap();
+                Ok((slot, Summary::<OwnedItem>::from(&OwnedItem::try_from_db(owned, conn)?)))
             })
             .collect()
     }
@@ -386,6 +642,22 @@ impl DBCharacter {
             .map(|f| Summary::<Feature>::db_get_by_id(&f.feature_id, conn))
             .collect()
     }
+    fn get_race_traits(&self, conn: &Connection) -> Result<Vec<Summary<RaceTrait>>, Error> {
+        DBCharacterRaceTrait::belonging_to(self)
+            .load::<DBCharacterRaceTrait>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|t| Summary::<RaceTrait>::db_get_by_id(&t.trait_id, conn))
+            .collect()
+    }
+    fn get_traits(&self, conn: &Connection) -> Result<Vec<Summary<Trait>>, Error> {
+        DBCharacterTrait::belonging_to(self)
+            .load::<DBCharacterTrait>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|t| Summary::<Trait>::db_get_by_id(&t.trait_id, conn))
+            .collect()
+    }
 }
 
 #[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
@@ -395,11 +667,11 @@ impl DBCharacter {
 #[primary_key(char_id, subclass_id)]
 #[belongs_to(DBCharacter, foreign_key = "char_id")]
 pub struct DBCharacterSubclass {
-    char_id: Uuid,
-    subclass_id: Uuid,
-    levels_taken: i16,
-    hp_taken: i16,
-    skills_taken: i16,
+    pub(crate) char_id: Uuid,
+    pub(crate) subclass_id: Uuid,
+    pub(crate) levels_taken: i16,
+    pub(crate) hp_taken: i16,
+    pub(crate) skills_taken: i16,
 }
 
 #[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
@@ -440,26 +712,58 @@ pub struct DBCharacterSpell {
 #[derive(GetAll, Delete, Insert)]
 #[tavern(is_identifiable, is_insertable, is_queryable)]
 #[table_name = "characterequipment"]
-#[primary_key(char_id, item_id)]
+#[primary_key(char_id, owned_item_id)]
 #[belongs_to(DBCharacter, foreign_key = "char_id")]
 pub struct DBCharacterEquipment {
     char_id: Uuid,
-    item_id: Uuid,
+    owned_item_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "characterracetraits"]
+#[primary_key(char_id, trait_id)]
+#[belongs_to(DBCharacter, foreign_key = "char_id")]
+pub struct DBCharacterRaceTrait {
+    pub(crate) char_id: Uuid,
+    pub(crate) trait_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "charactertraits"]
+#[primary_key(char_id, trait_id)]
+#[belongs_to(DBCharacter, foreign_key = "char_id")]
+pub struct DBCharacterTrait {
+    pub(crate) char_id: Uuid,
+    pub(crate) trait_id: Uuid,
 }
 
 // TODO: I think this can be implemented better
 
-#[derive(Serialize, Deserialize, Summarize, Clone, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
 pub struct Race {
-    id: Uuid,
-    links: Links,
-    main_type: RaceType,
-    sub_type: Option<RaceSubtype>,
-    name: String,
-    description: String,
-    move_speed: i16,
-    size: Size,
-    languages: Vec<String>,
+    pub(crate) id: Uuid,
+    pub(crate) links: Links,
+    pub(crate) main_type: RaceType,
+    pub(crate) sub_type: Option<RaceSubtype>,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) move_speed: i16,
+    pub(crate) size: Size,
+    /// The names of the languages every member of the race speaks.
+    pub(crate) languages: Vec<String>,
+    /// The languages a member of the race can learn for a high Intelligence.
+    pub(crate) bonus_languages: BTreeSet<Summary<Language>>,
+    /// The bonus a character of this race puts in an ability of their
+    /// choice, e.g. +2 for humans. Zero for races with fixed modifiers.
+    pub(crate) flexible_bonus: i16,
+    /// The effects of the race, its type and its subtype.
+    pub(crate) effects: BTreeSet<Summary<Effect>>,
+    /// Both the standard and the alternate racial traits.
+    pub(crate) traits: Vec<RaceTrait>,
 }
 
 impl Race {
@@ -470,6 +774,7 @@ impl Race {
     const FIELD_MOVE_SPEED: &'static str = "move-speed";
     const FIELD_SIZE: &'static str = "size";
     const FIELD_LANGUAGES: &'static str = "languages";
+    const FIELD_FLEXIBLE_BONUS: &'static str = "flexible-bonus";
 }
 
 impl TryFromForm for Race {
@@ -487,6 +792,11 @@ impl TryFromForm for Race {
         let languages: String = forms::get_required_form_text_field(&form, Race::FIELD_LANGUAGES)?;
         let languages = serde_json::from_str(&languages)
             .map_err(|_| forms::field_is_invalid_error(Race::FIELD_LANGUAGES))?;
+        let flexible_bonus = forms::get_optional_form_text_field(&form, Race::FIELD_FLEXIBLE_BONUS)?
+            .unwrap_or(0);
+        if flexible_bonus < 0 {
+            return Err(forms::field_is_invalid_error(Race::FIELD_FLEXIBLE_BONUS));
+        }
 
         let race = Race {
             id,
@@ -497,7 +807,11 @@ impl TryFromForm for Race {
             sub_type,
             move_speed,
             size,
-            languages
+            languages,
+            bonus_languages: Default::default(),
+            flexible_bonus,
+            effects: Default::default(),
+            traits: Default::default(),
         };
 
         Ok(race)
@@ -510,6 +824,9 @@ impl TryFromDb for Race {
     fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, Error> where Self: Sized {
         let main_type = RaceType::db_get_by_id(&other.type_id, conn)?;
         let sub_type = other.subtype_id.map(|id| RaceSubtype::db_get_by_id(&id, conn)).transpose()?;
+        let effects = other.get_effects(conn)?;
+        let traits = other.get_traits(conn)?;
+        let bonus_languages = other.get_bonus_languages(conn)?;
         let links = Links::new();
         let race = Race {
             id: other.id,
@@ -521,6 +838,10 @@ impl TryFromDb for Race {
             move_speed: other.move_speed,
             size: other.size,
             languages: other.languages,
+            bonus_languages,
+            flexible_bonus: other.flexible_bonus,
+            effects,
+            traits,
         };
         Ok(race)
     }
@@ -539,6 +860,7 @@ impl IntoDb for Race {
             move_speed: self.move_speed,
             size: self.size,
             languages: self.languages,
+            flexible_bonus: self.flexible_bonus,
         }
     }
 }
@@ -556,9 +878,134 @@ pub struct DBRace {
     move_speed: i16,
     size: Size,
     languages: Vec<String>,
+    flexible_bonus: i16,
+}
+
+impl DBRace {
+    fn get_effects(&self, conn: &Connection) -> Result<BTreeSet<Summary<Effect>>, Error> {
+        let mut effect_ids = raceeffects::table.select(raceeffects::effect_id)
+            .filter(raceeffects::race_id.eq(self.id))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?;
+        effect_ids.extend(
+            racetypeeffects::table.select(racetypeeffects::effect_id)
+                .filter(racetypeeffects::type_id.eq(self.type_id))
+                .load::<Uuid>(conn)
+                .map_err(Error::RunQuery)?
+        );
+        if let Some(subtype_id) = self.subtype_id {
+            effect_ids.extend(
+                racesubtypeeffects::table.select(racesubtypeeffects::effect_id)
+                    .filter(racesubtypeeffects::subtype_id.eq(subtype_id))
+                    .load::<Uuid>(conn)
+                    .map_err(Error::RunQuery)?
+            );
+        }
+        effect_ids.into_iter()
+            .map(|id| Summary::<Effect>::db_get_by_id(&id, conn))
+            .collect()
+    }
+
+    fn get_bonus_languages(&self, conn: &Connection) -> Result<BTreeSet<Summary<Language>>, Error> {
+        racebonuslanguages::table.select(racebonuslanguages::language_id)
+            .filter(racebonuslanguages::race_id.eq(self.id))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|id| Summary::<Language>::db_get_by_id(&id, conn))
+            .collect()
+    }
+
+    fn get_traits(&self, conn: &Connection) -> Result<Vec<RaceTrait>, Error> {
+        DBRaceTrait::belonging_to(self)
+            .order(racetraits::name)
+            .load::<DBRaceTrait>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|race_trait| RaceTrait::try_from_db(race_trait, conn))
+            .collect()
+    }
+}
+
+/// A racial trait, such as a dwarf's darkvision. Alternate traits can be
+/// taken in place of the standard traits they replace.
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct RaceTrait {
+    pub(crate) id: Uuid,
+    pub(crate) links: Links,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) alternate: bool,
+    /// The standard traits this trait is taken in place of.
+    pub(crate) replaces: BTreeSet<Uuid>,
+    pub(crate) effects: BTreeSet<Summary<Effect>>,
+}
+
+impl TryFromDb for RaceTrait {
+    type DBType = DBRaceTrait;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, Error> where Self: Sized {
+        let replaces = DBRaceTraitReplacement::belonging_to(&other)
+            .load::<DBRaceTraitReplacement>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|replacement| replacement.replaced_id)
+            .collect();
+        let effects = DBRaceTraitEffect::belonging_to(&other)
+            .load::<DBRaceTraitEffect>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|effect| Summary::<Effect>::db_get_by_id(&effect.effect_id, conn))
+            .collect::<Result<_, Error>>()?;
+        let race_trait = RaceTrait {
+            id: other.id,
+            links: Links::new(),
+            name: other.name,
+            description: other.description,
+            alternate: other.alternate,
+            replaces,
+            effects,
+        };
+        Ok(race_trait)
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "racetraits"]
+#[belongs_to(DBRace, foreign_key = "race_id")]
+pub struct DBRaceTrait {
+    id: Uuid,
+    race_id: Uuid,
+    name: String,
+    description: String,
+    alternate: bool,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "racetraitreplacements"]
+#[primary_key(trait_id, replaced_id)]
+#[belongs_to(DBRaceTrait, foreign_key = "trait_id")]
+pub struct DBRaceTraitReplacement {
+    trait_id: Uuid,
+    replaced_id: Uuid,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "racetraiteffects"]
+#[primary_key(trait_id, effect_id)]
+#[belongs_to(DBRaceTrait, foreign_key = "trait_id")]
+pub struct DBRaceTraitEffect {
+    trait_id: Uuid,
+    effect_id: Uuid,
 }
 
-#[derive(Serialize, Deserialize, AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone)]
+#[derive(Serialize, Deserialize, AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Debug)]
 #[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
 #[tavern(is_identifiable, is_insertable, is_queryable)]
 #[table_name = "racetypes"]
@@ -610,7 +1057,7 @@ impl Eq for RaceType{}
 #[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
 #[tavern(is_identifiable, is_insertable, is_queryable)]
 #[table_name = "racesubtypes"]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct RaceSubtype {
     id: Uuid,
     name: String,
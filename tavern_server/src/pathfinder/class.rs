@@ -1,7 +1,9 @@
-use super::item::{Armor, ArmorClass, Weapon, WeaponClass};
-use super::spell::CasterType;
+use super::companions::CompanionKind;
+use super::item::{Armor, ArmorClass, Item, Weapon, WeaponClass};
+use super::spell::{CasterType, MagicTradition};
 use super::summary::{Summarize, Summary};
-use super::Attribute;
+use super::wealth::WealthDice;
+use super::{Alignment, Attribute};
 use super::Links;
 
 use serde::{Deserialize, Serialize};
@@ -15,7 +17,7 @@ use diesel::result::Error as DieselError;
 use crate::schema::{
     classes, classnotproficientarmor, classnotproficientweapons, classproficientarmor,
     classproficientarmorclasses, classproficientweaponclasses, classproficientweapons, features,
-    subclasses, subclassfeatures,
+    starterkititems, starterkits, subclasses, subclassfeatures,
 };
 use std::cmp::Ordering;
 use crate::db::{Connection, TryFromDb, IntoDb, IntoDbWithId, GetById, GetAll, Delete, DeleteById, Insert, Update, Error as DBError};
@@ -30,13 +32,21 @@ use nebula_status::{Status, StatusCode};
 #[derive(Serialize, Deserialize, Summarize, Clone, Ord, PartialOrd, PartialEq, Eq)]
 pub struct Subclass {
     links: Links,
-    id: Uuid,
+    pub(crate) id: Uuid,
     name: String,
     description: String,
     parent_class: Summary<Class>,
 
-    caster_type: Option<CasterType>,
-    casting_attr: Option<Attribute>,
+    pub(crate) caster_type: Option<CasterType>,
+    pub(crate) casting_attr: Option<Attribute>,
+    pub(crate) tradition: Option<MagicTradition>,
+
+    /// Whether the subclass draws its power from the character's deity.
+    pub(crate) divine_patron: bool,
+    /// The number of the deity's domains the subclass grants.
+    pub(crate) domains: i16,
+    /// The kind of companion the subclass grants, if any.
+    pub(crate) companion: Option<CompanionKind>,
 
     features: Vec<Feature>,
 }
@@ -46,6 +56,10 @@ impl Subclass {
     const FIELD_DESCRIPTION: &'static str = "description";
     const FIELD_CASTER_TYPE: &'static str = "caster-type";
     const FIELD_CASTING_ATTR: &'static str = "casting-attr";
+    const FIELD_TRADITION: &'static str = "tradition";
+    const FIELD_DIVINE_PATRON: &'static str = "divine-patron";
+    const FIELD_DOMAINS: &'static str = "domains";
+    const FIELD_COMPANION: &'static str = "companion";
     const FIELD_FEATURES: &'static str = "features";
 }
 
@@ -65,9 +79,23 @@ impl TryFromForm for Subclass {
         let caster_type = forms::get_optional_form_text_field(&form, Subclass::FIELD_CASTER_TYPE)?;
         let casting_attr = forms::get_optional_form_text_field(&form, Subclass::FIELD_CASTING_ATTR)?;
 
+        let tradition: Option<MagicTradition> = forms::get_optional_form_text_field(&form, Subclass::FIELD_TRADITION)?;
+
         if casting_attr.is_some() != caster_type.is_some() {
             return Err(forms::field_is_invalid_error(Subclass::FIELD_CASTING_ATTR));
         }
+        if tradition.is_some() && caster_type.is_none() {
+            return Err(forms::field_is_invalid_error(Subclass::FIELD_TRADITION));
+        }
+
+        let divine_patron = forms::get_optional_form_text_field(&form, Subclass::FIELD_DIVINE_PATRON)?
+            .unwrap_or(false);
+        let domains = forms::get_optional_form_text_field(&form, Subclass::FIELD_DOMAINS)?
+            .unwrap_or(0);
+        if domains < 0 || (domains > 0 && !divine_patron) {
+            return Err(forms::field_is_invalid_error(Subclass::FIELD_DOMAINS));
+        }
+        let companion = forms::get_optional_form_text_field(&form, Subclass::FIELD_COMPANION)?;
 
         let features: String = forms::get_required_form_text_field(&form, Subclass::FIELD_FEATURES)?;
         let features = serde_json::from_str::<Vec<Uuid>>(&features)
@@ -84,6 +112,10 @@ impl TryFromForm for Subclass {
             parent_class,
             caster_type,
             casting_attr,
+            tradition,
+            divine_patron,
+            domains,
+            companion,
             features,
         };
 
@@ -106,6 +138,10 @@ impl TryFromDb for Subclass {
             parent_class,
             caster_type: other.caster_type,
             casting_attr: other.casting_attr,
+            tradition: other.tradition,
+            divine_patron: other.divine_patron,
+            domains: other.domains,
+            companion: other.companion,
             features,
         };
         Ok(subclass)
@@ -128,6 +164,10 @@ impl IntoDb for Subclass {
             class_id: self.parent_class.id().to_owned(),
             caster_type: self.caster_type,
             casting_attr: self.casting_attr,
+            tradition: self.tradition,
+            divine_patron: self.divine_patron,
+            domains: self.domains,
+            companion: self.companion,
         };
         (db_subclass, features)
     }
@@ -145,6 +185,10 @@ pub struct DBSubclass {
     class_id: Uuid,
     caster_type: Option<CasterType>,
     casting_attr: Option<Attribute>,
+    tradition: Option<MagicTradition>,
+    divine_patron: bool,
+    domains: i16,
+    companion: Option<CompanionKind>,
 }
 
 impl DBSubclass {
@@ -183,6 +227,9 @@ pub struct Class {
     bab_per_level: f64,
     skills_per_level: i16,
     skills_attr: Attribute,
+    /// The alignments the class can be taken with. Any alignment is allowed
+    /// when this is empty.
+    alignments: BTreeSet<Alignment>,
 }
 
 impl Class {
@@ -199,6 +246,34 @@ impl Class {
     const FIELD_PROF_WEAPON_CLASS: &'static str = "prof-weapon-class";
     const FIELD_PROF_WEAPON: &'static str = "prof-weapon";
     const FIELD_NOT_PROF_WEAPON: &'static str = "not-prof-weapon";
+    const FIELD_ALIGNMENTS: &'static str = "alignments";
+
+    pub fn weapon_proficiencies(&self) -> &WeaponProficiencies {
+        &self.weapon_proficiencies
+    }
+
+    /// The dice rolled for a new character's coins, e.g. "5d6 × 10 gp".
+    pub fn starting_wealth(&self) -> &str {
+        &self.starting_wealth
+    }
+
+    pub fn alignments(&self) -> &BTreeSet<Alignment> {
+        &self.alignments
+    }
+
+    /// The die rolled for the class's hit points each level, e.g. "d8".
+    pub fn hit_die(&self) -> &str {
+        &self.hit_die
+    }
+
+    pub fn skills_per_level(&self) -> i16 {
+        self.skills_per_level
+    }
+
+    /// The ability whose modifier adds to the skill points of each level.
+    pub fn skills_attr(&self) -> Attribute {
+        self.skills_attr
+    }
 }
 
 impl TryFromForm for Class {
@@ -207,7 +282,9 @@ impl TryFromForm for Class {
         let name = forms::get_required_form_text_field(&form, Class::FIELD_NAME)?;
         let description = forms::get_required_form_text_field(&form, Class::FIELD_DESCRIPTION)?;
         let hit_die = forms::get_required_form_text_field(&form, Class::FIELD_HIT_DIE)?;
-        let starting_wealth = forms::get_required_form_text_field(&form, Class::FIELD_STARTING_WEALTH)?;
+        let starting_wealth: String = forms::get_required_form_text_field(&form, Class::FIELD_STARTING_WEALTH)?;
+        starting_wealth.parse::<WealthDice>()
+            .map_err(|_| forms::field_is_invalid_error(Class::FIELD_STARTING_WEALTH))?;
         let bab_per_level = forms::get_required_form_text_field(&form, Class::FIELD_BAB_PER_LVL)?;
         let skills_per_level = forms::get_required_form_text_field(&form, Class::FIELD_SKILLS_PER_LVL)?;
         let skills_attr = forms::get_required_form_text_field(&form, Class::FIELD_SKILLS_ATTR)?;
@@ -260,6 +337,18 @@ impl TryFromForm for Class {
             .map(|id| forms::value_by_id(id, conn))
             .collect::<Result<_, _>>()?;
 
+        let alignments: BTreeSet<Alignment> = match forms::get_optional_form_text_field::<String>(&form, Class::FIELD_ALIGNMENTS)? {
+            Some(alignments) => serde_json::from_str::<Vec<String>>(&alignments)
+                .map_err(|_| forms::field_is_invalid_error(Class::FIELD_ALIGNMENTS))?
+                .into_iter()
+                .map(|val| {
+                    val.as_str().parse()
+                        .map_err(|_| forms::field_is_invalid_error(Class::FIELD_ALIGNMENTS))
+                })
+                .collect::<Result<_, _>>()?,
+            None => BTreeSet::new(),
+        };
+
         let class = Class {
             links: Default::default(),
             id,
@@ -280,6 +369,7 @@ impl TryFromForm for Class {
             bab_per_level,
             skills_per_level,
             skills_attr,
+            alignments,
         };
 
         Ok(class)
@@ -305,6 +395,7 @@ impl TryFromDb for Class {
             bab_per_level: other.bab_per_level,
             skills_per_level: other.skills_per_level,
             skills_attr: other.skills_attr,
+            alignments: other.alignments.into_iter().collect(),
         };
         Ok(class)
     }
@@ -325,6 +416,7 @@ impl IntoDb for Class {
             bab_per_level: self.bab_per_level,
             skills_per_level: self.skills_per_level,
             skills_attr: self.skills_attr,
+            alignments: self.alignments.into_iter().collect(),
         };
 
         (db_class, db_weapon_prof, db_armor_prof)
@@ -364,6 +456,7 @@ pub struct DBClass {
     bab_per_level: f64,
     skills_per_level: i16,
     skills_attr: Attribute,
+    alignments: Vec<Alignment>,
 }
 
 impl DBClass {
@@ -450,6 +543,88 @@ impl PartialEq for DBClass {
 
 impl Eq for DBClass{}
 
+/// A package of catalog items that a new character of a class can buy in one
+/// go instead of shopping for each item.
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug)]
+pub struct StarterKit {
+    links: Links,
+    id: Uuid,
+    name: String,
+    description: String,
+    pub class_id: Uuid,
+    pub items: Vec<StarterKitItem>,
+}
+
+impl StarterKit {
+    /// The price of everything in the kit, in copper.
+    pub fn cost(&self) -> i32 {
+        self.items.iter()
+            .map(|item| item.cost * item.count)
+            .sum()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StarterKitItem {
+    pub item: Summary<Item>,
+    pub count: i32,
+    /// The price of a single item, in copper.
+    pub cost: i32,
+}
+
+impl TryFromDb for StarterKit {
+    type DBType = DBStarterKit;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let items = DBStarterKitItem::belonging_to(&other)
+            .load::<DBStarterKitItem>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|kit_item| {
+                let item = Item::db_get_by_id(&kit_item.item_id, conn)?;
+                Ok(StarterKitItem {
+                    item: Summary::from(&item),
+                    count: kit_item.count,
+                    cost: item.cost,
+                })
+            })
+            .collect::<Result<_, DBError>>()?;
+        let kit = StarterKit {
+            links: Links::new(),
+            id: other.id,
+            name: other.name,
+            description: other.description,
+            class_id: other.class_id,
+            items,
+        };
+        Ok(kit)
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "starterkits"]
+#[belongs_to(DBClass, foreign_key = "class_id")]
+pub struct DBStarterKit {
+    id: Uuid,
+    class_id: Uuid,
+    name: String,
+    description: String,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "starterkititems"]
+#[primary_key(kit_id, item_id)]
+#[belongs_to(DBStarterKit, foreign_key = "kit_id")]
+pub struct DBStarterKitItem {
+    kit_id: Uuid,
+    item_id: Uuid,
+    count: i32,
+}
+
 #[derive(
 Serialize,
 Deserialize,
@@ -527,6 +702,9 @@ pub trait Proficiencies<T> {
     fn add_class(&mut self, class: Self::Class);
     fn proficient(&mut self, item: T);
     fn not_proficient(&mut self, item: T);
+    /// Whether an item, which is in `class`, is covered. Items singled out as
+    /// not proficient are excluded even when their whole class is covered.
+    fn is_proficient(&self, item: &T, class: Self::Class) -> bool;
 }
 
 #[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, PartialEq, Eq)]
@@ -621,6 +799,10 @@ impl Proficiencies<Summary<Armor>> for ArmorProficiencies {
         self.prof.remove(&item);
         self.not_prof.insert(item);
     }
+
+    fn is_proficient(&self, item: &Summary<Armor>, class: Self::Class) -> bool {
+        !self.not_prof.contains(item) && (self.prof.contains(item) || self.classes.contains(&class))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, PartialEq, Eq)]
@@ -721,4 +903,8 @@ impl Proficiencies<Summary<Weapon>> for WeaponProficiencies {
         self.prof.remove(&item);
         self.not_prof.insert(item);
     }
+
+    fn is_proficient(&self, item: &Summary<Weapon>, class: Self::Class) -> bool {
+        !self.not_prof.contains(item) && (self.prof.contains(item) || self.classes.contains(&class))
+    }
 }
@@ -0,0 +1,525 @@
+use super::bestiary::Creature;
+use super::character::DBCharacter;
+use super::experience::MAX_LEVEL;
+use super::feat::Feat;
+use super::summary::Summary;
+use super::{Attribute, Attributes, CombatStat, CombatStats};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, GetById, Insert};
+use crate::db::{Delete, DeleteById, GetAll, Update};
+use crate::forms;
+use crate::schema::{charactersubclasses, companionfeats, companions, subclasses};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::Connection as DieselConnection;
+use diesel_derive_enum::DbEnum;
+use nebula_form::Form;
+use nebula_status::{Empty, Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progression_follows_the_master_level() {
+        assert_eq!(CompanionKind::AnimalCompanion.progression(0), None);
+        let first = CompanionKind::AnimalCompanion.progression(1).unwrap();
+        assert_eq!((first.hit_dice, first.natural_armor, first.str_dex_bonus, first.bonus_tricks), (2, 0, 0, 1));
+        let last = CompanionKind::AnimalCompanion.progression(20).unwrap();
+        assert_eq!((last.hit_dice, last.natural_armor, last.str_dex_bonus, last.bonus_tricks), (16, 10, 5, 7));
+        // Masters past the last level keep the last step.
+        assert_eq!(CompanionKind::AnimalCompanion.progression(25), Some(last));
+
+        let familiar = CompanionKind::Familiar.progression(5).unwrap();
+        assert_eq!((familiar.hit_dice, familiar.natural_armor), (5, 3));
+        let eidolon = CompanionKind::Eidolon.progression(8).unwrap();
+        assert_eq!((eidolon.hit_dice, eidolon.natural_armor, eidolon.str_dex_bonus), (6, 6, 3));
+    }
+
+    #[test]
+    fn progression_raises_physical_scores_and_natural_armor() {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::Strength, 13);
+        attributes.insert(Attribute::Dexterity, 17);
+        attributes.insert(Attribute::Intelligence, 2);
+        let mut combat_stats = CombatStats::new();
+        combat_stats.insert(CombatStat::ArmorClass, 14);
+        combat_stats.insert(CombatStat::TouchAC, 13);
+        combat_stats.insert(CombatStat::FlatFootedAC, 11);
+
+        CompanionKind::AnimalCompanion.progression(5).unwrap()
+            .apply(&mut attributes, &mut combat_stats);
+        assert_eq!(attributes[&Attribute::Strength], 15);
+        assert_eq!(attributes[&Attribute::Dexterity], 19);
+        assert_eq!(attributes[&Attribute::Intelligence], 2);
+        assert_eq!(combat_stats[&CombatStat::ArmorClass], 18);
+        assert_eq!(combat_stats[&CombatStat::TouchAC], 13);
+        assert_eq!(combat_stats[&CombatStat::FlatFootedAC], 15);
+    }
+
+    #[test]
+    fn abilities_are_gained_by_level() {
+        assert_eq!(CompanionKind::AnimalCompanion.abilities(1), vec!["link", "share spells"]);
+        assert!(CompanionKind::AnimalCompanion.abilities(2).contains(&"evasion"));
+        assert!(!CompanionKind::Familiar.abilities(4).contains(&"speak with master"));
+        assert!(CompanionKind::Familiar.abilities(5).contains(&"speak with master"));
+    }
+
+    #[test]
+    fn companions_gain_feats_on_odd_hit_dice() {
+        assert_eq!(feat_allowance(1), 1);
+        assert_eq!(feat_allowance(2), 1);
+        assert_eq!(feat_allowance(3), 2);
+        assert_eq!(feat_allowance(16), 8);
+    }
+
+    #[test]
+    fn ability_score_increases_raise_the_chosen_scores() {
+        let allowance = ability_increase_allowance(&CompanionKind::AnimalCompanion.abilities(9));
+        assert_eq!(allowance, 2);
+        assert_eq!(ability_increase_allowance(&CompanionKind::Familiar.abilities(20)), 0);
+
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::Strength, 13);
+        attributes.insert(Attribute::Constitution, 15);
+        let chosen = [Attribute::Strength, Attribute::Constitution, Attribute::Strength];
+        apply_ability_increases(&mut attributes, &chosen, allowance);
+        assert_eq!(attributes[&Attribute::Strength], 14);
+        assert_eq!(attributes[&Attribute::Constitution], 16);
+    }
+}
+
+const FIELD_NAME: &str = "name";
+const FIELD_KIND: &str = "kind";
+const FIELD_CREATURE_ID: &str = "creature-id";
+const FIELD_FEATS: &str = "feats";
+const FIELD_ABILITY_INCREASES: &str = "ability-increases";
+/// The special ability that raises one of a companion's ability scores by 1.
+const ABILITY_SCORE_INCREASE: &str = "ability score increase";
+
+/// Animal companion progression by master level: hit dice, natural armor
+/// bonus, Strength and Dexterity bonus, and bonus tricks.
+const ANIMAL_COMPANION: [(i16, i16, i16, i16); 20] = [
+    (2, 0, 0, 1), (3, 2, 1, 1), (3, 2, 1, 2), (4, 2, 1, 2), (5, 4, 2, 2),
+    (6, 4, 2, 3), (6, 4, 2, 3), (7, 4, 2, 3), (8, 6, 3, 4), (9, 6, 3, 4),
+    (9, 6, 3, 4), (10, 6, 3, 5), (11, 8, 4, 5), (12, 8, 4, 5), (12, 8, 4, 6),
+    (13, 8, 4, 6), (14, 10, 5, 6), (15, 10, 5, 7), (15, 10, 5, 7), (16, 10, 5, 7),
+];
+/// Eidolon progression by summoner level, laid out like `ANIMAL_COMPANION`.
+/// Eidolons learn no tricks.
+const EIDOLON: [(i16, i16, i16, i16); 20] = [
+    (1, 0, 0, 0), (2, 2, 1, 0), (3, 2, 1, 0), (3, 2, 1, 0), (4, 4, 2, 0),
+    (5, 4, 2, 0), (6, 6, 3, 0), (6, 6, 3, 0), (7, 6, 3, 0), (8, 8, 4, 0),
+    (9, 8, 4, 0), (9, 10, 5, 0), (10, 10, 5, 0), (11, 10, 5, 0), (12, 12, 6, 0),
+    (12, 12, 6, 0), (13, 14, 7, 0), (14, 14, 7, 0), (15, 14, 7, 0), (15, 16, 8, 0),
+];
+
+const ANIMAL_COMPANION_ABILITIES: [(i16, &str); 9] = [
+    (1, "link"), (1, "share spells"), (2, "evasion"), (4, "ability score increase"), (6, "devotion"),
+    (9, "ability score increase"), (9, "multiattack"), (14, "ability score increase"), (15, "improved evasion"),
+];
+const FAMILIAR_ABILITIES: [(i16, &str); 9] = [
+    (1, "alertness"), (1, "improved evasion"), (1, "share spells"), (1, "empathic link"),
+    (3, "deliver touch spells"), (5, "speak with master"), (7, "speak with animals of its kind"),
+    (11, "spell resistance"), (13, "scry on familiar"),
+];
+const EIDOLON_ABILITIES: [(i16, &str); 10] = [
+    (1, "darkvision"), (1, "link"), (1, "share spells"), (2, "evasion"), (5, "ability score increase"),
+    (6, "devotion"), (8, "multiattack"), (10, "ability score increase"), (12, "improved evasion"),
+    (15, "ability score increase"),
+];
+
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum CompanionKind {
+    AnimalCompanion,
+    Familiar,
+    Eidolon,
+}
+
+impl CompanionKind {
+    /// How a companion of this kind has improved for a master of `level`, or
+    /// None if the master has no levels that grant it.
+    pub fn progression(self, level: i16) -> Option<Progression> {
+        if level < 1 {
+            return None;
+        }
+        let level = level.min(MAX_LEVEL);
+        let (hit_dice, natural_armor, str_dex_bonus, bonus_tricks) = match self {
+            CompanionKind::AnimalCompanion => ANIMAL_COMPANION[level as usize - 1],
+            // Familiars use their master's hit dice.
+            CompanionKind::Familiar => (level, (level + 1) / 2, 0, 0),
+            CompanionKind::Eidolon => EIDOLON[level as usize - 1],
+        };
+        Some(Progression { hit_dice, natural_armor, str_dex_bonus, bonus_tricks })
+    }
+
+    /// The special abilities, such as share spells and link, a companion of
+    /// this kind has for a master of `level`.
+    pub fn abilities(self, level: i16) -> Vec<&'static str> {
+        let abilities: &[(i16, &'static str)] = match self {
+            CompanionKind::AnimalCompanion => &ANIMAL_COMPANION_ABILITIES,
+            CompanionKind::Familiar => &FAMILIAR_ABILITIES,
+            CompanionKind::Eidolon => &EIDOLON_ABILITIES,
+        };
+        abilities.iter()
+            .filter(|(gained, _)| *gained <= level)
+            .map(|(_, ability)| *ability)
+            .collect()
+    }
+}
+
+/// How far a companion has improved on its base creature.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progression {
+    /// The companion's total hit dice.
+    pub hit_dice: i16,
+    pub natural_armor: i16,
+    /// The bonus to the companion's Strength and Dexterity.
+    pub str_dex_bonus: i16,
+    /// The tricks an animal companion knows on top of those it can learn.
+    pub bonus_tricks: i16,
+}
+
+impl Progression {
+    /// Adds the progression's bonuses to a creature's stats. Natural armor
+    /// doesn't count against touch attacks.
+    pub fn apply(&self, attributes: &mut Attributes, combat_stats: &mut CombatStats) {
+        for attr in [Attribute::Strength, Attribute::Dexterity].iter() {
+            if let Some(score) = attributes.get_mut(attr) {
+                *score += self.str_dex_bonus;
+            }
+        }
+        for stat in [CombatStat::ArmorClass, CombatStat::FlatFootedAC].iter() {
+            if let Some(value) = combat_stats.get_mut(stat) {
+                *value += self.natural_armor;
+            }
+        }
+    }
+}
+
+/// The number of ability score increases among a companion's abilities.
+pub fn ability_increase_allowance(abilities: &[&str]) -> usize {
+    abilities.iter()
+        .filter(|ability| **ability == ABILITY_SCORE_INCREASE)
+        .count()
+}
+
+/// Raises the chosen ability scores by 1 each, leaving out any choices past
+/// `allowance`, such as those made before the master lost a level.
+pub fn apply_ability_increases(attributes: &mut Attributes, chosen: &[Attribute], allowance: usize) {
+    for attr in chosen.iter().take(allowance) {
+        if let Some(score) = attributes.get_mut(attr) {
+            *score += 1;
+        }
+    }
+}
+
+/// The number of feats a companion with `hit_dice` can have: one for its
+/// first hit die and one for every odd hit die after that.
+pub fn feat_allowance(hit_dice: i16) -> i16 {
+    (hit_dice + 1) / 2
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "companions"]
+#[belongs_to(DBCharacter, foreign_key = "char_id")]
+pub struct DBCompanion {
+    pub(crate) id: Uuid,
+    pub(crate) char_id: Uuid,
+    pub(crate) creature_id: Uuid,
+    pub(crate) kind: CompanionKind,
+    pub(crate) name: String,
+    pub(crate) ability_increases: Vec<Attribute>,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Insert, Delete)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "companionfeats"]
+#[primary_key(companion_id, feat_id)]
+#[belongs_to(DBCompanion, foreign_key = "companion_id")]
+pub struct DBCompanionFeat {
+    companion_id: Uuid,
+    feat_id: Uuid,
+}
+
+/// A companion's stat block: its base creature improved by its master's
+/// level.
+#[derive(Serialize, Clone, Debug)]
+pub struct CompanionSheet {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: CompanionKind,
+    pub creature: Summary<Creature>,
+    /// The master's levels in subclasses that grant this kind of companion.
+    pub master_level: i16,
+    pub progression: Option<Progression>,
+    pub hit_dice: i16,
+    /// The hit dice the companion has on top of its base creature's.
+    pub bonus_hit_dice: i16,
+    pub attributes: Attributes,
+    pub combat_stats: CombatStats,
+    pub feats: Vec<Summary<Feat>>,
+    pub feat_allowance: i16,
+    pub abilities: Vec<String>,
+    /// The abilities raised by the companion's ability score increases.
+    pub ability_increases: Vec<Attribute>,
+    pub ability_increase_allowance: i16,
+}
+
+impl CompanionSheet {
+    fn load(companion: DBCompanion, conn: &Connection) -> Result<Self, DBError> {
+        let creature = Creature::db_get_by_id(&companion.creature_id, conn)?;
+        let master_level = master_level(&companion.char_id, companion.kind, conn)?;
+        let progression = companion.kind.progression(master_level);
+        let feats = DBCompanionFeat::belonging_to(&companion)
+            .load::<DBCompanionFeat>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|feat| Summary::<Feat>::db_get_by_id(&feat.feat_id, conn))
+            .collect::<Result<Vec<_>, DBError>>()?;
+
+        let abilities = companion.kind.abilities(master_level);
+        let allowance = ability_increase_allowance(&abilities);
+        let ability_increases = companion.ability_increases.iter()
+            .take(allowance)
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut attributes = creature.attributes.clone();
+        let mut combat_stats = creature.combat_stats.clone();
+        let hit_dice = match progression {
+            Some(progression) => {
+                progression.apply(&mut attributes, &mut combat_stats);
+                progression.hit_dice.max(creature.hit_dice)
+            }
+            None => creature.hit_dice,
+        };
+        apply_ability_increases(&mut attributes, &ability_increases, allowance);
+
+        Ok(CompanionSheet {
+            id: companion.id,
+            name: companion.name,
+            kind: companion.kind,
+            creature: Summary::from(&creature),
+            master_level,
+            progression,
+            hit_dice,
+            bonus_hit_dice: hit_dice - creature.hit_dice,
+            attributes,
+            combat_stats,
+            feats,
+            feat_allowance: feat_allowance(hit_dice),
+            abilities: abilities.into_iter()
+                .map(String::from)
+                .collect(),
+            ability_increases,
+            ability_increase_allowance: allowance as i16,
+        })
+    }
+}
+
+impl From<CompanionSheet> for Bytes {
+    fn from(sheet: CompanionSheet) -> Self {
+        status::serialize_to_bytes(&sheet)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CompanionList {
+    pub companions: Vec<CompanionSheet>,
+}
+
+impl From<CompanionList> for Bytes {
+    fn from(list: CompanionList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+/// A character's levels in the subclasses that grant a kind of companion.
+fn master_level(char_id: &Uuid, kind: CompanionKind, conn: &Connection) -> Result<i16, DBError> {
+    let levels = charactersubclasses::table.inner_join(subclasses::table)
+        .select(charactersubclasses::levels_taken)
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .filter(subclasses::companion.eq(Some(kind)))
+        .load::<i16>(conn)
+        .map_err(DBError::RunQuery)?;
+    Ok(levels.into_iter().sum())
+}
+
+/// Finds one of a character's companions. Companions of other characters
+/// are treated as missing.
+fn companion_of(char_id: Uuid, companion_id: Uuid, conn: &Connection) -> Result<DBCompanion, Rejection> {
+    let companion = DBCompanion::db_get_by_id(&companion_id, conn)
+        .map_err(|err| match err {
+            DBError::RunQuery(DieselError::NotFound) => status::not_found(),
+            err => Rejection::from(err),
+        })?;
+    if companion.char_id != char_id {
+        return Err(status::not_found());
+    }
+    Ok(companion)
+}
+
+async fn list_companions(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<CompanionList>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let companions = companions::table.filter(companions::char_id.eq(char_id))
+        .order(companions::name)
+        .load::<DBCompanion>(&conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|companion| CompanionSheet::load(companion, &conn))
+        .collect::<Result<_, DBError>>()?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(CompanionList { companions })))
+}
+
+async fn add_companion(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<CompanionSheet>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let name: String = forms::get_required_form_text_field(&form, FIELD_NAME)?;
+    if name.trim().is_empty() {
+        return Err(forms::field_is_invalid_error(FIELD_NAME));
+    }
+    let kind: CompanionKind = forms::get_required_form_text_field(&form, FIELD_KIND)?;
+    let creature_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CREATURE_ID)?;
+    forms::value_by_id::<Summary<Creature>>(creature_id, &conn)?;
+    if master_level(&char_id, kind, &conn)? == 0 {
+        return Err(status::bad_request(format!("{} has no levels in a class that grants a {}", character.name(), kind)));
+    }
+
+    let companion = DBCompanion {
+        id: Uuid::new_v4(),
+        char_id,
+        creature_id,
+        kind,
+        name: name.trim().to_string(),
+        ability_increases: Vec::new(),
+    };
+    companion.db_insert(&conn)
+        .map_err(|err| forms::db_error_to_rejection(err, FIELD_KIND))?;
+    let sheet = CompanionSheet::load(companion, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(sheet)))
+}
+
+/// Sets the feats a companion took to those given in the form.
+async fn choose_companion_feats(char_id: Uuid, companion_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<CompanionSheet>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let companion = companion_of(char_id, companion_id, &conn)?;
+    let feats: String = forms::get_required_form_text_field(&form, FIELD_FEATS)?;
+    let feats = serde_json::from_str::<BTreeSet<Uuid>>(&feats)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_FEATS))?;
+    for id in feats.iter() {
+        forms::value_by_id::<Summary<Feat>>(*id, &conn)?;
+    }
+
+    let allowance = CompanionSheet::load(companion.clone(), &conn)?.feat_allowance;
+    if feats.len() as i16 > allowance {
+        return Err(status::bad_request(format!("{} can only have {} feats", companion.name, allowance)));
+    }
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(companionfeats::table.filter(companionfeats::companion_id.eq(companion_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        for feat_id in feats.into_iter() {
+            DBCompanionFeat { companion_id, feat_id }.db_insert(&conn)?;
+        }
+        Ok(())
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_FEATS))?;
+
+    let sheet = CompanionSheet::load(companion, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(sheet)))
+}
+
+/// Sets the abilities raised by a companion's ability score increases to
+/// those given in the form, in the order the increases were gained.
+async fn choose_ability_increases(char_id: Uuid, companion_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<CompanionSheet>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let companion = companion_of(char_id, companion_id, &conn)?;
+    let chosen: String = forms::get_required_form_text_field(&form, FIELD_ABILITY_INCREASES)?;
+    let chosen = serde_json::from_str::<Vec<Attribute>>(&chosen)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_ABILITY_INCREASES))?;
+
+    let allowance = CompanionSheet::load(companion.clone(), &conn)?.ability_increase_allowance;
+    if chosen.len() as i16 > allowance {
+        return Err(status::bad_request(format!("{} can only have {} ability score increases", companion.name, allowance)));
+    }
+
+    let companion = diesel::update(companions::table.filter(companions::id.eq(companion_id)))
+        .set(companions::ability_increases.eq(chosen))
+        .get_result::<DBCompanion>(&conn)
+        .map_err(DBError::RunQuery)?;
+    let sheet = CompanionSheet::load(companion, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(sheet)))
+}
+
+async fn dismiss_companion(char_id: Uuid, companion_id: Uuid, user: User, conn: Connection) -> Result<Status<Empty>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let companion = companion_of(char_id, companion_id, &conn)?;
+    companion.db_delete(&conn)?;
+    Ok(Status::new(&StatusCode::OK))
+}
+
+/// A warp Filter containing the endpoints for a character's companions,
+/// relative to the `/characters` path.
+pub fn companions_filter() -> BoxedFilter<(impl Reply,)> {
+    let list = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("companions"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(list_companions);
+    let add = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("companions"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(add_companion);
+    let feats = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("companions"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("feats"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_companion_feats);
+    let ability_increases = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("companions"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("ability-increases"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_ability_increases);
+    let dismiss = warp::delete()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("companions"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(dismiss_companion);
+
+    list.or(add)
+        .or(feats)
+        .or(ability_increases)
+        .or(dismiss)
+        .boxed()
+}
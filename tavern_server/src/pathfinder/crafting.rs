@@ -0,0 +1,314 @@
+use super::inventory;
+use super::item::{
+    AbilityCost, AbilityTarget, Armor, DBArmor, DBOwnedItem, DBOwnedItemAbility, DBWeapon, Item, Material, OwnedItem,
+    SpecialAbility, Weapon,
+};
+use super::summary::Summarize;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error, Insert, TryFromDb};
+use crate::forms;
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enhancement_price_grows_with_the_square_of_the_bonus() {
+        assert_eq!(enhancement_price(AbilityTarget::Weapon, 0), 0);
+        assert_eq!(enhancement_price(AbilityTarget::Weapon, 1), 2000 * COPPER_PER_GOLD);
+        assert_eq!(enhancement_price(AbilityTarget::Weapon, 3), 18000 * COPPER_PER_GOLD);
+        assert_eq!(enhancement_price(AbilityTarget::Armor, 2), 4000 * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn flaming_longsword_is_priced_as_plus_two() {
+        // A +1 flaming longsword: 15 gp longsword, 300 gp masterwork and
+        // 8,000 gp for a +2 equivalent bonus.
+        let price = price_item(AbilityTarget::Weapon, 15 * COPPER_PER_GOLD, None, 1, &[AbilityCost::BonusEquivalent(1)])
+            .expect("a +2 equivalent weapon is allowed");
+        assert_eq!(price.total_bonus, 2);
+        assert_eq!(price.market_price, 8315 * COPPER_PER_GOLD);
+        assert_eq!(price.crafting_cost, 4315 * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn flat_costs_are_added_without_counting_towards_the_bonus() {
+        let price = price_item(AbilityTarget::Armor, 100 * COPPER_PER_GOLD, None, 1, &[AbilityCost::Flat(3750 * COPPER_PER_GOLD)])
+            .expect("flat costs don't count towards the cap");
+        assert_eq!(price.total_bonus, 1);
+        assert_eq!(price.market_price, (100 + 150 + 1000 + 3750) * COPPER_PER_GOLD);
+        assert_eq!(price.crafting_cost, (100 + 150) * COPPER_PER_GOLD + (1000 + 3750) * COPPER_PER_GOLD / 2);
+    }
+
+    #[test]
+    fn special_materials_include_the_masterwork_cost() {
+        // A +1 mithral chain shirt: 100 gp chain shirt and 1,000 gp for
+        // mithral, which already makes it masterwork.
+        let price = price_item(AbilityTarget::Armor, 100 * COPPER_PER_GOLD, Some(1000 * COPPER_PER_GOLD), 1, &[])
+            .expect("a +1 armor is allowed");
+        assert_eq!(price.market_price, (100 + 1000 + 1000) * COPPER_PER_GOLD);
+        assert_eq!(price.crafting_cost, (100 + 1000 + 500) * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn total_bonus_is_capped_at_ten() {
+        let abilities = [AbilityCost::BonusEquivalent(5), AbilityCost::BonusEquivalent(1)];
+        assert_eq!(price_item(AbilityTarget::Weapon, 0, None, 5, &abilities), Err(CraftingError::TotalBonusTooHigh(11)));
+        assert!(price_item(AbilityTarget::Weapon, 0, None, 4, &abilities).is_ok());
+    }
+
+    #[test]
+    fn special_abilities_need_an_enhancement_bonus() {
+        let abilities = [AbilityCost::BonusEquivalent(1)];
+        assert_eq!(price_item(AbilityTarget::Weapon, 0, None, 0, &abilities), Err(CraftingError::NeedsEnhancement));
+        assert_eq!(price_item(AbilityTarget::Weapon, 0, None, 6, &[]), Err(CraftingError::EnhancementTooHigh));
+    }
+
+    #[test]
+    fn prices_too_high_to_store_are_refused() {
+        let abilities = [AbilityCost::Flat(i32::MAX), AbilityCost::Flat(1)];
+        assert_eq!(price_item(AbilityTarget::Weapon, 0, None, 1, &abilities), Err(CraftingError::TooExpensive));
+        let abilities = [AbilityCost::Flat(i32::MAX - 1000)];
+        assert_eq!(price_item(AbilityTarget::Weapon, 15 * COPPER_PER_GOLD, None, 1, &abilities), Err(CraftingError::TooExpensive));
+    }
+}
+
+/// The number of copper pieces in a gold piece. Item costs are kept in copper.
+pub const COPPER_PER_GOLD: i32 = 100;
+/// The most an item's enhancement bonus and the bonus equivalents of its
+/// special abilities can add up to.
+pub const MAX_TOTAL_BONUS: i16 = 10;
+
+const FIELD_ITEM_ID: &str = "item-id";
+const FIELD_BAG_ID: &str = "bag-id";
+const FIELD_MATERIAL_ID: &str = "material-id";
+const FIELD_ENHANCEMENT: &str = "enhancement";
+const FIELD_ABILITIES: &str = "abilities";
+const FIELD_NAME: &str = "name";
+
+/// Why an item can't be given the requested enchantments.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CraftingError {
+    EnhancementTooHigh,
+    /// Special abilities can only be added to an item with at least a +1
+    /// enhancement bonus.
+    NeedsEnhancement,
+    TotalBonusTooHigh(i16),
+    /// The price is more copper than can be stored.
+    TooExpensive,
+}
+
+impl fmt::Display for CraftingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CraftingError::EnhancementTooHigh => write!(f, "enhancement bonus cannot be more than +{}", OwnedItem::MAX_ENHANCEMENT),
+            CraftingError::NeedsEnhancement => write!(f, "special abilities need at least a +1 enhancement bonus"),
+            CraftingError::TotalBonusTooHigh(total) => write!(f, "total bonus of +{} is more than +{}", total, MAX_TOTAL_BONUS),
+            CraftingError::TooExpensive => write!(f, "the item would cost more than can be paid"),
+        }
+    }
+}
+
+/// What a crafted item is worth and what it costs to make, in copper.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemPrice {
+    /// The enhancement bonus plus the bonus equivalents of its abilities.
+    pub total_bonus: i16,
+    pub market_price: i32,
+    pub crafting_cost: i32,
+}
+
+/// The extra cost of a masterwork item, in copper.
+pub fn masterwork_cost(kind: AbilityTarget) -> i32 {
+    match kind {
+        AbilityTarget::Weapon => 300 * COPPER_PER_GOLD,
+        AbilityTarget::Armor => 150 * COPPER_PER_GOLD,
+    }
+}
+
+/// The price of an effective enhancement bonus, in copper.
+pub fn enhancement_price(kind: AbilityTarget, total_bonus: i16) -> i32 {
+    let per_bonus = match kind {
+        AbilityTarget::Weapon => 2000,
+        AbilityTarget::Armor => 1000,
+    };
+    i32::from(total_bonus).pow(2) * per_bonus * COPPER_PER_GOLD
+}
+
+/// Prices a masterwork weapon or armor with an enhancement bonus and special
+/// abilities. `material_cost` is the extra cost of a special material, if it's
+/// made of one, which already pays for the masterwork quality. Only the
+/// magical part of the price is halved when crafting it.
+pub fn price_item(kind: AbilityTarget, base_cost: i32, material_cost: Option<i32>, enhancement: i16, abilities: &[AbilityCost]) -> Result<ItemPrice, CraftingError> {
+    if !(0..=OwnedItem::MAX_ENHANCEMENT).contains(&enhancement) {
+        return Err(CraftingError::EnhancementTooHigh);
+    }
+    if enhancement == 0 && !abilities.is_empty() {
+        return Err(CraftingError::NeedsEnhancement);
+    }
+    let mut total_bonus = enhancement;
+    let mut flat_cost: i32 = 0;
+    for cost in abilities.iter() {
+        match cost {
+            AbilityCost::BonusEquivalent(bonus) => total_bonus = total_bonus.saturating_add(*bonus),
+            AbilityCost::Flat(cost) => flat_cost = flat_cost.checked_add(*cost).ok_or(CraftingError::TooExpensive)?,
+        }
+    }
+    if total_bonus > MAX_TOTAL_BONUS {
+        return Err(CraftingError::TotalBonusTooHigh(total_bonus));
+    }
+
+    let too_expensive = || CraftingError::TooExpensive;
+    let mundane = base_cost.checked_add(material_cost.unwrap_or_else(|| masterwork_cost(kind)))
+        .ok_or_else(too_expensive)?;
+    let magic = enhancement_price(kind, total_bonus).checked_add(flat_cost)
+        .ok_or_else(too_expensive)?;
+    Ok(ItemPrice {
+        total_bonus,
+        market_price: mundane.checked_add(magic).ok_or_else(too_expensive)?,
+        crafting_cost: mundane.checked_add(magic / 2).ok_or_else(too_expensive)?,
+    })
+}
+
+/// Whether a catalog item is a weapon or armor, if it's either, along with
+/// its price when made of `material`, or of its usual material if not given,
+/// and whether that is a special material.
+fn crafting_base(item: &Uuid, material: Option<Material>, conn: &Connection) -> Result<Option<(AbilityTarget, i32, bool)>, Error> {
+    let catalog_weapon = {
+        use crate::schema::weapons::dsl::*;
+        weapons.filter(id.eq(item))
+            .first::<DBWeapon>(conn)
+            .optional()
+            .map_err(Error::RunQuery)?
+    };
+    if let Some(catalog) = catalog_weapon {
+        let mut weapon = Weapon::try_from_db(catalog, conn)?;
+        if let Some(material) = material {
+            weapon = weapon.with_material(material);
+        }
+        return Ok(Some((AbilityTarget::Weapon, weapon.effective.cost, weapon.material().is_some())));
+    }
+    let catalog_armor = {
+        use crate::schema::armor::dsl::*;
+        armor.filter(id.eq(item))
+            .first::<DBArmor>(conn)
+            .optional()
+            .map_err(Error::RunQuery)?
+    };
+    if let Some(catalog) = catalog_armor {
+        let mut armor = Armor::try_from_db(catalog, conn)?;
+        if let Some(material) = material {
+            armor = armor.with_material(material);
+        }
+        return Ok(Some((AbilityTarget::Armor, armor.effective.cost, armor.material().is_some())));
+    }
+    Ok(None)
+}
+
+/// A newly crafted item along with its price.
+#[derive(Serialize, Clone, Debug)]
+pub struct CraftedItem {
+    pub item: OwnedItem,
+    #[serde(flatten)]
+    pub price: ItemPrice,
+}
+
+impl From<CraftedItem> for Bytes {
+    fn from(crafted: CraftedItem) -> Self {
+        status::serialize_to_bytes(&crafted)
+    }
+}
+
+async fn craft_item(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<CraftedItem>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let item_id: Uuid = forms::get_required_form_text_field(&form, FIELD_ITEM_ID)?;
+    let bag: Uuid = forms::get_required_form_text_field(&form, FIELD_BAG_ID)?;
+    let material_id: Option<Uuid> = forms::get_optional_form_text_field(&form, FIELD_MATERIAL_ID)?;
+    let enhancement: i16 = forms::get_optional_form_text_field(&form, FIELD_ENHANCEMENT)?.unwrap_or(0);
+    let name: Option<String> = forms::get_optional_form_text_field(&form, FIELD_NAME)?;
+    let abilities: Option<String> = forms::get_optional_form_text_field(&form, FIELD_ABILITIES)?;
+    let abilities = match abilities {
+        Some(abilities) => serde_json::from_str::<BTreeSet<Uuid>>(&abilities)
+            .map_err(|_| forms::field_is_invalid_error(FIELD_ABILITIES))?
+            .into_iter()
+            .map(|id| forms::value_by_id::<SpecialAbility>(id, &conn))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+
+    let owner = match inventory::bag_owner(&bag, &conn) {
+        Ok(owner) => Some(owner),
+        Err(Error::NoRows) => None,
+        Err(err) => return Err(err.into()),
+    };
+    if owner != Some(char_id) {
+        return Err(forms::field_is_invalid_error(FIELD_BAG_ID));
+    }
+    let base = forms::value_by_id::<Item>(item_id, &conn)?;
+    let material = material_id
+        .map(|id| forms::value_by_id::<Material>(id, &conn))
+        .transpose()?;
+    let (kind, cost, special) = crafting_base(&item_id, material.clone(), &conn)?
+        .ok_or_else(|| forms::field_is_invalid_error(FIELD_ITEM_ID))?;
+    if abilities.iter().any(|ability| ability.applies_to != kind) {
+        return Err(forms::field_is_invalid_error(FIELD_ABILITIES));
+    }
+
+    let costs = abilities.iter()
+        .map(|ability| ability.cost)
+        .collect::<Vec<AbilityCost>>();
+    let material_cost = Some(cost - base.cost).filter(|_| special);
+    let price = price_item(kind, base.cost, material_cost, enhancement, &costs)
+        .map_err(|err| status::bad_request(err.to_string()))?;
+
+    let crafted = conn.transaction::<_, Error, _>(|| {
+        let mut owned = DBOwnedItem::new(item_id);
+        owned.name = name.filter(|name| !name.is_empty());
+        owned.enhancement = enhancement;
+        owned.material_id = material.as_ref().map(|material| material.id().to_owned());
+        owned.masterwork = true;
+        owned.db_insert(&conn)?;
+        for ability in abilities.iter() {
+            DBOwnedItemAbility {
+                owned_item_id: owned.id,
+                ability_id: ability.id().to_owned(),
+            }.db_insert(&conn)?;
+        }
+        inventory::put_in_bag(&bag, &owned.id, 1, &conn)?;
+        OwnedItem::try_from_db(owned, &conn)
+    })?;
+
+    let crafted = CraftedItem {
+        item: crafted,
+        price,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(crafted)))
+}
+
+/// A warp Filter for crafting magic weapons and armor, relative to
+/// `/characters`.
+pub fn crafting_filter() -> BoxedFilter<(impl Reply,)> {
+    warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("craft"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(craft_item)
+        .boxed()
+}
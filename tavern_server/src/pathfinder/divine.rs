@@ -0,0 +1,394 @@
+use super::character::DBCharacter;
+use super::class::{Class, Proficiencies, WeaponProficiencies};
+use super::item::Weapon;
+use super::religion::{Deity, Domain, Subdomain};
+use super::summary::{Summarize, Summary};
+use super::wealth::classes_of_character;
+use super::Alignment;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, GetById};
+use crate::forms;
+use crate::schema::{characterdomains, characterdomainspells, characters, characterspells, charactersubclasses, domainspells, subclasses};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment_must_be_one_step_from_the_deity() {
+        assert!(alignment_allowed(Alignment::LawfulGood, Alignment::LawfulGood));
+        assert!(alignment_allowed(Alignment::NeutralGood, Alignment::LawfulGood));
+        assert!(alignment_allowed(Alignment::LawfulNeutral, Alignment::LawfulGood));
+        assert!(!alignment_allowed(Alignment::TrueNeutral, Alignment::LawfulGood));
+        assert!(!alignment_allowed(Alignment::ChaoticGood, Alignment::LawfulGood));
+
+        // Every step along an axis counts, so the corners are two steps from
+        // true neutral.
+        assert!(alignment_allowed(Alignment::ChaoticNeutral, Alignment::TrueNeutral));
+        assert!(!alignment_allowed(Alignment::ChaoticEvil, Alignment::TrueNeutral));
+    }
+
+    #[test]
+    fn domains_must_be_offered_by_the_deity() {
+        let (sun, glory, war) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (day, heroism) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut offered = BTreeMap::new();
+        offered.insert(sun, vec![day].into_iter().collect::<BTreeSet<Uuid>>());
+        offered.insert(glory, vec![heroism].into_iter().collect::<BTreeSet<Uuid>>());
+
+        let mut chosen = BTreeMap::new();
+        chosen.insert(sun, Some(day));
+        chosen.insert(glory, None);
+        assert!(check_domains(&chosen, &offered, 2).is_ok());
+        // No more domains than the class grants.
+        assert!(check_domains(&chosen, &offered, 1).is_err());
+
+        // A subdomain has to belong to the domain it's chosen with.
+        chosen.insert(glory, Some(day));
+        assert!(check_domains(&chosen, &offered, 2).is_err());
+
+        chosen.insert(glory, Some(heroism));
+        chosen.insert(war, None);
+        assert!(check_domains(&chosen, &offered, 3).is_err());
+    }
+}
+
+const FIELD_DEITY_ID: &str = "deity-id";
+const FIELD_DOMAINS: &str = "domains";
+
+/// How many steps along the alignment axes a divine caster can be from
+/// their deity.
+pub const MAX_ALIGNMENT_STEPS: i16 = 1;
+
+/// Whether a divine caster with `alignment` can serve a deity with the
+/// alignment `deity`.
+pub fn alignment_allowed(alignment: Alignment, deity: Alignment) -> bool {
+    alignment.steps_from(deity) <= MAX_ALIGNMENT_STEPS
+}
+
+/// Checks a choice of domains, each with an optional subdomain, against the
+/// domains a deity offers mapped to the subdomains of each.
+pub fn check_domains(chosen: &BTreeMap<Uuid, Option<Uuid>>, offered: &BTreeMap<Uuid, BTreeSet<Uuid>>, allowance: i16) -> Result<(), Error> {
+    if chosen.len() > allowance as usize {
+        return Err(Error::new(format!("{} domains were chosen but only {} are allowed", chosen.len(), allowance)));
+    }
+    for (domain, subdomain) in chosen.iter() {
+        let subdomains = offered.get(domain)
+            .ok_or_else(|| Error::new(format!("domain {} isn't one of the deity's domains", domain)))?;
+        if let Some(subdomain) = subdomain {
+            if !subdomains.contains(subdomain) {
+                return Err(Error::new(format!("subdomain {} doesn't belong to domain {}", subdomain, domain)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What a character's classes get out of serving a deity.
+#[derive(Clone, Copy, Default)]
+struct Patronage {
+    /// Whether any of the character's classes draws power from their deity.
+    divine_patron: bool,
+    /// The most domains any of the character's classes grants.
+    domains: i16,
+}
+
+fn patronage(char_id: &Uuid, conn: &Connection) -> Result<Patronage, DBError> {
+    let taken = charactersubclasses::table.inner_join(subclasses::table)
+        .select((subclasses::divine_patron, subclasses::domains))
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .load::<(bool, i16)>(conn)
+        .map_err(DBError::RunQuery)?;
+    let patronage = taken.into_iter()
+        .fold(Patronage::default(), |patronage, (divine_patron, domains)| Patronage {
+            divine_patron: patronage.divine_patron || divine_patron,
+            domains: patronage.domains.max(domains),
+        });
+    Ok(patronage)
+}
+
+/// The weapon proficiencies of each of a character's classes. When one of
+/// their classes draws power from their deity, the deity's favored weapons are
+/// added to each of them.
+pub fn weapon_proficiencies(character: &DBCharacter, conn: &Connection) -> Result<Vec<WeaponProficiencies>, DBError> {
+    let mut favored = Vec::new();
+    if let Some(deity_id) = character.deity_id() {
+        if patronage(character.id(), conn)?.divine_patron {
+            favored.extend(Deity::db_get_by_id(deity_id, conn)?.weapons().iter().cloned());
+        }
+    }
+    classes_of_character(character.id(), conn)?
+        .into_iter()
+        .map(|id| {
+            let mut proficiencies = Class::db_get_by_id(&id, conn)?.weapon_proficiencies().clone();
+            for weapon in favored.iter() {
+                proficiencies.proficient(weapon.clone());
+            }
+            Ok(proficiencies)
+        })
+        .collect()
+}
+
+/// A character's domains, mapped to the subdomain chosen for each.
+fn chosen_domains(char_id: &Uuid, conn: &Connection) -> Result<BTreeMap<Uuid, Option<Uuid>>, DBError> {
+    characterdomains::table
+        .select((characterdomains::domain_id, characterdomains::subdomain_id))
+        .filter(characterdomains::char_id.eq(char_id))
+        .load::<(Uuid, Option<Uuid>)>(conn)
+        .map(|domains| domains.into_iter().collect())
+        .map_err(DBError::RunQuery)
+}
+
+/// The spells granted by some domains, with the number of casts of each.
+fn domain_spells(domain_ids: Vec<Uuid>, conn: &Connection) -> Result<BTreeMap<Uuid, i16>, DBError> {
+    domainspells::table
+        .select((domainspells::spell_id, domainspells::casts))
+        .filter(domainspells::domain_id.eq_any(domain_ids))
+        .load::<(Uuid, i16)>(conn)
+        .map(|spells| spells.into_iter().collect())
+        .map_err(DBError::RunQuery)
+}
+
+/// Replaces a character's domains and adds the new domains' spells to the
+/// character's spells. Spells from dropped domains are removed unless one
+/// of the new domains grants them too, or the character already knew them
+/// before a domain granted them.
+fn replace_domains(char_id: Uuid, domains: &BTreeMap<Uuid, Option<Uuid>>, conn: &Connection) -> Result<(), DBError> {
+    conn.transaction::<_, DBError, _>(|| {
+        let dropped = chosen_domains(&char_id, conn)?
+            .into_keys()
+            .filter(|domain_id| !domains.contains_key(domain_id))
+            .collect();
+        let spells = domain_spells(domains.keys().cloned().collect(), conn)?;
+        let dropped_spells = domain_spells(dropped, conn)?
+            .into_keys()
+            .filter(|spell_id| !spells.contains_key(spell_id))
+            .collect::<Vec<Uuid>>();
+
+        let granted = characterdomainspells::table.select(characterdomainspells::spell_id)
+            .filter(characterdomainspells::char_id.eq(char_id));
+        diesel::delete(characterspells::table
+                .filter(characterspells::char_id.eq(char_id))
+                .filter(characterspells::spell_id.eq_any(dropped_spells))
+                .filter(characterspells::spell_id.eq_any(granted)))
+            .execute(conn)
+            .map_err(DBError::RunQuery)?;
+        diesel::delete(characterdomains::table.filter(characterdomains::char_id.eq(char_id)))
+            .execute(conn)
+            .map_err(DBError::RunQuery)?;
+
+        let domain_rows = domains.iter()
+            .map(|(domain_id, subdomain_id)| (
+                characterdomains::char_id.eq(char_id),
+                characterdomains::domain_id.eq(*domain_id),
+                characterdomains::subdomain_id.eq(*subdomain_id),
+            ))
+            .collect::<Vec<_>>();
+        diesel::insert_into(characterdomains::table)
+            .values(&domain_rows)
+            .execute(conn)
+            .map_err(DBError::RunQuery)?;
+
+        // Spells the character already knows keep their remaining casts.
+        let spell_rows = spells.iter()
+            .map(|(spell_id, casts)| (
+                characterspells::char_id.eq(char_id),
+                characterspells::spell_id.eq(*spell_id),
+                characterspells::casts_remaining.eq(*casts),
+            ))
+            .collect::<Vec<_>>();
+        let added = diesel::insert_into(characterspells::table)
+            .values(&spell_rows)
+            .on_conflict((characterspells::char_id, characterspells::spell_id))
+            .do_nothing()
+            .returning(characterspells::spell_id)
+            .get_results::<Uuid>(conn)
+            .map_err(DBError::RunQuery)?;
+        let granted_rows = added.into_iter()
+            .map(|spell_id| (
+                characterdomainspells::char_id.eq(char_id),
+                characterdomainspells::spell_id.eq(spell_id),
+            ))
+            .collect::<Vec<_>>();
+        diesel::insert_into(characterdomainspells::table)
+            .values(&granted_rows)
+            .execute(conn)
+            .map(|_| ())
+            .map_err(DBError::RunQuery)
+    })
+}
+
+/// A domain a character has chosen from their deity.
+#[derive(Serialize, Clone, Debug)]
+pub struct ChosenDomain {
+    pub domain: Summary<Domain>,
+    pub subdomain: Option<Subdomain>,
+}
+
+/// One of a deity's favored weapons and whether the character can wield it
+/// proficiently.
+#[derive(Serialize, Clone, Debug)]
+pub struct FavoredWeapon {
+    pub weapon: Summary<Weapon>,
+    pub proficient: bool,
+}
+
+/// A character's deity and what they get out of serving it.
+#[derive(Serialize, Clone, Debug)]
+pub struct Devotion {
+    pub deity: Option<Summary<Deity>>,
+    pub deity_alignment: Option<Alignment>,
+    pub alignment: Alignment,
+    /// Whether the character's alignment is close enough to their deity's.
+    /// Only divine casters are held to this.
+    pub alignment_allowed: bool,
+    pub divine_patron: bool,
+    /// The number of domains the character can choose from their deity.
+    pub domain_allowance: i16,
+    pub domains: Vec<ChosenDomain>,
+    pub favored_weapons: Vec<FavoredWeapon>,
+}
+
+impl From<Devotion> for Bytes {
+    fn from(devotion: Devotion) -> Self {
+        status::serialize_to_bytes(&devotion)
+    }
+}
+
+fn devotion(character: &DBCharacter, conn: &Connection) -> Result<Devotion, DBError> {
+    let patronage = patronage(character.id(), conn)?;
+    let deity = character.deity_id()
+        .map(|id| Deity::db_get_by_id(id, conn))
+        .transpose()?;
+    let proficiencies = weapon_proficiencies(character, conn)?;
+
+    let domains = chosen_domains(character.id(), conn)?
+        .into_iter()
+        .map(|(domain_id, subdomain_id)| Ok(ChosenDomain {
+            domain: Summary::<Domain>::db_get_by_id(&domain_id, conn)?,
+            subdomain: subdomain_id.map(|id| Subdomain::db_get_by_id(&id, conn)).transpose()?,
+        }))
+        .collect::<Result<Vec<_>, DBError>>()?;
+    let favored_weapons = match deity.as_ref() {
+        Some(deity) => deity.weapons().iter()
+            .map(|weapon| {
+                let weapon_type = Weapon::db_get_by_id(weapon.id(), conn)?.weapon_type();
+                Ok(FavoredWeapon {
+                    weapon: weapon.clone(),
+                    proficient: proficiencies.iter().any(|proficiencies| proficiencies.is_proficient(weapon, weapon_type)),
+                })
+            })
+            .collect::<Result<Vec<_>, DBError>>()?,
+        None => Vec::new(),
+    };
+
+    Ok(Devotion {
+        deity_alignment: deity.as_ref().map(Deity::alignment),
+        alignment_allowed: deity.iter()
+            .all(|deity| !patronage.divine_patron || alignment_allowed(character.alignment(), deity.alignment())),
+        deity: deity.as_ref().map(Summary::from),
+        alignment: character.alignment(),
+        divine_patron: patronage.divine_patron,
+        domain_allowance: patronage.domains,
+        domains,
+        favored_weapons,
+    })
+}
+
+async fn get_devotion(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Devotion>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let devotion = devotion(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(devotion)))
+}
+
+async fn choose_deity(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Devotion>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let deity_id: Uuid = forms::get_required_form_text_field(&form, FIELD_DEITY_ID)?;
+    let deity = forms::value_by_id::<Deity>(deity_id, &conn)?;
+    if patronage(&char_id, &conn)?.divine_patron && !alignment_allowed(character.alignment(), deity.alignment()) {
+        return Err(status::bad_request(format!("{}'s alignment is more than {} step from {}'s", character.name(), MAX_ALIGNMENT_STEPS, deity.name())));
+    }
+
+    // Domains chosen from the old deity don't carry over.
+    let character = conn.transaction::<_, DBError, _>(|| {
+        if character.deity_id() != Some(&deity_id) {
+            replace_domains(char_id, &BTreeMap::new(), &conn)?;
+        }
+        diesel::update(characters::table.filter(characters::id.eq(char_id)))
+            .set(characters::deity_id.eq(Some(deity_id)))
+            .get_result::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)
+    })?;
+    let devotion = devotion(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(devotion)))
+}
+
+async fn choose_domains(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Devotion>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let domains: String = forms::get_required_form_text_field(&form, FIELD_DOMAINS)?;
+    let domains = serde_json::from_str::<BTreeMap<Uuid, Option<Uuid>>>(&domains)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_DOMAINS))?;
+
+    let deity_id = character.deity_id().copied()
+        .ok_or_else(|| status::bad_request(format!("{} doesn't serve a deity", character.name())))?;
+    let deity = forms::value_by_id::<Deity>(deity_id, &conn)?;
+    let offered = deity.domains().iter()
+        .map(|domain| {
+            let domain = Domain::db_get_by_id(domain.id(), &conn)?;
+            let subdomains = domain.subdomains().iter()
+                .map(|subdomain| subdomain.id().to_owned())
+                .collect();
+            Ok((domain.id().to_owned(), subdomains))
+        })
+        .collect::<Result<BTreeMap<Uuid, BTreeSet<Uuid>>, DBError>>()?;
+    check_domains(&domains, &offered, patronage(&char_id, &conn)?.domains)
+        .map_err(|err| status::bad_request(err.message))?;
+
+    replace_domains(char_id, &domains, &conn)?;
+    let devotion = devotion(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(devotion)))
+}
+
+/// A warp Filter containing the deity and domain endpoints, relative to the
+/// `/characters` path.
+pub fn divine_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("devotion"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_devotion);
+    let deity = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("deity"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_deity);
+    let domains = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("domains"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_domains);
+
+    get.or(deity)
+        .or(domains)
+        .boxed()
+}
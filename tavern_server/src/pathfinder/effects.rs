@@ -1,13 +1,13 @@
 use super::summary::Summarize;
-use super::{Attributes, CharacterStats, CombatStats, Links, Skills};
+use super::{Attributes, CharacterStats, CombatStats, Links, Resistances, Skills};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{Attribute, CharacterStat, CombatStat, Skill};
+use super::{Attribute, CharacterStat, CombatStat, DamageType, Skill};
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
 
-use crate::schema::{attributeunits, characterunits, combatunits, effects, miscunits, skillunits};
+use crate::schema::{attributeunits, characterunits, combatunits, effects, miscunits, resistanceunits, skillunits};
 use crate::db::{Connection, TryFromDb, IntoDb, Error, GetAll, GetById, Delete, DeleteById, Insert, Update};
 use crate::forms::{self, TryFromForm};
 use warp::Rejection;
@@ -26,6 +26,7 @@ pub struct Effect {
     skill_effects: Skills,
     char_effects: CharacterStats,
     combat_effects: CombatStats,
+    resist_effects: Resistances,
     misc_effect: Option<String>,
 }
 
@@ -37,9 +38,77 @@ impl Effect {
     const FIELD_SKILL_EFFECTS: &'static str = "skill-effects";
     const FIELD_CHAR_EFFECTS: &'static str = "char-effects";
     const FIELD_COMBAT_EFFECTS: &'static str = "combat-effects";
+    const FIELD_RESIST_EFFECTS: &'static str = "resist-effects";
     const FIELD_MISC_EFFECT: &'static str = "misc-effect";
 }
 
+/// The combined modifiers of any number of effects. Most bonuses simply add
+/// up, but damage reduction, spell resistance and energy resistance do not
+/// stack, so only the highest value of each is kept.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Modifiers {
+    pub attributes: Attributes,
+    pub skills: Skills,
+    pub character: CharacterStats,
+    pub combat: CombatStats,
+    pub resistances: Resistances,
+    pub misc: Vec<String>,
+}
+
+impl Modifiers {
+    pub fn add_effect(&mut self, effect: &Effect) {
+        for (attr, modifier) in effect.attr_effects.iter() {
+            *self.attributes.entry(*attr).or_insert(0) += modifier;
+        }
+        for (skill, modifier) in effect.skill_effects.iter() {
+            *self.skills.entry(*skill).or_insert(0) += modifier;
+        }
+        for (stat, modifier) in effect.char_effects.iter() {
+            *self.character.entry(*stat).or_insert(0) += modifier;
+        }
+        for (stat, modifier) in effect.combat_effects.iter() {
+            let total = self.combat.entry(*stat).or_insert(0);
+            match stat {
+                CombatStat::DamageReduction | CombatStat::SpellResistance => *total = (*total).max(*modifier),
+                _ => *total += modifier,
+            }
+        }
+        for (damage_type, amount) in effect.resist_effects.iter() {
+            let total = self.resistances.entry(*damage_type).or_insert(0);
+            *total = (*total).max(*amount);
+        }
+        if let Some(misc) = &effect.misc_effect {
+            self.misc.push(misc.to_owned());
+        }
+    }
+
+    pub fn attribute(&self, attr: Attribute) -> i16 {
+        self.attributes.get(&attr).copied().unwrap_or(0)
+    }
+
+    pub fn skill(&self, skill: Skill) -> i16 {
+        self.skills.get(&skill).copied().unwrap_or(0)
+    }
+
+    pub fn combat(&self, stat: CombatStat) -> i16 {
+        self.combat.get(&stat).copied().unwrap_or(0)
+    }
+
+    pub fn resistance(&self, damage_type: DamageType) -> i16 {
+        self.resistances.get(&damage_type).copied().unwrap_or(0)
+    }
+}
+
+impl<'a> std::iter::FromIterator<&'a Effect> for Modifiers {
+    fn from_iter<I: IntoIterator<Item = &'a Effect>>(iter: I) -> Self {
+        let mut modifiers = Modifiers::default();
+        for effect in iter {
+            modifiers.add_effect(effect);
+        }
+        modifiers
+    }
+}
+
 impl TryFromForm for Effect {
     fn try_from_form(conn: &Connection, form: Form, this_id: Option<Uuid>, parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
         let id = forms::valid_id_or_new::<Effect>(this_id, conn)?;
@@ -91,6 +160,20 @@ impl TryFromForm for Effect {
             })
             .collect::<Result<_, _>>()?;
 
+        let resist_effects: Option<String> = forms::get_optional_form_text_field(&form, Effect::FIELD_RESIST_EFFECTS)?;
+        let resist_effects: Resistances = match resist_effects {
+            None => Resistances::new(),
+            Some(resist_effects) => serde_json::from_str::<BTreeMap<String, i16>>(&resist_effects)
+                .map_err(|_| forms::field_is_invalid_error(Effect::FIELD_RESIST_EFFECTS))?
+                .into_iter()
+                .map(|(type_string, amount)| {
+                    type_string.as_str().parse()
+                        .map_err(|_| forms::field_is_invalid_error(Effect::FIELD_RESIST_EFFECTS))
+                        .map(|damage_type| (damage_type, amount))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
         let misc_effect: Option<String> = forms::get_optional_form_text_field(&form, Effect::FIELD_MISC_EFFECT)?;
 
         let effect = Effect {
@@ -103,6 +186,7 @@ impl TryFromForm for Effect {
             skill_effects,
             char_effects,
             combat_effects,
+            resist_effects,
             misc_effect,
         };
 
@@ -139,6 +223,12 @@ impl TryFromDb for Effect {
             .into_iter()
             .map(|unit| (unit.stat, unit.modifier))
             .collect();
+        let resist_effects = DBEffectResistanceUnit::belonging_to(&other)
+            .load::<DBEffectResistanceUnit>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|unit| (unit.damage_type, unit.amount))
+            .collect();
         let misc_effect = {
             let result = DBEffectMiscUnit::belonging_to(&other)
                 .first::<DBEffectMiscUnit>(conn);
@@ -161,6 +251,7 @@ impl TryFromDb for Effect {
             char_effects,
             combat_effects,
             skill_effects,
+            resist_effects,
             misc_effect,
         };
 
@@ -169,7 +260,7 @@ impl TryFromDb for Effect {
 }
 
 impl IntoDb for Effect {
-    type DBType = (DBEffect, Vec<DBEffectAttributeUnit>, Vec<DBEffectSkillUnit>, Vec<DBEffectCharacterUnit>, Vec<DBEffectCombatUnit>, Option<DBEffectMiscUnit>);
+    type DBType = (DBEffect, Vec<DBEffectAttributeUnit>, Vec<DBEffectSkillUnit>, Vec<DBEffectCharacterUnit>, Vec<DBEffectCombatUnit>, Vec<DBEffectResistanceUnit>, Option<DBEffectMiscUnit>);
 
     fn into_db(self) -> Self::DBType {
         let attr_units = self.attr_effects.iter()
@@ -200,6 +291,13 @@ impl IntoDb for Effect {
                 modifier: *modifier,
             }).collect();
 
+        let resist_units = self.resist_effects.iter()
+            .map(|(damage_type, amount)| DBEffectResistanceUnit {
+                effect_id: self.id,
+                damage_type: *damage_type,
+                amount: *amount,
+            }).collect();
+
         let misc_unit = self.misc_effect.as_ref().map(|val| DBEffectMiscUnit {
             effect_id: self.id.clone(),
             description: val.to_owned(),
@@ -212,7 +310,7 @@ impl IntoDb for Effect {
             long_description: self.long_description,
         };
 
-        (effect, attr_units, skill_units, char_units, combat_units, misc_unit)
+        (effect, attr_units, skill_units, char_units, combat_units, resist_units, misc_unit)
     }
 }
 
@@ -274,6 +372,18 @@ pub struct DBEffectMiscUnit {
     description: String,
 }
 
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, Delete, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "resistanceunits"]
+#[primary_key(effect_id, damage_type)]
+#[belongs_to(DBEffect, foreign_key = "effect_id")]
+pub struct DBEffectResistanceUnit {
+    effect_id: Uuid,
+    damage_type: DamageType,
+    amount: i16,
+}
+
 #[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
 #[derive(GetAll, Delete, Insert, Update)]
 #[tavern(is_identifiable, is_insertable, is_queryable)]
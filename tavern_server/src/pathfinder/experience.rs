@@ -0,0 +1,337 @@
+use super::character::DBCharacter;
+use super::class::Class;
+use super::divine;
+use super::membership;
+use super::religion::Deity;
+use super::sheet::CharacterSheet;
+use super::summary::Summarize;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error, GetById};
+use crate::forms;
+use crate::schema::{campaigns, characters, charactersubclasses, subclasses};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use diesel_derive_enum::DbEnum;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_follow_the_advancement_table() {
+        assert_eq!(AdvancementTrack::Medium.xp_for_level(1), Some(0));
+        assert_eq!(AdvancementTrack::Medium.xp_for_level(2), Some(2000));
+        assert_eq!(AdvancementTrack::Slow.xp_for_level(20), Some(5_350_000));
+        assert_eq!(AdvancementTrack::Fast.xp_for_level(21), None);
+
+        assert_eq!(AdvancementTrack::Medium.level_for_xp(0), 1);
+        assert_eq!(AdvancementTrack::Medium.level_for_xp(1999), 1);
+        assert_eq!(AdvancementTrack::Medium.level_for_xp(2000), 2);
+        assert_eq!(AdvancementTrack::Fast.level_for_xp(2000), 2);
+        assert_eq!(AdvancementTrack::Slow.level_for_xp(2000), 1);
+        assert_eq!(AdvancementTrack::Medium.level_for_xp(i32::MAX), MAX_LEVEL);
+    }
+
+    #[test]
+    fn ready_to_level_up_compares_xp_with_the_current_level() {
+        let behind = Experience::new(AdvancementTrack::Medium, 5000, 2);
+        assert_eq!(behind.xp_level, 3);
+        assert!(behind.ready_to_level_up);
+        assert_eq!(behind.next_level_xp, Some(5000));
+
+        let current = Experience::new(AdvancementTrack::Medium, 5000, 3);
+        assert!(!current.ready_to_level_up);
+
+        let capped = Experience::new(AdvancementTrack::Medium, 4_000_000, MAX_LEVEL);
+        assert!(!capped.ready_to_level_up);
+        assert_eq!(capped.next_level_xp, None);
+    }
+
+    #[test]
+    fn even_splits_round_down() {
+        assert_eq!(split_evenly(1000, 4), 250);
+        assert_eq!(split_evenly(1000, 3), 333);
+        assert_eq!(split_evenly(1000, 0), 0);
+    }
+
+    #[test]
+    fn hit_dice_are_read_with_or_without_a_count() {
+        assert_eq!(die_sides("d8"), Some(8));
+        assert_eq!(die_sides("1d12"), Some(12));
+        assert_eq!(die_sides(" D6 "), Some(6));
+        assert_eq!(die_sides("2d6"), None);
+        assert_eq!(die_sides("8"), None);
+        assert_eq!(die_sides("d0"), None);
+    }
+
+    #[test]
+    fn every_level_gives_at_least_one_skill_point() {
+        assert_eq!(skill_points(2, 3), 5);
+        assert_eq!(skill_points(2, -1), 1);
+        assert_eq!(skill_points(4, -4), 1);
+    }
+}
+
+/// The highest level a character can reach.
+pub const MAX_LEVEL: i16 = 20;
+
+const FIELD_SUBCLASS_ID: &str = "subclass-id";
+const FIELD_HP: &str = "hp";
+const FIELD_SKILLS: &str = "skills";
+
+/// The number of sides of a single die written like "d8" or "1d8".
+fn die_sides(die: &str) -> Option<i16> {
+    let die = die.trim().to_lowercase();
+    let mut parts = die.splitn(2, 'd');
+    let count = parts.next().unwrap_or_default();
+    if !count.is_empty() && count != "1" {
+        return None;
+    }
+    parts.next()
+        .and_then(|sides| sides.parse::<i16>().ok())
+        .filter(|sides| *sides > 0)
+}
+
+/// The skill points a level in a class gives, which is never less than one.
+fn skill_points(per_level: i16, modifier: i16) -> i16 {
+    (per_level + modifier).max(1)
+}
+
+const SLOW: [i32; 19] = [
+    3000, 7500, 14000, 23000, 35000, 53000, 77000, 115000, 160000, 235000,
+    330000, 475000, 665000, 955000, 1350000, 1900000, 2700000, 3850000, 5350000,
+];
+const MEDIUM: [i32; 19] = [
+    2000, 5000, 9000, 15000, 23000, 35000, 51000, 75000, 105000, 155000,
+    220000, 315000, 445000, 635000, 890000, 1300000, 1800000, 2550000, 3600000,
+];
+const FAST: [i32; 19] = [
+    1300, 3300, 6000, 10000, 15000, 23000, 34000, 50000, 71000, 105000,
+    145000, 210000, 295000, 425000, 600000, 850000, 1200000, 1700000, 2400000,
+];
+
+/// How quickly the characters in a campaign gain levels. Each track has its
+/// own table of the experience needed to reach every level.
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Default)]
+pub enum AdvancementTrack {
+    Slow,
+    #[default]
+    Medium,
+    Fast,
+}
+
+impl AdvancementTrack {
+    /// The experience needed for each level from 2 up to `MAX_LEVEL`.
+    fn table(self) -> &'static [i32] {
+        match self {
+            AdvancementTrack::Slow => &SLOW,
+            AdvancementTrack::Medium => &MEDIUM,
+            AdvancementTrack::Fast => &FAST,
+        }
+    }
+
+    /// The experience needed to reach a level, or None if the level is out of
+    /// range.
+    pub fn xp_for_level(self, level: i16) -> Option<i32> {
+        match level {
+            1 => Some(0),
+            2..=MAX_LEVEL => Some(self.table()[level as usize - 2]),
+            _ => None,
+        }
+    }
+
+    /// The level a character with the given experience has earned.
+    pub fn level_for_xp(self, xp: i32) -> i16 {
+        let reached = self.table().iter()
+            .take_while(|needed| **needed <= xp)
+            .count();
+        1 + reached as i16
+    }
+}
+
+/// A character's experience and how it compares to the levels they have
+/// actually taken.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Experience {
+    pub track: AdvancementTrack,
+    pub xp: i32,
+    /// The character's total level across all of their classes.
+    pub level: i16,
+    /// The level the character's experience entitles them to.
+    pub xp_level: i16,
+    /// The experience needed for the level after `level`, if there is one.
+    pub next_level_xp: Option<i32>,
+    pub ready_to_level_up: bool,
+}
+
+impl Experience {
+    pub fn new(track: AdvancementTrack, xp: i32, level: i16) -> Self {
+        let xp_level = track.level_for_xp(xp);
+        Experience {
+            track,
+            xp,
+            level,
+            xp_level,
+            next_level_xp: track.xp_for_level(level + 1),
+            ready_to_level_up: level < MAX_LEVEL && xp_level > level,
+        }
+    }
+
+    /// Works out the experience of a character, using the advancement track
+    /// of their campaign if they are in one.
+    pub fn of_character(character: &DBCharacter, conn: &Connection) -> Result<Self, Error> {
+        let track = match campaign::campaign_of_character(character.id(), conn)? {
+            Some(campaign_id) => advancement_of_campaign(&campaign_id, conn)?,
+            None => AdvancementTrack::default(),
+        };
+        let level = character.total_level(conn)?;
+        Ok(Experience::new(track, character.xp(), level))
+    }
+}
+
+impl From<Experience> for Bytes {
+    fn from(experience: Experience) -> Self {
+        status::serialize_to_bytes(experience)
+    }
+}
+
+/// The experience given to a single character.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExperienceAward {
+    pub character_id: Uuid,
+    pub awarded: i32,
+    pub experience: Experience,
+}
+
+/// Splits an award between some number of characters. Any experience that
+/// can't be divided evenly is lost.
+pub fn split_evenly(total: i32, count: usize) -> i32 {
+    if count == 0 {
+        0
+    } else {
+        total / count as i32
+    }
+}
+
+/// Returns the advancement track a campaign uses.
+pub fn advancement_of_campaign(campaign_id: &Uuid, conn: &Connection) -> Result<AdvancementTrack, Error> {
+    campaigns::table.select(campaigns::advancement)
+        .filter(campaigns::id.eq(campaign_id))
+        .first::<AdvancementTrack>(conn)
+        .map_err(Error::RunQuery)
+}
+
+/// Adds experience to a character and returns the updated character.
+pub fn award_xp(char_id: &Uuid, amount: i32, conn: &Connection) -> Result<DBCharacter, Error> {
+    diesel::update(characters::table.filter(characters::id.eq(char_id)))
+        .set(characters::xp.eq(characters::xp + amount))
+        .get_result::<DBCharacter>(conn)
+        .map_err(Error::RunQuery)
+}
+
+async fn get_experience(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Experience>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let experience = Experience::of_character(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(experience)))
+}
+
+/// Takes a level in a subclass, along with the hit points and skill points
+/// rolled or chosen for it.
+async fn level_up(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Experience>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let subclass_id: Uuid = forms::get_required_form_text_field(&form, FIELD_SUBCLASS_ID)?;
+    let hp: i16 = forms::get_required_form_text_field(&form, FIELD_HP)?;
+    let skills: i16 = forms::get_required_form_text_field(&form, FIELD_SKILLS)?;
+
+    let (class_id, divine_patron) = subclasses::table.select((subclasses::class_id, subclasses::divine_patron))
+        .filter(subclasses::id.eq(subclass_id))
+        .first::<(Uuid, bool)>(&conn)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_SUBCLASS_ID))?;
+    let class = Class::db_get_by_id(&class_id, &conn)?;
+    if !membership::alignment_allowed(class.alignments(), character.alignment()) {
+        return Err(status::bad_request(format!("{} can't take levels in {} while {}", character.name(), class.name(), character.alignment())));
+    }
+    if let (true, Some(deity_id)) = (divine_patron, character.deity_id()) {
+        let deity = Deity::db_get_by_id(deity_id, &conn)?;
+        if !divine::alignment_allowed(character.alignment(), deity.alignment()) {
+            return Err(status::bad_request(format!("{} is too far from {}'s alignment to take levels in {}", character.name(), deity.name(), class.name())));
+        }
+    }
+
+    let sides = die_sides(class.hit_die())
+        .ok_or_else(|| status::bad_request(format!("{}'s hit die can't be rolled", class.name())))?;
+    if hp < 1 || hp > sides {
+        return Err(forms::field_is_invalid_error(FIELD_HP));
+    }
+    let modifier = CharacterSheet::load_from_db(character.clone(), &conn)?
+        .attribute_modifier(class.skills_attr());
+    if skills < 0 || skills > skill_points(class.skills_per_level(), modifier) {
+        return Err(forms::field_is_invalid_error(FIELD_SKILLS));
+    }
+
+    // The character is locked while levelling up, so that two requests can't
+    // both spend the same experience.
+    let experience = conn.transaction::<_, Error, _>(|| {
+        let character = characters::table.filter(characters::id.eq(char_id))
+            .for_update()
+            .first::<DBCharacter>(&conn)
+            .map_err(Error::RunQuery)?;
+        if !Experience::of_character(&character, &conn)?.ready_to_level_up {
+            return Ok(None);
+        }
+        diesel::insert_into(charactersubclasses::table)
+            .values((
+                charactersubclasses::char_id.eq(char_id),
+                charactersubclasses::subclass_id.eq(subclass_id),
+                charactersubclasses::levels_taken.eq(1),
+                charactersubclasses::hp_taken.eq(hp),
+                charactersubclasses::skills_taken.eq(skills),
+            ))
+            .on_conflict((charactersubclasses::char_id, charactersubclasses::subclass_id))
+            .do_update()
+            .set((
+                charactersubclasses::levels_taken.eq(charactersubclasses::levels_taken + 1),
+                charactersubclasses::hp_taken.eq(charactersubclasses::hp_taken + hp),
+                charactersubclasses::skills_taken.eq(charactersubclasses::skills_taken + skills),
+            ))
+            .execute(&conn)
+            .map_err(Error::RunQuery)?;
+        Experience::of_character(&character, &conn).map(Some)
+    })?;
+    let experience = experience
+        .ok_or_else(|| status::bad_request(format!("{} doesn't have the experience for another level", character.name())))?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(experience)))
+}
+
+/// A warp Filter containing the experience endpoints, relative to the
+/// `/characters` path.
+pub fn experience_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("experience"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_experience);
+    let level_up = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("level-up"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(level_up);
+
+    get.or(level_up)
+        .boxed()
+}
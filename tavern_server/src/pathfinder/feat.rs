@@ -6,9 +6,10 @@ use super::summary::{Summarize, Summary};
 use super::{Attributes, Skills};
 
 use super::effects::Effect;
+use super::maneuvers::{CombatManeuver, ManeuverBonus, ManeuverBonuses};
 use super::{Attribute, Skill};
 
-use crate::schema::{attributefeatunits, feateffects, featrequirements, feats, skillfeatunits};
+use crate::schema::{attributefeatunits, feateffects, featmaneuverbonuses, featrequirements, feats, skillfeatunits};
 use crate::db::{TryFromDb, IntoDb, Connection, Error, GetById, GetAll, Delete, DeleteById, Insert, Update};
 use diesel::prelude::*;
 use diesel::associations::BelongsTo;
@@ -31,6 +32,9 @@ pub struct Feat {
     req_attrs: Attributes,
     req_feats: Vec<Summary<Feat>>,
     effects: BTreeSet<Summary<Effect>>,
+    /// Bonuses to the CMB and CMD of specific combat maneuvers, such as
+    /// Improved Trip's.
+    maneuver_bonuses: ManeuverBonuses,
 }
 
 impl Feat {
@@ -41,6 +45,11 @@ impl Feat {
     const FIELD_REQ_ATTRS: &'static str = "required-attrs";
     const FIELD_REQ_FEATS: &'static str = "required-feats";
     const FIELD_EFFECTS: &'static str = "effects";
+    const FIELD_MANEUVER_BONUSES: &'static str = "maneuver-bonuses";
+
+    pub fn maneuver_bonuses(&self) -> &ManeuverBonuses {
+        &self.maneuver_bonuses
+    }
 }
 
 impl TryFromForm for Feat {
@@ -85,6 +94,18 @@ impl TryFromForm for Feat {
                 forms::value_by_id(id, conn)
             })
             .collect::<Result<_, _>>()?;
+        let maneuver_bonuses = match forms::get_optional_form_text_field::<String>(&form, Feat::FIELD_MANEUVER_BONUSES)? {
+            Some(bonuses) => serde_json::from_str::<BTreeMap<String, ManeuverBonus>>(&bonuses)
+                .map_err(|_| forms::field_is_invalid_error(Feat::FIELD_MANEUVER_BONUSES))?
+                .into_iter()
+                .map(|(maneuver, bonus)| {
+                    let maneuver = maneuver.as_str().parse()
+                        .map_err(|_| forms::field_is_invalid_error(Feat::FIELD_MANEUVER_BONUSES))?;
+                    Ok((maneuver, bonus))
+                })
+                .collect::<Result<_, Rejection>>()?,
+            None => ManeuverBonuses::new(),
+        };
 
         let feat = Feat {
             links: Default::default(),
@@ -96,6 +117,7 @@ impl TryFromForm for Feat {
             req_attrs,
             req_feats,
             effects,
+            maneuver_bonuses,
         };
 
         Ok(feat)
@@ -131,6 +153,12 @@ impl TryFromDb for Feat {
             .into_iter()
             .map(|e| Summary::<Effect>::db_get_by_id(&e.effect_id, conn))
             .collect::<Result<_, Error>>()?;
+        let maneuver_bonuses = DBFeatManeuverBonus::belonging_to(&other)
+            .load::<DBFeatManeuverBonus>(conn)
+            .map_err(Error::RunQuery)?
+            .into_iter()
+            .map(|bonus| (bonus.maneuver, ManeuverBonus { offense: bonus.offense, defense: bonus.defense }))
+            .collect();
 
         let feat = Feat {
             id: other.id,
@@ -142,6 +170,7 @@ impl TryFromDb for Feat {
             req_feats,
             req_skills,
             effects,
+            maneuver_bonuses,
         };
 
         Ok(feat)
@@ -149,7 +178,7 @@ impl TryFromDb for Feat {
 }
 
 impl IntoDb for Feat {
-    type DBType = (DBFeat, Vec<DBFeatRequiredAttribute>, Vec<DBFeatRequiredSkill>, Vec<DBFeatRequiredFeat>);
+    type DBType = (DBFeat, Vec<DBFeatRequiredAttribute>, Vec<DBFeatRequiredSkill>, Vec<DBFeatRequiredFeat>, Vec<DBFeatManeuverBonus>);
 
     fn into_db(self) -> Self::DBType {
         let req_attrs = self.req_attrs.iter()
@@ -175,6 +204,15 @@ impl IntoDb for Feat {
             })
             .collect();
 
+        let maneuver_bonuses = self.maneuver_bonuses.iter()
+            .map(|(maneuver, bonus)| DBFeatManeuverBonus {
+                feat_id: self.id,
+                maneuver: *maneuver,
+                offense: bonus.offense,
+                defense: bonus.defense,
+            })
+            .collect();
+
         let feat = DBFeat {
             id: self.id.clone(),
             name: self.name,
@@ -182,7 +220,7 @@ impl IntoDb for Feat {
             long_description: self.long_description,
         };
 
-        (feat, req_attrs, req_skills, req_feats)
+        (feat, req_attrs, req_skills, req_feats, maneuver_bonuses)
     }
 }
 
@@ -242,3 +280,16 @@ pub struct DBFeatEffect {
     feat_id: Uuid,
     effect_id: Uuid,
 }
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Insert, Update, Delete)]
+#[tavern(is_insertable, is_identifiable, is_queryable)]
+#[table_name = "featmaneuverbonuses"]
+#[primary_key(feat_id, maneuver)]
+#[belongs_to(DBFeat, foreign_key = "feat_id")]
+pub struct DBFeatManeuverBonus {
+    feat_id: Uuid,
+    maneuver: CombatManeuver,
+    offense: i16,
+    defense: i16,
+}
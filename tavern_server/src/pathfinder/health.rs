@@ -0,0 +1,355 @@
+use super::effects::Modifiers;
+use super::sheet::CharacterSheet;
+use super::{CombatStat, DamageType};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error};
+use crate::events::{self, Event};
+use crate::forms;
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit_points(damage: i16, nonlethal: i16) -> HitPoints {
+        HitPoints {
+            max: 20,
+            damage,
+            nonlethal,
+            temporary: 0,
+            constitution: 12,
+        }
+    }
+
+    #[test]
+    fn thresholds_follow_current_hit_points() {
+        assert_eq!(hit_points(0, 0).state(), HealthState::Healthy);
+        assert_eq!(hit_points(19, 0).state(), HealthState::Healthy);
+        assert_eq!(hit_points(20, 0).state(), HealthState::Disabled);
+        assert_eq!(hit_points(21, 0).state(), HealthState::Dying);
+        assert_eq!(hit_points(31, 0).state(), HealthState::Dying);
+        assert_eq!(hit_points(32, 0).state(), HealthState::Dead);
+    }
+
+    #[test]
+    fn nonlethal_damage_staggers_then_knocks_out() {
+        assert_eq!(hit_points(5, 14).state(), HealthState::Healthy);
+        assert_eq!(hit_points(5, 15).state(), HealthState::Staggered);
+        assert_eq!(hit_points(5, 16).state(), HealthState::Unconscious);
+        assert_eq!(hit_points(20, 1).state(), HealthState::Unconscious);
+    }
+
+    #[test]
+    fn damage_reduction_only_applies_to_physical_damage() {
+        let mut modifiers = Modifiers::default();
+        modifiers.combat.insert(CombatStat::DamageReduction, 5);
+        modifiers.resistances.insert(DamageType::Fire, 10);
+
+        let mut hp = hit_points(0, 0);
+        let result = hp.take_damage(8, DamageType::Slashing, &modifiers);
+        assert_eq!(result, DamageResult { prevented: 5, absorbed: 0, taken: 3 });
+        let result = hp.take_damage(8, DamageType::Fire, &modifiers);
+        assert_eq!(result, DamageResult { prevented: 8, absorbed: 0, taken: 0 });
+        let result = hp.take_damage(8, DamageType::Cold, &modifiers);
+        assert_eq!(result, DamageResult { prevented: 0, absorbed: 0, taken: 8 });
+        assert_eq!(hp.damage, 11);
+    }
+
+    #[test]
+    fn temporary_hit_points_are_lost_first_and_do_not_stack() {
+        let mut hp = hit_points(0, 0);
+        hp.grant_temporary(5);
+        hp.grant_temporary(3);
+        assert_eq!(hp.temporary, 5);
+
+        let result = hp.take_damage(7, DamageType::Piercing, &Modifiers::default());
+        assert_eq!(result, DamageResult { prevented: 0, absorbed: 5, taken: 2 });
+        assert_eq!(hp.temporary, 0);
+        assert_eq!(hp.damage, 2);
+    }
+
+    #[test]
+    fn nonlethal_damage_beyond_max_becomes_lethal() {
+        let mut hp = hit_points(0, 18);
+        hp.take_damage(5, DamageType::Nonlethal, &Modifiers::default());
+        assert_eq!(hp.nonlethal, 20);
+        assert_eq!(hp.damage, 3);
+    }
+
+    #[test]
+    fn healing_removes_lethal_and_nonlethal_damage() {
+        let mut hp = hit_points(10, 4);
+        hp.heal(6, false);
+        assert_eq!(hp.damage, 4);
+        assert_eq!(hp.nonlethal, 0);
+
+        hp.heal(2, true);
+        assert_eq!(hp.damage, 4);
+    }
+}
+
+/// The form field holding the amount of damage or healing.
+pub const FIELD_AMOUNT: &str = "amount";
+/// The form field holding the type of damage being dealt.
+pub const FIELD_DAMAGE_TYPE: &str = "damage-type";
+/// The form field that restricts healing to nonlethal damage only.
+pub const FIELD_NONLETHAL_ONLY: &str = "nonlethal-only";
+
+/// The condition a character is in as a result of their current hit points.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HealthState {
+    Healthy,
+    /// Nonlethal damage is exactly equal to current hit points.
+    Staggered,
+    /// Current hit points are exactly 0.
+    Disabled,
+    /// Nonlethal damage exceeds current hit points.
+    Unconscious,
+    /// Current hit points are negative, but not below -Constitution.
+    Dying,
+    Dead,
+}
+
+/// A character's hit points. `constitution` is the effective Constitution
+/// score, which determines how far below 0 hit points a character can go
+/// before they die.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HitPoints {
+    pub max: i16,
+    pub damage: i16,
+    pub nonlethal: i16,
+    pub temporary: i16,
+    pub constitution: i16,
+}
+
+/// How a single instance of damage was applied.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DamageResult {
+    /// Damage stopped by damage reduction or energy resistance.
+    pub prevented: i16,
+    /// Damage taken from temporary hit points.
+    pub absorbed: i16,
+    /// Damage actually dealt to the character.
+    pub taken: i16,
+}
+
+impl HitPoints {
+    pub fn current(&self) -> i16 {
+        self.max - self.damage
+    }
+
+    pub fn state(&self) -> HealthState {
+        let current = self.current();
+        if current <= -self.constitution {
+            HealthState::Dead
+        } else if current < 0 {
+            HealthState::Dying
+        } else if self.nonlethal > current {
+            HealthState::Unconscious
+        } else if current == 0 {
+            HealthState::Disabled
+        } else if self.nonlethal == current {
+            HealthState::Staggered
+        } else {
+            HealthState::Healthy
+        }
+    }
+
+    /// Apply damage of the given type, after damage reduction (for physical
+    /// and nonlethal damage) or energy resistance (for everything else).
+    /// Temporary hit points are used up first. Nonlethal damage that would
+    /// take a character's nonlethal damage above their maximum hit points is
+    /// dealt as lethal damage instead.
+    pub fn take_damage(&mut self, amount: i16, damage_type: DamageType, modifiers: &Modifiers) -> DamageResult {
+        let reduction = if damage_type.is_physical() || damage_type == DamageType::Nonlethal {
+            modifiers.combat(CombatStat::DamageReduction)
+        } else {
+            modifiers.resistance(damage_type)
+        };
+        let prevented = amount.min(reduction.max(0));
+        let absorbed = (amount - prevented).min(self.temporary);
+        let taken = amount - prevented - absorbed;
+        self.temporary -= absorbed;
+
+        if damage_type == DamageType::Nonlethal {
+            let nonlethal = taken.min((self.max - self.nonlethal).max(0));
+            self.nonlethal += nonlethal;
+            self.damage = self.damage.saturating_add(taken - nonlethal);
+        } else {
+            self.damage = self.damage.saturating_add(taken);
+        }
+
+        DamageResult {
+            prevented,
+            absorbed,
+            taken,
+        }
+    }
+
+    /// Healing removes an equal amount of lethal and nonlethal damage, unless
+    /// it only affects nonlethal damage.
+    pub fn heal(&mut self, amount: i16, nonlethal_only: bool) {
+        if !nonlethal_only {
+            self.damage = (self.damage - amount).max(0);
+        }
+        self.nonlethal = (self.nonlethal - amount).max(0);
+    }
+
+    /// Temporary hit points from different sources don't stack; only the
+    /// highest value is kept.
+    pub fn grant_temporary(&mut self, amount: i16) {
+        self.temporary = self.temporary.max(amount);
+    }
+
+    fn save(&self, char_id: &Uuid, conn: &Connection) -> Result<(), Error> {
+        use crate::schema::characters::dsl::*;
+        diesel::update(characters.filter(id.eq(char_id)))
+            .set((
+                damage.eq(self.damage),
+                nonlethal.eq(self.nonlethal),
+                temp_hp.eq(self.temporary),
+            ))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(Error::RunQuery)
+    }
+}
+
+/// The hit points and resulting state of a character after a change.
+#[derive(Serialize, Clone, Debug)]
+pub struct HealthReport {
+    pub hit_points: HitPoints,
+    pub current: i16,
+    pub state: HealthState,
+    pub damage: Option<DamageResult>,
+}
+
+impl HealthReport {
+    fn new(hit_points: HitPoints, damage: Option<DamageResult>) -> Self {
+        HealthReport {
+            current: hit_points.current(),
+            state: hit_points.state(),
+            hit_points,
+            damage,
+        }
+    }
+}
+
+impl From<HealthReport> for Bytes {
+    fn from(report: HealthReport) -> Self {
+        status::serialize_to_bytes(&report)
+    }
+}
+
+pub(crate) fn amount_from_form(form: &Form) -> Result<i16, Rejection> {
+    let amount: i16 = forms::get_required_form_text_field(form, FIELD_AMOUNT)?;
+    if amount < 0 {
+        Err(forms::field_is_invalid_error(FIELD_AMOUNT))
+    } else {
+        Ok(amount)
+    }
+}
+
+/// Save the character's new hit points and let the rest of the party know.
+pub(crate) fn save_and_report(char_id: Uuid, hit_points: HitPoints, damage: Option<DamageResult>, conn: &Connection) -> Result<HealthReport, Error> {
+    hit_points.save(&char_id, conn)?;
+    let report = HealthReport::new(hit_points, damage);
+    events::publish_for_character(&char_id, Event::HitPointsChanged {
+        character_id: char_id,
+        report: report.clone(),
+    }, conn)?;
+    Ok(report)
+}
+
+/// Applies `change` to the character's current hit points and saves the
+/// result. The character's row is locked while this happens, so concurrent
+/// damage and healing can't overwrite each other.
+fn change_hit_points<F>(char_id: Uuid, user: &User, conn: &Connection, change: F) -> Result<HealthReport, Rejection>
+where
+    F: FnOnce(&mut HitPoints, &Modifiers) -> Option<DamageResult>,
+{
+    use crate::schema::characters::dsl::*;
+    campaign::managed_character(user, char_id, conn)?;
+    conn.transaction::<_, Error, _>(|| {
+        let character = characters.filter(id.eq(char_id))
+            .for_update()
+            .first(conn)
+            .map_err(Error::RunQuery)?;
+        let sheet = CharacterSheet::load_from_db(character, conn)?;
+        let mut hit_points = sheet.hit_points;
+        let damage_result = change(&mut hit_points, &sheet.modifiers);
+        save_and_report(char_id, hit_points, damage_result, conn)
+    }).map_err(Rejection::from)
+}
+
+async fn damage_character(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<HealthReport>>, Rejection> {
+    let amount = amount_from_form(&form)?;
+    let damage_type: DamageType = forms::get_required_form_text_field(&form, FIELD_DAMAGE_TYPE)?;
+    let report = change_hit_points(char_id, &user, &conn, |hit_points, modifiers| {
+        Some(hit_points.take_damage(amount, damage_type, modifiers))
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+async fn heal_character(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<HealthReport>>, Rejection> {
+    let amount = amount_from_form(&form)?;
+    let nonlethal_only = forms::get_optional_form_text_field(&form, FIELD_NONLETHAL_ONLY)?
+        .unwrap_or(false);
+    let report = change_hit_points(char_id, &user, &conn, |hit_points, _| {
+        hit_points.heal(amount, nonlethal_only);
+        None
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+async fn grant_temporary_hp(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<HealthReport>>, Rejection> {
+    let amount = amount_from_form(&form)?;
+    let report = change_hit_points(char_id, &user, &conn, |hit_points, _| {
+        hit_points.grant_temporary(amount);
+        None
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(report)))
+}
+
+/// A warp Filter containing the damage, healing and temporary hit point
+/// endpoints, relative to `/characters`.
+pub fn health_filter() -> BoxedFilter<(impl Reply,)> {
+    let damage = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("damage"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(damage_character);
+    let heal = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("heal"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(heal_character);
+    let temp_hp = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("temp-hp"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(grant_temporary_hp);
+
+    damage.or(heal)
+        .or(temp_hp)
+        .boxed()
+}
@@ -0,0 +1,535 @@
+use super::active_effect::{self, ActiveEffect};
+use super::item::{Consumable, DBOwnedItem, DBOwnedItemAbility, Item, OwnedItem};
+use super::spell::Spell;
+use super::summary::{Summarize, Summary};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, DeleteById, Error, GetById, Insert, IntoDb, TryFromDb, Update};
+use crate::events::{self, Event};
+use crate::forms;
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_are_taken_from_the_largest_stacks_first() {
+        let (big, small) = (Uuid::new_v4(), Uuid::new_v4());
+        let stacks = [(big, 5), (small, 2)];
+        assert_eq!(take_plan(&stacks, 3), Some(vec![(big, 3)]));
+        assert_eq!(take_plan(&stacks, 6), Some(vec![(big, 5), (small, 1)]));
+        assert_eq!(take_plan(&stacks, 7), Some(vec![(big, 5), (small, 2)]));
+        assert_eq!(take_plan(&stacks, 8), None);
+        assert_eq!(take_plan(&[], 1), None);
+    }
+
+    #[test]
+    fn plain_copies_merge_and_special_ones_keep_to_themselves() {
+        assert_eq!(move_kind(5, 2, true), Some(MoveKind::Merge));
+        assert_eq!(move_kind(5, 5, true), Some(MoveKind::Merge));
+        assert_eq!(move_kind(5, 2, false), Some(MoveKind::Split));
+        assert_eq!(move_kind(5, 5, false), Some(MoveKind::Whole));
+    }
+
+    #[test]
+    fn only_held_copies_can_be_moved() {
+        assert_eq!(move_kind(2, 3, true), None);
+        assert_eq!(move_kind(0, 1, false), None);
+        assert_eq!(move_kind(2, 0, false), None);
+    }
+
+    #[test]
+    fn fresh_wands_start_from_full_charges() {
+        assert_eq!(charges_after_use(None, 50), Some(49));
+        assert_eq!(charges_after_use(Some(3), 50), Some(2));
+        assert_eq!(charges_after_use(Some(1), 50), Some(0));
+    }
+
+    #[test]
+    fn empty_wands_cannot_be_used() {
+        assert_eq!(charges_after_use(Some(0), 50), None);
+        assert_eq!(charges_after_use(None, 0), None);
+    }
+}
+
+/// The form field holding the bag items are taken from.
+pub const FIELD_FROM_BAG: &str = "from-bag";
+/// The form field holding the bag items are put into.
+pub const FIELD_TO_BAG: &str = "to-bag";
+/// The form field holding the owned item being moved or used.
+pub const FIELD_ITEM_ID: &str = "item-id";
+/// The form field holding how many of the item to move.
+pub const FIELD_COUNT: &str = "count";
+/// The form field holding the bag an item is used from.
+pub const FIELD_BAG_ID: &str = "bag-id";
+/// The form field holding the character a consumable item is used on.
+pub const FIELD_TARGET_ID: &str = "target-id";
+
+/// The result of moving items from one character's bag to another.
+#[derive(Serialize, Clone, Debug)]
+pub struct Transfer {
+    pub from_character: Uuid,
+    pub to_character: Uuid,
+    /// The items as they are in the other bag.
+    pub item: Summary<OwnedItem>,
+    pub count: i32,
+}
+
+impl From<Transfer> for Bytes {
+    fn from(transfer: Transfer) -> Self {
+        status::serialize_to_bytes(&transfer)
+    }
+}
+
+/// The result of using a consumable item.
+#[derive(Serialize, Clone, Debug)]
+pub struct ItemUsed {
+    pub item: Summary<Item>,
+    pub target: Uuid,
+    /// The charges left on a wand. Potions and scrolls are used up instead.
+    pub charges_left: Option<i16>,
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl From<ItemUsed> for Bytes {
+    fn from(used: ItemUsed) -> Self {
+        status::serialize_to_bytes(&used)
+    }
+}
+
+impl From<OwnedItem> for Bytes {
+    fn from(item: OwnedItem) -> Self {
+        status::serialize_to_bytes(&item)
+    }
+}
+
+/// Returns the ID of the character carrying a bag.
+pub fn bag_owner(bag: &Uuid, conn: &Connection) -> Result<Uuid, Error> {
+    use crate::schema::bags::dsl::*;
+    bags.select(char_id)
+        .filter(id.eq(bag))
+        .first::<Uuid>(conn)
+        .optional()
+        .map_err(Error::RunQuery)?
+        .ok_or(Error::NoRows)
+}
+
+/// Put `amount` of a catalog item into a bag, on top of any plain copies
+/// already in it, returning the owned item they were added to.
+pub fn add_to_bag(bag: &Uuid, item: &Uuid, amount: i32, conn: &Connection) -> Result<Uuid, Error> {
+    let stacks = {
+        use crate::schema::{itemsinbags, owneditems};
+        itemsinbags::table.inner_join(owneditems::table)
+            .select(owneditems::all_columns)
+            .filter(itemsinbags::bag_id.eq(bag))
+            .filter(owneditems::item_id.eq(item))
+            .load::<DBOwnedItem>(conn)
+            .map_err(Error::RunQuery)?
+    };
+    for stack in stacks {
+        if stack.is_plain(conn)? {
+            use crate::schema::itemsinbags::dsl::*;
+            diesel::update(itemsinbags.filter(owned_item_id.eq(stack.id)))
+                .set(count.eq(count + amount))
+                .execute(conn)
+                .map_err(Error::RunQuery)?;
+            return Ok(stack.id);
+        }
+    }
+    let owned = DBOwnedItem::new(*item);
+    owned.db_insert(conn)?;
+    put_in_bag(bag, &owned.id, amount, conn)?;
+    Ok(owned.id)
+}
+
+/// Put a stack of an owned item that isn't in any bag into one.
+pub fn put_in_bag(bag: &Uuid, owned: &Uuid, amount: i32, conn: &Connection) -> Result<(), Error> {
+    use crate::schema::itemsinbags::dsl::*;
+    diesel::insert_into(itemsinbags)
+        .values((bag_id.eq(bag), owned_item_id.eq(owned), count.eq(amount)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::RunQuery)
+}
+
+/// Take `amount` of an owned item out of a bag, getting rid of the item
+/// entirely once none are left. Fails without changing anything if the bag
+/// doesn't hold enough of it.
+pub fn remove_from_bag(bag: &Uuid, owned: &Uuid, amount: i32, conn: &Connection) -> Result<(), Error> {
+    let remaining = {
+        use crate::schema::itemsinbags::dsl::*;
+        diesel::update(itemsinbags
+                .filter(bag_id.eq(bag))
+                .filter(owned_item_id.eq(owned))
+                .filter(count.ge(amount)))
+            .set(count.eq(count - amount))
+            .returning(count)
+            .get_result::<i32>(conn)
+            .optional()
+            .map_err(Error::RunQuery)?
+            .ok_or_else(|| Error::InvalidValues(vec![FIELD_COUNT.to_string()]))?
+    };
+    if remaining == 0 {
+        // Removing the owned item takes it out of the bag along with it.
+        DBOwnedItem::db_delete_by_id(owned, conn)?;
+    }
+    Ok(())
+}
+
+/// Works out how many to take from each of the `(owned item, count)` stacks
+/// to make up `amount`, in the order given. None if they don't hold enough.
+fn take_plan(stacks: &[(Uuid, i32)], amount: i32) -> Option<Vec<(Uuid, i32)>> {
+    let mut plan = Vec::new();
+    let mut remaining = amount;
+    for (owned, held) in stacks.iter() {
+        if remaining == 0 {
+            break;
+        }
+        let taken = remaining.min(*held);
+        plan.push((*owned, taken));
+        remaining -= taken;
+    }
+    if remaining > 0 {
+        None
+    } else {
+        Some(plan)
+    }
+}
+
+/// Take `amount` of a catalog item out of a bag, from however many of its
+/// copies it takes. The biggest stacks are used first.
+pub fn take_from_bag(bag: &Uuid, item: &Uuid, amount: i32, conn: &Connection) -> Result<(), Error> {
+    let stacks = {
+        use crate::schema::{itemsinbags, owneditems};
+        itemsinbags::table.inner_join(owneditems::table)
+            .select((itemsinbags::owned_item_id, itemsinbags::count))
+            .filter(itemsinbags::bag_id.eq(bag))
+            .filter(owneditems::item_id.eq(item))
+            .order(itemsinbags::count.desc())
+            .load::<(Uuid, i32)>(conn)
+            .map_err(Error::RunQuery)?
+    };
+    let plan = take_plan(&stacks, amount)
+        .ok_or_else(|| Error::InvalidValues(vec![FIELD_COUNT.to_string()]))?;
+    for (owned, taken) in plan {
+        remove_from_bag(bag, &owned, taken, conn)?;
+    }
+    Ok(())
+}
+
+fn held_count(bag: &Uuid, owned: &Uuid, conn: &Connection) -> Result<i32, Error> {
+    use crate::schema::itemsinbags::dsl::*;
+    itemsinbags.select(count)
+        .filter(bag_id.eq(bag))
+        .filter(owned_item_id.eq(owned))
+        .first::<i32>(conn)
+        .optional()
+        .map(|held| held.unwrap_or(0))
+        .map_err(Error::RunQuery)
+}
+
+/// Split `amount` off a stack of an owned item into a copy of its own, which
+/// is put into `to`.
+fn split_off(from: &Uuid, to: &Uuid, owned: &DBOwnedItem, amount: i32, conn: &Connection) -> Result<Uuid, Error> {
+    remove_from_bag(from, &owned.id, amount, conn)?;
+    let copy = DBOwnedItem {
+        id: Uuid::new_v4(),
+        ..owned.clone()
+    };
+    copy.db_insert(conn)?;
+    let abilities = DBOwnedItemAbility::belonging_to(owned)
+        .load::<DBOwnedItemAbility>(conn)
+        .map_err(Error::RunQuery)?;
+    for ability in abilities {
+        DBOwnedItemAbility {
+            owned_item_id: copy.id,
+            ..ability
+        }.db_insert(conn)?;
+    }
+    put_in_bag(to, &copy.id, amount, conn)?;
+    Ok(copy.id)
+}
+
+/// How some copies of an owned item are moved to another bag.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MoveKind {
+    /// Plain copies join any plain copies already in the other bag.
+    Merge,
+    /// Some copies of a stack that isn't plain are split off into a stack
+    /// of their own.
+    Split,
+    /// The whole stack changes bags.
+    Whole,
+}
+
+/// Decides how to move `amount` out of a stack of `held` copies, which may
+/// be `plain`. None if the stack doesn't hold that many.
+fn move_kind(held: i32, amount: i32, plain: bool) -> Option<MoveKind> {
+    if amount <= 0 || held < amount {
+        None
+    } else if plain {
+        Some(MoveKind::Merge)
+    } else if held > amount {
+        Some(MoveKind::Split)
+    } else {
+        Some(MoveKind::Whole)
+    }
+}
+
+/// Move `amount` of an owned item from one bag to another, returning the
+/// owned item they ended up as. Plain copies join any plain copies already
+/// in the other bag, while anything else keeps its name, hit points and the
+/// like.
+pub fn move_to_bag(from: &Uuid, to: &Uuid, owned: &Uuid, amount: i32, conn: &Connection) -> Result<Uuid, Error> {
+    let held = held_count(from, owned, conn)?;
+    let owned = DBOwnedItem::db_get_by_id(owned, conn)?;
+    let kind = move_kind(held, amount, owned.is_plain(conn)?)
+        .ok_or_else(|| Error::InvalidValues(vec![FIELD_COUNT.to_string()]))?;
+    match kind {
+        MoveKind::Merge => {
+            remove_from_bag(from, &owned.id, amount, conn)?;
+            add_to_bag(to, &owned.item_id, amount, conn)
+        },
+        MoveKind::Split => split_off(from, to, &owned, amount, conn),
+        MoveKind::Whole => {
+            use crate::schema::itemsinbags::dsl::*;
+            diesel::update(itemsinbags.filter(owned_item_id.eq(owned.id)))
+                .set(bag_id.eq(to))
+                .execute(conn)
+                .map_err(Error::RunQuery)?;
+            Ok(owned.id)
+        },
+    }
+}
+
+/// The charges left on an item after using one, given the charges it has
+/// (None for an unused item, which has `full_charges`). None if it has run
+/// out.
+fn charges_after_use(charges: Option<i16>, full_charges: i16) -> Option<i16> {
+    Some(charges.unwrap_or(full_charges) - 1).filter(|left| *left >= 0)
+}
+
+/// Use up one charge of a charged item in a bag, returning the item used and
+/// how many charges are left on it. A wand used from a stack of fresh ones,
+/// which have `full_charges`, is split off to keep track of its charges.
+pub fn use_charge(bag: &Uuid, owned: &Uuid, full_charges: i16, conn: &Connection) -> Result<(Uuid, i16), Error> {
+    let held = held_count(bag, owned, conn)?;
+    let mut used = DBOwnedItem::db_get_by_id(owned, conn)?;
+    let left = charges_after_use(used.charges, full_charges)
+        .filter(|_| held > 0)
+        .ok_or_else(|| Error::InvalidValues(vec![FIELD_ITEM_ID.to_string()]))?;
+    if held > 1 {
+        let copy = split_off(bag, bag, &used, 1, conn)?;
+        used = DBOwnedItem::db_get_by_id(&copy, conn)?;
+    }
+    used.charges = Some(left);
+    used.db_update(conn)?;
+    Ok((used.id, left))
+}
+
+/// The character holding an owned item, whether it's in one of their bags or
+/// equipped.
+fn item_holder(owned: &Uuid, conn: &Connection) -> Result<Option<Uuid>, Error> {
+    let carried = {
+        use crate::schema::{bags, itemsinbags};
+        itemsinbags::table.inner_join(bags::table)
+            .select(bags::char_id)
+            .filter(itemsinbags::owned_item_id.eq(owned))
+            .first::<Uuid>(conn)
+            .optional()
+            .map_err(Error::RunQuery)?
+    };
+    if carried.is_some() {
+        return Ok(carried);
+    }
+    use crate::schema::characterequipment::dsl::*;
+    characterequipment.select(char_id)
+        .filter(owned_item_id.eq(owned))
+        .first::<Uuid>(conn)
+        .optional()
+        .map_err(Error::RunQuery)
+}
+
+async fn transfer_items(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Transfer>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let from_bag: Uuid = forms::get_required_form_text_field(&form, FIELD_FROM_BAG)?;
+    let to_bag: Uuid = forms::get_required_form_text_field(&form, FIELD_TO_BAG)?;
+    let owned: Uuid = forms::get_required_form_text_field(&form, FIELD_ITEM_ID)?;
+    let count: i32 = forms::get_optional_form_text_field(&form, FIELD_COUNT)?.unwrap_or(1);
+    if count <= 0 {
+        return Err(forms::field_is_invalid_error(FIELD_COUNT));
+    }
+    if bag_owner(&from_bag, &conn).ok() != Some(char_id) {
+        return Err(forms::field_is_invalid_error(FIELD_FROM_BAG));
+    }
+    let to_character = bag_owner(&to_bag, &conn)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_TO_BAG))?;
+    // Moving items between a character's own bags needs no party.
+    let own_bag = to_character == char_id;
+    if from_bag == to_bag || !(own_bag || campaign::same_party(&char_id, &to_character, &conn)?) {
+        return Err(forms::field_is_invalid_error(FIELD_TO_BAG));
+    }
+    if held_count(&from_bag, &owned, &conn)? == 0 {
+        return Err(forms::field_is_invalid_error(FIELD_ITEM_ID));
+    }
+
+    let moved = conn.transaction::<_, Error, _>(|| {
+        move_to_bag(&from_bag, &to_bag, &owned, count, &conn)
+    })?;
+    let item = Summary::<OwnedItem>::db_get_by_id(&moved, &conn)?;
+
+    let transfer = Transfer {
+        from_character: char_id,
+        to_character,
+        item,
+        count,
+    };
+    events::publish_for_character(&char_id, Event::InventoryTransferred {
+        from_character: transfer.from_character,
+        to_character: transfer.to_character,
+        item: transfer.item.clone(),
+        count,
+    }, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(transfer)))
+}
+
+async fn use_item(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<ItemUsed>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let bag: Uuid = forms::get_required_form_text_field(&form, FIELD_BAG_ID)?;
+    let owned: Uuid = forms::get_required_form_text_field(&form, FIELD_ITEM_ID)?;
+    let target: Uuid = forms::get_optional_form_text_field(&form, FIELD_TARGET_ID)?
+        .unwrap_or(char_id);
+    if bag_owner(&bag, &conn).ok() != Some(char_id) {
+        return Err(forms::field_is_invalid_error(FIELD_BAG_ID));
+    }
+    if target != char_id && !campaign::same_party(&char_id, &target, &conn)? {
+        return Err(forms::field_is_invalid_error(FIELD_TARGET_ID));
+    }
+    let owned = DBOwnedItem::db_get_by_id(&owned, &conn)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_ITEM_ID))?;
+    let consumable = Consumable::db_get_by_id(&owned.item_id, &conn)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_ITEM_ID))?;
+    let spell = Spell::db_get_by_id(consumable.spell.id(), &conn)?;
+    let duration = spell.duration_in_rounds(consumable.caster_level);
+    let source = consumable.item().name.clone();
+
+    let (charges_left, effects) = conn.transaction::<_, Error, _>(|| {
+        let charges_left = if consumable.kind.is_single_use() {
+            remove_from_bag(&bag, &owned.id, 1, &conn)
+                .map_err(|_| Error::InvalidValues(vec![FIELD_ITEM_ID.to_string()]))?;
+            None
+        } else {
+            Some(use_charge(&bag, &owned.id, consumable.charges, &conn)?.1)
+        };
+        let effects = spell.effects.iter()
+            .map(|effect| active_effect::apply_effect(&target, effect.id(), source.clone(), duration, &conn))
+            .collect::<Result<Vec<ActiveEffect>, Error>>()?;
+        Ok((charges_left, effects))
+    })?;
+
+    for effect in effects.iter() {
+        events::publish_for_character(&target, Event::EffectApplied {
+            character_id: target,
+            effect: effect.clone(),
+        }, &conn)?;
+    }
+    let used = ItemUsed {
+        item: Summary::from(consumable.item()),
+        target,
+        charges_left,
+        effects,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(used)))
+}
+
+async fn edit_item(char_id: Uuid, owned: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<OwnedItem>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    if item_holder(&owned, &conn)? != Some(char_id) {
+        return Err(status::not_found());
+    }
+    // Blank fields clear the custom name and notes.
+    let name: Option<String> = forms::get_optional_form_text_field(&form, OwnedItem::FIELD_NAME)?;
+    let notes: Option<String> = forms::get_optional_form_text_field(&form, OwnedItem::FIELD_NOTES)?;
+    let hit_points: Option<i32> = forms::get_optional_form_text_field(&form, OwnedItem::FIELD_HIT_POINTS)?;
+
+    let edited = conn.transaction::<_, Error, _>(|| {
+        let mut edited = DBOwnedItem::db_get_by_id(&owned, &conn)?;
+        // Only one copy from a stack is edited.
+        let bag = {
+            use crate::schema::itemsinbags::dsl::*;
+            itemsinbags.select(bag_id)
+                .filter(owned_item_id.eq(owned))
+                .first::<Uuid>(&conn)
+                .optional()
+                .map_err(Error::RunQuery)?
+        };
+        if let Some(bag) = bag {
+            if held_count(&bag, &owned, &conn)? > 1 {
+                let copy = split_off(&bag, &bag, &edited, 1, &conn)?;
+                edited = DBOwnedItem::db_get_by_id(&copy, &conn)?;
+            }
+        }
+        let mut item = OwnedItem::try_from_db(edited, &conn)?;
+        if let Some(name) = name {
+            item.name = Some(name).filter(|name| !name.is_empty());
+        }
+        if let Some(notes) = notes {
+            item.notes = Some(notes).filter(|notes| !notes.is_empty());
+        }
+        if let Some(hit_points) = hit_points {
+            match item.max_hit_points {
+                Some(max) if hit_points >= 0 && hit_points <= max => item.hit_points = Some(hit_points),
+                _ => return Err(Error::InvalidValues(vec![OwnedItem::FIELD_HIT_POINTS.to_string()])),
+            }
+        }
+        let (db_item, _abilities) = item.clone().into_db();
+        db_item.db_update(&conn)?;
+        Ok(item)
+    })?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(edited)))
+}
+
+/// A warp Filter for moving items between bags, using consumable items and
+/// naming or annotating owned items, relative to `/characters`.
+pub fn inventory_filter() -> BoxedFilter<(impl Reply,)> {
+    let transfer = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("transfer"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(transfer_items);
+    let use_consumable = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("use-item"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(use_item);
+
+    let edit = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("items"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(edit_item);
+
+    transfer.or(use_consumable)
+        .or(edit)
+        .boxed()
+}
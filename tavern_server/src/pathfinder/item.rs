@@ -6,10 +6,14 @@ use uuid::Uuid;
 
 use super::character::{Character, DBCharacter};
 use super::effects::Effect;
+use super::spell::Spell;
 use super::summary::{Summarize, Summary};
 use super::{DamageType, EquipmentSlot, Links};
 
-use crate::schema::{armor, bags, itemeffects, items, itemsinbags, materials, weapons};
+use crate::schema::{
+    armor, bags, consumables, itemeffects, items, itemsinbags, materials, owneditemabilities, owneditems,
+    specialabilities, specialabilityeffects, weapons,
+};
 use diesel::prelude::*;
 use diesel_derive_enum::DbEnum;
 use tavern_derive::{Display, FromStr};
@@ -22,6 +26,143 @@ use nebula_status::{Status, StatusCode};
 use crate::status::Error;
 use std::str::FromStr;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_hit_points_scale_with_thickness() {
+        // A 2-inch-thick wooden door, at 10 hit points per inch.
+        assert_eq!(object_hit_points(Some(10), Some(2.0)), Some(20));
+        assert_eq!(object_hit_points(Some(30), Some(0.5)), Some(15));
+        assert_eq!(object_hit_points(Some(2), Some(0.1)), Some(1));
+        assert_eq!(object_hit_points(None, Some(2.0)), None);
+        assert_eq!(object_hit_points(Some(10), None), None);
+    }
+
+    #[test]
+    fn items_break_at_half_their_hit_points() {
+        assert!(!is_broken(Some(11), Some(20)));
+        assert!(is_broken(Some(10), Some(20)));
+        assert!(is_broken(Some(0), Some(20)));
+        assert!(!is_broken(None, None));
+    }
+
+    #[test]
+    fn only_wands_keep_their_charges() {
+        assert!(ConsumableKind::Potion.is_single_use());
+        assert!(ConsumableKind::Scroll.is_single_use());
+        assert!(!ConsumableKind::Wand.is_single_use());
+    }
+
+    #[test]
+    fn potions_and_scrolls_have_a_single_charge() {
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Potion, None), Some(1));
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Scroll, Some(20)), Some(1));
+    }
+
+    #[test]
+    fn wands_are_full_unless_told_otherwise() {
+        let full = Consumable::MAX_WAND_CHARGES;
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Wand, None), Some(full));
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Wand, Some(12)), Some(12));
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Wand, Some(0)), None);
+        assert_eq!(Consumable::starting_charges(ConsumableKind::Wand, Some(full + 1)), None);
+    }
+
+    const GP: i32 = 100;
+
+    fn material(name: &str, modifiers: MaterialModifiers) -> Material {
+        Material {
+            id: Uuid::new_v4(),
+            links: Links::new(),
+            name: name.to_string(),
+            description: String::new(),
+            hp_per_inch: None,
+            hardness: None,
+            modifiers,
+        }
+    }
+
+    fn mithral() -> Material {
+        material("mithral", MaterialModifiers {
+            weight_multiplier: 0.5,
+            light_armor_cost: 1000 * GP,
+            medium_armor_cost: 4000 * GP,
+            heavy_armor_cost: 9000 * GP,
+            armor_category_shift: -1,
+            max_dex_bonus: 2,
+            check_penalty_reduction: 3,
+            spell_failure_reduction: 10,
+            ..MaterialModifiers::default()
+        })
+    }
+
+    fn adamantine() -> Material {
+        material("adamantine", MaterialModifiers {
+            weapon_cost: 3000 * GP,
+            light_armor_cost: 5000 * GP,
+            medium_armor_cost: 10000 * GP,
+            heavy_armor_cost: 15000 * GP,
+            ..MaterialModifiers::default()
+        })
+    }
+
+    fn darkwood() -> Material {
+        material("darkwood", MaterialModifiers {
+            weight_multiplier: 0.5,
+            cost_per_pound: 10 * GP,
+            check_penalty_reduction: 2,
+            ..MaterialModifiers::default()
+        })
+    }
+
+    fn armor(cost: i32, weight: f64, armor_type: ArmorClass, max_dex_bonus: i32, spell_failure: i32, check_penalty: i32) -> ArmorStats {
+        ArmorStats { weight, cost, armor_type, max_dex_bonus, spell_failure, check_penalty }
+    }
+
+    #[test]
+    fn armor_shifts_stop_at_light_and_heavy() {
+        assert_eq!(ArmorClass::Medium.shifted(-1), ArmorClass::Light);
+        assert_eq!(ArmorClass::Medium.shifted(1), ArmorClass::Heavy);
+        assert_eq!(ArmorClass::Light.shifted(-1), ArmorClass::Light);
+        assert_eq!(ArmorClass::Heavy.shifted(2), ArmorClass::Heavy);
+        assert_eq!(ArmorClass::Heavy.shifted(-2), ArmorClass::Light);
+        assert_eq!(ArmorClass::Light.shifted(0), ArmorClass::Light);
+    }
+
+    #[test]
+    fn mithral_armor_is_lighter_by_a_category() {
+        // Chainmail is priced as medium armor even though mithral makes it light.
+        let chainmail = armor(150 * GP, 40.0, ArmorClass::Medium, 2, 30, 5);
+        assert_eq!(mithral().modify_armor(&chainmail), armor(4150 * GP, 20.0, ArmorClass::Light, 4, 20, 2));
+    }
+
+    #[test]
+    fn mithral_penalties_stop_at_zero() {
+        let chain_shirt = armor(100 * GP, 25.0, ArmorClass::Light, 4, 20, 2);
+        assert_eq!(mithral().modify_armor(&chain_shirt), armor(1100 * GP, 12.5, ArmorClass::Light, 6, 10, 0));
+        let padded = armor(5 * GP, 10.0, ArmorClass::Light, 8, 5, 0);
+        assert_eq!(mithral().modify_armor(&padded), armor(1005 * GP, 5.0, ArmorClass::Light, 10, 0, 0));
+    }
+
+    #[test]
+    fn adamantine_only_changes_the_price() {
+        let longsword = WeaponStats { weight: 4.0, cost: 15 * GP };
+        assert_eq!(adamantine().modify_weapon(&longsword), WeaponStats { weight: 4.0, cost: 3015 * GP });
+        let full_plate = armor(1500 * GP, 50.0, ArmorClass::Heavy, 1, 35, 6);
+        assert_eq!(adamantine().modify_armor(&full_plate), armor(16500 * GP, 50.0, ArmorClass::Heavy, 1, 35, 6));
+    }
+
+    #[test]
+    fn darkwood_is_priced_by_its_original_weight() {
+        let quarterstaff = WeaponStats { weight: 4.0, cost: 0 };
+        assert_eq!(darkwood().modify_weapon(&quarterstaff), WeaponStats { weight: 2.0, cost: 40 * GP });
+        let heavy_shield = armor(7 * GP, 10.0, ArmorClass::Light, 0, 15, 2);
+        assert_eq!(darkwood().modify_armor(&heavy_shield), armor(107 * GP, 5.0, ArmorClass::Light, 0, 15, 0));
+    }
+}
+
 #[derive(Serialize, Deserialize, Summarize, Clone)]
 pub struct Item {
     pub links: Links,
@@ -34,6 +175,8 @@ pub struct Item {
 
     pub equip_slot: Option<EquipmentSlot>,
     pub consumed_effects: BTreeSet<ItemEffect>,
+    /// How thick the item is, in inches, for working out its hit points.
+    pub thickness: Option<f64>,
 }
 
 impl Item {
@@ -43,6 +186,7 @@ impl Item {
     const FIELD_WEIGHT: &'static str = "weight";
     const FIELD_EQUIP_SLOT: &'static str = "equipment-slot";
     const FIELD_EFFECTS: &'static str = "effects";
+    const FIELD_THICKNESS: &'static str = "thickness";
 }
 
 impl TryFromForm for Item {
@@ -53,6 +197,10 @@ impl TryFromForm for Item {
         let cost = forms::get_required_form_text_field(&form, Item::FIELD_COST)?;
         let weight = forms::get_required_form_text_field(&form, Item::FIELD_WEIGHT)?;
         let equip_slot = forms::get_optional_form_text_field(&form, Item::FIELD_EQUIP_SLOT)?;
+        let thickness: Option<f64> = forms::get_optional_form_text_field(&form, Item::FIELD_THICKNESS)?;
+        if matches!(thickness, Some(thickness) if thickness.is_nan() || thickness <= 0.0) {
+            return Err(forms::field_is_invalid_error(Item::FIELD_THICKNESS));
+        }
         let effects: String = forms::get_required_form_text_field(&form, Item::FIELD_EFFECTS)?;
         let consumed_effects = serde_json::from_str::<BTreeMap<Uuid, bool>>(&effects)
             .map_err(|_| forms::field_is_invalid_error(Item::FIELD_EFFECTS))?
@@ -76,6 +224,7 @@ impl TryFromForm for Item {
             weight,
             equip_slot,
             consumed_effects,
+            thickness,
         };
 
         Ok(item)
@@ -97,6 +246,7 @@ impl TryFromDb for Item {
             weight: other.weight,
             equip_slot: other.equip_slot,
             consumed_effects,
+            thickness: other.thickness,
         };
         Ok(item)
     }
@@ -116,7 +266,8 @@ impl IntoDb for Item {
             description: self.description,
             cost: self.cost,
             weight: self.weight,
-            equip_slot: self.equip_slot
+            equip_slot: self.equip_slot,
+            thickness: self.thickness,
         };
 
         (item, effects)
@@ -210,6 +361,7 @@ pub struct DBItem {
     cost: i32,
     weight: f64,
     equip_slot: Option<EquipmentSlot>,
+    thickness: Option<f64>,
 }
 
 impl DBItem {
@@ -371,7 +523,7 @@ pub struct DBBag {
 
 #[derive(Serialize, Deserialize, StandaloneDbMarker, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct ItemInBag {
-    pub item: Summary<Item>,
+    pub item: OwnedItem,
     pub count: i32,
 }
 
@@ -380,7 +532,7 @@ impl TryFromDb for ItemInBag {
 
     fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
         let count = other.count;
-        let item = Summary::<Item>::db_get_by_id(&other.item_id, conn)?;
+        let item = OwnedItem::db_get_by_id(&other.owned_item_id, conn)?;
         let bag_item = ItemInBag {
             item,
             count,
@@ -394,21 +546,493 @@ impl IntoDbWithId for ItemInBag {
 
     fn into_db(self, bag_id: Uuid) -> Self::DBType {
         DBItemInBag {
-            item_id: self.item.id().to_owned(),
             bag_id,
             count: self.count,
+            owned_item_id: self.item.id,
         }
     }
 }
 
 #[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
 #[table_name = "itemsinbags"]
-#[primary_key(item_id, bag_id)]
+#[primary_key(owned_item_id)]
 #[belongs_to(DBBag, foreign_key = "bag_id")]
 pub struct DBItemInBag {
-    item_id: Uuid,
     bag_id: Uuid,
     count: i32,
+    owned_item_id: Uuid,
+}
+
+/// A particular copy of a catalog item that a character owns. Copies nobody
+/// has done anything to are kept together in one stack, while naming,
+/// damaging, enchanting or using a copy gives it an `OwnedItem` of its own.
+#[derive(Serialize, Deserialize, Clone, Debug, StandaloneDbMarker)]
+pub struct OwnedItem {
+    pub id: Uuid,
+    pub links: Links,
+    pub item: Summary<Item>,
+    pub name: Option<String>,
+    pub notes: Option<String>,
+    /// Only set when the item is made of a different material to the catalog
+    /// item.
+    pub material: Option<Summary<Material>>,
+    pub masterwork: bool,
+    /// Only known for items of a known thickness, made of a material with
+    /// hit points.
+    pub hit_points: Option<i32>,
+    pub max_hit_points: Option<i32>,
+    /// Whether the item has lost at least half of its hit points.
+    #[serde(default)]
+    pub broken: bool,
+    pub hardness: Option<i32>,
+    pub enhancement: i16,
+    /// The charges left on a charged item, once it has been used.
+    pub charges: Option<i16>,
+    pub abilities: BTreeSet<Summary<SpecialAbility>>,
+}
+
+impl OwnedItem {
+    pub const FIELD_NAME: &'static str = "name";
+    pub const FIELD_NOTES: &'static str = "notes";
+    pub const FIELD_HIT_POINTS: &'static str = "hit-points";
+
+    /// The highest enhancement bonus an item can have.
+    pub const MAX_ENHANCEMENT: i16 = 5;
+}
+
+/// An object's hit points are its material's hit points per inch of
+/// thickness times its thickness, but always at least 1.
+pub fn object_hit_points(hp_per_inch: Option<i32>, thickness: Option<f64>) -> Option<i32> {
+    let hit_points = (f64::from(hp_per_inch?) * thickness?).floor() as i32;
+    Some(hit_points.max(1))
+}
+
+/// An item is broken once it has lost half of its hit points.
+pub fn is_broken(hit_points: Option<i32>, max_hit_points: Option<i32>) -> bool {
+    match (hit_points, max_hit_points) {
+        (Some(current), Some(max)) => current <= max / 2,
+        _ => false,
+    }
+}
+
+impl TryFromDb for OwnedItem {
+    type DBType = DBOwnedItem;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let item = Summary::<Item>::db_get_by_id(&other.item_id, conn)?;
+        let own_material = other.material_id
+            .map(|id| Material::db_get_by_id(&id, conn))
+            .transpose()?;
+        let material = match own_material {
+            Some(ref material) => Some(material.clone()),
+            None => item_material(&other.item_id, conn)?,
+        };
+        let thickness = {
+            use crate::schema::items::dsl::*;
+            items.select(thickness)
+                .filter(id.eq(&other.item_id))
+                .first::<Option<f64>>(conn)
+                .map_err(DBError::RunQuery)?
+        };
+        let max_hit_points = object_hit_points(material.as_ref().and_then(|material| material.hp_per_inch), thickness);
+        let hit_points = other.hit_points.or(max_hit_points);
+        let abilities = other.get_abilities(conn)?;
+        let owned = OwnedItem {
+            id: other.id,
+            links: Links::new(),
+            item,
+            name: other.name,
+            notes: other.notes,
+            material: own_material.as_ref().map(Summary::from),
+            masterwork: other.masterwork,
+            hit_points,
+            max_hit_points,
+            broken: is_broken(hit_points, max_hit_points),
+            hardness: material.and_then(|material| material.hardness),
+            enhancement: other.enhancement,
+            charges: other.charges,
+            abilities,
+        };
+        Ok(owned)
+    }
+}
+
+impl IntoDb for OwnedItem {
+    type DBType = (DBOwnedItem, BTreeSet<DBOwnedItemAbility>);
+
+    fn into_db(self) -> Self::DBType {
+        let abilities = self.abilities.iter()
+            .map(|ability| DBOwnedItemAbility {
+                owned_item_id: self.id,
+                ability_id: ability.id().to_owned(),
+            })
+            .collect();
+
+        // Undamaged items don't keep track of their hit points.
+        let hit_points = self.hit_points.filter(|hp| Some(*hp) != self.max_hit_points);
+        let owned = DBOwnedItem {
+            id: self.id,
+            item_id: self.item.id().to_owned(),
+            name: self.name,
+            notes: self.notes,
+            hit_points,
+            enhancement: self.enhancement,
+            charges: self.charges,
+            material_id: self.material.map(|material| material.id().to_owned()),
+            masterwork: self.masterwork,
+        };
+
+        (owned, abilities)
+    }
+}
+
+impl Summarize<OwnedItem> for OwnedItem {
+    fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    fn links(&self) -> Option<&Links> {
+        Some(&self.links)
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_else(|| self.item.name())
+    }
+
+    fn description(&self) -> &str {
+        self.item.description()
+    }
+}
+
+impl Ord for OwnedItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl PartialOrd for OwnedItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OwnedItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for OwnedItem{}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetById, GetAll, Delete, DeleteById, Insert, Update)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "owneditems"]
+#[belongs_to(DBItem, foreign_key = "item_id")]
+pub struct DBOwnedItem {
+    pub(crate) id: Uuid,
+    pub(crate) item_id: Uuid,
+    pub(crate) name: Option<String>,
+    pub(crate) notes: Option<String>,
+    pub(crate) hit_points: Option<i32>,
+    pub(crate) enhancement: i16,
+    pub(crate) charges: Option<i16>,
+    pub(crate) material_id: Option<Uuid>,
+    pub(crate) masterwork: bool,
+}
+
+impl DBOwnedItem {
+    /// A fresh copy of a catalog item.
+    pub fn new(item_id: Uuid) -> Self {
+        DBOwnedItem {
+            id: Uuid::new_v4(),
+            item_id,
+            name: None,
+            notes: None,
+            hit_points: None,
+            enhancement: 0,
+            charges: None,
+            material_id: None,
+            masterwork: false,
+        }
+    }
+
+    fn get_abilities(&self, conn: &Connection) -> Result<BTreeSet<Summary<SpecialAbility>>, DBError> {
+        DBOwnedItemAbility::belonging_to(self)
+            .load::<DBOwnedItemAbility>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|ability| Summary::<SpecialAbility>::db_get_by_id(&ability.ability_id, conn))
+            .collect()
+    }
+
+    /// The armor this is a copy of, made of the material it was made of, if
+    /// it is armor at all.
+    pub fn armor(&self, conn: &Connection) -> Result<Option<Armor>, DBError> {
+        let catalog = {
+            use crate::schema::armor::dsl::*;
+            armor.filter(id.eq(self.item_id))
+                .first::<DBArmor>(conn)
+                .optional()
+                .map_err(DBError::RunQuery)?
+        };
+        let catalog = match catalog {
+            Some(catalog) => Armor::try_from_db(catalog, conn)?,
+            None => return Ok(None),
+        };
+        match self.material_id {
+            Some(material) => Ok(Some(catalog.with_material(Material::db_get_by_id(&material, conn)?))),
+            None => Ok(Some(catalog)),
+        }
+    }
+
+    /// Whether this copy is indistinguishable from a fresh one, and so can
+    /// share a stack with other fresh copies.
+    pub fn is_plain(&self, conn: &Connection) -> Result<bool, DBError> {
+        let plain = self.name.is_none()
+            && self.notes.is_none()
+            && self.hit_points.is_none()
+            && self.enhancement == 0
+            && self.charges.is_none()
+            && self.material_id.is_none()
+            && !self.masterwork;
+        Ok(plain && self.get_abilities(conn)?.is_empty())
+    }
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "owneditemabilities"]
+#[primary_key(owned_item_id, ability_id)]
+#[belongs_to(DBOwnedItem, foreign_key = "owned_item_id")]
+pub struct DBOwnedItemAbility {
+    pub(crate) owned_item_id: Uuid,
+    pub(crate) ability_id: Uuid,
+}
+
+/// The material a weapon or armor catalog item is made of, if any.
+fn item_material(item: &Uuid, conn: &Connection) -> Result<Option<Material>, DBError> {
+    let weapon_material = {
+        use crate::schema::weapons::dsl::*;
+        weapons.select(material_id)
+            .filter(id.eq(item))
+            .first::<Option<Uuid>>(conn)
+            .optional()
+            .map_err(DBError::RunQuery)?
+    };
+    let material_id = match weapon_material {
+        Some(material) => material,
+        None => {
+            use crate::schema::armor::dsl::*;
+            armor.select(material_id)
+                .filter(id.eq(item))
+                .first::<Option<Uuid>>(conn)
+                .optional()
+                .map_err(DBError::RunQuery)?
+                .flatten()
+        }
+    };
+    material_id.map(|material| Material::db_get_by_id(&material, conn)).transpose()
+}
+
+#[derive(
+    Serialize, Deserialize, Display, PartialEq, PartialOrd, Eq, Ord, Copy, Clone, DbEnum, Debug, FromStr
+)]
+pub enum AbilityTarget {
+    Weapon,
+    Armor,
+}
+
+/// What a special ability adds to the price of an item.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Ord, PartialOrd, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AbilityCost {
+    /// Priced as though the item's enhancement bonus were this much higher.
+    BonusEquivalent(i16),
+    /// A flat amount, in copper.
+    Flat(i32),
+}
+
+/// A special ability, such as flaming or shadow, that can be given to a
+/// weapon or armor.
+#[derive(Serialize, Deserialize, Summarize, Clone, Ord, PartialOrd, PartialEq, Eq)]
+pub struct SpecialAbility {
+    id: Uuid,
+    links: Links,
+    name: String,
+    description: String,
+    pub applies_to: AbilityTarget,
+    pub cost: AbilityCost,
+    /// Applied to whoever has an item with this ability equipped.
+    pub effects: BTreeSet<Summary<Effect>>,
+}
+
+impl SpecialAbility {
+    const FIELD_NAME: &'static str = "name";
+    const FIELD_DESCRIPTION: &'static str = "description";
+    const FIELD_APPLIES_TO: &'static str = "applies-to";
+    const FIELD_BONUS_EQUIVALENT: &'static str = "bonus-equivalent";
+    const FIELD_FLAT_COST: &'static str = "flat-cost";
+    const FIELD_EFFECTS: &'static str = "effects";
+}
+
+impl TryFromForm for SpecialAbility {
+    fn try_from_form(conn: &Connection, form: Form, this_id: Option<Uuid>, _parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
+        let id = forms::valid_id_or_new::<SpecialAbility>(this_id, conn)?;
+        let name = forms::get_required_form_text_field(&form, SpecialAbility::FIELD_NAME)?;
+        let description = forms::get_required_form_text_field(&form, SpecialAbility::FIELD_DESCRIPTION)?;
+        let applies_to = forms::get_required_form_text_field(&form, SpecialAbility::FIELD_APPLIES_TO)?;
+        let bonus_equivalent: Option<i16> = forms::get_optional_form_text_field(&form, SpecialAbility::FIELD_BONUS_EQUIVALENT)?;
+        let flat_cost: Option<i32> = forms::get_optional_form_text_field(&form, SpecialAbility::FIELD_FLAT_COST)?;
+        let cost = match (bonus_equivalent, flat_cost) {
+            (Some(bonus), None) if bonus > 0 && bonus <= OwnedItem::MAX_ENHANCEMENT => AbilityCost::BonusEquivalent(bonus),
+            (None, Some(cost)) if cost >= 0 => AbilityCost::Flat(cost),
+            (_, Some(_)) => return Err(forms::field_is_invalid_error(SpecialAbility::FIELD_FLAT_COST)),
+            _ => return Err(forms::field_is_invalid_error(SpecialAbility::FIELD_BONUS_EQUIVALENT)),
+        };
+        let effects: String = forms::get_required_form_text_field(&form, SpecialAbility::FIELD_EFFECTS)?;
+        let effects = serde_json::from_str::<Vec<Uuid>>(&effects)
+            .map_err(|_| forms::field_is_invalid_error(SpecialAbility::FIELD_EFFECTS))?
+            .into_iter()
+            .map(|id| forms::value_by_id(id, conn))
+            .collect::<Result<_, _>>()?;
+
+        let ability = SpecialAbility {
+            id,
+            links: Links::new(),
+            name,
+            description,
+            applies_to,
+            cost,
+            effects,
+        };
+
+        Ok(ability)
+    }
+}
+
+impl TryFromDb for SpecialAbility {
+    type DBType = DBSpecialAbility;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let effects = other.get_effects(conn)?;
+        // The database ensures that exactly one of the costs is set.
+        let cost = match other.bonus_equivalent {
+            Some(bonus) => AbilityCost::BonusEquivalent(bonus),
+            None => AbilityCost::Flat(other.flat_cost.unwrap_or(0)),
+        };
+        let ability = SpecialAbility {
+            id: other.id,
+            links: Links::new(),
+            name: other.name,
+            description: other.description,
+            applies_to: other.applies_to,
+            cost,
+            effects,
+        };
+        Ok(ability)
+    }
+}
+
+impl IntoDb for SpecialAbility {
+    type DBType = (DBSpecialAbility, BTreeSet<DBSpecialAbilityEffect>);
+
+    fn into_db(self) -> Self::DBType {
+        let effects = self.effects.iter()
+            .map(|effect| DBSpecialAbilityEffect {
+                ability_id: self.id,
+                effect_id: effect.id().to_owned(),
+            })
+            .collect();
+
+        let (bonus_equivalent, flat_cost) = match self.cost {
+            AbilityCost::BonusEquivalent(bonus) => (Some(bonus), None),
+            AbilityCost::Flat(cost) => (None, Some(cost)),
+        };
+        let ability = DBSpecialAbility {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            applies_to: self.applies_to,
+            bonus_equivalent,
+            flat_cost,
+        };
+
+        (ability, effects)
+    }
+}
+
+impl Insert for SpecialAbility {
+    fn db_insert(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction::<_, DBError, _>(|| {
+            let (ability, effects) = self.to_owned().into_db();
+            ability.db_insert(conn)?;
+            for effect in effects.into_iter() {
+                effect.db_insert(conn)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Update for SpecialAbility {
+    fn db_update(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction::<_, DBError, _>(|| {
+            let (ability, effects) = self.to_owned().into_db();
+            ability.db_update(conn)?;
+
+            let old_effects = DBSpecialAbilityEffect::belonging_to(&ability)
+                .load::<DBSpecialAbilityEffect>(conn)
+                .map_err(DBError::RunQuery)?
+                .into_iter()
+                .collect::<BTreeSet<_>>();
+            for effect in old_effects.difference(&effects) {
+                effect.db_delete(conn)?;
+            }
+            for effect in effects.difference(&old_effects) {
+                effect.db_insert(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetById, GetAll, Delete, DeleteById, Insert, Update)]
+#[changeset_options(treat_none_as_null = "true")]
+#[tavern(is_insertable, is_identifiable, is_queryable)]
+#[table_name = "specialabilities"]
+pub struct DBSpecialAbility {
+    id: Uuid,
+    name: String,
+    description: String,
+    applies_to: AbilityTarget,
+    bonus_equivalent: Option<i16>,
+    flat_cost: Option<i32>,
+}
+
+impl DBSpecialAbility {
+    fn get_effects(&self, conn: &Connection) -> Result<BTreeSet<Summary<Effect>>, DBError> {
+        DBSpecialAbilityEffect::belonging_to(self)
+            .load::<DBSpecialAbilityEffect>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|effect| Summary::<Effect>::db_get_by_id(&effect.effect_id, conn))
+            .collect()
+    }
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Delete, Insert)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "specialabilityeffects"]
+#[primary_key(ability_id, effect_id)]
+#[belongs_to(DBSpecialAbility, foreign_key = "ability_id")]
+pub struct DBSpecialAbilityEffect {
+    ability_id: Uuid,
+    effect_id: Uuid,
 }
 
 #[derive(
@@ -443,6 +1067,34 @@ pub enum ArmorClass {
     Heavy,
 }
 
+impl ArmorClass {
+    /// The category `by` steps heavier (or lighter, if negative) than this
+    /// one, stopping at light and heavy.
+    pub fn shifted(self, by: i16) -> Self {
+        let categories = [ArmorClass::Light, ArmorClass::Medium, ArmorClass::Heavy];
+        let index = categories.iter().position(|category| *category == self).unwrap_or(0) as i16;
+        categories[index.saturating_add(by).clamp(0, 2) as usize]
+    }
+}
+
+/// A weapon's weight and price once its material has been taken into account.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WeaponStats {
+    pub weight: f64,
+    pub cost: i32,
+}
+
+/// Armor's stats and price once its material has been taken into account.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ArmorStats {
+    pub weight: f64,
+    pub cost: i32,
+    pub armor_type: ArmorClass,
+    pub max_dex_bonus: i32,
+    pub spell_failure: i32,
+    pub check_penalty: i32,
+}
+
 #[derive(Serialize, Deserialize, Clone, StandaloneDbMarker)]
 pub struct Weapon {
     #[serde(flatten)]
@@ -452,6 +1104,8 @@ pub struct Weapon {
     damage: Vec<String>,
     damage_type: Vec<DamageType>,
     weapon_type: WeaponClass,
+    /// The weapon's weight and price made of `material`.
+    pub effective: WeaponStats,
 }
 
 impl Weapon {
@@ -462,6 +1116,36 @@ impl Weapon {
     const FIELD_DAMAGE: &'static str = "damage";
     const FIELD_DAMAGE_TYPE: &'static str = "damage-type";
     const FIELD_WEAPON_TYPE: &'static str = "weapon-type";
+
+    pub fn weapon_type(&self) -> WeaponClass {
+        self.weapon_type
+    }
+
+    fn stats(item: &Item, material: Option<&Material>) -> WeaponStats {
+        let base = WeaponStats {
+            weight: item.weight,
+            cost: item.cost,
+        };
+        material.map(|material| material.modify_weapon(&base)).unwrap_or(base)
+    }
+
+    /// The same weapon made of a different material.
+    pub fn with_material(self, material: Material) -> Self {
+        let effective = Weapon::stats(&self.item, Some(&material));
+        Weapon {
+            material: Some(material),
+            effective,
+            ..self
+        }
+    }
+
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    pub fn material(&self) -> Option<&Material> {
+        self.material.as_ref()
+    }
 }
 
 impl TryFromForm for Weapon {
@@ -491,6 +1175,7 @@ impl TryFromForm for Weapon {
                 Rejection::from(Status::with_data(&StatusCode::BAD_REQUEST, err))
             })?;
         let weapon_type: WeaponClass = forms::get_required_form_text_field(&form, Weapon::FIELD_WEAPON_TYPE)?;
+        let effective = Weapon::stats(&item, material.as_ref());
 
         let weapon = Weapon {
             item,
@@ -498,7 +1183,8 @@ impl TryFromForm for Weapon {
             crit_range,
             damage,
             damage_type,
-            weapon_type
+            weapon_type,
+            effective,
         };
 
         Ok(weapon)
@@ -526,6 +1212,7 @@ impl TryFromDb for Weapon {
             };
             Range { start, end }
         };
+        let effective = Weapon::stats(&item, material.as_ref());
         let weapon = Weapon {
             item,
             material,
@@ -533,6 +1220,7 @@ impl TryFromDb for Weapon {
             damage: other.damage,
             damage_type: other.damage_type,
             weapon_type: other.weapon_type,
+            effective,
         };
         Ok(weapon)
     }
@@ -624,7 +1312,7 @@ impl Summarize<Weapon> for Weapon {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
+#[derive(Serialize, Deserialize, Clone, StandaloneDbMarker)]
 pub struct Armor {
     #[serde(flatten)]
     item: Item,
@@ -634,6 +1322,8 @@ pub struct Armor {
     spell_failure: i32,
     check_penalty: i32,
     armor_type: ArmorClass,
+    /// The armor's stats and price made of `material`.
+    pub effective: ArmorStats,
 }
 
 impl Armor {
@@ -644,6 +1334,36 @@ impl Armor {
     const FIELD_SPELL_FAILURE: &'static str = "spell-failure";
     const FIELD_CHECK_PENALTY: &'static str = "check-penalty";
     const FIELD_ARMOR_TYPE: &'static str = "armor-type";
+
+    fn stats(&self, material: Option<&Material>) -> ArmorStats {
+        let base = ArmorStats {
+            weight: self.item.weight,
+            cost: self.item.cost,
+            armor_type: self.armor_type,
+            max_dex_bonus: self.max_dex_bonus,
+            spell_failure: self.spell_failure,
+            check_penalty: self.check_penalty,
+        };
+        material.map(|material| material.modify_armor(&base)).unwrap_or(base)
+    }
+
+    /// The same armor made of a different material.
+    pub fn with_material(self, material: Material) -> Self {
+        let effective = self.stats(Some(&material));
+        Armor {
+            material: Some(material),
+            effective,
+            ..self
+        }
+    }
+
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    pub fn material(&self) -> Option<&Material> {
+        self.material.as_ref()
+    }
 }
 
 impl TryFromForm for Armor {
@@ -660,7 +1380,7 @@ impl TryFromForm for Armor {
         let check_penalty = forms::get_required_form_text_field(&form, Armor::FIELD_CHECK_PENALTY)?;
         let armor_type = forms::get_required_form_text_field(&form, Armor::FIELD_ARMOR_TYPE)?;
 
-        let armor = Armor {
+        let mut armor = Armor {
             item,
             material,
             max_dex_bonus,
@@ -668,7 +1388,16 @@ impl TryFromForm for Armor {
             spell_failure,
             check_penalty,
             armor_type,
+            effective: ArmorStats {
+                weight: 0.0,
+                cost: 0,
+                armor_type,
+                max_dex_bonus,
+                spell_failure,
+                check_penalty,
+            },
         };
+        armor.effective = armor.stats(armor.material.as_ref());
 
         Ok(armor)
     }
@@ -680,7 +1409,7 @@ impl TryFromDb for Armor {
     fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
         let item = Item::db_get_by_id(&other.id, conn)?;
         let material = other.material_id.map(|id| Material::db_get_by_id(&id, conn)).transpose()?;
-        let armor = Armor {
+        let mut armor = Armor {
             item,
             material,
             max_dex_bonus: other.max_dex_bonus,
@@ -688,7 +1417,16 @@ impl TryFromDb for Armor {
             spell_failure: other.spell_failure,
             check_penalty: other.check_penalty,
             armor_type: other.armor_type,
+            effective: ArmorStats {
+                weight: 0.0,
+                cost: 0,
+                armor_type: other.armor_type,
+                max_dex_bonus: other.max_dex_bonus,
+                spell_failure: other.spell_failure,
+                check_penalty: other.check_penalty,
+            },
         };
+        armor.effective = armor.stats(armor.material.as_ref());
         Ok(armor)
     }
 }
@@ -723,6 +1461,26 @@ pub struct DBArmor {
     armor_type: ArmorClass,
 }
 
+impl Ord for Armor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item.cmp(&other.item)
+    }
+}
+
+impl PartialOrd for Armor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Armor {
+    fn eq(&self, other: &Self) -> bool {
+        self.item == other.item
+    }
+}
+
+impl Eq for Armor{}
+
 impl Summarize<Armor> for Armor {
     fn id(&self) -> &Uuid {
         &self.item.id
@@ -741,7 +1499,156 @@ impl Summarize<Armor> for Armor {
     }
 }
 
-#[derive(Serialize, Deserialize, Summarize, Clone, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
+#[derive(
+    Serialize, Deserialize, Display, PartialEq, PartialOrd, Eq, Ord, Copy, Clone, DbEnum, Debug, FromStr
+)]
+pub enum ConsumableKind {
+    Potion,
+    Scroll,
+    Wand,
+}
+
+impl ConsumableKind {
+    /// Potions and scrolls are used up after a single use, while wands keep
+    /// going until their charges run out.
+    pub fn is_single_use(&self) -> bool {
+        match self {
+            ConsumableKind::Potion | ConsumableKind::Scroll => true,
+            ConsumableKind::Wand => false,
+        }
+    }
+}
+
+/// An item that casts a spell when used. `charges` is how many charges a new
+/// item of this kind has; the charges left on a particular wand are kept on
+/// its `OwnedItem`.
+#[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
+pub struct Consumable {
+    #[serde(flatten)]
+    item: Item,
+    pub kind: ConsumableKind,
+    pub spell: Summary<Spell>,
+    pub caster_level: i16,
+    pub charges: i16,
+}
+
+impl Consumable {
+    const FIELD_ITEM_ID: &'static str = "item-id";
+    const FIELD_KIND: &'static str = "kind";
+    const FIELD_SPELL_ID: &'static str = "spell-id";
+    const FIELD_CASTER_LEVEL: &'static str = "caster-level";
+    const FIELD_CHARGES: &'static str = "charges";
+
+    /// The most charges a wand can hold.
+    pub const MAX_WAND_CHARGES: i16 = 50;
+
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    /// The charges a new consumable of the given kind has. Potions and
+    /// scrolls always have one, while wands have as many as requested, or a
+    /// full wand's worth. Returns None if that isn't between 1 and
+    /// `MAX_WAND_CHARGES`.
+    pub fn starting_charges(kind: ConsumableKind, requested: Option<i16>) -> Option<i16> {
+        let charges = if kind.is_single_use() {
+            1
+        } else {
+            requested.unwrap_or(Consumable::MAX_WAND_CHARGES)
+        };
+        Some(charges).filter(|charges| *charges >= 1 && *charges <= Consumable::MAX_WAND_CHARGES)
+    }
+}
+
+impl TryFromForm for Consumable {
+    fn try_from_form(conn: &Connection, form: Form, _this_id: Option<Uuid>, _parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
+        let item_id = forms::get_required_form_text_field(&form, Consumable::FIELD_ITEM_ID)?;
+        let item = forms::value_by_id(item_id, conn)?;
+        let kind: ConsumableKind = forms::get_required_form_text_field(&form, Consumable::FIELD_KIND)?;
+        let spell_id = forms::get_required_form_text_field(&form, Consumable::FIELD_SPELL_ID)?;
+        let spell = forms::value_by_id(spell_id, conn)?;
+        let caster_level: i16 = forms::get_required_form_text_field(&form, Consumable::FIELD_CASTER_LEVEL)?;
+        if caster_level < 1 {
+            return Err(forms::field_is_invalid_error(Consumable::FIELD_CASTER_LEVEL));
+        }
+        let requested = forms::get_optional_form_text_field(&form, Consumable::FIELD_CHARGES)?;
+        let charges = Consumable::starting_charges(kind, requested)
+            .ok_or_else(|| forms::field_is_invalid_error(Consumable::FIELD_CHARGES))?;
+
+        let consumable = Consumable {
+            item,
+            kind,
+            spell,
+            caster_level,
+            charges,
+        };
+
+        Ok(consumable)
+    }
+}
+
+impl TryFromDb for Consumable {
+    type DBType = DBConsumable;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let item = Item::db_get_by_id(&other.id, conn)?;
+        let spell = Summary::<Spell>::db_get_by_id(&other.spell_id, conn)?;
+        let consumable = Consumable {
+            item,
+            kind: other.kind,
+            spell,
+            caster_level: other.caster_level,
+            charges: other.charges,
+        };
+        Ok(consumable)
+    }
+}
+
+impl IntoDb for Consumable {
+    type DBType = DBConsumable;
+
+    fn into_db(self) -> Self::DBType {
+        DBConsumable {
+            id: self.item.id,
+            kind: self.kind,
+            spell_id: self.spell.id().to_owned(),
+            caster_level: self.caster_level,
+            charges: self.charges,
+        }
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[table_name = "consumables"]
+#[belongs_to(DBItem, foreign_key = "id")]
+pub struct DBConsumable {
+    id: Uuid,
+    kind: ConsumableKind,
+    spell_id: Uuid,
+    caster_level: i16,
+    charges: i16,
+}
+
+impl Summarize<Consumable> for Consumable {
+    fn id(&self) -> &Uuid {
+        &self.item.id
+    }
+
+    fn name(&self) -> &str {
+        &self.item.name
+    }
+
+    fn description(&self) -> &str {
+        &self.item.description
+    }
+
+    fn links(&self) -> Option<&Links> {
+        Some(&self.item.links)
+    }
+}
+
+#[derive(Serialize, Deserialize, Summarize, Clone, StandaloneDbMarker)]
 pub struct Material {
     id: Uuid,
     links: Links,
@@ -749,6 +1656,46 @@ pub struct Material {
     description: String,
     hp_per_inch: Option<i32>,
     hardness: Option<i32>,
+    modifiers: MaterialModifiers,
+}
+
+/// How a special material changes the weapons and armor made from it. Costs
+/// are in copper and, since items of a special material are always
+/// masterwork, include the masterwork cost.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct MaterialModifiers {
+    pub weight_multiplier: f64,
+    pub cost_multiplier: f64,
+    /// Charged for every pound the item weighs before its weight is changed.
+    pub cost_per_pound: i32,
+    pub weapon_cost: i32,
+    /// Depends on the category of the armor before it is shifted.
+    pub light_armor_cost: i32,
+    pub medium_armor_cost: i32,
+    pub heavy_armor_cost: i32,
+    /// How many categories lighter (if negative) or heavier armor counts as.
+    pub armor_category_shift: i16,
+    pub max_dex_bonus: i16,
+    pub check_penalty_reduction: i16,
+    pub spell_failure_reduction: i16,
+}
+
+impl Default for MaterialModifiers {
+    fn default() -> Self {
+        MaterialModifiers {
+            weight_multiplier: 1.0,
+            cost_multiplier: 1.0,
+            cost_per_pound: 0,
+            weapon_cost: 0,
+            light_armor_cost: 0,
+            medium_armor_cost: 0,
+            heavy_armor_cost: 0,
+            armor_category_shift: 0,
+            max_dex_bonus: 0,
+            check_penalty_reduction: 0,
+            spell_failure_reduction: 0,
+        }
+    }
 }
 
 impl Material {
@@ -756,6 +1703,49 @@ impl Material {
     const FIELD_DESCRIPTION: &'static str = "description";
     const FIELD_HP_PER_INCH: &'static str = "hp-per-inch";
     const FIELD_HARDNESS: &'static str = "hardness";
+    const FIELD_WEIGHT_MULTIPLIER: &'static str = "weight-multiplier";
+    const FIELD_COST_MULTIPLIER: &'static str = "cost-multiplier";
+    const FIELD_COST_PER_POUND: &'static str = "cost-per-pound";
+    const FIELD_WEAPON_COST: &'static str = "weapon-cost";
+    const FIELD_LIGHT_ARMOR_COST: &'static str = "light-armor-cost";
+    const FIELD_MEDIUM_ARMOR_COST: &'static str = "medium-armor-cost";
+    const FIELD_HEAVY_ARMOR_COST: &'static str = "heavy-armor-cost";
+    const FIELD_ARMOR_CATEGORY_SHIFT: &'static str = "armor-category-shift";
+    const FIELD_MAX_DEX_BONUS: &'static str = "max-dex-bonus";
+    const FIELD_CHECK_PENALTY_REDUCTION: &'static str = "check-penalty-reduction";
+    const FIELD_SPELL_FAILURE_REDUCTION: &'static str = "spell-failure-reduction";
+
+    fn modified_cost(&self, stats_cost: i32, stats_weight: f64) -> i32 {
+        let modifiers = &self.modifiers;
+        (f64::from(stats_cost) * modifiers.cost_multiplier).round() as i32
+            + (f64::from(modifiers.cost_per_pound) * stats_weight).round() as i32
+    }
+
+    /// The weight and price of a weapon made of this material.
+    pub fn modify_weapon(&self, base: &WeaponStats) -> WeaponStats {
+        WeaponStats {
+            weight: base.weight * self.modifiers.weight_multiplier,
+            cost: self.modified_cost(base.cost, base.weight) + self.modifiers.weapon_cost,
+        }
+    }
+
+    /// The stats and price of armor made of this material.
+    pub fn modify_armor(&self, base: &ArmorStats) -> ArmorStats {
+        let modifiers = &self.modifiers;
+        let category_cost = match base.armor_type {
+            ArmorClass::Light => modifiers.light_armor_cost,
+            ArmorClass::Medium => modifiers.medium_armor_cost,
+            ArmorClass::Heavy => modifiers.heavy_armor_cost,
+        };
+        ArmorStats {
+            weight: base.weight * modifiers.weight_multiplier,
+            cost: self.modified_cost(base.cost, base.weight) + category_cost,
+            armor_type: base.armor_type.shifted(modifiers.armor_category_shift),
+            max_dex_bonus: (base.max_dex_bonus + i32::from(modifiers.max_dex_bonus)).max(0),
+            spell_failure: (base.spell_failure - i32::from(modifiers.spell_failure_reduction)).max(0),
+            check_penalty: (base.check_penalty - i32::from(modifiers.check_penalty_reduction)).max(0),
+        }
+    }
 }
 
 impl TryFromForm for Material {
@@ -766,13 +1756,49 @@ impl TryFromForm for Material {
         let hp_per_inch = forms::get_optional_form_text_field(&form, Material::FIELD_HP_PER_INCH)?;
         let hardness = forms::get_optional_form_text_field(&form, Material::FIELD_HARDNESS)?;
 
+        let defaults = MaterialModifiers::default();
+        let modifiers = MaterialModifiers {
+            weight_multiplier: forms::get_optional_form_text_field(&form, Material::FIELD_WEIGHT_MULTIPLIER)?
+                .unwrap_or(defaults.weight_multiplier),
+            cost_multiplier: forms::get_optional_form_text_field(&form, Material::FIELD_COST_MULTIPLIER)?
+                .unwrap_or(defaults.cost_multiplier),
+            cost_per_pound: forms::get_optional_form_text_field(&form, Material::FIELD_COST_PER_POUND)?
+                .unwrap_or(defaults.cost_per_pound),
+            weapon_cost: forms::get_optional_form_text_field(&form, Material::FIELD_WEAPON_COST)?
+                .unwrap_or(defaults.weapon_cost),
+            light_armor_cost: forms::get_optional_form_text_field(&form, Material::FIELD_LIGHT_ARMOR_COST)?
+                .unwrap_or(defaults.light_armor_cost),
+            medium_armor_cost: forms::get_optional_form_text_field(&form, Material::FIELD_MEDIUM_ARMOR_COST)?
+                .unwrap_or(defaults.medium_armor_cost),
+            heavy_armor_cost: forms::get_optional_form_text_field(&form, Material::FIELD_HEAVY_ARMOR_COST)?
+                .unwrap_or(defaults.heavy_armor_cost),
+            armor_category_shift: forms::get_optional_form_text_field(&form, Material::FIELD_ARMOR_CATEGORY_SHIFT)?
+                .unwrap_or(defaults.armor_category_shift),
+            max_dex_bonus: forms::get_optional_form_text_field(&form, Material::FIELD_MAX_DEX_BONUS)?
+                .unwrap_or(defaults.max_dex_bonus),
+            check_penalty_reduction: forms::get_optional_form_text_field(&form, Material::FIELD_CHECK_PENALTY_REDUCTION)?
+                .unwrap_or(defaults.check_penalty_reduction),
+            spell_failure_reduction: forms::get_optional_form_text_field(&form, Material::FIELD_SPELL_FAILURE_REDUCTION)?
+                .unwrap_or(defaults.spell_failure_reduction),
+        };
+        if modifiers.weight_multiplier <= 0.0 {
+            return Err(forms::field_is_invalid_error(Material::FIELD_WEIGHT_MULTIPLIER));
+        }
+        if modifiers.cost_multiplier <= 0.0 {
+            return Err(forms::field_is_invalid_error(Material::FIELD_COST_MULTIPLIER));
+        }
+        if modifiers.armor_category_shift.abs() > 2 {
+            return Err(forms::field_is_invalid_error(Material::FIELD_ARMOR_CATEGORY_SHIFT));
+        }
+
         let material = Material {
             id,
             links: Links::new(),
             name,
             description,
             hp_per_inch,
-            hardness
+            hardness,
+            modifiers,
         };
 
         Ok(material)
@@ -785,6 +1811,19 @@ impl TryFromDb for Material {
     fn try_from_db(other: Self::DBType, _conn: &Connection) -> Result<Self, DBError> where Self: Sized {
         let mut links = Links::new();
         links.insert("self".to_string(), format!("/materials/{}", other.id));
+        let modifiers = MaterialModifiers {
+            weight_multiplier: other.weight_multiplier,
+            cost_multiplier: other.cost_multiplier,
+            cost_per_pound: other.cost_per_pound,
+            weapon_cost: other.weapon_cost,
+            light_armor_cost: other.light_armor_cost,
+            medium_armor_cost: other.medium_armor_cost,
+            heavy_armor_cost: other.heavy_armor_cost,
+            armor_category_shift: other.armor_category_shift,
+            max_dex_bonus: other.max_dex_bonus,
+            check_penalty_reduction: other.check_penalty_reduction,
+            spell_failure_reduction: other.spell_failure_reduction,
+        };
         let material = Material {
             id: other.id,
             links,
@@ -792,6 +1831,7 @@ impl TryFromDb for Material {
             description: other.description,
             hp_per_inch: other.hp_per_inch,
             hardness: other.hardness,
+            modifiers,
         };
         Ok(material)
     }
@@ -801,17 +1841,49 @@ impl IntoDb for Material {
     type DBType = DBMaterial;
 
     fn into_db(self) -> Self::DBType {
+        let modifiers = self.modifiers;
         DBMaterial {
             id: self.id,
             name: self.name,
             description: self.description,
             hp_per_inch: self.hp_per_inch,
             hardness: self.hardness,
+            weight_multiplier: modifiers.weight_multiplier,
+            cost_multiplier: modifiers.cost_multiplier,
+            cost_per_pound: modifiers.cost_per_pound,
+            weapon_cost: modifiers.weapon_cost,
+            light_armor_cost: modifiers.light_armor_cost,
+            medium_armor_cost: modifiers.medium_armor_cost,
+            heavy_armor_cost: modifiers.heavy_armor_cost,
+            armor_category_shift: modifiers.armor_category_shift,
+            max_dex_bonus: modifiers.max_dex_bonus,
+            check_penalty_reduction: modifiers.check_penalty_reduction,
+            spell_failure_reduction: modifiers.spell_failure_reduction,
         }
     }
 }
 
-#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+impl Ord for Material {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl PartialOrd for Material {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Material{}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone)]
 #[derive(GetById, GetAll, Delete, DeleteById, Insert, Update)]
 #[tavern(is_insertable, is_identifiable, is_queryable)]
 #[table_name = "materials"]
@@ -821,4 +1893,15 @@ pub struct DBMaterial {
     description: String,
     hp_per_inch: Option<i32>,
     hardness: Option<i32>,
+    weight_multiplier: f64,
+    cost_multiplier: f64,
+    cost_per_pound: i32,
+    weapon_cost: i32,
+    light_armor_cost: i32,
+    medium_armor_cost: i32,
+    heavy_armor_cost: i32,
+    armor_category_shift: i16,
+    max_dex_bonus: i16,
+    check_penalty_reduction: i16,
+    spell_failure_reduction: i16,
 }
@@ -0,0 +1,358 @@
+use super::character::{DBCharacter, Race};
+use super::sheet::CharacterSheet;
+use super::skills;
+use super::summary::{Summarize, Summary};
+use super::{Attribute, CharacterStat, Skill};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, Insert, IntoDb, StandaloneDbMarker, TryFromDb};
+use crate::db::{Delete, DeleteById, GetAll, GetById, Update};
+use crate::forms::{self, TryFromForm};
+use crate::schema::{characterlanguages, languages};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intelligence_picks_come_from_the_bonus_list() {
+        let (elven, gnoll) = (Uuid::new_v4(), Uuid::new_v4());
+        let bonus_list = vec![elven, gnoll].into_iter().collect::<BTreeSet<Uuid>>();
+        let budget = LanguageBudget::new(2, 0, 0);
+
+        assert!(budget.check(&[elven, gnoll], &bonus_list).is_ok());
+        assert!(budget.check(&[elven, Uuid::new_v4()], &bonus_list).is_err());
+        assert!(LanguageBudget::new(1, 0, 0).check(&[elven, gnoll], &bonus_list).is_err());
+    }
+
+    #[test]
+    fn linguistics_picks_can_be_any_language() {
+        let elven = Uuid::new_v4();
+        let bonus_list = vec![elven].into_iter().collect::<BTreeSet<Uuid>>();
+        let budget = LanguageBudget::new(1, 1, 0);
+
+        assert!(budget.check(&[elven, Uuid::new_v4()], &bonus_list).is_ok());
+        assert!(budget.check(&[Uuid::new_v4(), Uuid::new_v4()], &bonus_list).is_err());
+        // Unused Linguistics picks can go to the bonus list too.
+        assert!(LanguageBudget::new(0, 2, 0).check(&[elven, Uuid::new_v4()], &bonus_list).is_ok());
+    }
+
+    #[test]
+    fn a_low_intelligence_gives_no_bonus_languages() {
+        let budget = LanguageBudget::new(-2, 1, 0);
+        assert_eq!(budget.from_intelligence, 0);
+        assert_eq!(budget.total(), 1);
+    }
+}
+
+const FIELD_LANGUAGES: &str = "languages";
+
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker)]
+pub struct Language {
+    id: Uuid,
+    name: String,
+    description: String,
+}
+
+impl Language {
+    const FIELD_NAME: &'static str = "name";
+    const FIELD_DESCRIPTION: &'static str = "description";
+}
+
+impl TryFromForm for Language {
+    fn try_from_form(conn: &Connection, form: Form, this_id: Option<Uuid>, _parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
+        let id = forms::valid_id_or_new::<Language>(this_id, conn)?;
+        let name: String = forms::get_required_form_text_field(&form, Language::FIELD_NAME)?;
+        if name.trim().is_empty() {
+            return Err(forms::field_is_invalid_error(Language::FIELD_NAME));
+        }
+        let description = forms::get_optional_form_text_field(&form, Language::FIELD_DESCRIPTION)?
+            .unwrap_or_default();
+
+        Ok(Language { id, name: name.trim().to_string(), description })
+    }
+}
+
+impl TryFromDb for Language {
+    type DBType = DBLanguage;
+
+    fn try_from_db(other: Self::DBType, _conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let language = Language {
+            id: other.id,
+            name: other.name,
+            description: other.description,
+        };
+        Ok(language)
+    }
+}
+
+impl IntoDb for Language {
+    type DBType = DBLanguage;
+
+    fn into_db(self) -> Self::DBType {
+        DBLanguage {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+        }
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "languages"]
+pub struct DBLanguage {
+    id: Uuid,
+    name: String,
+    description: String,
+}
+
+/// How many bonus languages a character can learn. Picks from Intelligence
+/// must come from their race's bonus languages, while those from Linguistics
+/// ranks and other effects can be any language.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LanguageBudget {
+    pub from_intelligence: i16,
+    pub from_linguistics: i16,
+    pub from_effects: i16,
+}
+
+impl LanguageBudget {
+    pub fn new(int_modifier: i16, linguistics_ranks: i16, effects: i16) -> Self {
+        LanguageBudget {
+            from_intelligence: int_modifier.max(0),
+            from_linguistics: linguistics_ranks.max(0),
+            from_effects: effects.max(0),
+        }
+    }
+
+    pub fn total(&self) -> i16 {
+        self.from_intelligence + self.from_linguistics + self.from_effects
+    }
+
+    /// Checks that the chosen bonus languages fit in the budget, given the
+    /// languages the character's race allows as Intelligence picks.
+    pub fn check(&self, chosen: &[Uuid], bonus_list: &BTreeSet<Uuid>) -> Result<(), Error> {
+        let off_list = chosen.iter()
+            .filter(|language| !bonus_list.contains(language))
+            .count() as i16;
+        let on_list = chosen.len() as i16 - off_list;
+        let unrestricted = self.from_linguistics + self.from_effects;
+
+        if off_list > unrestricted {
+            return Err(Error::new(format!(
+                "only {} languages outside the race's bonus languages can be learned",
+                unrestricted
+            )));
+        }
+        if on_list + off_list > self.total() {
+            return Err(Error::new(format!("only {} bonus languages can be learned", self.total())));
+        }
+        Ok(())
+    }
+}
+
+/// The languages a character knows and how many more they could learn.
+#[derive(Serialize, Clone, Debug)]
+pub struct KnownLanguages {
+    /// The languages every member of the character's race speaks.
+    pub automatic: Vec<Summary<Language>>,
+    /// The bonus languages the character chose.
+    pub bonus: Vec<Summary<Language>>,
+    /// The languages the character's race can pick with Intelligence.
+    pub bonus_list: Vec<Summary<Language>>,
+    pub budget: LanguageBudget,
+    pub remaining: i16,
+}
+
+impl From<KnownLanguages> for Bytes {
+    fn from(known: KnownLanguages) -> Self {
+        status::serialize_to_bytes(&known)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LanguageList {
+    pub languages: Vec<Summary<Language>>,
+}
+
+impl From<LanguageList> for Bytes {
+    fn from(list: LanguageList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+impl From<Language> for Bytes {
+    fn from(language: Language) -> Self {
+        status::serialize_to_bytes(&language)
+    }
+}
+
+/// Finds the catalog entries for a race's automatic languages, which are
+/// stored by name. Saving a race adds its languages to the catalog.
+fn automatic_languages(race: &Race, conn: &Connection) -> Result<Vec<Language>, DBError> {
+    languages::table.filter(languages::name.eq_any(&race.languages))
+        .order(languages::name)
+        .load::<DBLanguage>(conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|language| Language::try_from_db(language, conn))
+        .collect()
+}
+
+fn budget_of(character: DBCharacter, conn: &Connection) -> Result<LanguageBudget, DBError> {
+    let linguistics = skills::ranks_in(character.id(), Skill::Linguistics, conn)?;
+    let sheet = CharacterSheet::load_from_db(character, conn)?;
+    let effects = sheet.modifiers.character.get(&CharacterStat::Languages).copied().unwrap_or(0);
+    Ok(LanguageBudget::new(sheet.attribute_modifier(Attribute::Intelligence), linguistics, effects))
+}
+
+fn known_languages(character: DBCharacter, conn: &Connection) -> Result<KnownLanguages, DBError> {
+    let race = Race::db_get_by_id(character.race_id(), conn)?;
+    let bonus = characterlanguages::table.inner_join(languages::table)
+        .select(languages::all_columns)
+        .filter(characterlanguages::char_id.eq(character.id()))
+        .order(languages::name)
+        .load::<DBLanguage>(conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|language| Summary::<Language>::try_from_db(language, conn))
+        .collect::<Result<Vec<_>, DBError>>()?;
+    let automatic = automatic_languages(&race, conn)?
+        .iter()
+        .map(Summary::from)
+        .collect();
+    let budget = budget_of(character, conn)?;
+
+    Ok(KnownLanguages {
+        automatic,
+        remaining: budget.total() - bonus.len() as i16,
+        bonus,
+        bonus_list: race.bonus_languages.into_iter().collect(),
+        budget,
+    })
+}
+
+async fn get_known_languages(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<KnownLanguages>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let known = known_languages(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(known)))
+}
+
+/// Sets the bonus languages a character knows to those given in the form.
+async fn learn_languages(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<KnownLanguages>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let chosen: String = forms::get_required_form_text_field(&form, FIELD_LANGUAGES)?;
+    let chosen = serde_json::from_str::<BTreeSet<Uuid>>(&chosen)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_LANGUAGES))?
+        .into_iter()
+        .map(|id| forms::value_by_id::<Language>(id, &conn))
+        .collect::<Result<Vec<_>, Rejection>>()?;
+
+    let race = forms::value_by_id::<Race>(*character.race_id(), &conn)?;
+    if chosen.iter().any(|language| race.languages.contains(&language.name)) {
+        return Err(status::bad_request(format!("every {} already knows those languages", race.name)));
+    }
+    let bonus_list = race.bonus_languages.iter()
+        .map(|language| language.id().to_owned())
+        .collect::<BTreeSet<Uuid>>();
+    let chosen_ids = chosen.iter()
+        .map(|language| language.id)
+        .collect::<Vec<Uuid>>();
+    budget_of(character.clone(), &conn)?
+        .check(&chosen_ids, &bonus_list)
+        .map_err(|err| status::bad_request(err.message))?;
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(characterlanguages::table.filter(characterlanguages::char_id.eq(char_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        let rows = chosen_ids.iter()
+            .map(|language_id| (
+                characterlanguages::char_id.eq(char_id),
+                characterlanguages::language_id.eq(language_id),
+            ))
+            .collect::<Vec<_>>();
+        diesel::insert_into(characterlanguages::table)
+            .values(&rows)
+            .execute(&conn)
+            .map(|_| ())
+            .map_err(DBError::RunQuery)
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_LANGUAGES))?;
+
+    let known = known_languages(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(known)))
+}
+
+async fn list_languages(user: User, conn: Connection) -> Result<Status<Success<LanguageList>>, Rejection> {
+    user.id.ok_or_else(status::not_authorized)?;
+    let languages = languages::table.order(languages::name)
+        .load::<DBLanguage>(&conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|language| Summary::<Language>::try_from_db(language, &conn))
+        .collect::<Result<_, DBError>>()?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(LanguageList { languages })))
+}
+
+async fn create_language(user: User, conn: Connection, form: Form) -> Result<Status<Success<Language>>, Rejection> {
+    campaign::catalog_editor(&user, &conn)?;
+    let language = Language::try_from_form(&conn, form, None, None)?;
+    language.db_insert(&conn)
+        .map_err(|err| forms::db_error_to_rejection(err, Language::FIELD_NAME))?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(language)))
+}
+
+/// A warp Filter containing the language catalog endpoints, relative to the
+/// `/languages` path.
+pub fn languages_filter() -> BoxedFilter<(impl Reply,)> {
+    let list = warp::get()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(list_languages);
+    let create = warp::post()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(create_language);
+
+    list.or(create)
+        .boxed()
+}
+
+/// A warp Filter containing the endpoints for the languages a character
+/// knows, relative to the `/characters` path.
+pub fn known_languages_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("languages"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_known_languages);
+    let learn = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("languages"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(learn_languages);
+
+    get.or(learn)
+        .boxed()
+}
@@ -0,0 +1,225 @@
+use super::character::Character;
+use super::effects::Modifiers;
+use super::summary::Summarize;
+use super::{Attribute, Attributes, CombatStat, Size};
+use crate::db::{Connection, Error};
+use crate::schema::{charactersubclasses, classes, featmaneuverbonuses, subclasses};
+use diesel::prelude::*;
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute_modifiers(strength: i16, dexterity: i16) -> Attributes {
+        vec![(Attribute::Strength, strength), (Attribute::Dexterity, dexterity)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn base_attack_bonus_is_rounded_down_per_class() {
+        assert_eq!(base_attack_bonus(&[(1.0, 5)]), 5);
+        assert_eq!(base_attack_bonus(&[(0.75, 3)]), 2);
+        // Two levels each of two three-quarter classes don't add up to a
+        // full point more.
+        assert_eq!(base_attack_bonus(&[(0.75, 2), (0.75, 2), (0.5, 3)]), 3);
+    }
+
+    #[test]
+    fn maneuvers_use_strength_dexterity_and_size() {
+        let maneuvers = CombatManeuvers::new(4, Size::Large, &attribute_modifiers(3, 1), &Modifiers::default(), &ManeuverBonuses::new());
+        assert_eq!(maneuvers.size_modifier, 1);
+        assert_eq!(maneuvers.cmb_attribute, Attribute::Strength);
+        assert_eq!(maneuvers.cmb, 4 + 3 + 1);
+        assert_eq!(maneuvers.cmd, 10 + 4 + 3 + 1 + 1);
+    }
+
+    #[test]
+    fn tiny_creatures_use_dexterity_for_cmb() {
+        let maneuvers = CombatManeuvers::new(1, Size::Tiny, &attribute_modifiers(-2, 3), &Modifiers::default(), &ManeuverBonuses::new());
+        assert_eq!(maneuvers.cmb_attribute, Attribute::Dexterity);
+        assert_eq!(maneuvers.cmb, 1 + 3 - 2);
+        assert_eq!(maneuvers.cmd, 10 + 1 - 2 + 3 - 2);
+    }
+
+    #[test]
+    fn feat_bonuses_only_apply_to_their_maneuver() {
+        let mut modifiers = Modifiers::default();
+        modifiers.combat.insert(CombatStat::CMB, 1);
+        modifiers.combat.insert(CombatStat::CMD, -1);
+        let mut bonuses = ManeuverBonuses::new();
+        add_bonus(&mut bonuses, CombatManeuver::Trip, ManeuverBonus { offense: 2, defense: 2 });
+        add_bonus(&mut bonuses, CombatManeuver::Trip, ManeuverBonus { offense: 2, defense: 0 });
+
+        let maneuvers = CombatManeuvers::new(2, Size::Medium, &attribute_modifiers(1, 0), &modifiers, &bonuses);
+        assert_eq!((maneuvers.cmb, maneuvers.cmd), (4, 12));
+        let trip = maneuvers.maneuvers[&CombatManeuver::Trip];
+        assert_eq!((trip.offense, trip.defense, trip.cmb, trip.cmd), (4, 2, 8, 14));
+        let grapple = maneuvers.maneuvers[&CombatManeuver::Grapple];
+        assert_eq!((grapple.cmb, grapple.cmd), (4, 12));
+        assert_eq!(maneuvers.maneuvers.len(), MANEUVERS.len());
+    }
+}
+
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub enum CombatManeuver {
+    BullRush,
+    DirtyTrick,
+    Disarm,
+    Drag,
+    Grapple,
+    Overrun,
+    Reposition,
+    Steal,
+    Sunder,
+    Trip,
+}
+
+const MANEUVERS: [CombatManeuver; 10] = [
+    CombatManeuver::BullRush,
+    CombatManeuver::DirtyTrick,
+    CombatManeuver::Disarm,
+    CombatManeuver::Drag,
+    CombatManeuver::Grapple,
+    CombatManeuver::Overrun,
+    CombatManeuver::Reposition,
+    CombatManeuver::Steal,
+    CombatManeuver::Sunder,
+    CombatManeuver::Trip,
+];
+
+/// A bonus to the CMB of one maneuver (`offense`) and to the CMD against it
+/// (`defense`).
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, Ord, PartialOrd, PartialEq, Eq)]
+#[serde(default)]
+pub struct ManeuverBonus {
+    pub offense: i16,
+    pub defense: i16,
+}
+
+pub type ManeuverBonuses = BTreeMap<CombatManeuver, ManeuverBonus>;
+
+/// Bonuses to the same maneuver from different feats stack, e.g. Improved
+/// and Greater Trip.
+pub fn add_bonus(bonuses: &mut ManeuverBonuses, maneuver: CombatManeuver, bonus: ManeuverBonus) {
+    let total = bonuses.entry(maneuver).or_default();
+    total.offense += bonus.offense;
+    total.defense += bonus.defense;
+}
+
+/// The base attack bonus from the levels taken in each class, given as the
+/// class's BAB per level and the levels taken in it. Each class is rounded
+/// down separately.
+pub fn base_attack_bonus(class_levels: &[(f64, i16)]) -> i16 {
+    class_levels.iter()
+        .map(|(bab_per_level, levels)| (bab_per_level * f64::from(*levels)).floor() as i16)
+        .sum()
+}
+
+/// Loads a character's base attack bonus from the classes they've taken
+/// levels in.
+pub fn character_base_attack_bonus(char_id: &Uuid, conn: &Connection) -> Result<i16, Error> {
+    let taken = charactersubclasses::table.inner_join(subclasses::table.inner_join(classes::table))
+        .select((classes::id, classes::bab_per_level, charactersubclasses::levels_taken))
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .load::<(Uuid, f64, i16)>(conn)
+        .map_err(Error::RunQuery)?;
+    let mut levels: BTreeMap<Uuid, (f64, i16)> = BTreeMap::new();
+    for (class_id, bab_per_level, levels_taken) in taken.into_iter() {
+        levels.entry(class_id).or_insert((bab_per_level, 0)).1 += levels_taken;
+    }
+    Ok(base_attack_bonus(&levels.values().copied().collect::<Vec<_>>()))
+}
+
+/// Loads the maneuver bonuses of every feat the character has.
+pub fn feat_bonuses(character: &Character, conn: &Connection) -> Result<ManeuverBonuses, Error> {
+    let feat_ids = character.feats().iter()
+        .map(|feat| feat.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    let rows = featmaneuverbonuses::table
+        .select((featmaneuverbonuses::maneuver, featmaneuverbonuses::offense, featmaneuverbonuses::defense))
+        .filter(featmaneuverbonuses::feat_id.eq_any(feat_ids))
+        .load::<(CombatManeuver, i16, i16)>(conn)
+        .map_err(Error::RunQuery)?;
+    let mut bonuses = ManeuverBonuses::new();
+    for (maneuver, offense, defense) in rows.into_iter() {
+        add_bonus(&mut bonuses, maneuver, ManeuverBonus { offense, defense });
+    }
+    Ok(bonuses)
+}
+
+/// The CMB and CMD for a single maneuver, including feat bonuses to it.
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManeuverBreakdown {
+    pub offense: i16,
+    pub defense: i16,
+    pub cmb: i16,
+    pub cmd: i16,
+}
+
+/// A character's combat maneuver bonus and defense, along with what they're
+/// made up of.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CombatManeuvers {
+    pub base_attack_bonus: i16,
+    /// The special size modifier of the character's size.
+    pub size_modifier: i16,
+    /// The ability added to CMB: Strength, or Dexterity for Tiny and smaller
+    /// creatures.
+    pub cmb_attribute: Attribute,
+    pub strength_modifier: i16,
+    pub dexterity_modifier: i16,
+    /// Bonuses and penalties to every maneuver from effects.
+    pub cmb_modifier: i16,
+    pub cmd_modifier: i16,
+    pub cmb: i16,
+    pub cmd: i16,
+    pub maneuvers: BTreeMap<CombatManeuver, ManeuverBreakdown>,
+}
+
+impl CombatManeuvers {
+    /// CMB is BAB + Strength modifier + special size modifier, and CMD is
+    /// 10 + BAB + Strength modifier + Dexterity modifier + special size
+    /// modifier. The CMB and CMD stats of `modifiers` are added to both.
+    pub fn new(base_attack_bonus: i16, size: Size, attribute_modifiers: &Attributes, modifiers: &Modifiers, bonuses: &ManeuverBonuses) -> Self {
+        let size_modifier = size.special_modifier();
+        let cmb_attribute = if size <= Size::Tiny { Attribute::Dexterity } else { Attribute::Strength };
+        let modifier_of = |attr: Attribute| attribute_modifiers.get(&attr).copied().unwrap_or(0);
+        let strength_modifier = modifier_of(Attribute::Strength);
+        let dexterity_modifier = modifier_of(Attribute::Dexterity);
+        let cmb_modifier = modifiers.combat(CombatStat::CMB);
+        let cmd_modifier = modifiers.combat(CombatStat::CMD);
+
+        let cmb = base_attack_bonus + modifier_of(cmb_attribute) + size_modifier + cmb_modifier;
+        let cmd = 10 + base_attack_bonus + strength_modifier + dexterity_modifier + size_modifier + cmd_modifier;
+        let maneuvers = MANEUVERS.iter()
+            .map(|maneuver| {
+                let bonus = bonuses.get(maneuver).copied().unwrap_or_default();
+                (*maneuver, ManeuverBreakdown {
+                    offense: bonus.offense,
+                    defense: bonus.defense,
+                    cmb: cmb + bonus.offense,
+                    cmd: cmd + bonus.defense,
+                })
+            })
+            .collect();
+
+        CombatManeuvers {
+            base_attack_bonus,
+            size_modifier,
+            cmb_attribute,
+            strength_modifier,
+            dexterity_modifier,
+            cmb_modifier,
+            cmd_modifier,
+            cmb,
+            cmd,
+            maneuvers,
+        }
+    }
+}
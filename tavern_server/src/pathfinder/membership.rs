@@ -0,0 +1,152 @@
+use super::class::{Class, Feature};
+use super::summary::{Summarize, Summary};
+use super::Alignment;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, GetById};
+use crate::schema::{charactersubclasses, subclasses, subclassfeatures};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes_without_alignments_allow_any() {
+        let any = BTreeSet::new();
+        assert!(alignment_allowed(&any, Alignment::LawfulGood));
+        assert!(alignment_allowed(&any, Alignment::ChaoticEvil));
+    }
+
+    #[test]
+    fn classes_only_allow_their_alignments() {
+        let paladin = vec![Alignment::LawfulGood].into_iter().collect();
+        assert!(alignment_allowed(&paladin, Alignment::LawfulGood));
+        assert!(!alignment_allowed(&paladin, Alignment::LawfulNeutral));
+
+        let monk = vec![Alignment::LawfulGood, Alignment::LawfulNeutral, Alignment::LawfulEvil]
+            .into_iter()
+            .collect();
+        assert!(alignment_allowed(&monk, Alignment::LawfulEvil));
+        assert!(!alignment_allowed(&monk, Alignment::TrueNeutral));
+    }
+}
+
+/// Whether a class that allows `alignments` can be taken by a character with
+/// `alignment`. An empty list allows any alignment.
+pub fn alignment_allowed(alignments: &BTreeSet<Alignment>, alignment: Alignment) -> bool {
+    alignments.is_empty() || alignments.contains(&alignment)
+}
+
+/// A class a character has taken levels in.
+#[derive(Serialize, Clone, Debug)]
+pub struct ClassMembership {
+    pub class: Summary<Class>,
+    pub alignments: BTreeSet<Alignment>,
+    /// The levels taken across all of the class's subclasses.
+    pub levels: i16,
+    /// Whether the character's alignment has moved out of the class's
+    /// alignments. Ex-members lose the features of the class's subclasses
+    /// and can't take more levels in it until they move back.
+    pub ex_member: bool,
+}
+
+/// The classes a character with `alignment` has taken levels in.
+pub fn memberships(char_id: &Uuid, alignment: Alignment, conn: &Connection) -> Result<Vec<ClassMembership>, DBError> {
+    let taken = charactersubclasses::table.inner_join(subclasses::table)
+        .select((subclasses::class_id, charactersubclasses::levels_taken))
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .load::<(Uuid, i16)>(conn)
+        .map_err(DBError::RunQuery)?;
+    let mut levels = BTreeMap::new();
+    for (class_id, levels_taken) in taken.into_iter() {
+        *levels.entry(class_id).or_insert(0) += levels_taken;
+    }
+
+    levels.into_iter()
+        .map(|(class_id, levels)| {
+            let class = Class::db_get_by_id(&class_id, conn)?;
+            Ok(ClassMembership {
+                ex_member: !alignment_allowed(class.alignments(), alignment),
+                alignments: class.alignments().to_owned(),
+                class: Summary::from(&class),
+                levels,
+            })
+        })
+        .collect()
+}
+
+/// The IDs of the subclass features a character has lost by becoming an
+/// ex-member of their classes.
+pub fn disabled_features(char_id: &Uuid, alignment: Alignment, conn: &Connection) -> Result<BTreeSet<Uuid>, DBError> {
+    let ex_classes = memberships(char_id, alignment, conn)?
+        .into_iter()
+        .filter(|membership| membership.ex_member)
+        .map(|membership| membership.class.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    if ex_classes.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+
+    let subclass_ids = charactersubclasses::table.inner_join(subclasses::table)
+        .select(charactersubclasses::subclass_id)
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .filter(subclasses::class_id.eq_any(ex_classes))
+        .load::<Uuid>(conn)
+        .map_err(DBError::RunQuery)?;
+    subclassfeatures::table.select(subclassfeatures::feature_id)
+        .filter(subclassfeatures::subclass_id.eq_any(subclass_ids))
+        .load::<Uuid>(conn)
+        .map(|features| features.into_iter().collect())
+        .map_err(DBError::RunQuery)
+}
+
+/// A character's classes and the features they've lost as an ex-member of
+/// any of them.
+#[derive(Serialize, Clone, Debug)]
+pub struct Memberships {
+    pub alignment: Alignment,
+    pub classes: Vec<ClassMembership>,
+    pub disabled_features: Vec<Summary<Feature>>,
+}
+
+impl From<Memberships> for Bytes {
+    fn from(memberships: Memberships) -> Self {
+        status::serialize_to_bytes(&memberships)
+    }
+}
+
+async fn get_memberships(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<Memberships>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let disabled_features = disabled_features(&char_id, character.alignment(), &conn)?
+        .into_iter()
+        .map(|id| Summary::<Feature>::db_get_by_id(&id, &conn))
+        .collect::<Result<_, _>>()?;
+    let memberships = Memberships {
+        alignment: character.alignment(),
+        classes: memberships(&char_id, character.alignment(), &conn)?,
+        disabled_features,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(memberships)))
+}
+
+/// A warp Filter containing the class membership endpoints, relative to the
+/// `/characters` path.
+pub fn membership_filter() -> BoxedFilter<(impl Reply,)> {
+    warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("classes"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_memberships)
+        .boxed()
+}
@@ -1,11 +1,30 @@
+pub mod active_effect;
+pub mod afflictions;
+pub mod bestiary;
+pub mod casting;
 pub mod character;
 pub mod class;
+pub mod companions;
+pub mod crafting;
+pub mod divine;
 pub mod effects;
+pub mod experience;
 pub mod feat;
+pub mod health;
+pub mod inventory;
 pub mod item;
+pub mod languages;
+pub mod maneuvers;
+pub mod membership;
+pub mod racial;
 pub mod religion;
+pub mod sheet;
+pub mod skills;
 pub mod spell;
 pub mod summary;
+pub mod traits;
+pub mod treasure;
+pub mod wealth;
 
 use diesel_derive_enum::DbEnum;
 use serde::{Deserialize, Serialize};
@@ -34,6 +53,24 @@ pub enum Size {
     Colossal,
 }
 
+impl Size {
+    /// The special size modifier added to combat maneuver bonus and defense,
+    /// e.g. -1 for Small or +2 for Huge.
+    pub fn special_modifier(self) -> i16 {
+        match self {
+            Size::Fine => -8,
+            Size::Diminutive => -4,
+            Size::Tiny => -2,
+            Size::Small => -1,
+            Size::Medium => 0,
+            Size::Large => 1,
+            Size::Huge => 2,
+            Size::Gargantuan => 4,
+            Size::Colossal => 8,
+        }
+    }
+}
+
 #[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
 pub enum Alignment {
     LawfulGood,
@@ -47,6 +84,32 @@ pub enum Alignment {
     ChaoticEvil,
 }
 
+impl Alignment {
+    /// Where the alignment sits on the lawful-chaotic and good-evil axes,
+    /// with lawful and good as 1 and chaotic and evil as -1.
+    pub fn axes(self) -> (i16, i16) {
+        match self {
+            Alignment::LawfulGood => (1, 1),
+            Alignment::LawfulNeutral => (1, 0),
+            Alignment::LawfulEvil => (1, -1),
+            Alignment::NeutralGood => (0, 1),
+            Alignment::TrueNeutral => (0, 0),
+            Alignment::NeutralEvil => (0, -1),
+            Alignment::ChaoticGood => (-1, 1),
+            Alignment::ChaoticNeutral => (-1, 0),
+            Alignment::ChaoticEvil => (-1, -1),
+        }
+    }
+
+    /// The number of steps along the axes between two alignments, e.g. one
+    /// from lawful good to neutral good and two to true neutral.
+    pub fn steps_from(self, other: Alignment) -> i16 {
+        let (law, good) = self.axes();
+        let (other_law, other_good) = other.axes();
+        (law - other_law).abs() + (good - other_good).abs()
+    }
+}
+
 #[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, PartialOrd, Ord, Hash, PartialEq, Eq, Copy, Clone)]
 pub enum Attribute {
     Strength,
@@ -141,6 +204,9 @@ pub enum CombatStat {
     Fortitude,
     Reflex,
     Will,
+    CasterLevel,
+    SaveDC,
+    Concentration,
 }
 
 pub type CombatStats = BTreeMap<CombatStat, i16>;
@@ -164,7 +230,7 @@ pub enum EquipmentSlot {
     Wrist,
 }
 
-#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum DamageType {
     Bludgeoning,
     Slashing,
@@ -180,4 +246,13 @@ pub enum DamageType {
     Nonlethal,
 }
 
+impl DamageType {
+    /// Whether this is weapon damage, which damage reduction applies to.
+    pub fn is_physical(&self) -> bool {
+        matches!(self, DamageType::Bludgeoning | DamageType::Slashing | DamageType::Piercing)
+    }
+}
+
+pub type Resistances = BTreeMap<DamageType, i16>;
+
 pub type Links = BTreeMap<String, String>;
@@ -0,0 +1,424 @@
+use super::character::{Character, DBCharacter, DBCharacterRaceTrait, Race, RaceTrait};
+use super::class::Class;
+use super::summary::{Summarize, Summary};
+use super::Attribute;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, Insert, TryFromDb};
+use crate::forms;
+use crate::schema::{characterracetraits, characters, charactersubclasses, favoredclassbonuses, favoredclassoptions, subclasses};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use diesel_derive_enum::DbEnum;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinder::Links;
+
+    fn race_trait(name: &str, alternate: bool, replaces: &[&RaceTrait]) -> RaceTrait {
+        RaceTrait {
+            id: Uuid::new_v4(),
+            links: Links::new(),
+            name: name.to_string(),
+            description: String::new(),
+            alternate,
+            replaces: replaces.iter().map(|replaced| replaced.id).collect(),
+            effects: BTreeSet::new(),
+        }
+    }
+
+    fn names(traits: Vec<&RaceTrait>) -> Vec<&str> {
+        traits.into_iter()
+            .map(|race_trait| race_trait.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn alternate_traits_replace_standard_ones() {
+        let darkvision = race_trait("Darkvision", false, &[]);
+        let hardy = race_trait("Hardy", false, &[]);
+        let stability = race_trait("Stability", false, &[]);
+        let deep_warrior = race_trait("Deep Warrior", true, &[&hardy, &stability]);
+        let stonesinger = race_trait("Stonesinger", true, &[&darkvision]);
+        let traits = vec![darkvision, hardy, stability, deep_warrior.clone(), stonesinger];
+
+        assert_eq!(names(active_traits(&traits, &[]).unwrap()), vec!["Darkvision", "Hardy", "Stability"]);
+        assert_eq!(names(active_traits(&traits, &[deep_warrior.id]).unwrap()), vec!["Darkvision", "Deep Warrior"]);
+    }
+
+    #[test]
+    fn conflicting_alternate_traits_are_rejected() {
+        let hardy = race_trait("Hardy", false, &[]);
+        let first = race_trait("Ancient Enmity", true, &[&hardy]);
+        let second = race_trait("Deep Warrior", true, &[&hardy]);
+        let traits = vec![hardy.clone(), first.clone(), second.clone()];
+
+        assert!(active_traits(&traits, &[first.id, second.id]).is_err());
+        // Only alternate traits of the race can be chosen.
+        assert!(active_traits(&traits, &[hardy.id]).is_err());
+        assert!(active_traits(&traits, &[Uuid::new_v4()]).is_err());
+    }
+
+    #[test]
+    fn favored_class_bonuses_are_tallied() {
+        let option = Uuid::new_v4();
+        let levels = vec![
+            FavoredClassLevel { level: 1, bonus: FavoredClassBonus::HitPoint, option_id: None },
+            FavoredClassLevel { level: 2, bonus: FavoredClassBonus::SkillRank, option_id: None },
+            FavoredClassLevel { level: 3, bonus: FavoredClassBonus::HitPoint, option_id: None },
+            FavoredClassLevel { level: 4, bonus: FavoredClassBonus::ClassOption, option_id: Some(option) },
+        ];
+        let totals = FavoredClassTotals::tally(&levels);
+        assert_eq!(totals.hit_points, 2);
+        assert_eq!(totals.skill_ranks, 1);
+        assert_eq!(totals.class_options, vec![option]);
+    }
+}
+
+const FIELD_TRAITS: &str = "traits";
+const FIELD_ATTRIBUTE: &str = "attribute";
+const FIELD_CLASS_ID: &str = "class-id";
+const FIELD_LEVEL: &str = "level";
+const FIELD_BONUS: &str = "bonus";
+const FIELD_OPTION_ID: &str = "option-id";
+
+/// What a character gains for a level taken in their favored class.
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum FavoredClassBonus {
+    HitPoint,
+    SkillRank,
+    /// One of the race's alternate bonuses for the class.
+    ClassOption,
+}
+
+/// A race's alternative to a hit point or skill rank for levels in one class,
+/// such as a human sorcerer's extra spell known.
+#[derive(Serialize, Queryable, Clone, Debug)]
+pub struct FavoredClassOption {
+    pub id: Uuid,
+    pub race_id: Uuid,
+    pub class_id: Uuid,
+    pub description: String,
+}
+
+/// The favored class bonus a character took for one level.
+#[derive(Serialize, Queryable, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FavoredClassLevel {
+    pub level: i16,
+    pub bonus: FavoredClassBonus,
+    pub option_id: Option<Uuid>,
+}
+
+/// The favored class bonuses a character has taken, added up.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FavoredClassTotals {
+    pub hit_points: i16,
+    pub skill_ranks: i16,
+    pub class_options: Vec<Uuid>,
+}
+
+impl FavoredClassTotals {
+    pub fn tally(levels: &[FavoredClassLevel]) -> Self {
+        let mut totals = FavoredClassTotals::default();
+        for level in levels {
+            match level.bonus {
+                FavoredClassBonus::HitPoint => totals.hit_points += 1,
+                FavoredClassBonus::SkillRank => totals.skill_ranks += 1,
+                FavoredClassBonus::ClassOption => totals.class_options.extend(level.option_id),
+            }
+        }
+        totals
+    }
+}
+
+/// Works out which of a race's traits a character has when they take the
+/// `chosen` alternate traits. Fails if a chosen trait isn't one of the
+/// race's alternate traits, or if two chosen traits replace the same one.
+pub fn active_traits<'a>(traits: &'a [RaceTrait], chosen: &[Uuid]) -> Result<Vec<&'a RaceTrait>, Error> {
+    let mut replaced = BTreeSet::new();
+    for id in chosen {
+        let alternate = traits.iter()
+            .find(|race_trait| race_trait.id == *id && race_trait.alternate)
+            .ok_or_else(|| Error::new(format!("{} is not an alternate trait of the race", id)))?;
+        for standard in alternate.replaces.iter() {
+            if !replaced.insert(*standard) {
+                return Err(Error::new(format!("{} replaces a trait that has already been replaced", alternate.name)));
+            }
+        }
+    }
+
+    let active = traits.iter()
+        .filter(|race_trait| if race_trait.alternate {
+            chosen.contains(&race_trait.id)
+        } else {
+            !replaced.contains(&race_trait.id)
+        })
+        .collect();
+    Ok(active)
+}
+
+/// A character's race along with the choices they have made about it.
+#[derive(Serialize, Clone, Debug)]
+pub struct RacialTraits {
+    pub race: Summary<Race>,
+    pub flexible_bonus: i16,
+    pub racial_bonus: Option<Attribute>,
+    /// The traits the character has, after swapping in alternate traits.
+    pub traits: Vec<RaceTrait>,
+    /// The alternate traits the character could still take.
+    pub alternate_traits: Vec<RaceTrait>,
+    pub favored_class: Option<Summary<Class>>,
+    /// The number of levels taken in the favored class, each of which earns
+    /// a favored class bonus.
+    pub favored_class_levels: i16,
+    pub favored_class_bonuses: Vec<FavoredClassLevel>,
+    pub favored_class_totals: FavoredClassTotals,
+    pub favored_class_options: Vec<FavoredClassOption>,
+}
+
+impl From<RacialTraits> for Bytes {
+    fn from(traits: RacialTraits) -> Self {
+        status::serialize_to_bytes(&traits)
+    }
+}
+
+/// The number of levels a character has taken in a class.
+fn levels_in_class(char_id: &Uuid, class_id: &Uuid, conn: &Connection) -> Result<i16, DBError> {
+    let levels = charactersubclasses::table.inner_join(subclasses::table)
+        .select(charactersubclasses::levels_taken)
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .filter(subclasses::class_id.eq(class_id))
+        .load::<i16>(conn)
+        .map_err(DBError::RunQuery)?;
+    Ok(levels.into_iter().sum())
+}
+
+fn favored_class_bonuses(char_id: &Uuid, conn: &Connection) -> Result<Vec<FavoredClassLevel>, DBError> {
+    favoredclassbonuses::table
+        .select((favoredclassbonuses::level, favoredclassbonuses::bonus, favoredclassbonuses::option_id))
+        .filter(favoredclassbonuses::char_id.eq(char_id))
+        .order(favoredclassbonuses::level)
+        .load::<FavoredClassLevel>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+fn favored_class_options(race_id: &Uuid, class_id: &Uuid, conn: &Connection) -> Result<Vec<FavoredClassOption>, DBError> {
+    favoredclassoptions::table
+        .filter(favoredclassoptions::race_id.eq(race_id))
+        .filter(favoredclassoptions::class_id.eq(class_id))
+        .load::<FavoredClassOption>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+fn racial_traits(character: DBCharacter, conn: &Connection) -> Result<RacialTraits, DBError> {
+    let char_id = *character.id();
+    let character = Character::try_from_db(character, conn)?;
+    let traits = character.active_race_traits()?
+        .into_iter()
+        .cloned()
+        .collect::<Vec<RaceTrait>>();
+    let alternate_traits = character.race().traits.iter()
+        .filter(|race_trait| race_trait.alternate && !traits.contains(race_trait))
+        .cloned()
+        .collect();
+
+    let (favored_class_levels, favored_class_options) = match character.favored_class() {
+        Some(class) => (
+            levels_in_class(&char_id, class.id(), conn)?,
+            favored_class_options(&character.race().id, class.id(), conn)?,
+        ),
+        None => (0, Vec::new()),
+    };
+    let favored_class_bonuses = favored_class_bonuses(&char_id, conn)?;
+
+    Ok(RacialTraits {
+        race: Summary::from(character.race()),
+        flexible_bonus: character.race().flexible_bonus,
+        racial_bonus: character.racial_bonus(),
+        traits,
+        alternate_traits,
+        favored_class: character.favored_class().cloned(),
+        favored_class_levels,
+        favored_class_totals: FavoredClassTotals::tally(&favored_class_bonuses),
+        favored_class_bonuses,
+        favored_class_options,
+    })
+}
+
+async fn get_racial_traits(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<RacialTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let traits = racial_traits(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+async fn choose_race_traits(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<RacialTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let chosen: String = forms::get_required_form_text_field(&form, FIELD_TRAITS)?;
+    let chosen = serde_json::from_str::<BTreeSet<Uuid>>(&chosen)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_TRAITS))?
+        .into_iter()
+        .collect::<Vec<Uuid>>();
+
+    let race = forms::value_by_id::<Race>(*character.race_id(), &conn)?;
+    active_traits(&race.traits, &chosen)
+        .map_err(|err| status::bad_request(err.message))?;
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(characterracetraits::table.filter(characterracetraits::char_id.eq(char_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        for trait_id in chosen.into_iter() {
+            DBCharacterRaceTrait { char_id, trait_id }.db_insert(&conn)?;
+        }
+        Ok(())
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_TRAITS))?;
+
+    let traits = racial_traits(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+async fn choose_racial_bonus(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<RacialTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let attribute: Attribute = forms::get_required_form_text_field(&form, FIELD_ATTRIBUTE)?;
+    let race = forms::value_by_id::<Race>(*character.race_id(), &conn)?;
+    if race.flexible_bonus == 0 {
+        return Err(status::bad_request(format!("{} has fixed ability modifiers", race.name)));
+    }
+
+    let character = diesel::update(characters::table.filter(characters::id.eq(char_id)))
+        .set(characters::racial_bonus.eq(Some(attribute)))
+        .get_result::<DBCharacter>(&conn)
+        .map_err(DBError::RunQuery)?;
+    let traits = racial_traits(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+async fn choose_favored_class(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<RacialTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let class_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CLASS_ID)?;
+    forms::value_by_id::<Summary<Class>>(class_id, &conn)?;
+    if levels_in_class(&char_id, &class_id, &conn)? == 0 {
+        return Err(forms::field_is_invalid_error(FIELD_CLASS_ID));
+    }
+    if character.favored_class_id() == Some(&class_id) {
+        let traits = racial_traits(character, &conn)?;
+        return Ok(Status::with_data(&StatusCode::OK, Success::new(traits)));
+    }
+
+    // Bonuses taken for the old favored class don't carry over.
+    let character = conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(favoredclassbonuses::table.filter(favoredclassbonuses::char_id.eq(char_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        diesel::update(characters::table.filter(characters::id.eq(char_id)))
+            .set(characters::favored_class_id.eq(Some(class_id)))
+            .get_result::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)
+    })?;
+    let traits = racial_traits(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+async fn take_favored_class_bonus(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<RacialTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let level: i16 = forms::get_required_form_text_field(&form, FIELD_LEVEL)?;
+    let bonus: FavoredClassBonus = forms::get_required_form_text_field(&form, FIELD_BONUS)?;
+    let option_id: Option<Uuid> = forms::get_optional_form_text_field(&form, FIELD_OPTION_ID)?;
+
+    let class_id = character.favored_class_id().copied()
+        .ok_or_else(|| status::bad_request(format!("{} has no favored class", character.name())))?;
+    if level < 1 || level > levels_in_class(&char_id, &class_id, &conn)? {
+        return Err(forms::field_is_invalid_error(FIELD_LEVEL));
+    }
+    match (bonus, option_id) {
+        (FavoredClassBonus::ClassOption, Some(option_id)) => {
+            let options = favored_class_options(character.race_id(), &class_id, &conn)?;
+            if !options.iter().any(|option| option.id == option_id) {
+                return Err(forms::field_is_invalid_error(FIELD_OPTION_ID));
+            }
+        }
+        (FavoredClassBonus::ClassOption, None) => return Err(forms::missing_field_error(FIELD_OPTION_ID)),
+        (_, Some(_)) => return Err(forms::field_is_invalid_error(FIELD_OPTION_ID)),
+        (_, None) => {}
+    }
+
+    diesel::insert_into(favoredclassbonuses::table)
+        .values((
+            favoredclassbonuses::char_id.eq(char_id),
+            favoredclassbonuses::level.eq(level),
+            favoredclassbonuses::bonus.eq(bonus),
+            favoredclassbonuses::option_id.eq(option_id),
+        ))
+        .on_conflict((favoredclassbonuses::char_id, favoredclassbonuses::level))
+        .do_update()
+        .set((
+            favoredclassbonuses::bonus.eq(bonus),
+            favoredclassbonuses::option_id.eq(option_id),
+        ))
+        .execute(&conn)
+        .map_err(DBError::RunQuery)?;
+
+    let traits = racial_traits(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+/// A warp Filter containing the racial trait and favored class endpoints,
+/// relative to the `/characters` path.
+pub fn racial_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("race"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_racial_traits);
+    let traits = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("race-traits"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_race_traits);
+    let bonus = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("racial-bonus"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_racial_bonus);
+    let favored_class = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("favored-class"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_favored_class);
+    let favored_class_bonus = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("favored-class-bonus"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(take_favored_class_bonus);
+
+    get.or(traits)
+        .or(bonus)
+        .or(favored_class)
+        .or(favored_class_bonus)
+        .boxed()
+}
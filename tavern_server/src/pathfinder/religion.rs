@@ -1,7 +1,7 @@
 use super::item::Weapon;
 use super::spell::Spell;
 use super::summary::Summarize;
-use super::Links;
+use super::{Alignment, Links};
 use crate::schema::{deities, deitydomains, deityweapons, domains, domainspells, subdomains};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -21,6 +21,7 @@ pub struct Deity {
     id: Uuid,
     name: String,
     description: String,
+    alignment: Alignment,
     favored_animals: Option<String>,
     domains: BTreeSet<Summary<Domain>>,
     weapons: BTreeSet<Summary<Weapon>>,
@@ -29,9 +30,24 @@ pub struct Deity {
 impl Deity {
     const FIELD_NAME: &'static str = "name";
     const FIELD_DESCRIPTION: &'static str = "description";
+    const FIELD_ALIGNMENT: &'static str = "alignment";
     const FIELD_ANIMALS: &'static str = "favored-animals";
     const FIELD_DOMAINS: &'static str = "domains";
     const FIELD_WEAPONS: &'static str = "weapons";
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    /// The domains the deity's clerics can choose from.
+    pub fn domains(&self) -> &BTreeSet<Summary<Domain>> {
+        &self.domains
+    }
+
+    /// The deity's favored weapons.
+    pub fn weapons(&self) -> &BTreeSet<Summary<Weapon>> {
+        &self.weapons
+    }
 }
 
 impl TryFromForm for Deity {
@@ -39,6 +55,7 @@ impl TryFromForm for Deity {
         let id = forms::valid_id_or_new::<Deity>(this_id, conn)?;
         let name = forms::get_required_form_text_field(&form, Deity::FIELD_NAME)?;
         let description = forms::get_required_form_text_field(&form, Deity::FIELD_DESCRIPTION)?;
+        let alignment = forms::get_required_form_text_field(&form, Deity::FIELD_ALIGNMENT)?;
         let favored_animals = forms::get_optional_form_text_field(&form, Deity::FIELD_ANIMALS)?;
         let domains: String = forms::get_required_form_text_field(&form, Deity::FIELD_DOMAINS)?;
         let domains: BTreeSet<Summary<Domain>> = serde_json::from_str::<Vec<Uuid>>(&domains)
@@ -64,9 +81,10 @@ impl TryFromForm for Deity {
             id,
             name,
             description,
+            alignment,
             favored_animals,
-            domains: Default::default(),
-            weapons: Default::default()
+            domains,
+            weapons,
         };
         Ok(deity)
     }
@@ -81,6 +99,7 @@ pub struct DBDeity {
     name: String,
     description: String,
     favored_animals: Option<String>,
+    alignment: Alignment,
 }
 
 impl TryFromDb for Deity {
@@ -96,6 +115,7 @@ impl TryFromDb for Deity {
             links,
             name: db_deity.name,
             description: db_deity.description,
+            alignment: db_deity.alignment,
             favored_animals: db_deity.favored_animals,
             domains,
             weapons,
@@ -121,6 +141,7 @@ impl IntoDb for Deity {
             name: self.name,
             description: self.description,
             favored_animals: self.favored_animals,
+            alignment: self.alignment,
         };
 
         (deity, domains, weapons)
@@ -205,6 +226,15 @@ impl Domain {
     const FIELD_POWER_DESC: &'static str = "power-description";
     const FIELD_SUBDOMAINS: &'static str = "subdomains";
     const FIELD_SPELLS: &'static str = "spells";
+
+    pub fn subdomains(&self) -> &BTreeSet<Subdomain> {
+        &self.subdomains
+    }
+
+    /// The domain's spells and how many times each can be cast.
+    pub fn spells(&self) -> &BTreeSet<(Summary<Spell>, i16)> {
+        &self.spells
+    }
 }
 
 impl TryFromForm for Domain {
@@ -440,7 +470,7 @@ impl IntoDbWithId for (Summary<Spell>, i16) {
     }
 }
 
-#[derive(Serialize, Deserialize, Summarize, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker, Clone)]
+#[derive(Serialize, Deserialize, Summarize, Ord, PartialOrd, PartialEq, Eq, StandaloneDbMarker, Clone, Debug)]
 pub struct Subdomain {
     id: Uuid,
     links: Links,
@@ -0,0 +1,199 @@
+use super::active_effect::{self, ActiveEffect};
+use super::character::{Character, DBCharacter};
+use super::effects::{Effect, Modifiers};
+use super::health::{HealthState, HitPoints};
+use super::maneuvers::{self, CombatManeuvers, ManeuverBonuses};
+use super::membership;
+use super::summary::{Summarize, Summary};
+use super::{Attribute, Attributes};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error, GetById, TryFromDb};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+/// Returns the modifier for an ability score, e.g. +2 for 14 or -1 for 8.
+pub fn attribute_modifier(score: i16) -> i16 {
+    (score - 10).div_euclid(2)
+}
+
+/// A character along with every value derived from it and the effects that
+/// apply to it.
+#[derive(Serialize, Clone, Debug)]
+pub struct CharacterSheet {
+    pub character: Character,
+    pub effects: Vec<Summary<Effect>>,
+    /// The timed effects currently applied to the character. These are also
+    /// included in `effects` and `modifiers`.
+    pub active_effects: Vec<ActiveEffect>,
+    /// The modifiers of every effect, along with the penalty negative levels
+    /// impose on combat stats.
+    pub modifiers: Modifiers,
+    /// Ability scores after all effects and ability drain have been applied.
+    pub attributes: Attributes,
+    /// Ability modifiers after the penalty for ability damage.
+    pub attribute_modifiers: Attributes,
+    /// The penalty negative levels impose on every d20 roll, including skill
+    /// and ability checks.
+    pub d20_penalty: i16,
+    /// Combat maneuver bonus and defense, in total and for each maneuver.
+    pub maneuvers: CombatManeuvers,
+    pub hit_points: HitPoints,
+    pub health: HealthState,
+}
+
+impl CharacterSheet {
+    /// Builds the sheet for a character. `effects` must contain every effect
+    /// that applies to the character, including those in `active_effects`,
+    /// and `maneuver_bonuses` the maneuver bonuses of all of its feats.
+    pub fn new(character: Character, effects: Vec<Effect>, active_effects: Vec<ActiveEffect>, base_attack_bonus: i16, maneuver_bonuses: &ManeuverBonuses) -> Self {
+        let afflictions = character.afflictions();
+        let mut modifiers: Modifiers = effects.iter().collect();
+        afflictions.apply(&mut modifiers);
+        let attributes: Attributes = character.base_attributes()
+            .into_iter()
+            .map(|(attr, score)| (attr, afflictions.drained_score(attr, (score + modifiers.attribute(attr)).max(0))))
+            .collect();
+        let attribute_modifiers: Attributes = attributes.iter()
+            .map(|(attr, score)| (*attr, afflictions.damaged_modifier(*attr, attribute_modifier(*score))))
+            .collect();
+        let maneuvers = CombatManeuvers::new(base_attack_bonus, character.size(), &attribute_modifiers, &modifiers, maneuver_bonuses);
+        let d20_penalty = afflictions.d20_penalty();
+        let hit_points = HitPoints {
+            max: character.max_hp().saturating_sub(afflictions.hp_penalty()),
+            damage: character.damage(),
+            nonlethal: character.nonlethal(),
+            temporary: character.temp_hp(),
+            constitution: attributes[&Attribute::Constitution],
+        };
+        let health = hit_points.state();
+        let effects = effects.iter()
+            .map(Summary::<Effect>::from)
+            .collect();
+
+        CharacterSheet {
+            character,
+            effects,
+            active_effects,
+            modifiers,
+            attributes,
+            attribute_modifiers,
+            d20_penalty,
+            maneuvers,
+            hit_points,
+            health,
+        }
+    }
+
+    pub fn load(character: Character, conn: &Connection) -> Result<Self, Error> {
+        let mut effects = character_effects(&character, conn)?;
+        let active_effects = active_effect::active_effects(character.id(), conn)?;
+        for active in active_effects.iter() {
+            effects.push(Effect::db_get_by_id(active.effect_id(), conn)?);
+        }
+        let base_attack_bonus = maneuvers::character_base_attack_bonus(character.id(), conn)?;
+        let maneuver_bonuses = maneuvers::feat_bonuses(&character, conn)?;
+        Ok(CharacterSheet::new(character, effects, active_effects, base_attack_bonus, &maneuver_bonuses))
+    }
+
+    pub fn load_from_db(character: DBCharacter, conn: &Connection) -> Result<Self, Error> {
+        let character = Character::try_from_db(character, conn)?;
+        CharacterSheet::load(character, conn)
+    }
+
+    pub fn attribute_modifier(&self, attr: Attribute) -> i16 {
+        self.attribute_modifiers.get(&attr).copied().unwrap_or(0)
+    }
+}
+
+impl From<CharacterSheet> for Bytes {
+    fn from(sheet: CharacterSheet) -> Self {
+        status::serialize_to_bytes(&sheet)
+    }
+}
+
+/// Loads every permanent effect that applies to the character: those granted
+/// by its race and racial traits, character traits, feats, class features
+/// and the special abilities of its equipment. Features lost as an ex-member
+/// of a class are left out.
+pub fn character_effects(character: &Character, conn: &Connection) -> Result<Vec<Effect>, Error> {
+    let race_traits = character.active_race_traits()?;
+    let racial_ids = character.race().effects.iter()
+        .chain(race_traits.into_iter().flat_map(|race_trait| race_trait.effects.iter()))
+        .map(|effect| effect.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    let trait_ids = character.traits().iter()
+        .map(|character_trait| character_trait.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    let feat_ids = character.feats().iter()
+        .map(|feat| feat.id().to_owned())
+        .collect::<Vec<Uuid>>();
+    let disabled_features = membership::disabled_features(character.id(), character.alignment(), conn)?;
+    let feature_ids = character.features().iter()
+        .map(|feature| feature.id().to_owned())
+        .filter(|id| !disabled_features.contains(id))
+        .collect::<Vec<Uuid>>();
+    let equipment_ids = character.equipment().values()
+        .map(|item| item.id().to_owned())
+        .collect::<Vec<Uuid>>();
+
+    let mut effect_ids = {
+        use crate::schema::feateffects::dsl::*;
+        feateffects.select(effect_id)
+            .filter(feat_id.eq_any(feat_ids))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+    };
+    effect_ids.extend(racial_ids);
+    effect_ids.extend({
+        use crate::schema::traiteffects::dsl::*;
+        traiteffects.select(effect_id)
+            .filter(trait_id.eq_any(trait_ids))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+    });
+    effect_ids.extend({
+        use crate::schema::featureeffects::dsl::*;
+        featureeffects.select(effect_id)
+            .filter(feature_id.eq_any(feature_ids))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+    });
+    effect_ids.extend({
+        use crate::schema::{owneditemabilities, specialabilityeffects};
+        owneditemabilities::table
+            .inner_join(specialabilityeffects::table.on(specialabilityeffects::ability_id.eq(owneditemabilities::ability_id)))
+            .select(specialabilityeffects::effect_id)
+            .filter(owneditemabilities::owned_item_id.eq_any(equipment_ids))
+            .load::<Uuid>(conn)
+            .map_err(Error::RunQuery)?
+    });
+
+    effect_ids.into_iter()
+        .map(|id| Effect::db_get_by_id(&id, conn))
+        .collect()
+}
+
+async fn get_sheet(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<CharacterSheet>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let sheet = CharacterSheet::load_from_db(character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(sheet)))
+}
+
+/// A warp Filter serving computed character sheets, relative to `/characters`.
+pub fn sheet_filter() -> BoxedFilter<(impl Reply,)> {
+    warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("sheet"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_sheet)
+        .boxed()
+}
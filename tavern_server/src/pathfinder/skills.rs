@@ -0,0 +1,180 @@
+use super::character::DBCharacter;
+use super::{Skill, Skills};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError};
+use crate::forms;
+use crate::schema::{characterskillranks, charactersubclasses};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_are_limited_by_level_and_skill_points() {
+        let mut ranks = Skills::new();
+        ranks.insert(Skill::Linguistics, 3);
+        ranks.insert(Skill::Perception, 2);
+
+        assert!(check_ranks(&ranks, 3, 5).is_ok());
+        // No skill can have more ranks than the character has levels.
+        assert!(check_ranks(&ranks, 2, 5).is_err());
+        // The ranks can't add up to more than the skill points taken.
+        assert!(check_ranks(&ranks, 3, 4).is_err());
+
+        ranks.insert(Skill::Climb, 0);
+        assert!(check_ranks(&ranks, 3, 5).is_err());
+    }
+}
+
+const FIELD_RANKS: &str = "ranks";
+
+/// Checks that a character with the given level and skill points could have
+/// put ranks into their skills this way.
+pub fn check_ranks(ranks: &Skills, level: i16, skill_points: i16) -> Result<(), Error> {
+    for (skill, ranks) in ranks.iter() {
+        if *ranks <= 0 {
+            return Err(Error::new(format!("{} must have at least one rank", skill)));
+        }
+        if *ranks > level {
+            return Err(Error::new(format!("{} can't have more ranks than the character's level", skill)));
+        }
+    }
+    let spent: i16 = ranks.values().sum();
+    if spent > skill_points {
+        return Err(Error::new(format!("{} ranks were put into skills but only {} skill points were taken", spent, skill_points)));
+    }
+    Ok(())
+}
+
+/// The ranks a character has put into each of their skills. Skills without
+/// any ranks are left out.
+pub fn skill_ranks(char_id: &Uuid, conn: &Connection) -> Result<Skills, DBError> {
+    characterskillranks::table
+        .select((characterskillranks::skill, characterskillranks::ranks))
+        .filter(characterskillranks::char_id.eq(char_id))
+        .load::<(Skill, i16)>(conn)
+        .map(|ranks| ranks.into_iter().collect())
+        .map_err(DBError::RunQuery)
+}
+
+/// The number of ranks a character has in one skill.
+pub fn ranks_in(char_id: &Uuid, skill: Skill, conn: &Connection) -> Result<i16, DBError> {
+    Ok(skill_ranks(char_id, conn)?.get(&skill).copied().unwrap_or(0))
+}
+
+/// The skill points a character has taken across all of their classes.
+fn skill_points(char_id: &Uuid, conn: &Connection) -> Result<i16, DBError> {
+    let points = charactersubclasses::table.select(charactersubclasses::skills_taken)
+        .filter(charactersubclasses::char_id.eq(char_id))
+        .load::<i16>(conn)
+        .map_err(DBError::RunQuery)?;
+    Ok(points.into_iter().sum())
+}
+
+/// A character's skill ranks and what they had to spend.
+#[derive(Serialize, Clone, Debug)]
+pub struct SkillRanks {
+    pub ranks: Skills,
+    pub level: i16,
+    pub skill_points: i16,
+    pub spent: i16,
+}
+
+impl SkillRanks {
+    fn load(character: &DBCharacter, conn: &Connection) -> Result<Self, DBError> {
+        let ranks = skill_ranks(character.id(), conn)?;
+        Ok(SkillRanks {
+            level: character.total_level(conn)?,
+            skill_points: skill_points(character.id(), conn)?,
+            spent: ranks.values().sum(),
+            ranks,
+        })
+    }
+}
+
+impl From<SkillRanks> for Bytes {
+    fn from(ranks: SkillRanks) -> Self {
+        status::serialize_to_bytes(&ranks)
+    }
+}
+
+async fn get_skill_ranks(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<SkillRanks>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let ranks = SkillRanks::load(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(ranks)))
+}
+
+async fn set_skill_ranks(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<SkillRanks>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let ranks: String = forms::get_required_form_text_field(&form, FIELD_RANKS)?;
+    let ranks = serde_json::from_str::<BTreeMap<String, i16>>(&ranks)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_RANKS))?
+        .into_iter()
+        .map(|(skill, ranks)| {
+            let skill = skill.as_str().parse::<Skill>()
+                .map_err(|_| forms::field_is_invalid_error(FIELD_RANKS))?;
+            Ok((skill, ranks))
+        })
+        .collect::<Result<Skills, Rejection>>()?;
+
+    let level = character.total_level(&conn)?;
+    let points = skill_points(&char_id, &conn)?;
+    check_ranks(&ranks, level, points)
+        .map_err(|err| Rejection::from(Status::with_data(&StatusCode::BAD_REQUEST, err)))?;
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(characterskillranks::table.filter(characterskillranks::char_id.eq(char_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        let rows = ranks.iter()
+            .map(|(skill, ranks)| (
+                characterskillranks::char_id.eq(char_id),
+                characterskillranks::skill.eq(*skill),
+                characterskillranks::ranks.eq(*ranks),
+            ))
+            .collect::<Vec<_>>();
+        diesel::insert_into(characterskillranks::table)
+            .values(&rows)
+            .execute(&conn)
+            .map(|_| ())
+            .map_err(DBError::RunQuery)
+    })?;
+
+    let ranks = SkillRanks::load(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(ranks)))
+}
+
+/// A warp Filter containing the skill rank endpoints, relative to the
+/// `/characters` path.
+pub fn skills_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("skill-ranks"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_skill_ranks);
+    let set = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("skill-ranks"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(set_skill_ranks);
+
+    get.or(set)
+        .boxed()
+}
@@ -23,6 +23,77 @@ use nebula_status::{Status, StatusCode};
 use crate::pathfinder::Skill::Spellcraft;
 use crate::{forms, status};
 use std::convert::TryFrom;
+use std::fmt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_parse_back_from_their_names() {
+        let time_units = [TimeUnit::Free, TimeUnit::Standard, TimeUnit::FullRound, TimeUnit::Minute, TimeUnit::Day];
+        for unit in time_units.iter() {
+            assert_eq!(unit.to_string().parse::<TimeUnit>().ok(), Some(*unit));
+        }
+        let duration_units = [DurationUnit::Instantaneous, DurationUnit::Round, DurationUnit::Permanent];
+        for unit in duration_units.iter() {
+            assert_eq!(unit.to_string().parse::<DurationUnit>().ok(), Some(*unit));
+        }
+        assert_eq!("full round".parse::<TimeUnit>().ok(), Some(TimeUnit::FullRound));
+        assert_eq!(AreaShape::Cone.to_string().parse::<AreaShape>().ok(), Some(AreaShape::Cone));
+        assert_eq!("creature or object".parse::<TargetKind>().ok(), Some(TargetKind::CreatureOrObject));
+    }
+
+    #[test]
+    fn unknown_units_are_rejected() {
+        assert!("fortnight".parse::<TimeUnit>().is_err());
+        assert!("full-round".parse::<TimeUnit>().is_err());
+        assert!("".parse::<DurationUnit>().is_err());
+        assert!("sphere".parse::<AreaShape>().is_err());
+    }
+
+    #[test]
+    fn units_convert_to_rounds() {
+        let standard = CastingTime { amount: 1, unit: TimeUnit::Standard };
+        assert_eq!(standard.in_rounds(), 0);
+        assert_eq!(CastingTime { amount: 10, unit: TimeUnit::Minute }.in_rounds(), 100);
+
+        let duration = SpellDuration {
+            unit: DurationUnit::Minute,
+            base: 1,
+            per_level: 1,
+            concentration: false,
+            dismissible: true,
+        };
+        assert_eq!(duration.in_rounds(3), Some(40));
+        let instantaneous = SpellDuration { unit: DurationUnit::Instantaneous, base: 0, per_level: 0, ..duration };
+        assert_eq!(instantaneous.in_rounds(3), Some(0));
+        let permanent = SpellDuration { unit: DurationUnit::Permanent, ..duration };
+        assert_eq!(permanent.in_rounds(3), None);
+    }
+
+    #[test]
+    fn units_render_as_in_a_stat_block() {
+        assert_eq!(CastingTime { amount: 1, unit: TimeUnit::Standard }.to_string(), "1 standard action");
+        assert_eq!(CastingTime { amount: 10, unit: TimeUnit::Minute }.to_string(), "10 minutes");
+
+        let duration = SpellDuration {
+            unit: DurationUnit::Minute,
+            base: 0,
+            per_level: 10,
+            concentration: false,
+            dismissible: true,
+        };
+        assert_eq!(duration.to_string(), "10 min./level (D)");
+        let concentration = SpellDuration { unit: DurationUnit::Round, per_level: 1, concentration: true, dismissible: false, ..duration };
+        assert_eq!(concentration.to_string(), "concentration, up to 1 round/level");
+
+        assert_eq!(SpellArea { shape: AreaShape::Burst, size: 20 }.to_string(), "20-ft.-radius burst");
+        assert_eq!(SpellArea { shape: AreaShape::Cone, size: 15 }.to_string(), "15-ft. cone");
+        let targets = SpellTargets { kind: TargetKind::Creature, count: 0, per_level: 1 };
+        assert_eq!(targets.to_string(), "one creature/level");
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, Summarize, Ord, PartialOrd, PartialEq, Eq,)]
 pub struct Spell {
@@ -33,10 +104,11 @@ pub struct Spell {
     pub name: String,
     pub school: MagicSchool,
     pub effects: BTreeSet<Summary<Effect>>,
-    pub casting_time: i64,
+    pub casting_time: CastingTime,
     pub range: SpellRange,
-    pub area: String,
-    pub duration_per_level: i64,
+    pub area: Option<SpellArea>,
+    pub targets: Option<SpellTargets>,
+    pub duration: SpellDuration,
     pub saving_throw: Option<SaveThrow>,
     pub spell_resistance: bool,
     pub description: String,
@@ -47,12 +119,27 @@ impl Spell {
     const FIELD_SCHOOL: &'static str = "school";
     const FIELD_EFFECTS: &'static str = "spell-effects";
     const FIELD_CASTING_TIME: &'static str = "casting-time";
+    const FIELD_CASTING_TIME_UNIT: &'static str = "casting-time-unit";
     const FIELD_RANGE: &'static str = "range";
-    const FIELD_AREA: &'static str = "area-of-effect";
-    const FIELD_DURATION: &'static str = "duration-per-level";
+    const FIELD_AREA_SHAPE: &'static str = "area-shape";
+    const FIELD_AREA_SIZE: &'static str = "area-size";
+    const FIELD_TARGET_KIND: &'static str = "target-kind";
+    const FIELD_TARGET_COUNT: &'static str = "target-count";
+    const FIELD_TARGETS_PER_LEVEL: &'static str = "targets-per-level";
+    const FIELD_DURATION_UNIT: &'static str = "duration-unit";
+    const FIELD_DURATION: &'static str = "duration";
+    const FIELD_DURATION_PER_LEVEL: &'static str = "duration-per-level";
+    const FIELD_CONCENTRATION: &'static str = "concentration";
+    const FIELD_DISMISSIBLE: &'static str = "dismissible";
     const FIELD_SAVING_THROW: &'static str = "saving-throw";
     const FIELD_HAS_RESISTANCE: &'static str = "has-spell-resistance";
     const FIELD_DESCRIPTION: &'static str = "description";
+
+    /// The number of rounds the spell lasts when cast at the given caster
+    /// level, or `None` if it lasts until dispelled or dismissed.
+    pub fn duration_in_rounds(&self, caster_level: i16) -> Option<i64> {
+        self.duration.in_rounds(caster_level)
+    }
 }
 
 impl TryFromForm for Spell {
@@ -113,10 +200,39 @@ impl TryFromForm for Spell {
                     .map_err(|err| forms::db_error_to_rejection(err, Spell::FIELD_EFFECTS))
             })
             .collect::<Result<BTreeSet<Summary<Effect>>, _>>()?;
-        let casting_time: i64 = forms::get_required_form_text_field(&form, Spell::FIELD_CASTING_TIME)?;
+        let casting_time = CastingTime {
+            amount: forms::get_optional_form_text_field(&form, Spell::FIELD_CASTING_TIME)?.unwrap_or(1),
+            unit: forms::get_required_form_text_field(&form, Spell::FIELD_CASTING_TIME_UNIT)?,
+        };
+        if casting_time.amount <= 0 {
+            return Err(forms::field_is_invalid_error(Spell::FIELD_CASTING_TIME));
+        }
         let range: SpellRange = forms::get_required_form_text_field(&form, Spell::FIELD_RANGE)?;
-        let area: String = forms::get_required_form_text_field(&form, Spell::FIELD_AREA)?;
-        let duration_per_level: i64 = forms::get_required_form_text_field(&form, Spell::FIELD_DURATION)?;
+        let area_shape: Option<AreaShape> = forms::get_optional_form_text_field(&form, Spell::FIELD_AREA_SHAPE)?;
+        let area = area_shape
+            .map(|shape| -> Result<SpellArea, Rejection> {
+                let size = forms::get_required_form_text_field(&form, Spell::FIELD_AREA_SIZE)?;
+                Ok(SpellArea { shape, size })
+            })
+            .transpose()?;
+        let target_kind: Option<TargetKind> = forms::get_optional_form_text_field(&form, Spell::FIELD_TARGET_KIND)?;
+        let targets = target_kind
+            .map(|kind| -> Result<SpellTargets, Rejection> {
+                let count = forms::get_optional_form_text_field(&form, Spell::FIELD_TARGET_COUNT)?.unwrap_or(1);
+                let per_level = forms::get_optional_form_text_field(&form, Spell::FIELD_TARGETS_PER_LEVEL)?.unwrap_or(0);
+                Ok(SpellTargets { kind, count, per_level })
+            })
+            .transpose()?;
+        let duration = SpellDuration {
+            unit: forms::get_required_form_text_field(&form, Spell::FIELD_DURATION_UNIT)?,
+            base: forms::get_optional_form_text_field(&form, Spell::FIELD_DURATION)?.unwrap_or(0),
+            per_level: forms::get_optional_form_text_field(&form, Spell::FIELD_DURATION_PER_LEVEL)?.unwrap_or(0),
+            concentration: forms::get_optional_form_text_field(&form, Spell::FIELD_CONCENTRATION)?.unwrap_or(false),
+            dismissible: forms::get_optional_form_text_field(&form, Spell::FIELD_DISMISSIBLE)?.unwrap_or(false),
+        };
+        if duration.base < 0 || duration.per_level < 0 {
+            return Err(forms::field_is_invalid_error(Spell::FIELD_DURATION));
+        }
         let saving_throw: Option<SaveThrow> = forms::get_optional_form_text_field(&form, Spell::FIELD_SAVING_THROW)?;
         let spell_resistance: bool = forms::get_required_form_text_field(&form, Spell::FIELD_HAS_RESISTANCE)?;
         let description: String = forms::get_required_form_text_field(&form, Spell::FIELD_DESCRIPTION)?;
@@ -132,7 +248,8 @@ impl TryFromForm for Spell {
             casting_time,
             range,
             area,
-            duration_per_level,
+            targets,
+            duration,
             saving_throw,
             spell_resistance,
             description,
@@ -148,6 +265,7 @@ impl TryFromDb for Spell {
         let effects = db_spell.get_effects(conn)?;
 
         let components = db_spell.get_components(conn)?;
+        let (target_count, targets_per_level) = (db_spell.target_count, db_spell.targets_per_level);
 
         let spell = Spell {
             links: Default::default(),
@@ -158,10 +276,27 @@ impl TryFromDb for Spell {
             //level: db_spell.level, TODO: remove from DBSpell and the migration script, reqs updating patch
             school: db_spell.school,
             effects,
-            casting_time: db_spell.casting_time,
+            casting_time: CastingTime {
+                amount: db_spell.casting_time,
+                unit: db_spell.casting_time_unit,
+            },
             range: db_spell.range,
-            area: db_spell.area,
-            duration_per_level: db_spell.duration_per_level,
+            area: match (db_spell.area_shape, db_spell.area_size) {
+                (Some(shape), Some(size)) => Some(SpellArea { shape, size }),
+                _ => None,
+            },
+            targets: db_spell.target_kind.map(|kind| SpellTargets {
+                kind,
+                count: target_count,
+                per_level: targets_per_level,
+            }),
+            duration: SpellDuration {
+                unit: db_spell.duration_unit,
+                base: db_spell.duration,
+                per_level: db_spell.duration_per_level,
+                concentration: db_spell.concentration,
+                dismissible: db_spell.dismissible,
+            },
             saving_throw: db_spell.saving_throw,
             spell_resistance: db_spell.spell_resistance,
             description: db_spell.description,
@@ -270,10 +405,19 @@ pub struct DBSpell {
     pub name: String,
     pub level: i16,
     pub school: MagicSchool,
-    pub casting_time: i64,
+    pub casting_time: i16,
+    pub casting_time_unit: TimeUnit,
     pub range: SpellRange,
-    pub area: String,
-    pub duration_per_level: i64,
+    pub area_shape: Option<AreaShape>,
+    pub area_size: Option<i16>,
+    pub target_kind: Option<TargetKind>,
+    pub target_count: i16,
+    pub targets_per_level: i16,
+    pub duration_unit: DurationUnit,
+    pub duration: i16,
+    pub duration_per_level: i16,
+    pub concentration: bool,
+    pub dismissible: bool,
     pub saving_throw: Option<SaveThrow>,
     pub spell_resistance: bool,
     pub description: String,
@@ -318,10 +462,19 @@ impl IntoDb for Spell {
             name: self.name,
             level: self.level,
             school: self.school,
-            casting_time: self.casting_time,
+            casting_time: self.casting_time.amount,
+            casting_time_unit: self.casting_time.unit,
             range: self.range,
-            area: self.area,
-            duration_per_level: self.duration_per_level,
+            area_shape: self.area.map(|area| area.shape),
+            area_size: self.area.map(|area| area.size),
+            target_kind: self.targets.map(|targets| targets.kind),
+            target_count: self.targets.map(|targets| targets.count).unwrap_or(1),
+            targets_per_level: self.targets.map(|targets| targets.per_level).unwrap_or(0),
+            duration_unit: self.duration.unit,
+            duration: self.duration.base,
+            duration_per_level: self.duration.per_level,
+            concentration: self.duration.concentration,
+            dismissible: self.duration.dismissible,
             saving_throw: self.saving_throw,
             spell_resistance: self.spell_resistance,
             description: self.description,
@@ -370,8 +523,8 @@ impl TryFromForm for SpellComponent {
         };
         let component_type: ComponentType = forms::get_required_form_text_field(&form, SpellComponent::FIELD_COMPONENT_TYPE)?;
 
-        // Item should be given iff material component
-        if (component_type == ComponentType::Material) != (item.is_some()) {
+        // Item should be given iff material component or focus
+        if component_type.needs_item() != item.is_some() {
             return Err(forms::field_is_invalid_error(SpellComponent::FIELD_ITEM_ID));
         }
 
@@ -457,11 +610,35 @@ pub enum CasterType {
     Prepared,
 }
 
+/// Where a caster's magic comes from. Arcane casters risk spell failure when
+/// casting spells with somatic components in armor.
+#[derive(Serialize, Deserialize, DbEnum, Debug, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum MagicTradition {
+    Arcane,
+    Divine,
+}
+
 #[derive(Serialize, Deserialize, Display, FromStr, PartialOrd, Ord, PartialEq, Eq, DbEnum, Debug, Copy, Clone)]
 pub enum ComponentType {
     Somatic,
     Material,
     Verbal,
+    /// An item the caster must have, but which casting doesn't use up.
+    Focus,
+    /// A holy symbol, or whatever the caster's faith uses instead.
+    DivineFocus,
+}
+
+impl ComponentType {
+    /// Whether the component is a particular item.
+    pub fn needs_item(self) -> bool {
+        self == ComponentType::Material || self == ComponentType::Focus
+    }
+
+    /// Whether casting the spell uses the component up.
+    pub fn is_consumed(self) -> bool {
+        self == ComponentType::Material
+    }
 }
 
 #[derive(Serialize, Deserialize, DbEnum, Display, FromStr, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
@@ -485,3 +662,235 @@ pub enum SpellRange {
     Long,
     Unlimited,
 }
+
+/// The units casting times and durations are measured in. The first five
+/// are actions and take up part of a single round.
+#[derive(Serialize, Deserialize, DbEnum, Display, FromStr, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum TimeUnit {
+    Free,
+    Immediate,
+    Swift,
+    Move,
+    Standard,
+    FullRound,
+    Round,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl TimeUnit {
+    /// The length of one of this unit in rounds. Actions shorter than a full
+    /// round count as 0.
+    pub fn rounds(&self) -> i64 {
+        match self {
+            TimeUnit::Free | TimeUnit::Immediate | TimeUnit::Swift | TimeUnit::Move | TimeUnit::Standard => 0,
+            TimeUnit::FullRound | TimeUnit::Round => 1,
+            TimeUnit::Minute => 10,
+            TimeUnit::Hour => 600,
+            TimeUnit::Day => 14_400,
+        }
+    }
+
+    fn name(&self, plural: bool) -> &'static str {
+        match (self, plural) {
+            (TimeUnit::Free, _) => "free action",
+            (TimeUnit::Immediate, _) => "immediate action",
+            (TimeUnit::Swift, _) => "swift action",
+            (TimeUnit::Move, _) => "move action",
+            (TimeUnit::Standard, _) => "standard action",
+            (TimeUnit::FullRound, _) => "full-round action",
+            (TimeUnit::Round, false) => "round",
+            (TimeUnit::Round, true) => "rounds",
+            (TimeUnit::Minute, false) => "minute",
+            (TimeUnit::Minute, true) => "minutes",
+            (TimeUnit::Hour, false) => "hour",
+            (TimeUnit::Hour, true) => "hours",
+            (TimeUnit::Day, false) => "day",
+            (TimeUnit::Day, true) => "days",
+        }
+    }
+
+    /// The abbreviation used for durations that scale with level.
+    fn per_level_name(&self) -> &'static str {
+        match self {
+            TimeUnit::Minute => "min.",
+            TimeUnit::Hour => "hour",
+            TimeUnit::Day => "day",
+            _ => "round",
+        }
+    }
+}
+
+/// How long it takes to cast a spell, e.g. 1 standard action or 10 minutes.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct CastingTime {
+    pub amount: i16,
+    pub unit: TimeUnit,
+}
+
+impl CastingTime {
+    pub fn in_rounds(&self) -> i64 {
+        i64::from(self.amount) * self.unit.rounds()
+    }
+}
+
+impl fmt::Display for CastingTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.unit.name(self.amount != 1))
+    }
+}
+
+#[derive(Serialize, Deserialize, DbEnum, Display, FromStr, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum DurationUnit {
+    Instantaneous,
+    Round,
+    Minute,
+    Hour,
+    Day,
+    Permanent,
+}
+
+impl DurationUnit {
+    fn time_unit(&self) -> Option<TimeUnit> {
+        match self {
+            DurationUnit::Round => Some(TimeUnit::Round),
+            DurationUnit::Minute => Some(TimeUnit::Minute),
+            DurationUnit::Hour => Some(TimeUnit::Hour),
+            DurationUnit::Day => Some(TimeUnit::Day),
+            DurationUnit::Instantaneous | DurationUnit::Permanent => None,
+        }
+    }
+}
+
+/// How long a spell lasts: `base` plus `per_level` for every caster level,
+/// in `unit`s. Concentration spells last as long as the caster concentrates,
+/// up to that limit, or with no limit if both are 0.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct SpellDuration {
+    pub unit: DurationUnit,
+    pub base: i16,
+    pub per_level: i16,
+    pub concentration: bool,
+    pub dismissible: bool,
+}
+
+impl SpellDuration {
+    /// The number of rounds the spell lasts at the given caster level, or
+    /// `None` if it doesn't end on its own.
+    pub fn in_rounds(&self, caster_level: i16) -> Option<i64> {
+        let unit = match self.unit {
+            DurationUnit::Instantaneous => return Some(0),
+            DurationUnit::Permanent => return None,
+            unit => unit.time_unit()?,
+        };
+        if self.base == 0 && self.per_level == 0 {
+            return None;
+        }
+        let amount = i64::from(self.base) + i64::from(self.per_level) * i64::from(caster_level);
+        Some(amount * unit.rounds())
+    }
+}
+
+impl fmt::Display for SpellDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = match self.unit.time_unit() {
+            None if self.unit == DurationUnit::Instantaneous => Some("instantaneous".to_string()),
+            None => Some("permanent".to_string()),
+            Some(unit) => match (self.base, self.per_level) {
+                (0, 0) => None,
+                (base, 0) => Some(format!("{} {}", base, unit.name(base != 1))),
+                (0, per_level) => Some(format!("{} {}/level", per_level, unit.per_level_name())),
+                (base, per_level) => Some(format!(
+                    "{} {} + {} {}/level",
+                    base, unit.name(base != 1), per_level, unit.per_level_name()
+                )),
+            },
+        };
+        match (self.concentration, limit) {
+            (true, Some(limit)) => write!(f, "concentration, up to {}", limit)?,
+            (true, None) => write!(f, "concentration")?,
+            (false, Some(limit)) => write!(f, "{}", limit)?,
+            (false, None) => write!(f, "permanent")?,
+        }
+        if self.dismissible {
+            write!(f, " (D)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, DbEnum, Display, FromStr, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum AreaShape {
+    Burst,
+    Emanation,
+    Spread,
+    Cone,
+    Cylinder,
+    Line,
+    Cube,
+}
+
+/// The area a spell affects. `size` is in feet: the radius of bursts,
+/// emanations, spreads and cylinders, or the length of cones, lines and the
+/// sides of cubes.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct SpellArea {
+    pub shape: AreaShape,
+    pub size: i16,
+}
+
+impl fmt::Display for SpellArea {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.shape {
+            AreaShape::Burst => write!(f, "{}-ft.-radius burst", self.size),
+            AreaShape::Emanation => write!(f, "{}-ft.-radius emanation", self.size),
+            AreaShape::Spread => write!(f, "{}-ft.-radius spread", self.size),
+            AreaShape::Cylinder => write!(f, "{}-ft.-radius cylinder", self.size),
+            AreaShape::Cone => write!(f, "{}-ft. cone", self.size),
+            AreaShape::Line => write!(f, "{}-ft. line", self.size),
+            AreaShape::Cube => write!(f, "{}-ft. cube", self.size),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, DbEnum, Display, FromStr, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum TargetKind {
+    You,
+    Creature,
+    Object,
+    CreatureOrObject,
+}
+
+/// Who or what a spell targets: `count` of them, plus `per_level` more for
+/// every caster level.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct SpellTargets {
+    pub kind: TargetKind,
+    pub count: i16,
+    pub per_level: i16,
+}
+
+impl SpellTargets {
+    pub fn at_level(&self, caster_level: i16) -> i16 {
+        self.count + self.per_level * caster_level
+    }
+}
+
+impl fmt::Display for SpellTargets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (one, many) = match self.kind {
+            TargetKind::You => return write!(f, "you"),
+            TargetKind::Creature => ("creature", "creatures"),
+            TargetKind::Object => ("object", "objects"),
+            TargetKind::CreatureOrObject => ("creature or object", "creatures or objects"),
+        };
+        match (self.count, self.per_level) {
+            (1, 0) => write!(f, "one {}", one),
+            (count, 0) => write!(f, "{} {}", count, many),
+            (0, 1) => write!(f, "one {}/level", one),
+            (0, per_level) => write!(f, "{} {}/level", per_level, many),
+            (count, per_level) => write!(f, "{} {} + {}/level", count, many, per_level),
+        }
+    }
+}
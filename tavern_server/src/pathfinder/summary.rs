@@ -1,6 +1,7 @@
 use super::Links;
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+use std::fmt;
 use std::marker::PhantomData;
 pub use tavern_derive::Summarize;
 use uuid::Uuid;
@@ -98,6 +99,18 @@ impl<T> PartialEq for Summary<T> {
 
 impl<T> Eq for Summary<T> {}
 
+// Implemented by hand so that T is not required to implement Debug.
+impl<T> fmt::Debug for Summary<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Summary")
+            .field("id", &self.id)
+            .field("links", &self.links)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
 impl<T> Delete for Summary<T> where T: Summarize<T> + DeleteById + Sized {
     fn db_delete(&self, conn: &Connection) -> Result<(), db::Error> {
         T::db_delete_by_id(self.id(), conn)
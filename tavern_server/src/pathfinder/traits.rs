@@ -0,0 +1,351 @@
+use super::character::{DBCharacter, DBCharacterTrait};
+use super::effects::Effect;
+use super::summary::{Summarize, Summary};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, GetById, Insert, TryFromDb};
+use crate::db::{Delete, DeleteById, GetAll, Update};
+use crate::forms::{self, TryFromForm};
+use crate::schema::{charactertraits, traiteffects, traits};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use diesel_derive_enum::DbEnum;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_trait_per_category() {
+        assert!(check_traits(&[TraitCategory::Combat, TraitCategory::Faith]).is_ok());
+        assert!(check_traits(&[TraitCategory::Combat, TraitCategory::Combat]).is_err());
+        assert!(check_traits(&[TraitCategory::Combat, TraitCategory::Faith, TraitCategory::Social]).is_err());
+    }
+
+    #[test]
+    fn a_drawback_grants_an_extra_trait() {
+        let categories = [
+            TraitCategory::Combat,
+            TraitCategory::Faith,
+            TraitCategory::Social,
+            TraitCategory::Drawback,
+        ];
+        assert!(check_traits(&categories).is_ok());
+        assert_eq!(trait_allowance(1), BASE_TRAITS + 1);
+
+        // Taking more drawbacks doesn't grant more traits.
+        let categories = [TraitCategory::Drawback, TraitCategory::Drawback];
+        assert!(check_traits(&categories).is_err());
+        assert_eq!(trait_allowance(2), BASE_TRAITS + MAX_DRAWBACKS);
+    }
+}
+
+const FIELD_TRAITS: &str = "traits";
+
+/// The number of traits a character can take without drawbacks.
+pub const BASE_TRAITS: usize = 2;
+/// The number of drawbacks a character can take, each of which grants an
+/// extra trait.
+pub const MAX_DRAWBACKS: usize = 1;
+
+#[derive(DbEnum, Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum TraitCategory {
+    Combat,
+    Faith,
+    Magic,
+    Social,
+    Regional,
+    Race,
+    Drawback,
+}
+
+/// The number of traits, not counting drawbacks, a character who took
+/// `drawbacks` drawbacks can have.
+pub fn trait_allowance(drawbacks: usize) -> usize {
+    BASE_TRAITS + drawbacks.min(MAX_DRAWBACKS)
+}
+
+/// Checks the categories of the traits and drawbacks a character took: no
+/// more than one trait from each category, and no more traits than their
+/// drawbacks allow.
+pub fn check_traits(categories: &[TraitCategory]) -> Result<(), Error> {
+    let drawbacks = categories.iter()
+        .filter(|category| **category == TraitCategory::Drawback)
+        .count();
+    if drawbacks > MAX_DRAWBACKS {
+        return Err(Error::new(format!("only {} drawback can be taken", MAX_DRAWBACKS)));
+    }
+
+    let mut taken = BTreeSet::new();
+    for category in categories.iter().filter(|category| **category != TraitCategory::Drawback) {
+        if !taken.insert(*category) {
+            return Err(Error::new(format!("only one {} trait can be taken", category)));
+        }
+    }
+    let allowance = trait_allowance(drawbacks);
+    if taken.len() > allowance {
+        return Err(Error::new(format!("only {} traits can be taken", allowance)));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Summarize, Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct Trait {
+    id: Uuid,
+    name: String,
+    description: String,
+    pub category: TraitCategory,
+    effects: BTreeSet<Summary<Effect>>,
+}
+
+impl Trait {
+    const FIELD_NAME: &'static str = "name";
+    const FIELD_DESCRIPTION: &'static str = "description";
+    const FIELD_CATEGORY: &'static str = "category";
+    const FIELD_EFFECTS: &'static str = "effects";
+}
+
+impl TryFromForm for Trait {
+    fn try_from_form(conn: &Connection, form: Form, this_id: Option<Uuid>, _parent_id: Option<Uuid>) -> Result<Self, Rejection> where Self: Sized {
+        let id = forms::valid_id_or_new::<Trait>(this_id, conn)?;
+        let name: String = forms::get_required_form_text_field(&form, Trait::FIELD_NAME)?;
+        if name.trim().is_empty() {
+            return Err(forms::field_is_invalid_error(Trait::FIELD_NAME));
+        }
+        let description = forms::get_required_form_text_field(&form, Trait::FIELD_DESCRIPTION)?;
+        let category = forms::get_required_form_text_field(&form, Trait::FIELD_CATEGORY)?;
+        let effects = match forms::get_optional_form_text_field::<String>(&form, Trait::FIELD_EFFECTS)? {
+            Some(effects) => serde_json::from_str::<Vec<Uuid>>(&effects)
+                .map_err(|_| forms::field_is_invalid_error(Trait::FIELD_EFFECTS))?
+                .into_iter()
+                .map(|id| forms::value_by_id(id, conn))
+                .collect::<Result<_, _>>()?,
+            None => BTreeSet::new(),
+        };
+
+        Ok(Trait { id, name: name.trim().to_string(), description, category, effects })
+    }
+}
+
+impl TryFromDb for Trait {
+    type DBType = DBTrait;
+
+    fn try_from_db(other: Self::DBType, conn: &Connection) -> Result<Self, DBError> where Self: Sized {
+        let effects = DBTraitEffect::belonging_to(&other)
+            .load::<DBTraitEffect>(conn)
+            .map_err(DBError::RunQuery)?
+            .into_iter()
+            .map(|e| Summary::<Effect>::db_get_by_id(&e.effect_id, conn))
+            .collect::<Result<_, DBError>>()?;
+        let character_trait = Trait {
+            id: other.id,
+            name: other.name,
+            description: other.description,
+            category: other.category,
+            effects,
+        };
+        Ok(character_trait)
+    }
+}
+
+impl Insert for Trait {
+    fn db_insert(&self, conn: &Connection) -> Result<(), DBError> {
+        conn.transaction(|| {
+            let db_trait = DBTrait {
+                id: self.id,
+                name: self.name.clone(),
+                description: self.description.clone(),
+                category: self.category,
+            };
+            db_trait.db_insert(conn)?;
+            for effect in self.effects.iter() {
+                DBTraitEffect { trait_id: self.id, effect_id: effect.id().to_owned() }.db_insert(conn)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(AsChangeset, Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(GetAll, GetById, Delete, DeleteById, Insert, Update)]
+#[tavern(is_identifiable, is_insertable, is_queryable)]
+#[table_name = "traits"]
+pub struct DBTrait {
+    id: Uuid,
+    name: String,
+    description: String,
+    category: TraitCategory,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Clone, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Insert, Delete)]
+#[tavern(is_insertable, is_identifiable, is_queryable)]
+#[table_name = "traiteffects"]
+#[primary_key(trait_id, effect_id)]
+#[belongs_to(DBTrait, foreign_key = "trait_id")]
+pub struct DBTraitEffect {
+    trait_id: Uuid,
+    effect_id: Uuid,
+}
+
+/// The traits and drawbacks a character took and how many more traits they
+/// could take.
+#[derive(Serialize, Clone, Debug)]
+pub struct CharacterTraits {
+    pub traits: Vec<Trait>,
+    pub drawbacks: Vec<Trait>,
+    pub allowance: usize,
+    pub remaining: usize,
+}
+
+impl From<CharacterTraits> for Bytes {
+    fn from(traits: CharacterTraits) -> Self {
+        status::serialize_to_bytes(&traits)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TraitList {
+    pub traits: Vec<Trait>,
+}
+
+impl From<TraitList> for Bytes {
+    fn from(list: TraitList) -> Self {
+        status::serialize_to_bytes(&list)
+    }
+}
+
+impl From<Trait> for Bytes {
+    fn from(character_trait: Trait) -> Self {
+        status::serialize_to_bytes(&character_trait)
+    }
+}
+
+fn character_traits(character: &DBCharacter, conn: &Connection) -> Result<CharacterTraits, DBError> {
+    let (drawbacks, traits): (Vec<Trait>, Vec<Trait>) = charactertraits::table.inner_join(traits::table)
+        .select(traits::all_columns)
+        .filter(charactertraits::char_id.eq(character.id()))
+        .order((traits::category, traits::name))
+        .load::<DBTrait>(conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|character_trait| Trait::try_from_db(character_trait, conn))
+        .collect::<Result<Vec<_>, DBError>>()?
+        .into_iter()
+        .partition(|character_trait| character_trait.category == TraitCategory::Drawback);
+    let allowance = trait_allowance(drawbacks.len());
+
+    Ok(CharacterTraits {
+        remaining: allowance.saturating_sub(traits.len()),
+        traits,
+        drawbacks,
+        allowance,
+    })
+}
+
+async fn get_character_traits(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<CharacterTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let traits = character_traits(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+/// Sets the traits and drawbacks a character took to those given in the
+/// form.
+async fn choose_traits(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<CharacterTraits>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let chosen: String = forms::get_required_form_text_field(&form, FIELD_TRAITS)?;
+    let chosen = serde_json::from_str::<BTreeSet<Uuid>>(&chosen)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_TRAITS))?
+        .into_iter()
+        .map(|id| forms::value_by_id::<Trait>(id, &conn))
+        .collect::<Result<Vec<_>, Rejection>>()?;
+    let categories = chosen.iter()
+        .map(|character_trait| character_trait.category)
+        .collect::<Vec<_>>();
+    check_traits(&categories)
+        .map_err(|err| status::bad_request(err.message))?;
+
+    conn.transaction::<_, DBError, _>(|| {
+        diesel::delete(charactertraits::table.filter(charactertraits::char_id.eq(char_id)))
+            .execute(&conn)
+            .map_err(DBError::RunQuery)?;
+        for character_trait in chosen.iter() {
+            DBCharacterTrait { char_id, trait_id: character_trait.id }.db_insert(&conn)?;
+        }
+        Ok(())
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_TRAITS))?;
+
+    let traits = character_traits(&character, &conn)?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(traits)))
+}
+
+async fn list_traits(user: User, conn: Connection) -> Result<Status<Success<TraitList>>, Rejection> {
+    user.id.ok_or_else(status::not_authorized)?;
+    let traits = traits::table.order((traits::category, traits::name))
+        .load::<DBTrait>(&conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|character_trait| Trait::try_from_db(character_trait, &conn))
+        .collect::<Result<_, DBError>>()?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(TraitList { traits })))
+}
+
+async fn create_trait(user: User, conn: Connection, form: Form) -> Result<Status<Success<Trait>>, Rejection> {
+    campaign::catalog_editor(&user, &conn)?;
+    let character_trait = Trait::try_from_form(&conn, form, None, None)?;
+    character_trait.db_insert(&conn)
+        .map_err(|err| forms::db_error_to_rejection(err, Trait::FIELD_NAME))?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(character_trait)))
+}
+
+/// A warp Filter containing the trait catalog endpoints, relative to the
+/// `/traits` path.
+pub fn traits_filter() -> BoxedFilter<(impl Reply,)> {
+    let list = warp::get()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(list_traits);
+    let create = warp::post()
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(create_trait);
+
+    list.or(create)
+        .boxed()
+}
+
+/// A warp Filter containing the endpoints for the traits a character took,
+/// relative to the `/characters` path.
+pub fn character_traits_filter() -> BoxedFilter<(impl Reply,)> {
+    let get = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("traits"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_character_traits);
+    let choose = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("traits"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(choose_traits);
+
+    get.or(choose)
+        .boxed()
+}
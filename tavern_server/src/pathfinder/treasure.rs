@@ -0,0 +1,548 @@
+use super::bestiary::ChallengeRating;
+use super::character::DBCharacter;
+use super::crafting::COPPER_PER_GOLD;
+use super::experience::{self, AdvancementTrack};
+use super::inventory;
+use super::item::Item;
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, GetById};
+use crate::events::{self, Event};
+use crate::forms;
+use crate::pathfinder::summary::Summary;
+use crate::schema::{armor, characters, consumables, items, weapons};
+use crate::status::{self, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Vec<(Uuid, i32)> {
+        vec![
+            (Uuid::new_v4(), 5 * COPPER_PER_GOLD),
+            (Uuid::new_v4(), 50 * COPPER_PER_GOLD),
+            (Uuid::new_v4(), 300 * COPPER_PER_GOLD),
+        ]
+    }
+
+    fn items_value(hoard: &Hoard, catalog: &[(Uuid, i32)]) -> i32 {
+        hoard.items.iter()
+            .map(|(id, count)| catalog.iter().find(|(item, _)| item == id).unwrap().1 * count)
+            .sum()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_treasure() {
+        let catalog = catalog();
+        let first = generate(800 * COPPER_PER_GOLD, TreasureType::Standard, &catalog, &mut StdRng::seed_from_u64(7));
+        let second = generate(800 * COPPER_PER_GOLD, TreasureType::Standard, &catalog, &mut StdRng::seed_from_u64(7));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn treasure_is_worth_exactly_the_budget() {
+        let catalog = catalog();
+        for seed in 0..20 {
+            let budget = 1234 * COPPER_PER_GOLD + 56;
+            let hoard = generate(budget, TreasureType::Items, &catalog, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(hoard.coins.value() + items_value(&hoard, &catalog), budget);
+        }
+    }
+
+    #[test]
+    fn coin_treasure_has_no_items() {
+        let hoard = generate(500 * COPPER_PER_GOLD, TreasureType::Coins, &catalog(), &mut StdRng::seed_from_u64(1));
+        assert!(hoard.items.is_empty());
+        assert_eq!(hoard.coins.value(), 500 * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn treasure_per_encounter_follows_the_track() {
+        let cr = |cr: &str| cr.parse::<ChallengeRating>().unwrap();
+        assert_eq!(treasure_per_encounter(cr("1"), AdvancementTrack::Medium), 260 * COPPER_PER_GOLD);
+        assert_eq!(treasure_per_encounter(cr("20"), AdvancementTrack::Fast), 100_000 * COPPER_PER_GOLD);
+        // Fractional CRs get a share of CR 1's treasure by experience.
+        assert_eq!(treasure_per_encounter(cr("1/2"), AdvancementTrack::Medium), 130 * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn paying_keeps_platinum_and_gives_change() {
+        let purse = Coins { copper: 5, silver: 0, gold: 3, platinum: 2 };
+        assert_eq!(purse.pay(150), Some(Coins { copper: 5, silver: 5, gold: 1, platinum: 2 }));
+        assert_eq!(purse.pay(400), Some(Coins { copper: 5, silver: 0, gold: 9, platinum: 1 }));
+        assert_eq!(purse.pay(purse.value()), Some(Coins::default()));
+        assert_eq!(purse.pay(purse.value() + 1), None);
+        assert_eq!(purse.pay(-1), None);
+    }
+
+    #[test]
+    fn adding_coins_stops_at_the_limit() {
+        let hoard = Coins { copper: i32::MAX, silver: 0, gold: 1, platinum: 0 };
+        let more = Coins { copper: 1, silver: 2, gold: 3, platinum: 4 };
+        assert_eq!(hoard + more, Coins { copper: i32::MAX, silver: 2, gold: 4, platinum: 4 });
+    }
+
+    #[test]
+    fn purses_can_only_hold_so_many_coins() {
+        let coins = Coins { copper: 1, silver: 2, gold: 3, platinum: 4 };
+        assert_eq!(add_to_purse((1, 1, 1, 1), coins), Some((2, 3, 4, 5)));
+        assert_eq!(add_to_purse((i16::MAX, 0, 0, 0), coins), None);
+        assert_eq!(add_to_purse((0, 0, 0, 0), Coins { gold: i32::MAX, ..Coins::default() }), None);
+        assert_eq!(add_to_purse((5, 0, 0, 0), Coins { copper: -5, ..Coins::default() }), Some((0, 0, 0, 0)));
+    }
+}
+
+/// The most items a single treasure can have, so that a large budget full of
+/// cheap items stays manageable.
+pub const MAX_TREASURE_ITEMS: usize = 50;
+
+/// The gold given out per encounter of each CR from 1 to 20, on the slow,
+/// medium and fast tracks.
+const TREASURE_PER_ENCOUNTER: [(i32, i32, i32); 20] = [
+    (170, 260, 400),
+    (350, 550, 800),
+    (550, 800, 1200),
+    (750, 1150, 1700),
+    (1000, 1550, 2300),
+    (1350, 2000, 3000),
+    (1750, 2600, 3900),
+    (2200, 3350, 5000),
+    (2850, 4250, 6400),
+    (3650, 5450, 8200),
+    (4650, 7000, 10500),
+    (6000, 9000, 13500),
+    (7750, 11600, 17500),
+    (10000, 15000, 22000),
+    (13000, 19500, 29000),
+    (16500, 25000, 38000),
+    (22000, 32000, 48000),
+    (28000, 41000, 62000),
+    (35000, 53000, 79000),
+    (44000, 67000, 100000),
+];
+
+const FIELD_GOLD: &str = "gold";
+const FIELD_CR: &str = "cr";
+const FIELD_TREASURE_TYPE: &str = "treasure-type";
+const FIELD_SEED: &str = "seed";
+const FIELD_SHARES: &str = "shares";
+
+/// What a treasure is made up of.
+#[derive(Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Default)]
+pub enum TreasureType {
+    /// Nothing but coins.
+    Coins,
+    /// Half coins, half items of any kind.
+    #[default]
+    Standard,
+    /// As many items of any kind as the budget allows.
+    Items,
+    /// Weapons and armor only.
+    ArmorAndWeapons,
+    /// Potions, scrolls and wands only.
+    Consumables,
+}
+
+impl TreasureType {
+    /// The percentage of the budget given out as coins. Whatever can't be
+    /// spent on items also ends up as coins.
+    fn coin_share(self) -> i32 {
+        match self {
+            TreasureType::Coins => 100,
+            TreasureType::Standard => 50,
+            TreasureType::Items | TreasureType::ArmorAndWeapons | TreasureType::Consumables => 0,
+        }
+    }
+}
+
+/// An amount of money in each kind of coin.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Coins {
+    pub copper: i32,
+    pub silver: i32,
+    pub gold: i32,
+    pub platinum: i32,
+}
+
+impl Coins {
+    /// Makes up an amount of copper using as few coins as possible, without
+    /// using platinum.
+    pub fn from_copper(copper: i32) -> Self {
+        Coins {
+            copper: copper % 10,
+            silver: copper % COPPER_PER_GOLD / 10,
+            gold: copper / COPPER_PER_GOLD,
+            platinum: 0,
+        }
+    }
+
+    /// The total value of the coins, in copper.
+    pub fn value(&self) -> i32 {
+        self.copper + self.silver * 10 + self.gold * COPPER_PER_GOLD + self.platinum * 10 * COPPER_PER_GOLD
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.copper >= 0 && self.silver >= 0 && self.gold >= 0 && self.platinum >= 0
+    }
+
+    /// The coins a character is carrying.
+    pub fn held_by(character: &DBCharacter) -> Self {
+        let (copper, silver, gold, platinum) = character.purse();
+        Coins {
+            copper: i32::from(copper),
+            silver: i32::from(silver),
+            gold: i32::from(gold),
+            platinum: i32::from(platinum),
+        }
+    }
+
+    /// The coins left after paying an amount of copper, or None if there
+    /// isn't enough. Platinum is kept where possible and the rest is given
+    /// back as change.
+    pub fn pay(&self, cost: i32) -> Option<Coins> {
+        let left = self.value() - cost;
+        if cost < 0 || left < 0 {
+            return None;
+        }
+        let platinum = self.platinum.min(left / (10 * COPPER_PER_GOLD));
+        let mut change = Coins::from_copper(left - platinum * 10 * COPPER_PER_GOLD);
+        change.platinum = platinum;
+        Some(change)
+    }
+}
+
+/// Coins are added and taken away one kind at a time, stopping at the limits
+/// of an `i32` rather than wrapping around.
+impl std::ops::Add for Coins {
+    type Output = Coins;
+
+    fn add(self, other: Coins) -> Coins {
+        Coins {
+            copper: self.copper.saturating_add(other.copper),
+            silver: self.silver.saturating_add(other.silver),
+            gold: self.gold.saturating_add(other.gold),
+            platinum: self.platinum.saturating_add(other.platinum),
+        }
+    }
+}
+
+impl std::ops::Sub for Coins {
+    type Output = Coins;
+
+    fn sub(self, other: Coins) -> Coins {
+        Coins {
+            copper: self.copper.saturating_sub(other.copper),
+            silver: self.silver.saturating_sub(other.silver),
+            gold: self.gold.saturating_sub(other.gold),
+            platinum: self.platinum.saturating_sub(other.platinum),
+        }
+    }
+}
+
+/// The coins and catalog items of a treasure, before they are looked up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hoard {
+    pub coins: Coins,
+    /// The number of each catalog item in the treasure.
+    pub items: BTreeMap<Uuid, i32>,
+}
+
+/// The treasure, in copper, handed out for an encounter of the given CR.
+/// CRs outside of the table get a share of its nearest end by experience.
+pub fn treasure_per_encounter(cr: ChallengeRating, track: AdvancementTrack) -> i32 {
+    let row = (cr.step().max(1).min(TREASURE_PER_ENCOUNTER.len() as i16) - 1) as usize;
+    let (slow, medium, fast) = TREASURE_PER_ENCOUNTER[row];
+    let gold = match track {
+        AdvancementTrack::Slow => slow,
+        AdvancementTrack::Medium => medium,
+        AdvancementTrack::Fast => fast,
+    };
+    let table_cr = ChallengeRating::nearest(row as i16 + 1);
+    let copper = i64::from(gold) * i64::from(COPPER_PER_GOLD) * i64::from(cr.xp()) / i64::from(table_cr.xp());
+    i32::try_from(copper).unwrap_or(i32::MAX)
+}
+
+/// Randomly fills a treasure worth `budget` copper with the given catalog
+/// items, given as their IDs and costs, and coins.
+pub fn generate<R: Rng>(budget: i32, kind: TreasureType, catalog: &[(Uuid, i32)], rng: &mut R) -> Hoard {
+    let item_budget = (i64::from(budget) * i64::from(100 - kind.coin_share()) / 100) as i32;
+    let mut remaining = item_budget;
+    let mut items = BTreeMap::new();
+    for _ in 0..MAX_TREASURE_ITEMS {
+        let affordable = catalog.iter()
+            .filter(|(_, cost)| *cost > 0 && *cost <= remaining)
+            .collect::<Vec<_>>();
+        if affordable.is_empty() {
+            break;
+        }
+        let (item, cost) = affordable[rng.gen_range(0, affordable.len())];
+        *items.entry(*item).or_insert(0) += 1;
+        remaining -= cost;
+    }
+
+    // Some of the gold is handed out as platinum instead.
+    let mut coins = Coins::from_copper(budget - item_budget + remaining);
+    let platinum = rng.gen_range(0, coins.gold / 20 + 1);
+    coins.gold -= platinum * 10;
+    coins.platinum = platinum;
+
+    Hoard { coins, items }
+}
+
+/// A catalog item found in a treasure.
+#[derive(Serialize, Clone, Debug)]
+pub struct TreasureItem {
+    pub item: Summary<Item>,
+    pub count: i32,
+    /// The cost of a single item, in copper.
+    pub cost: i32,
+}
+
+/// A randomly generated treasure. Generating a treasure with the same seed
+/// and budget gives the same result as long as the item catalog is the same.
+#[derive(Serialize, Clone, Debug)]
+pub struct Treasure {
+    pub seed: u32,
+    pub treasure_type: TreasureType,
+    /// The value of the whole treasure, in copper.
+    pub value: i32,
+    pub coins: Coins,
+    pub items: Vec<TreasureItem>,
+}
+
+impl From<Treasure> for Bytes {
+    fn from(treasure: Treasure) -> Self {
+        status::serialize_to_bytes(&treasure)
+    }
+}
+
+/// The part of a treasure given to a single bag. Coins go to the character
+/// carrying the bag.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LootShare {
+    #[serde(rename = "bag-id")]
+    pub bag_id: Uuid,
+    #[serde(default)]
+    pub items: BTreeMap<Uuid, i32>,
+    #[serde(default)]
+    pub coins: Coins,
+}
+
+/// Where a share of the loot ended up.
+#[derive(Serialize, Clone, Debug)]
+pub struct DistributedShare {
+    pub character_id: Uuid,
+    pub bag_id: Uuid,
+    /// The owned items the loot was added to.
+    pub items: Vec<Uuid>,
+    pub coins: Coins,
+}
+
+/// The loot handed out to a campaign's characters.
+#[derive(Serialize, Clone, Debug)]
+pub struct LootDistributed {
+    pub shares: Vec<DistributedShare>,
+}
+
+impl From<LootDistributed> for Bytes {
+    fn from(distributed: LootDistributed) -> Self {
+        status::serialize_to_bytes(&distributed)
+    }
+}
+
+/// The IDs and costs of the catalog items a treasure of the given type can
+/// contain.
+fn catalog_for(kind: TreasureType, conn: &Connection) -> Result<Vec<(Uuid, i32)>, DBError> {
+    let query = items::table.select((items::id, items::cost))
+        .filter(items::cost.gt(0))
+        .into_boxed();
+    let query = match kind {
+        TreasureType::Coins => return Ok(Vec::new()),
+        TreasureType::Standard | TreasureType::Items => query,
+        TreasureType::ArmorAndWeapons => query.filter(
+            items::id.eq_any(weapons::table.select(weapons::id))
+                .or(items::id.eq_any(armor::table.select(armor::id)))
+        ),
+        TreasureType::Consumables => query.filter(items::id.eq_any(consumables::table.select(consumables::id))),
+    };
+    query.order(items::id)
+        .load::<(Uuid, i32)>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+async fn generate_treasure(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<Treasure>>, Rejection> {
+    campaign::campaign_as_gm(&user, campaign_id, &conn)?;
+    let gold: Option<i32> = forms::get_optional_form_text_field(&form, FIELD_GOLD)?;
+    let cr: Option<ChallengeRating> = forms::get_optional_form_text_field(&form, FIELD_CR)?;
+    let budget = match (gold, cr) {
+        (Some(gold), None) if gold > 0 => gold.checked_mul(COPPER_PER_GOLD)
+            .ok_or_else(|| forms::field_is_invalid_error(FIELD_GOLD))?,
+        (Some(_), None) => return Err(forms::field_is_invalid_error(FIELD_GOLD)),
+        (None, Some(cr)) => {
+            let track = experience::advancement_of_campaign(&campaign_id, &conn)?;
+            treasure_per_encounter(cr, track)
+        }
+        (Some(_), Some(_)) => return Err(status::bad_request("a treasure is either worth an amount of gold or a CR, not both".to_string())),
+        (None, None) => return Err(forms::missing_field_error(FIELD_GOLD)),
+    };
+    let treasure_type = forms::get_optional_form_text_field(&form, FIELD_TREASURE_TYPE)?
+        .unwrap_or_default();
+    let seed = forms::get_optional_form_text_field(&form, FIELD_SEED)?
+        .unwrap_or_else(rand::random::<u32>);
+
+    let catalog = catalog_for(treasure_type, &conn)?;
+    let hoard = generate(budget, treasure_type, &catalog, &mut StdRng::seed_from_u64(u64::from(seed)));
+    let items = hoard.items.into_iter()
+        .map(|(id, count)| {
+            let item = Item::db_get_by_id(&id, &conn)?;
+            Ok(TreasureItem { item: Summary::from(&item), count, cost: item.cost })
+        })
+        .collect::<Result<Vec<_>, DBError>>()?;
+    let value = hoard.coins.value() + items.iter().map(|item| item.cost * item.count).sum::<i32>();
+
+    let treasure = Treasure {
+        seed,
+        treasure_type,
+        value,
+        coins: hoard.coins,
+        items,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(treasure)))
+}
+
+/// The coins in `purse` once `coins` are added to it, or None if there would
+/// be more of a coin than can be stored.
+pub(crate) fn add_to_purse((copper, silver, gold, platinum): (i16, i16, i16, i16), coins: Coins) -> Option<(i16, i16, i16, i16)> {
+    let add = |held: i16, extra: i32| {
+        i32::from(held).checked_add(extra)
+            .and_then(|total| i16::try_from(total).ok())
+    };
+    Some((
+        add(copper, coins.copper)?,
+        add(silver, coins.silver)?,
+        add(gold, coins.gold)?,
+        add(platinum, coins.platinum)?,
+    ))
+}
+
+/// The coins a character would have after being given more, or a Rejection
+/// if they would have more of a coin than can be stored.
+pub(crate) fn purse_after(character: &DBCharacter, coins: Coins) -> Result<(i16, i16, i16, i16), Rejection> {
+    add_to_purse(character.purse(), coins)
+        .ok_or_else(|| status::bad_request(format!("{} can't carry that many coins", character.name())))
+}
+
+pub(crate) fn set_purse(char_id: &Uuid, (copper, silver, gold, platinum): (i16, i16, i16, i16), conn: &Connection) -> Result<(), DBError> {
+    diesel::update(characters::table.filter(characters::id.eq(char_id)))
+        .set((
+            characters::copper.eq(copper),
+            characters::silver.eq(silver),
+            characters::gold.eq(gold),
+            characters::platinum.eq(platinum),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(DBError::RunQuery)
+}
+
+async fn distribute_loot(campaign_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<LootDistributed>>, Rejection> {
+    let campaign = campaign::campaign_as_gm(&user, campaign_id, &conn)?;
+    let shares: String = forms::get_required_form_text_field(&form, FIELD_SHARES)?;
+    let shares = serde_json::from_str::<Vec<LootShare>>(&shares)
+        .map_err(|_| forms::field_is_invalid_error(FIELD_SHARES))?;
+
+    // Work out everyone's coins up front so nothing is handed out if any of
+    // the shares are invalid.
+    let party = campaign.character_ids();
+    let mut owners = Vec::new();
+    let mut purses: BTreeMap<Uuid, Coins> = BTreeMap::new();
+    for share in shares.iter() {
+        let owner = inventory::bag_owner(&share.bag_id, &conn)
+            .map_err(|_| forms::field_is_invalid_error(FIELD_SHARES))?;
+        if !party.contains(&owner) {
+            return Err(status::bad_request("loot can only be given to characters in the campaign".to_string()));
+        }
+        if !share.coins.is_valid() || share.items.values().any(|count| *count <= 0) {
+            return Err(forms::field_is_invalid_error(FIELD_SHARES));
+        }
+        let purse = purses.entry(owner).or_default();
+        *purse = *purse + share.coins;
+        owners.push(owner);
+    }
+
+    for (owner, coins) in purses.iter() {
+        let character = DBCharacter::db_get_by_id(owner, &conn)?;
+        purse_after(&character, *coins)?;
+    }
+
+    // Work the purses out again while they're locked, so that coins given to
+    // the same characters in the meantime aren't lost.
+    let distributed = conn.transaction::<_, DBError, _>(|| {
+        for (owner, coins) in purses.into_iter() {
+            let character = characters::table.filter(characters::id.eq(owner))
+                .for_update()
+                .first::<DBCharacter>(&conn)
+                .map_err(DBError::RunQuery)?;
+            let purse = add_to_purse(character.purse(), coins)
+                .ok_or_else(|| DBError::InvalidValues(vec![FIELD_SHARES.to_string()]))?;
+            set_purse(&owner, purse, &conn)?;
+        }
+        shares.iter()
+            .zip(owners.iter())
+            .map(|(share, owner)| {
+                let items = share.items.iter()
+                    .map(|(item, count)| inventory::add_to_bag(&share.bag_id, item, *count, &conn))
+                    .collect::<Result<Vec<_>, DBError>>()?;
+                Ok(DistributedShare {
+                    character_id: *owner,
+                    bag_id: share.bag_id,
+                    items,
+                    coins: share.coins,
+                })
+            })
+            .collect::<Result<Vec<_>, DBError>>()
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_SHARES))?;
+
+    let distributed = LootDistributed { shares: distributed };
+    events::publish(campaign_id, Event::LootDistributed { shares: distributed.shares.clone() });
+    Ok(Status::with_data(&StatusCode::OK, Success::new(distributed)))
+}
+
+/// A warp Filter containing the treasure endpoints, relative to the
+/// `/campaigns` path.
+pub fn treasure_filter() -> BoxedFilter<(impl Reply,)> {
+    let generate = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("treasure"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(generate_treasure);
+    let distribute = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("loot"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(distribute_loot);
+
+    generate.or(distribute)
+        .boxed()
+}
@@ -0,0 +1,405 @@
+use super::character::DBCharacter;
+use super::class::{Class, DBStarterKit, StarterKit};
+use super::crafting::COPPER_PER_GOLD;
+use super::inventory::{self, FIELD_BAG_ID};
+use super::summary::{Summarize, Summary};
+use super::treasure::{self, Coins};
+use crate::auth::{self, User};
+use crate::campaign;
+use crate::db::{self, Connection, Error as DBError, TryFromDb};
+use crate::forms;
+use crate::schema::{bags, characters, charactersubclasses, starterkits, subclasses};
+use crate::status::{self, Error, Success};
+use bytes::Bytes;
+use diesel::prelude::*;
+use diesel::Connection as DieselConnection;
+use nebula_form::Form;
+use nebula_status::{Status, StatusCode};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tavern_derive::{Display, FromStr};
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_usual_ways_of_writing_starting_wealth() {
+        let fighter = WealthDice { count: 5, sides: 6, multiplier: 10 };
+        assert_eq!("5d6 × 10 gp".parse::<WealthDice>().unwrap(), fighter);
+        assert_eq!("5d6x10".parse::<WealthDice>().unwrap(), fighter);
+        assert_eq!("5D6 * 10 GP".parse::<WealthDice>().unwrap(), fighter);
+        assert_eq!("2d6 gp".parse::<WealthDice>().unwrap(), WealthDice { count: 2, sides: 6, multiplier: 1 });
+
+        assert!("".parse::<WealthDice>().is_err());
+        assert!("150 gp".parse::<WealthDice>().is_err());
+        assert!("d6 × 10".parse::<WealthDice>().is_err());
+        assert!("5d6 × ten".parse::<WealthDice>().is_err());
+        assert!("5d0".parse::<WealthDice>().is_err());
+    }
+
+    #[test]
+    fn average_wealth_matches_the_class_tables() {
+        let fighter: WealthDice = "5d6 × 10 gp".parse().unwrap();
+        assert_eq!(fighter.average(), 175 * COPPER_PER_GOLD);
+        let monk: WealthDice = "1d6 × 10 gp".parse().unwrap();
+        assert_eq!(monk.average(), 35 * COPPER_PER_GOLD);
+    }
+
+    #[test]
+    fn rolled_wealth_stays_within_the_dice() {
+        let dice: WealthDice = "3d6 × 10 gp".parse().unwrap();
+        for seed in 0..50 {
+            let rolled = dice.roll(&mut StdRng::seed_from_u64(seed));
+            assert!((30 * COPPER_PER_GOLD..=180 * COPPER_PER_GOLD).contains(&rolled));
+            assert_eq!(rolled % (10 * COPPER_PER_GOLD), 0);
+        }
+        let first = dice.roll(&mut StdRng::seed_from_u64(3));
+        assert_eq!(first, dice.roll(&mut StdRng::seed_from_u64(3)));
+    }
+
+    #[test]
+    fn starting_wealth_is_rolled_or_averaged() {
+        let dice: WealthDice = "5d6 × 10 gp".parse().unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(starting_copper(dice, WealthMethod::Average, &mut rng), 175 * COPPER_PER_GOLD);
+        let rolled = starting_copper(dice, WealthMethod::Roll, &mut rng);
+        assert_eq!(rolled, dice.roll(&mut StdRng::seed_from_u64(7)));
+    }
+}
+
+/// The most dice, sides or multiplier a class's starting wealth can use.
+const MAX_DICE: i32 = 100;
+const MAX_MULTIPLIER: i32 = 1000;
+
+const FIELD_CLASS_ID: &str = "class-id";
+const FIELD_METHOD: &str = "method";
+const FIELD_KIT_ID: &str = "kit-id";
+
+/// A class's starting wealth, as gold pieces rolled on dice, such as the
+/// 5d6 × 10 gp of a fighter.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WealthDice {
+    pub count: i32,
+    pub sides: i32,
+    pub multiplier: i32,
+}
+
+impl WealthDice {
+    /// The average of the dice, in copper.
+    pub fn average(&self) -> i32 {
+        self.count * (self.sides + 1) * self.multiplier * COPPER_PER_GOLD / 2
+    }
+
+    /// Rolls the dice, giving an amount of copper.
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> i32 {
+        let rolled: i32 = (0..self.count)
+            .map(|_| rng.gen_range(1, self.sides + 1))
+            .sum();
+        rolled * self.multiplier * COPPER_PER_GOLD
+    }
+}
+
+impl FromStr for WealthDice {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(format!("\"{}\" is not starting wealth dice", val));
+        let number = |part: &str| {
+            part.parse::<i32>().ok()
+                .filter(|num| *num > 0)
+                .ok_or_else(invalid)
+        };
+
+        let lower = val.to_lowercase();
+        let lower = lower.trim();
+        let lower = lower.strip_suffix("gp").unwrap_or(lower);
+        let compact = lower.split_whitespace().collect::<String>();
+        let mut parts = compact.splitn(2, ['x', '×', '*']);
+        let dice = parts.next().unwrap_or_default();
+        let multiplier = match parts.next() {
+            Some(multiplier) => number(multiplier)?,
+            None => 1,
+        };
+
+        let mut dice = dice.splitn(2, 'd');
+        let count = number(dice.next().unwrap_or_default())?;
+        let sides = number(dice.next().ok_or_else(invalid)?)?;
+        if count > MAX_DICE || sides > MAX_DICE || multiplier > MAX_MULTIPLIER {
+            return Err(invalid());
+        }
+
+        Ok(WealthDice { count, sides, multiplier })
+    }
+}
+
+/// How a new character's starting wealth is decided.
+#[derive(Debug, Serialize, Deserialize, Display, FromStr, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub enum WealthMethod {
+    Roll,
+    Average,
+}
+
+/// The starting wealth given by `method`, in copper.
+fn starting_copper<R: Rng>(dice: WealthDice, method: WealthMethod, rng: &mut R) -> i32 {
+    match method {
+        WealthMethod::Average => dice.average(),
+        WealthMethod::Roll => dice.roll(rng),
+    }
+}
+
+/// The starting wealth and starter kits on offer for one of a character's
+/// classes.
+#[derive(Serialize, Clone, Debug)]
+pub struct ClassStartingWealth {
+    pub class: Summary<Class>,
+    pub starting_wealth: String,
+    /// The average starting wealth, if the class's dice can be rolled.
+    pub average: Option<Coins>,
+    pub kits: Vec<StarterKit>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StartingWealthOptions {
+    pub classes: Vec<ClassStartingWealth>,
+}
+
+impl From<StartingWealthOptions> for Bytes {
+    fn from(options: StartingWealthOptions) -> Self {
+        status::serialize_to_bytes(&options)
+    }
+}
+
+/// The coins a character was given as their starting wealth.
+#[derive(Serialize, Clone, Debug)]
+pub struct StartingWealth {
+    pub character_id: Uuid,
+    pub class_id: Uuid,
+    pub method: WealthMethod,
+    pub coins: Coins,
+}
+
+impl From<StartingWealth> for Bytes {
+    fn from(wealth: StartingWealth) -> Self {
+        status::serialize_to_bytes(&wealth)
+    }
+}
+
+/// A starter kit bought into one of a character's bags.
+#[derive(Serialize, Clone, Debug)]
+pub struct BoughtKit {
+    pub character_id: Uuid,
+    pub kit_id: Uuid,
+    pub bag_id: Uuid,
+    /// The owned items the kit's items were put into.
+    pub items: Vec<Uuid>,
+    /// What the kit cost, in copper.
+    pub cost: i32,
+    /// The coins the character has left.
+    pub coins: Coins,
+}
+
+impl From<BoughtKit> for Bytes {
+    fn from(bought: BoughtKit) -> Self {
+        status::serialize_to_bytes(&bought)
+    }
+}
+
+/// Returns the IDs of the classes a character has taken levels in.
+pub(crate) fn classes_of_character(char_id: &Uuid, conn: &Connection) -> Result<Vec<Uuid>, DBError> {
+    let taken = charactersubclasses::table.select(charactersubclasses::subclass_id)
+        .filter(charactersubclasses::char_id.eq(char_id));
+    subclasses::table.select(subclasses::class_id)
+        .filter(subclasses::id.eq_any(taken))
+        .distinct()
+        .load::<Uuid>(conn)
+        .map_err(DBError::RunQuery)
+}
+
+fn kits_of_class(class_id: &Uuid, conn: &Connection) -> Result<Vec<StarterKit>, DBError> {
+    starterkits::table.filter(starterkits::class_id.eq(class_id))
+        .order(starterkits::name)
+        .load::<DBStarterKit>(conn)
+        .map_err(DBError::RunQuery)?
+        .into_iter()
+        .map(|kit| StarterKit::try_from_db(kit, conn))
+        .collect()
+}
+
+/// The bag starter kits go into when no bag is given: the character's first
+/// bag by name.
+fn default_bag(char_id: &Uuid, conn: &Connection) -> Result<Option<Uuid>, DBError> {
+    bags::table.select(bags::id)
+        .filter(bags::char_id.eq(char_id))
+        .order(bags::name)
+        .first::<Uuid>(conn)
+        .optional()
+        .map_err(DBError::RunQuery)
+}
+
+async fn get_starting_wealth(char_id: Uuid, user: User, conn: Connection) -> Result<Status<Success<StartingWealthOptions>>, Rejection> {
+    campaign::managed_character(&user, char_id, &conn)?;
+    let classes = classes_of_character(&char_id, &conn)?
+        .into_iter()
+        .map(|class_id| {
+            let class = forms::value_by_id::<Class>(class_id, &conn)?;
+            let average = class.starting_wealth().parse::<WealthDice>()
+                .ok()
+                .map(|dice| Coins::from_copper(dice.average()));
+            Ok(ClassStartingWealth {
+                class: Summary::from(&class),
+                starting_wealth: class.starting_wealth().to_string(),
+                average,
+                kits: kits_of_class(&class_id, &conn)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Rejection>>()?;
+    Ok(Status::with_data(&StatusCode::OK, Success::new(StartingWealthOptions { classes })))
+}
+
+async fn take_starting_wealth(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<StartingWealth>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let class_id: Uuid = forms::get_required_form_text_field(&form, FIELD_CLASS_ID)?;
+    let method: WealthMethod = forms::get_required_form_text_field(&form, FIELD_METHOD)?;
+
+    if !classes_of_character(&char_id, &conn)?.contains(&class_id) {
+        return Err(forms::field_is_invalid_error(FIELD_CLASS_ID));
+    }
+    let already_has_coins = || status::bad_request(format!("{} already has coins", character.name()));
+    // Starting wealth is only for new characters, who haven't got any coins
+    // yet.
+    if Coins::held_by(&character).value() != 0 {
+        return Err(already_has_coins());
+    }
+    let class = forms::value_by_id::<Class>(class_id, &conn)?;
+    let dice: WealthDice = class.starting_wealth().parse()
+        .map_err(|err: Error| status::bad_request(format!("{}'s starting wealth can't be rolled: {}", class.name(), err.message)))?;
+
+    let coins = Coins::from_copper(starting_copper(dice, method, &mut StdRng::from_entropy()));
+    let purse = treasure::purse_after(&character, coins)?;
+    // Check the purse again while it's locked, so that two requests can't
+    // both hand out starting wealth.
+    let taken = conn.transaction::<_, DBError, _>(|| {
+        let locked = characters::table.filter(characters::id.eq(char_id))
+            .for_update()
+            .first::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)?;
+        if Coins::held_by(&locked).value() != 0 {
+            return Ok(false);
+        }
+        treasure::set_purse(&char_id, purse, &conn)?;
+        Ok(true)
+    })?;
+    if !taken {
+        return Err(already_has_coins());
+    }
+
+    let wealth = StartingWealth {
+        character_id: char_id,
+        class_id,
+        method,
+        coins,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(wealth)))
+}
+
+async fn buy_starter_kit(char_id: Uuid, user: User, conn: Connection, form: Form) -> Result<Status<Success<BoughtKit>>, Rejection> {
+    let character = campaign::managed_character(&user, char_id, &conn)?;
+    let kit_id: Uuid = forms::get_required_form_text_field(&form, FIELD_KIT_ID)?;
+    let bag: Option<Uuid> = forms::get_optional_form_text_field(&form, FIELD_BAG_ID)?;
+
+    let kit = forms::value_by_id::<StarterKit>(kit_id, &conn)?;
+    if !classes_of_character(&char_id, &conn)?.contains(&kit.class_id) {
+        return Err(forms::field_is_invalid_error(FIELD_KIT_ID));
+    }
+    let bag = match bag {
+        Some(bag) => {
+            let owner = match inventory::bag_owner(&bag, &conn) {
+                Ok(owner) => Some(owner),
+                Err(DBError::NoRows) => None,
+                Err(err) => return Err(err.into()),
+            };
+            if owner != Some(char_id) {
+                return Err(forms::field_is_invalid_error(FIELD_BAG_ID));
+            }
+            bag
+        },
+        None => default_bag(&char_id, &conn)?
+            .ok_or_else(|| status::bad_request(format!("{} has no bag to put {} in", character.name(), kit.name())))?,
+    };
+
+    let cost = kit.cost();
+    let cant_afford = || status::bad_request(format!("{} can't afford {}", character.name(), kit.name()));
+    let held = Coins::held_by(&character);
+    let coins = held.pay(cost).ok_or_else(cant_afford)?;
+    treasure::purse_after(&character, coins - held)?;
+
+    // Pay again while the purse is locked, so that two purchases can't both
+    // spend the same coins.
+    let bought = conn.transaction::<_, DBError, _>(|| {
+        let locked = characters::table.filter(characters::id.eq(char_id))
+            .for_update()
+            .first::<DBCharacter>(&conn)
+            .map_err(DBError::RunQuery)?;
+        let held = Coins::held_by(&locked);
+        let coins = match held.pay(cost) {
+            Some(coins) => coins,
+            None => return Ok(None),
+        };
+        let purse = treasure::add_to_purse(locked.purse(), coins - held)
+            .ok_or_else(|| DBError::InvalidValues(vec![FIELD_KIT_ID.to_string()]))?;
+        treasure::set_purse(&char_id, purse, &conn)?;
+        let items = kit.items.iter()
+            .map(|kit_item| inventory::add_to_bag(&bag, kit_item.item.id(), kit_item.count, &conn))
+            .collect::<Result<Vec<_>, DBError>>()?;
+        Ok(Some((coins, items)))
+    }).map_err(|err| forms::db_error_to_rejection(err, FIELD_KIT_ID))?;
+    let (coins, items) = bought.ok_or_else(cant_afford)?;
+
+    let bought = BoughtKit {
+        character_id: char_id,
+        kit_id,
+        bag_id: bag,
+        items,
+        cost,
+        coins,
+    };
+    Ok(Status::with_data(&StatusCode::OK, Success::new(bought)))
+}
+
+/// A warp Filter containing the starting wealth and starter kit endpoints,
+/// relative to the `/characters` path.
+pub fn wealth_filter() -> BoxedFilter<(impl Reply,)> {
+    let options = warp::get()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("starting-wealth"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and_then(get_starting_wealth);
+    let take = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("starting-wealth"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(take_starting_wealth);
+    let buy_kit = warp::post()
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("starter-kit"))
+        .and(warp::path::end())
+        .and(auth::user_filter())
+        .and(db::conn_filter())
+        .and(nebula_form::form_filter())
+        .and_then(buy_starter_kit);
+
+    options.or(take)
+        .or(buy_kit)
+        .boxed()
+}
@@ -47,13 +47,98 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    campaigncharacters (campaign_id, char_id) {
+        campaign_id -> Uuid,
+        char_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    campaigninvitations (campaign_id, user_id) {
+        campaign_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    campaignmembers (campaign_id, user_id) {
+        campaign_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::experience::AdvancementTrackMapping;
+
+    campaigns (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        gm_id -> Uuid,
+        game_time -> Int8,
+        advancement -> AdvancementTrackMapping,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::AttributeMapping;
+
+    characterabilitydamage (char_id, attr) {
+        char_id -> Uuid,
+        attr -> AttributeMapping,
+        damage -> Int2,
+        drain -> Int2,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    characteractiveeffects (id) {
+        id -> Uuid,
+        char_id -> Uuid,
+        effect_id -> Uuid,
+        source -> Text,
+        started_at -> Int8,
+        expires_at -> Nullable<Int8>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    characterdomains (char_id, domain_id) {
+        char_id -> Uuid,
+        domain_id -> Uuid,
+        subdomain_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    characterdomainspells (char_id, spell_id) {
+        char_id -> Uuid,
+        spell_id -> Uuid,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     
 
-    characterequipment (char_id, item_id) {
+    characterequipment (char_id, owned_item_id) {
         char_id -> Uuid,
-        item_id -> Uuid,
+        owned_item_id -> Uuid,
     }
 }
 
@@ -77,7 +162,25 @@ table! {
 
 table! {
     use diesel::sql_types::*;
-    use crate::pathfinder::{AlignmentMapping, GenderMapping, SizeMapping};
+
+    characterlanguages (char_id, language_id) {
+        char_id -> Uuid,
+        language_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    characterracetraits (char_id, trait_id) {
+        char_id -> Uuid,
+        trait_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::{AlignmentMapping, AttributeMapping, GenderMapping, SizeMapping};
 
     characters (id) {
         id -> Uuid,
@@ -105,6 +208,22 @@ table! {
         silver -> Int2,
         gold -> Int2,
         platinum -> Int2,
+        temp_hp -> Int2,
+        xp -> Int4,
+        racial_bonus -> Nullable<AttributeMapping>,
+        favored_class_id -> Nullable<Uuid>,
+        negative_levels -> Int2,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::SkillMapping;
+
+    characterskillranks (char_id, skill) {
+        char_id -> Uuid,
+        skill -> SkillMapping,
+        ranks -> Int2,
     }
 }
 
@@ -130,6 +249,15 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    charactertraits (char_id, trait_id) {
+        char_id -> Uuid,
+        trait_id -> Uuid,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::CharacterStatMapping;
@@ -152,6 +280,7 @@ table! {
 
 table! {
     use diesel::sql_types::*;
+    use crate::pathfinder::AlignmentMapping;
     use crate::pathfinder::AttributeMapping;
 
     classes (id) {
@@ -163,6 +292,7 @@ table! {
         bab_per_level -> Float8,
         skills_per_level -> Int2,
         skills_attr -> AttributeMapping,
+        alignments -> Array<AlignmentMapping>,
     }
 }
 
@@ -241,6 +371,24 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use crate::encounter::CombatantStatusMapping;
+
+    combatants (id) {
+        id -> Uuid,
+        encounter_id -> Uuid,
+        char_id -> Nullable<Uuid>,
+        name -> Text,
+        initiative_bonus -> Int2,
+        dexterity -> Int2,
+        initiative -> Nullable<Int2>,
+        turn_order -> Nullable<Int2>,
+        status -> CombatantStatusMapping,
+        creature_id -> Nullable<Uuid>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::CombatStatMapping;
@@ -255,11 +403,142 @@ table! {
 table! {
     use diesel::sql_types::*;
 
+    companionfeats (companion_id, feat_id) {
+        companion_id -> Uuid,
+        feat_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::companions::CompanionKindMapping;
+    use crate::pathfinder::AttributeMapping;
+
+    companions (id) {
+        id -> Uuid,
+        char_id -> Uuid,
+        creature_id -> Uuid,
+        kind -> CompanionKindMapping,
+        name -> Text,
+        ability_increases -> Array<AttributeMapping>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::item::ConsumableKindMapping;
+
+    consumables (id) {
+        id -> Uuid,
+        kind -> ConsumableKindMapping,
+        spell_id -> Uuid,
+        caster_level -> Int2,
+        charges -> Int2,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    creatureabilities (creature_id, name) {
+        creature_id -> Uuid,
+        name -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::DamageTypeMapping;
+
+    creatureattacks (creature_id, name) {
+        creature_id -> Uuid,
+        name -> Text,
+        count -> Int2,
+        attack_bonus -> Int2,
+        damage -> Text,
+        damage_type -> DamageTypeMapping,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::AttributeMapping;
+
+    creatureattributes (creature_id, attr) {
+        creature_id -> Uuid,
+        attr -> AttributeMapping,
+        score -> Int2,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::CombatStatMapping;
+
+    creaturecombatstats (creature_id, stat) {
+        creature_id -> Uuid,
+        stat -> CombatStatMapping,
+        value -> Int2,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    creatureeffects (creature_id, effect_id) {
+        creature_id -> Uuid,
+        effect_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    creaturefeats (creature_id, feat_id) {
+        creature_id -> Uuid,
+        feat_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::{AlignmentMapping, SizeMapping};
+
+    creatures (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        cr -> Int2,
+        type_id -> Uuid,
+        subtype_id -> Nullable<Uuid>,
+        size -> SizeMapping,
+        alignment -> AlignmentMapping,
+        hit_dice -> Int2,
+        hit_points -> Int2,
+        dr_bypassed_by -> Nullable<Text>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    creaturespells (creature_id, spell_id) {
+        creature_id -> Uuid,
+        spell_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::AlignmentMapping;
+
     deities (id) {
         id -> Uuid,
         name -> Text,
         description -> Text,
         favored_animals -> Nullable<Text>,
+        alignment -> AlignmentMapping,
     }
 }
 
@@ -322,6 +601,41 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    encounters (id) {
+        id -> Uuid,
+        campaign_id -> Uuid,
+        name -> Text,
+        round -> Int4,
+        current_turn -> Nullable<Int2>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::racial::FavoredClassBonusMapping;
+
+    favoredclassbonuses (char_id, level) {
+        char_id -> Uuid,
+        level -> Int2,
+        bonus -> FavoredClassBonusMapping,
+        option_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    favoredclassoptions (id) {
+        id -> Uuid,
+        race_id -> Uuid,
+        class_id -> Uuid,
+        description -> Text,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -331,6 +645,18 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::maneuvers::CombatManeuverMapping;
+
+    featmaneuverbonuses (feat_id, maneuver) {
+        feat_id -> Uuid,
+        maneuver -> CombatManeuverMapping,
+        offense -> Int2,
+        defense -> Int2,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -391,16 +717,27 @@ table! {
         cost -> Int4,
         weight -> Float8,
         equip_slot -> Nullable<EquipmentSlotMapping>,
+        thickness -> Nullable<Float8>,
     }
 }
 
 table! {
     use diesel::sql_types::*;
 
-    itemsinbags (bag_id, item_id) {
-        item_id -> Uuid,
+    itemsinbags (owned_item_id) {
         bag_id -> Uuid,
         count -> Int4,
+        owned_item_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    languages (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
     }
 }
 
@@ -422,6 +759,17 @@ table! {
         description -> Text,
         hp_per_inch -> Nullable<Int4>,
         hardness -> Nullable<Int4>,
+        weight_multiplier -> Float8,
+        cost_multiplier -> Float8,
+        cost_per_pound -> Int4,
+        weapon_cost -> Int4,
+        light_armor_cost -> Int4,
+        medium_armor_cost -> Int4,
+        heavy_armor_cost -> Int4,
+        armor_category_shift -> Int2,
+        max_dex_bonus -> Int2,
+        check_penalty_reduction -> Int2,
+        spell_failure_reduction -> Int2,
     }
 }
 
@@ -434,6 +782,40 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    owneditemabilities (owned_item_id, ability_id) {
+        owned_item_id -> Uuid,
+        ability_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    owneditems (id) {
+        id -> Uuid,
+        item_id -> Uuid,
+        name -> Nullable<Text>,
+        notes -> Nullable<Text>,
+        hit_points -> Nullable<Int4>,
+        enhancement -> Int2,
+        charges -> Nullable<Int2>,
+        material_id -> Nullable<Uuid>,
+        masterwork -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    racebonuslanguages (race_id, language_id) {
+        race_id -> Uuid,
+        language_id -> Uuid,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -456,6 +838,7 @@ table! {
         move_speed -> Int2,
         size -> SizeMapping,
         languages -> Array<Text>,
+        flexible_bonus -> Int2,
     }
 }
 
@@ -478,6 +861,36 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    racetraiteffects (trait_id, effect_id) {
+        trait_id -> Uuid,
+        effect_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    racetraitreplacements (trait_id, replaced_id) {
+        trait_id -> Uuid,
+        replaced_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    racetraits (id) {
+        id -> Uuid,
+        race_id -> Uuid,
+        name -> Text,
+        description -> Text,
+        alternate -> Bool,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -507,6 +920,17 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::DamageTypeMapping;
+
+    resistanceunits (effect_id, damage_type) {
+        effect_id -> Uuid,
+        damage_type -> DamageTypeMapping,
+        amount -> Int2,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::SkillMapping;
@@ -529,6 +953,29 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::item::AbilityTargetMapping;
+
+    specialabilities (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        applies_to -> AbilityTargetMapping,
+        bonus_equivalent -> Nullable<Int2>,
+        flat_cost -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    specialabilityeffects (ability_id, effect_id) {
+        ability_id -> Uuid,
+        effect_id -> Uuid,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::spell::ComponentTypeMapping;
@@ -554,28 +1001,64 @@ table! {
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::SaveThrowMapping;
+    use crate::pathfinder::spell::AreaShapeMapping;
+    use crate::pathfinder::spell::DurationUnitMapping;
     use crate::pathfinder::spell::MagicSchoolMapping;
     use crate::pathfinder::spell::SpellRangeMapping;
+    use crate::pathfinder::spell::TargetKindMapping;
+    use crate::pathfinder::spell::TimeUnitMapping;
 
     spells (id) {
         id -> Uuid,
         name -> Text,
         level -> Int2,
         school -> MagicSchoolMapping,
-        casting_time -> Int8,
+        casting_time -> Int2,
+        casting_time_unit -> TimeUnitMapping,
         range -> SpellRangeMapping,
-        area -> Text,
-        duration_per_level -> Int8,
+        area_shape -> Nullable<AreaShapeMapping>,
+        area_size -> Nullable<Int2>,
+        target_kind -> Nullable<TargetKindMapping>,
+        target_count -> Int2,
+        targets_per_level -> Int2,
+        duration_unit -> DurationUnitMapping,
+        duration -> Int2,
+        duration_per_level -> Int2,
+        concentration -> Bool,
+        dismissible -> Bool,
         saving_throw -> Nullable<SaveThrowMapping>,
         spell_resistance -> Bool,
         description -> Text,
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    starterkititems (kit_id, item_id) {
+        kit_id -> Uuid,
+        item_id -> Uuid,
+        count -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    starterkits (id) {
+        id -> Uuid,
+        class_id -> Uuid,
+        name -> Text,
+        description -> Text,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::pathfinder::AttributeMapping;
     use crate::pathfinder::spell::CasterTypeMapping;
+    use crate::pathfinder::companions::CompanionKindMapping;
+    use crate::pathfinder::spell::MagicTraditionMapping;
 
     subclasses (id) {
         id -> Uuid,
@@ -584,6 +1067,10 @@ table! {
         class_id -> Uuid,
         caster_type -> Nullable<CasterTypeMapping>,
         casting_attr -> Nullable<AttributeMapping>,
+        tradition -> Nullable<MagicTraditionMapping>,
+        divine_patron -> Bool,
+        domains -> Int2,
+        companion -> Nullable<CompanionKindMapping>,
     }
 }
 
@@ -618,6 +1105,27 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    traiteffects (trait_id, effect_id) {
+        trait_id -> Uuid,
+        effect_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::pathfinder::traits::TraitCategoryMapping;
+
+    traits (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        category -> TraitCategoryMapping,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -655,19 +1163,42 @@ joinable!(attributefeatunits -> feats (feat_id));
 joinable!(attributeunits -> effects (effect_id));
 joinable!(bags -> characters (char_id));
 joinable!(bags -> items (item_id));
+joinable!(campaigncharacters -> campaigns (campaign_id));
+joinable!(campaigncharacters -> characters (char_id));
+joinable!(campaigninvitations -> campaigns (campaign_id));
+joinable!(campaigninvitations -> users (user_id));
+joinable!(campaignmembers -> campaigns (campaign_id));
+joinable!(campaignmembers -> users (user_id));
+joinable!(campaigns -> users (gm_id));
+joinable!(characterabilitydamage -> characters (char_id));
+joinable!(characteractiveeffects -> characters (char_id));
+joinable!(characteractiveeffects -> effects (effect_id));
+joinable!(characterdomains -> characters (char_id));
+joinable!(characterdomains -> domains (domain_id));
+joinable!(characterdomains -> subdomains (subdomain_id));
+joinable!(characterdomainspells -> characters (char_id));
+joinable!(characterdomainspells -> spells (spell_id));
 joinable!(characterequipment -> characters (char_id));
-joinable!(characterequipment -> items (item_id));
+joinable!(characterequipment -> owneditems (owned_item_id));
 joinable!(characterfeats -> characters (char_id));
 joinable!(characterfeats -> feats (feat_id));
 joinable!(characterfeatures -> characters (char_id));
 joinable!(characterfeatures -> features (feature_id));
+joinable!(characterlanguages -> characters (char_id));
+joinable!(characterlanguages -> languages (language_id));
+joinable!(characterracetraits -> characters (char_id));
+joinable!(characterracetraits -> racetraits (trait_id));
+joinable!(characters -> classes (favored_class_id));
 joinable!(characters -> deities (deity_id));
 joinable!(characters -> races (race_id));
 joinable!(characters -> users (user_id));
+joinable!(characterskillranks -> characters (char_id));
 joinable!(characterspells -> characters (char_id));
 joinable!(characterspells -> spells (spell_id));
 joinable!(charactersubclasses -> characters (char_id));
 joinable!(charactersubclasses -> subclasses (subclass_id));
+joinable!(charactertraits -> characters (char_id));
+joinable!(charactertraits -> traits (trait_id));
 joinable!(characterunits -> effects (effect_id));
 joinable!(classeffects -> classes (class_id));
 joinable!(classeffects -> effects (effect_id));
@@ -685,7 +1216,28 @@ joinable!(classproficientarmorclasses -> classes (class_id));
 joinable!(classproficientweaponclasses -> classes (class_id));
 joinable!(classproficientweapons -> classes (class_id));
 joinable!(classproficientweapons -> weapons (weapon_id));
+joinable!(combatants -> characters (char_id));
+joinable!(combatants -> creatures (creature_id));
+joinable!(combatants -> encounters (encounter_id));
 joinable!(combatunits -> effects (effect_id));
+joinable!(companionfeats -> companions (companion_id));
+joinable!(companionfeats -> feats (feat_id));
+joinable!(companions -> characters (char_id));
+joinable!(companions -> creatures (creature_id));
+joinable!(consumables -> items (id));
+joinable!(consumables -> spells (spell_id));
+joinable!(creatureabilities -> creatures (creature_id));
+joinable!(creatureattacks -> creatures (creature_id));
+joinable!(creatureattributes -> creatures (creature_id));
+joinable!(creaturecombatstats -> creatures (creature_id));
+joinable!(creatureeffects -> creatures (creature_id));
+joinable!(creatureeffects -> effects (effect_id));
+joinable!(creaturefeats -> creatures (creature_id));
+joinable!(creaturefeats -> feats (feat_id));
+joinable!(creatures -> racesubtypes (subtype_id));
+joinable!(creatures -> racetypes (type_id));
+joinable!(creaturespells -> creatures (creature_id));
+joinable!(creaturespells -> spells (spell_id));
 joinable!(deitydomains -> deities (deity_id));
 joinable!(deitydomains -> domains (domain_id));
 joinable!(deityweapons -> deities (deity_id));
@@ -694,39 +1246,62 @@ joinable!(domaineffects -> domains (domain_id));
 joinable!(domaineffects -> effects (effect_id));
 joinable!(domainspells -> domains (domain_id));
 joinable!(domainspells -> spells (spell_id));
+joinable!(encounters -> campaigns (campaign_id));
+joinable!(favoredclassbonuses -> characters (char_id));
+joinable!(favoredclassbonuses -> favoredclassoptions (option_id));
+joinable!(favoredclassoptions -> classes (class_id));
+joinable!(favoredclassoptions -> races (race_id));
 joinable!(feateffects -> effects (effect_id));
 joinable!(feateffects -> feats (feat_id));
+joinable!(featmaneuverbonuses -> feats (feat_id));
 joinable!(featureeffects -> effects (effect_id));
 joinable!(featureeffects -> features (feature_id));
 joinable!(itemeffects -> effects (effect_id));
 joinable!(itemeffects -> items (item_id));
 joinable!(itemsinbags -> bags (bag_id));
-joinable!(itemsinbags -> items (item_id));
+joinable!(itemsinbags -> owneditems (owned_item_id));
 joinable!(materialeffects -> effects (effect_id));
 joinable!(materialeffects -> materials (material_id));
 joinable!(miscunits -> effects (effect_id));
+joinable!(owneditemabilities -> owneditems (owned_item_id));
+joinable!(owneditemabilities -> specialabilities (ability_id));
+joinable!(owneditems -> items (item_id));
+joinable!(owneditems -> materials (material_id));
+joinable!(racebonuslanguages -> languages (language_id));
+joinable!(racebonuslanguages -> races (race_id));
 joinable!(raceeffects -> effects (effect_id));
 joinable!(raceeffects -> races (race_id));
 joinable!(races -> racesubtypes (subtype_id));
 joinable!(races -> racetypes (type_id));
 joinable!(racesubtypeeffects -> effects (effect_id));
 joinable!(racesubtypeeffects -> racesubtypes (subtype_id));
+joinable!(racetraiteffects -> effects (effect_id));
+joinable!(racetraiteffects -> racetraits (trait_id));
+joinable!(racetraits -> races (race_id));
 joinable!(racetypeeffects -> effects (effect_id));
 joinable!(racetypeeffects -> racetypes (type_id));
 joinable!(racialfeats -> feats (feat_id));
 joinable!(racialfeats -> races (race_id));
+joinable!(resistanceunits -> effects (effect_id));
 joinable!(skillfeatunits -> feats (feat_id));
 joinable!(skillunits -> effects (effect_id));
+joinable!(specialabilityeffects -> effects (effect_id));
+joinable!(specialabilityeffects -> specialabilities (ability_id));
 joinable!(spellcomponents -> items (item_id));
 joinable!(spellcomponents -> spells (spell_id));
 joinable!(spelleffects -> effects (effect_id));
 joinable!(spelleffects -> spells (spell_id));
+joinable!(starterkititems -> items (item_id));
+joinable!(starterkititems -> starterkits (kit_id));
+joinable!(starterkits -> classes (class_id));
 joinable!(subclasses -> classes (class_id));
 joinable!(subclassfeatures -> features (feature_id));
 joinable!(subclassfeatures -> subclasses (subclass_id));
 joinable!(subclassspells -> spells (spell_id));
 joinable!(subclassspells -> subclasses (subclass_id));
 joinable!(subdomains -> domains (domain_id));
+joinable!(traiteffects -> effects (effect_id));
+joinable!(traiteffects -> traits (trait_id));
 joinable!(weapons -> items (id));
 joinable!(weapons -> materials (material_id));
 
@@ -735,12 +1310,24 @@ allow_tables_to_appear_in_same_query!(
     attributefeatunits,
     attributeunits,
     bags,
+    campaigncharacters,
+    campaigninvitations,
+    campaignmembers,
+    campaigns,
+    characterabilitydamage,
+    characteractiveeffects,
+    characterdomains,
+    characterdomainspells,
     characterequipment,
     characterfeats,
     characterfeatures,
+    characterlanguages,
+    characterracetraits,
     characters,
+    characterskillranks,
     characterspells,
     charactersubclasses,
+    charactertraits,
     characterunits,
     classeffects,
     classes,
@@ -752,7 +1339,19 @@ allow_tables_to_appear_in_same_query!(
     classproficientarmorclasses,
     classproficientweaponclasses,
     classproficientweapons,
+    combatants,
     combatunits,
+    companionfeats,
+    companions,
+    consumables,
+    creatureabilities,
+    creatureattacks,
+    creatureattributes,
+    creaturecombatstats,
+    creatureeffects,
+    creaturefeats,
+    creatures,
+    creaturespells,
     deities,
     deitydomains,
     deityweapons,
@@ -760,7 +1359,11 @@ allow_tables_to_appear_in_same_query!(
     domains,
     domainspells,
     effects,
+    encounters,
+    favoredclassbonuses,
+    favoredclassoptions,
     feateffects,
+    featmaneuverbonuses,
     featrequirements,
     feats,
     featureeffects,
@@ -768,25 +1371,39 @@ allow_tables_to_appear_in_same_query!(
     itemeffects,
     items,
     itemsinbags,
+    languages,
     materialeffects,
     materials,
     miscunits,
+    owneditemabilities,
+    owneditems,
+    racebonuslanguages,
     raceeffects,
     races,
     racesubtypeeffects,
     racesubtypes,
+    racetraiteffects,
+    racetraitreplacements,
+    racetraits,
     racetypeeffects,
     racetypes,
     racialfeats,
+    resistanceunits,
     skillfeatunits,
     skillunits,
+    specialabilities,
+    specialabilityeffects,
     spellcomponents,
     spelleffects,
     spells,
+    starterkititems,
+    starterkits,
     subclasses,
     subclassfeatures,
     subclassspells,
     subdomains,
+    traiteffects,
+    traits,
     users,
     weapons,
 );
@@ -39,6 +39,12 @@ pub(crate) fn invalid_header_error(header: &str) -> Rejection {
     .into()
 }
 
+/// Generates a 400 Rejection containing a Status<Error> with the given
+/// message.
+pub(crate) fn bad_request(msg: String) -> Rejection {
+    Status::with_data(&StatusCode::BAD_REQUEST, Error::new(msg)).into()
+}
+
 pub(crate) fn not_found() -> Rejection {
     Status::new(&StatusCode::NOT_FOUND).into()
 }